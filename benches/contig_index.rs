@@ -0,0 +1,50 @@
+//! Measures contig name interning for assemblies with millions of contigs, such as fragmented drafts or transcriptomes.
+//! Run with `cargo bench --bench contig_index`; set `CONTIG_INDEX_BENCH_CONTIGS` to change the number of contigs (default 10,000,000).
+use rust_lib_reference_genome::reference_genome::ReferenceGenome;
+use std::hint::black_box;
+use std::time::Instant;
+
+/// The default number of contigs, matching the largest assemblies the index is designed for
+const DEFAULT_CONTIGS: usize = 10_000_000;
+
+/// The number of lookups timed per pass
+const LOOKUPS: usize = 1_000_000;
+
+fn main() {
+    let contig_count: usize = std::env::var("CONTIG_INDEX_BENCH_CONTIGS").ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_CONTIGS);
+    let names: Vec<String> = (0..contig_count).map(|i| format!("scaffold_{i}")).collect();
+
+    let start = Instant::now();
+    let reference_genome = ReferenceGenome::from_sequences(names.iter().map(|n| (n.clone(), "A"))).unwrap();
+    let load_time = start.elapsed();
+    let usage = reference_genome.memory_usage();
+    println!("contigs: {contig_count}");
+    println!("build: {:.3} s", load_time.as_secs_f64());
+    println!("index: {:.1} bytes/contig", usage.index_bytes as f64 / contig_count as f64);
+
+    // a fixed stride visits names across the whole table rather than in insertion order
+    let stride = 7_919;
+    let start = Instant::now();
+    let mut found = 0;
+    for i in 0..LOOKUPS {
+        found += usize::from(black_box(reference_genome.contig_id(&names[(i * stride) % contig_count])).is_some());
+    }
+    let hit_time = start.elapsed();
+    assert_eq!(found, LOOKUPS);
+    println!("contig_id hit: {:.1} ns", hit_time.as_nanos() as f64 / LOOKUPS as f64);
+
+    let start = Instant::now();
+    for i in 0..LOOKUPS {
+        black_box(reference_genome.contig_name(black_box(((i * stride) % contig_count) as u32)));
+    }
+    println!("contig_name: {:.1} ns", start.elapsed().as_nanos() as f64 / LOOKUPS as f64);
+
+    let misses: Vec<String> = (0..LOOKUPS).map(|i| format!("contig_{i}")).collect();
+    let start = Instant::now();
+    for miss in misses.iter() {
+        assert!(black_box(reference_genome.contig_id(miss)).is_none());
+    }
+    println!("contig_id miss: {:.1} ns", start.elapsed().as_nanos() as f64 / LOOKUPS as f64);
+}
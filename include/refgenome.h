@@ -0,0 +1,70 @@
+#ifndef REFGENOME_H
+#define REFGENOME_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Opaque handle to a loaded reference genome for the C API.
+// Functions that can fail return NULL or a negative status, and the error message for the calling thread is available
+// from `refgenome_last_error()`. Sequence and name pointers are borrowed from the handle; they are not NUL-terminated
+// and remain valid until the handle is freed. The header is generated with `cbindgen --config cbindgen.toml --output include/refgenome.h`.
+typedef struct RefGenome RefGenome;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns the last error message on the calling thread, or NULL if there has been no error.
+// The string is valid until the next failing call on the same thread.
+const char *refgenome_last_error(void);
+
+// Loads a reference genome from a FASTA file, directory, or wildcard pattern.
+// If `mmap` is non-zero, the FASTA is memory-mapped and contigs are decoded on first access.
+// Returns NULL on failure; the handle must be released with `refgenome_free(...)`.
+// # Safety
+// `path` must be a valid NUL-terminated string
+struct RefGenome *refgenome_load(const char *path, int32_t mmap);
+
+// Releases a handle from `refgenome_load(...)`; NULL is ignored
+// # Safety
+// `handle` must be NULL or a handle from `refgenome_load(...)` that has not been freed
+void refgenome_free(struct RefGenome *handle);
+
+// Returns the number of contigs
+// # Safety
+// `handle` must be a valid handle
+size_t refgenome_contig_count(const struct RefGenome *handle);
+
+// Returns the name of the contig at a 0-based index in load order and writes its length in bytes to `name_length`,
+// or returns NULL if the index is out of range
+// # Safety
+// `handle` must be a valid handle and `name_length` must be a valid pointer
+const uint8_t *refgenome_contig_name(const struct RefGenome *handle,
+                                     size_t index,
+                                     size_t *name_length);
+
+// Writes the length of a contig to `length`, returning 0 on success or -1 if the contig is not in the reference genome
+// # Safety
+// `handle` must be a valid handle, `contig` a valid NUL-terminated string, and `length` a valid pointer
+int32_t refgenome_contig_length(const struct RefGenome *handle,
+                                const char *contig,
+                                size_t *length);
+
+// Fetches the 0-based, half-open region `[start, end)` of a contig, truncated to the contig length.
+// Returns a pointer to the sequence and writes its length to `sequence_length`, or returns NULL if the contig
+// is not in the reference genome, has been unloaded, or `start` > `end`.
+// # Safety
+// `handle` must be a valid handle, `contig` a valid NUL-terminated string, and `sequence_length` a valid pointer
+const uint8_t *refgenome_fetch(const struct RefGenome *handle,
+                               const char *contig,
+                               size_t start,
+                               size_t end,
+                               size_t *sequence_length);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* REFGENOME_H */
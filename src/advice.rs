@@ -0,0 +1,81 @@
+use crate::reference_genome::{ContigSequence, ReferenceGenome};
+use rustc_hash::FxHashSet as HashSet;
+use simple_error::bail;
+use std::error::Error;
+
+/// How a memory-mapped genome is about to be read, passed to the OS as an `madvise` hint by `ReferenceGenome::advise_access(...)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessPattern {
+    /// No particular pattern, the OS default
+    Normal,
+    /// Front-to-back reads, e.g. whole-genome sweeps; the OS reads ahead aggressively and may free pages soon after they are read
+    Sequential,
+    /// Scattered small reads, e.g. variant lookups; the OS reads ahead little, so page cache is not spent on unused neighbors
+    Random
+}
+
+impl ReferenceGenome {
+    /// Passes an access pattern hint to the OS for every memory-mapped file behind the genome (`Backend::Mmap`),
+    /// so large-genome workloads can tune page cache behavior. Hints only affect performance, never results.
+    /// Other backends do not take hints, and hints are ignored on platforms without `madvise`.
+    /// # Arguments
+    /// * `pattern` - the expected access pattern
+    /// # Returns
+    /// * the number of mapped files that took the hint, 0 for a genome without any
+    /// # Errors
+    /// * if the OS rejects the hint
+    pub fn advise_access(&self, pattern: AccessPattern) -> Result<usize, Box<dyn Error>> {
+        let mut advised: HashSet<usize> = Default::default();
+        for contig in self.contig_keys().iter() {
+            if let Some(ContigSequence::Lazy(lazy_contig)) = self.contig_sequence(contig) {
+                if !advised.contains(&lazy_contig.loader_id()) && lazy_contig.advise(pattern)? {
+                    advised.insert(lazy_contig.loader_id());
+                }
+            }
+        }
+        Ok(advised.len())
+    }
+
+    /// Asks the OS to start reading the pages of a memory-mapped contig (`madvise` with `MADV_WILLNEED`), so a later access
+    /// does not wait on disk; e.g. call it for the next contig while processing the current one. This does not decode the
+    /// contig or keep it in memory, see `prefetch_contigs(...)` for that.
+    /// # Arguments
+    /// * `chromosome` - the contig to read ahead; no lookup normalization is applied
+    /// # Returns
+    /// * true if the hint was passed on, false if the contig is not memory-mapped or the platform lacks `madvise`
+    /// # Errors
+    /// * if the contig is not in the reference genome
+    /// * if the OS rejects the hint
+    pub fn will_need_contig(&self, chromosome: &str) -> Result<bool, Box<dyn Error>> {
+        match self.contig_sequence(chromosome) {
+            Some(ContigSequence::Lazy(lazy_contig)) => Ok(lazy_contig.will_need()?),
+            Some(_) => Ok(false),
+            None => bail!("{}", self.missing_contig_message(chromosome))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{Backend, ReferenceGenomeBuilder};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_advise_access() {
+        let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .backend(Backend::Mmap)
+            .build()
+            .unwrap();
+        // every contig shares one mapping
+        assert_eq!(reference_genome.advise_access(AccessPattern::Sequential).unwrap(), cfg!(unix) as usize);
+        assert_eq!(reference_genome.advise_access(AccessPattern::Random).unwrap(), cfg!(unix) as usize);
+        assert_eq!(reference_genome.will_need_contig("chr1").unwrap(), cfg!(unix));
+        assert!(reference_genome.will_need_contig("chr3").is_err());
+        assert_eq!(reference_genome.get_slice("chr2", 0, 3), b"ACC");
+
+        let in_memory = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        assert_eq!(in_memory.advise_access(AccessPattern::Normal).unwrap(), 0);
+        assert!(!in_memory.will_need_contig("chr1").unwrap());
+    }
+}
@@ -0,0 +1,107 @@
+use simple_error::{bail, SimpleError};
+
+/// The set of characters that are allowed in a contig sequence
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Alphabet {
+    /// No restrictions on the sequence content
+    #[default]
+    Any,
+    /// Only the nucleotides `ACGT` and the unknown base `N`
+    Dna,
+    /// The IUPAC nucleotide codes, `ACGTRYSWKMBDHVN`
+    Iupac
+}
+
+impl Alphabet {
+    /// Returns true if a sequence character is in the alphabet; comparisons are case-insensitive
+    /// # Arguments
+    /// * `symbol` - the ASCII sequence character to check
+    pub fn is_valid(&self, symbol: u8) -> bool {
+        match self {
+            Alphabet::Any => true,
+            Alphabet::Dna => matches!(symbol.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T' | b'N'),
+            Alphabet::Iupac => matches!(
+                symbol.to_ascii_uppercase(),
+                b'A' | b'C' | b'G' | b'T' | b'R' | b'Y' | b'S' | b'W' | b'K' | b'M' | b'B' | b'D' | b'H' | b'V' | b'N'
+            )
+        }
+    }
+
+    /// Checks that every character of a contig sequence is in the alphabet
+    /// # Arguments
+    /// * `contig` - the contig name, used for the error message
+    /// * `sequence` - the ASCII sequence to check
+    /// # Errors
+    /// * if any character is not in the alphabet, reporting the first 0-based position that failed
+    pub fn validate(&self, contig: &str, sequence: &[u8]) -> Result<(), SimpleError> {
+        self.validate_symbols(contig, sequence.iter().copied())
+    }
+
+    /// Same as `validate(...)`, but for sequences that are not contiguous in memory
+    pub(crate) fn validate_symbols<I>(&self, contig: &str, symbols: I) -> Result<(), SimpleError> where I: Iterator<Item = u8> {
+        if *self == Alphabet::Any {
+            return Ok(());
+        }
+        for (position, symbol) in symbols.enumerate() {
+            if !self.is_valid(symbol) {
+                bail!(
+                    "Contig {contig:?} contains invalid character {:?} at position {position} for alphabet {self:?}",
+                    symbol as char
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Complements a single IUPAC nucleotide code, preserving case; other characters (e.g. `N` and `-`) are returned unchanged
+/// # Arguments
+/// * `symbol` - the ASCII sequence character to complement
+pub fn complement(symbol: u8) -> u8 {
+    let complemented = match symbol.to_ascii_uppercase() {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' | b'U' => b'A',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        _ => return symbol
+    };
+    if symbol.is_ascii_lowercase() { complemented.to_ascii_lowercase() } else { complemented }
+}
+
+/// Computes the reverse complement of a sequence, see `complement(...)`
+/// # Arguments
+/// * `sequence` - the ASCII sequence to reverse complement
+pub fn reverse_complement(sequence: &[u8]) -> Vec<u8> {
+    sequence.iter().rev().map(|&c| complement(c)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(Alphabet::Any.validate("test", b"ACGT-*xyz").is_ok());
+        assert!(Alphabet::Dna.validate("test", b"ACGTNacgtn").is_ok());
+        assert!(Alphabet::Iupac.validate("test", b"ACGTRYSWKMBDHVNrysw").is_ok());
+
+        let error = Alphabet::Dna.validate("test", b"ACGTR").unwrap_err();
+        assert_eq!(error.to_string(), "Contig \"test\" contains invalid character 'R' at position 4 for alphabet Dna");
+        assert!(Alphabet::Iupac.validate("test", b"ACGT-").is_err());
+    }
+
+    #[test]
+    fn test_reverse_complement() {
+        assert_eq!(reverse_complement(b"ACGTN"), b"NACGT");
+        assert_eq!(reverse_complement(b"aaCCrs-"), b"-syGGtt");
+        assert_eq!(reverse_complement(b""), b"");
+    }
+}
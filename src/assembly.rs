@@ -0,0 +1,332 @@
+use crate::contig_class::{classify_contig_name, ContigClass};
+use crate::metadata::AssemblyMetadata;
+use crate::reference_genome::ReferenceGenome;
+use rustc_hash::FxHashSet as HashSet;
+use simple_error::{bail, SimpleError};
+use std::path::Path;
+
+/// Well-known reference assemblies with built-in contig presets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KnownAssembly {
+    /// Human GRCh38, UCSC-style names (e.g. the GRCh38 analysis set)
+    GRCh38,
+    /// Human GRCh37, Ensembl/1000 Genomes-style names (e.g. `human_g1k_v37`, `hs37d5`)
+    GRCh37,
+    /// Human hg19, UCSC-style names
+    Hg19,
+    /// Human T2T-CHM13 v2.0, UCSC-style names
+    T2tChm13,
+    /// Mouse GRCm39, UCSC-style names
+    GRCm39
+}
+
+/// The set of contigs to keep when applying a preset
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContigSet {
+    /// Only the autosomes, sex chromosomes, and mitochondrial genome
+    StandardChromosomes,
+    /// The standard chromosomes plus unlocalized/unplaced scaffolds; alts, patches, decoys, HLA, and EBV are removed
+    PrimaryAssembly
+}
+
+/// Contig naming conventions supported by `convert_naming(...)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NamingScheme {
+    /// UCSC style, e.g. `chr1` and `chrM`
+    Ucsc,
+    /// Ensembl style, e.g. `1` and `MT`
+    Ensembl,
+    /// RefSeq accessions, e.g. `NC_000001.11` and `NC_012920.1`
+    RefSeq
+}
+
+/// The names of a single contig under each naming scheme
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContigAlias {
+    /// The UCSC-style name
+    pub ucsc: String,
+    /// The Ensembl-style name, if there is an equivalent sequence in the Ensembl release
+    pub ensembl: Option<String>,
+    /// The RefSeq accession
+    pub refseq: String
+}
+
+impl ContigAlias {
+    /// Returns the name under a given naming scheme, if available
+    /// # Arguments
+    /// * `scheme` - the naming scheme to get
+    pub fn name(&self, scheme: NamingScheme) -> Option<&str> {
+        match scheme {
+            NamingScheme::Ucsc => Some(&self.ucsc),
+            NamingScheme::Ensembl => self.ensembl.as_deref(),
+            NamingScheme::RefSeq => Some(&self.refseq)
+        }
+    }
+
+    /// Returns true if the given name matches any of the aliases
+    /// # Arguments
+    /// * `name` - the contig name to check
+    pub fn matches(&self, name: &str) -> bool {
+        self.ucsc == name || self.ensembl.as_deref() == Some(name) || self.refseq == name
+    }
+}
+
+/// RefSeq accessions for the GRCh38 standard chromosomes in karyotype order
+const GRCH38_REFSEQ: [&str; 25] = [
+    "NC_000001.11", "NC_000002.12", "NC_000003.12", "NC_000004.12", "NC_000005.10", "NC_000006.12",
+    "NC_000007.14", "NC_000008.11", "NC_000009.12", "NC_000010.11", "NC_000011.10", "NC_000012.12",
+    "NC_000013.11", "NC_000014.9", "NC_000015.10", "NC_000016.10", "NC_000017.11", "NC_000018.10",
+    "NC_000019.10", "NC_000020.11", "NC_000021.9", "NC_000022.11", "NC_000023.11", "NC_000024.10",
+    "NC_012920.1"
+];
+/// RefSeq accessions for the GRCh37 standard chromosomes in karyotype order
+const GRCH37_REFSEQ: [&str; 25] = [
+    "NC_000001.10", "NC_000002.11", "NC_000003.11", "NC_000004.11", "NC_000005.9", "NC_000006.11",
+    "NC_000007.13", "NC_000008.10", "NC_000009.11", "NC_000010.10", "NC_000011.9", "NC_000012.11",
+    "NC_000013.10", "NC_000014.8", "NC_000015.9", "NC_000016.9", "NC_000017.10", "NC_000018.9",
+    "NC_000019.9", "NC_000020.10", "NC_000021.8", "NC_000022.10", "NC_000023.10", "NC_000024.9",
+    "NC_012920.1"
+];
+/// The hg19 mitochondrial sequence predates the rCRS and has no Ensembl equivalent
+const HG19_CHRM_REFSEQ: &str = "NC_001807.4";
+/// RefSeq accessions for the T2T-CHM13 v2.0 standard chromosomes in karyotype order
+const T2T_CHM13_REFSEQ: [&str; 25] = [
+    "NC_060925.1", "NC_060926.1", "NC_060927.1", "NC_060928.1", "NC_060929.1", "NC_060930.1",
+    "NC_060931.1", "NC_060932.1", "NC_060933.1", "NC_060934.1", "NC_060935.1", "NC_060936.1",
+    "NC_060937.1", "NC_060938.1", "NC_060939.1", "NC_060940.1", "NC_060941.1", "NC_060942.1",
+    "NC_060943.1", "NC_060944.1", "NC_060945.1", "NC_060946.1", "NC_060947.1", "NC_060948.1",
+    "NC_012920.1"
+];
+/// RefSeq accessions for the GRCm39 standard chromosomes in karyotype order
+const GRCM39_REFSEQ: [&str; 22] = [
+    "NC_000067.7", "NC_000068.8", "NC_000069.7", "NC_000070.7", "NC_000071.7", "NC_000072.7",
+    "NC_000073.7", "NC_000074.7", "NC_000075.7", "NC_000076.7", "NC_000077.7", "NC_000078.7",
+    "NC_000079.7", "NC_000080.7", "NC_000081.7", "NC_000082.7", "NC_000083.7", "NC_000084.7",
+    "NC_000085.7", "NC_000086.8", "NC_000087.8", "NC_005089.1"
+];
+
+impl KnownAssembly {
+    /// The number of autosomes in the assembly
+    pub fn autosome_count(&self) -> usize {
+        match self {
+            KnownAssembly::GRCh38 |
+            KnownAssembly::GRCh37 |
+            KnownAssembly::Hg19 |
+            KnownAssembly::T2tChm13 => 22,
+            KnownAssembly::GRCm39 => 19
+        }
+    }
+
+    /// The standard chromosome names (autosomes, X, Y, mitochondria) in the preset's naming convention and karyotype order
+    pub fn standard_contigs(&self) -> Vec<String> {
+        let (prefix, mito) = match self {
+            KnownAssembly::GRCh37 => ("", "MT"),
+            _ => ("chr", "M")
+        };
+        (1..=self.autosome_count()).map(|i| i.to_string())
+            .chain(["X", "Y", mito].iter().map(|s| s.to_string()))
+            .map(|c| format!("{prefix}{c}"))
+            .collect()
+    }
+
+    /// The bundled name mapping table for the standard chromosomes of the assembly, in karyotype order
+    pub fn contig_aliases(&self) -> Vec<ContigAlias> {
+        let refseq: &[&str] = match self {
+            KnownAssembly::GRCh38 => &GRCH38_REFSEQ,
+            KnownAssembly::GRCh37 |
+            KnownAssembly::Hg19 => &GRCH37_REFSEQ,
+            KnownAssembly::T2tChm13 => &T2T_CHM13_REFSEQ,
+            KnownAssembly::GRCm39 => &GRCM39_REFSEQ
+        };
+        let autosomes = (1..=self.autosome_count()).map(|i| i.to_string());
+        let chromosomes = autosomes.chain(["X", "Y", "M"].iter().map(|s| s.to_string()));
+        chromosomes.zip(refseq.iter())
+            .map(|(chrom, &accession)| {
+                if chrom == "M" && *self == KnownAssembly::Hg19 {
+                    ContigAlias {
+                        ucsc: "chrM".to_string(),
+                        ensembl: None,
+                        refseq: HG19_CHRM_REFSEQ.to_string()
+                    }
+                } else {
+                    ContigAlias {
+                        ucsc: format!("chr{chrom}"),
+                        ensembl: Some(if chrom == "M" { "MT".to_string() } else { chrom }),
+                        refseq: accession.to_string()
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Converts a single contig name to a naming scheme, or `None` if the contig is not in the mapping table
+    /// # Arguments
+    /// * `name` - the contig name in any supported naming scheme
+    /// * `scheme` - the target naming scheme
+    pub fn convert_contig_name(&self, name: &str, scheme: NamingScheme) -> Option<String> {
+        self.contig_aliases().iter()
+            .find(|a| a.matches(name))
+            .and_then(|a| a.name(scheme).map(|n| n.to_string()))
+    }
+}
+
+impl ReferenceGenome {
+    /// Creates a new reference genome containing only the contigs from a preset, preserving load order.
+    /// # Arguments
+    /// * `assembly` - the assembly preset that matches this genome
+    /// * `contig_set` - the set of contigs to retain
+    /// # Errors
+    /// * if any of the preset's standard chromosomes are missing, which usually indicates the wrong preset
+    pub fn filter_preset(&self, assembly: KnownAssembly, contig_set: ContigSet) -> Result<ReferenceGenome, SimpleError> {
+        let standard_contigs = assembly.standard_contigs();
+        let missing: Vec<&str> = standard_contigs.iter()
+            .filter(|c| self.resolve_contig_name(c).is_none())
+            .map(|c| c.as_str())
+            .collect();
+        if !missing.is_empty() {
+            bail!("Reference genome does not match the {assembly:?} preset, missing contigs: {missing:?}");
+        }
+
+        // the genome's own names, which differ from the preset's with normalized lookup
+        let kept: HashSet<String> = standard_contigs.iter()
+            .filter_map(|c| self.resolve_contig_name(c).map(|r| r.to_string()))
+            .collect();
+        Ok(self.subset(|c| {
+            kept.contains(c) || (
+                contig_set == ContigSet::PrimaryAssembly &&
+                classify_contig_name(c) == ContigClass::Unplaced
+            )
+        }))
+    }
+
+    /// Renames all contigs to a different naming scheme using the bundled mapping table for an assembly.
+    /// Contigs may be in any mix of the supported schemes.
+    /// # Arguments
+    /// * `assembly` - the assembly that the genome is from; GRCh37 and hg19 share a mapping table except for the mitochondrial genome
+    /// * `scheme` - the target naming scheme
+    /// # Errors
+    /// * if any contig cannot be mapped; no contigs are renamed in this case
+    pub fn convert_naming(&mut self, assembly: KnownAssembly, scheme: NamingScheme) -> Result<(), SimpleError> {
+        let aliases = assembly.contig_aliases();
+        let mut renames: Vec<(String, String)> = vec![];
+        let mut unmappable: Vec<&str> = vec![];
+        for contig in self.contig_keys().iter() {
+            match aliases.iter().find(|a| a.matches(contig)).and_then(|a| a.name(scheme)) {
+                Some(new_name) => renames.push((contig.clone(), new_name.to_string())),
+                None => unmappable.push(contig)
+            };
+        }
+        if !unmappable.is_empty() {
+            bail!("Contigs cannot be converted to {scheme:?} naming for {assembly:?}: {unmappable:?}");
+        }
+        self.rename_contigs(&renames)
+    }
+
+    /// Loads a reference genome from a given FASTA file and filters it down with a preset in one call.
+    /// The assembly metadata is set from the preset, see `AssemblyMetadata::from(...)`.
+    /// # Arguments
+    /// * `fasta_fn` - the FASTA filename, gzip is allowed
+    /// * `assembly` - the assembly preset that matches the FASTA file
+    /// * `contig_set` - the set of contigs to retain
+    /// # Errors
+    /// * any error from `from_fasta(...)`
+    /// * if the loaded genome does not match the preset, see `filter_preset(...)`
+    pub fn from_fasta_preset(fasta_fn: &Path, assembly: KnownAssembly, contig_set: ContigSet) -> Result<ReferenceGenome, Box<dyn std::error::Error>> {
+        let full_genome = ReferenceGenome::from_fasta(fasta_fn)?;
+        let mut reference_genome = full_genome.filter_preset(assembly, contig_set)?;
+        reference_genome.set_assembly_metadata(AssemblyMetadata::from(assembly));
+        Ok(reference_genome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_grch38() -> ReferenceGenome {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        for contig in KnownAssembly::GRCh38.standard_contigs().into_iter() {
+            reference_genome.add_contig(contig, "ACGT").unwrap();
+        }
+        for contig in ["chr1_KI270706v1_random", "chrUn_KI270302v1", "chr6_GL000250v2_alt", "chrUn_JTFH01000001v1_decoy", "chrEBV", "HLA-A*01:01:01:01"] {
+            reference_genome.add_contig(contig.to_string(), "ACGT").unwrap();
+        }
+        reference_genome
+    }
+
+    #[test]
+    fn test_standard_contigs() {
+        let grch38 = KnownAssembly::GRCh38.standard_contigs();
+        assert_eq!(grch38.len(), 25);
+        assert_eq!(grch38[0], "chr1");
+        assert_eq!(grch38[24], "chrM");
+
+        let grch37 = KnownAssembly::GRCh37.standard_contigs();
+        assert_eq!(grch37[21], "22");
+        assert_eq!(grch37[24], "MT");
+
+        assert_eq!(KnownAssembly::GRCm39.standard_contigs().len(), 22);
+    }
+
+    #[test]
+    fn test_filter_preset() {
+        let reference_genome = mock_grch38();
+        let standard = reference_genome.filter_preset(KnownAssembly::GRCh38, ContigSet::StandardChromosomes).unwrap();
+        assert_eq!(standard.contig_keys(), KnownAssembly::GRCh38.standard_contigs());
+
+        let primary = reference_genome.filter_preset(KnownAssembly::GRCh38, ContigSet::PrimaryAssembly).unwrap();
+        assert_eq!(primary.contig_keys().len(), 27);
+        assert!(primary.get("chrUn_KI270302v1").is_some());
+        assert!(primary.get("chrEBV").is_none());
+
+        // wrong naming convention
+        assert!(reference_genome.filter_preset(KnownAssembly::GRCh37, ContigSet::StandardChromosomes).is_err());
+    }
+
+    #[test]
+    fn test_filter_preset_normalized_lookup() {
+        let mut reference_genome = mock_grch38().filter_preset(KnownAssembly::GRCh38, ContigSet::StandardChromosomes).unwrap();
+        reference_genome.convert_naming(KnownAssembly::GRCh38, NamingScheme::Ensembl).unwrap();
+        reference_genome.add_contig("KI270302.1".to_string(), "ACGT").unwrap();
+        assert!(reference_genome.filter_preset(KnownAssembly::GRCh38, ContigSet::StandardChromosomes).is_err());
+
+        // UCSC preset names resolve to the Ensembl names of the genome
+        reference_genome.set_normalized_lookup(true);
+        let standard = reference_genome.filter_preset(KnownAssembly::GRCh38, ContigSet::StandardChromosomes).unwrap();
+        assert_eq!(standard.contig_keys().len(), 25);
+        assert_eq!(standard.contig_keys()[0], "1");
+        assert_eq!(standard.contig_keys()[24], "MT");
+        assert!(standard.get("KI270302.1").is_none());
+    }
+
+    #[test]
+    fn test_contig_aliases() {
+        for assembly in [KnownAssembly::GRCh38, KnownAssembly::GRCh37, KnownAssembly::Hg19, KnownAssembly::T2tChm13, KnownAssembly::GRCm39] {
+            assert_eq!(assembly.contig_aliases().len(), assembly.standard_contigs().len());
+        }
+        assert_eq!(KnownAssembly::GRCh38.convert_contig_name("chrX", NamingScheme::RefSeq), Some("NC_000023.11".to_string()));
+        assert_eq!(KnownAssembly::GRCh37.convert_contig_name("NC_012920.1", NamingScheme::Ucsc), Some("chrM".to_string()));
+        assert_eq!(KnownAssembly::Hg19.convert_contig_name("chrM", NamingScheme::Ensembl), None);
+        assert_eq!(KnownAssembly::GRCm39.convert_contig_name("19", NamingScheme::Ucsc), Some("chr19".to_string()));
+    }
+
+    #[test]
+    fn test_convert_naming() {
+        let mut reference_genome = mock_grch38().filter_preset(KnownAssembly::GRCh38, ContigSet::StandardChromosomes).unwrap();
+        reference_genome.convert_naming(KnownAssembly::GRCh38, NamingScheme::Ensembl).unwrap();
+        assert_eq!(reference_genome.contig_keys()[0], "1");
+        assert_eq!(reference_genome.contig_keys()[24], "MT");
+
+        reference_genome.convert_naming(KnownAssembly::GRCh38, NamingScheme::RefSeq).unwrap();
+        assert_eq!(reference_genome.contig_keys()[0], "NC_000001.11");
+
+        reference_genome.convert_naming(KnownAssembly::GRCh38, NamingScheme::Ucsc).unwrap();
+        assert_eq!(reference_genome.contig_keys(), KnownAssembly::GRCh38.standard_contigs());
+
+        // non-standard contigs are not in the table
+        let mut full_genome = mock_grch38();
+        let error = full_genome.convert_naming(KnownAssembly::GRCh38, NamingScheme::Ensembl).unwrap_err();
+        assert!(error.to_string().contains("chrEBV"));
+        assert_eq!(full_genome.contig_keys()[0], "chr1");
+    }
+}
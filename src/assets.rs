@@ -0,0 +1,278 @@
+use crate::reference_genome::ReferenceGenome;
+use crate::user_dirs::{default_data_dir, non_empty_env};
+use simple_error::{bail, SimpleError};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// The environment variable naming the local asset cache directory
+pub const ASSETS_ENV_VAR: &str = "REFGENOME_ASSETS";
+/// The environment variable naming the base URL of a remote asset registry
+pub const REGISTRY_ENV_VAR: &str = "REFGENOME_ASSET_REGISTRY";
+/// The asset name of a genome's FASTA, used by `AssetRegistry::load_genome(...)`
+pub const FASTA_ASSET: &str = "fasta";
+
+/// An asset offered by a remote registry, from its `<genome>/assets.tsv` manifest
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemoteAsset {
+    /// The asset name, e.g. "fasta" or "bwa_index"
+    pub asset: String,
+    /// The URL of each file of the asset
+    pub urls: Vec<String>,
+    /// The expected MD5 digest of each file, if published
+    pub md5s: Vec<Option<String>>
+}
+
+/// Parses a remote asset manifest with one `asset<TAB>url[<TAB>md5]` line per file; assets with several files repeat the asset name.
+/// Blank lines and lines starting with `#` are ignored.
+/// # Arguments
+/// * `manifest` - the manifest content
+/// # Errors
+/// * if a line does not have an asset and a URL
+pub fn parse_asset_manifest(manifest: &str) -> Result<Vec<RemoteAsset>, SimpleError> {
+    let mut assets: Vec<RemoteAsset> = vec![];
+    for (line_index, line) in manifest.lines().enumerate() {
+        let line = line.trim_end();
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 2 || fields[0].is_empty() || fields[1].is_empty() {
+            bail!("Expected \"asset<TAB>url[<TAB>md5]\" on line {} of the asset manifest", line_index + 1);
+        }
+        let md5 = fields.get(2).filter(|m| !m.is_empty()).map(|m| m.to_ascii_lowercase());
+        let index = match assets.iter().position(|a| a.asset == fields[0]) {
+            Some(index) => index,
+            None => {
+                assets.push(RemoteAsset { asset: fields[0].to_string(), urls: vec![], md5s: vec![] });
+                assets.len() - 1
+            }
+        };
+        assets[index].urls.push(fields[1].to_string());
+        assets[index].md5s.push(md5);
+    }
+    Ok(assets)
+}
+
+/// Resolves reference assets by (genome, asset) name, in the style of refgenie and genomepy, so several tools on a host share one managed copy.
+/// Each asset is a directory `<cache root>/<genome>/<asset>/` holding its files (e.g. the FASTA, or all files of an aligner index).
+/// Assets that are not cached can be fetched from a remote registry, which serves a manifest at `<base URL>/<genome>/assets.tsv`
+/// (see `parse_asset_manifest(...)`); fetching requires the `download` feature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetRegistry {
+    /// The local cache directory
+    cache_root: PathBuf,
+    /// The base URL of the remote registry, if any
+    remote: Option<String>
+}
+
+/// Checks that a genome or asset name is a single, non-hidden path component
+fn validate_name(name: &str) -> Result<(), SimpleError> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        bail!("Invalid genome or asset name {:?}", name);
+    }
+    Ok(())
+}
+
+impl AssetRegistry {
+    /// Creates a registry with a local cache and no remote
+    /// # Arguments
+    /// * `cache_root` - the local cache directory, which does not need to exist yet
+    pub fn new(cache_root: &Path) -> Self {
+        Self {
+            cache_root: cache_root.to_path_buf(),
+            remote: None
+        }
+    }
+
+    /// Sets the base URL of a remote registry to fetch missing assets from
+    pub fn with_remote(mut self, base_url: &str) -> Self {
+        self.remote = Some(base_url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Builds a registry from the environment: the cache from `REFGENOME_ASSETS`, or if unset, `refgenome/assets` under
+    /// `$XDG_DATA_HOME` or `$HOME/.local/share`; and the remote from `REFGENOME_ASSET_REGISTRY`, if set
+    /// # Errors
+    /// * if none of the cache environment variables are set
+    pub fn from_env() -> Result<AssetRegistry, SimpleError> {
+        let cache_root = match non_empty_env(ASSETS_ENV_VAR) {
+            Some(cache_root) => PathBuf::from(cache_root),
+            None => match default_data_dir("refgenome/assets") {
+                Some(cache_root) => cache_root,
+                None => bail!("Cannot determine the asset cache directory; set {}", ASSETS_ENV_VAR)
+            }
+        };
+        let registry = AssetRegistry::new(&cache_root);
+        Ok(match non_empty_env(REGISTRY_ENV_VAR) {
+            Some(base_url) => registry.with_remote(&base_url),
+            None => registry
+        })
+    }
+
+    /// The local cache directory
+    pub fn cache_root(&self) -> &Path {
+        &self.cache_root
+    }
+
+    /// The local directory of an asset, whether or not it exists
+    /// # Errors
+    /// * if the genome or asset name is empty, hidden, or contains a path separator
+    pub fn asset_directory(&self, genome: &str, asset: &str) -> Result<PathBuf, SimpleError> {
+        validate_name(genome)?;
+        validate_name(asset)?;
+        Ok(self.cache_root.join(genome).join(asset))
+    }
+
+    /// The local directory of an asset, or `None` if it is not cached
+    /// # Arguments
+    /// * `genome` - the genome name, e.g. "hg38"
+    /// * `asset` - the asset name, e.g. "fasta"
+    pub fn locate(&self, genome: &str, asset: &str) -> Option<PathBuf> {
+        self.asset_directory(genome, asset).ok().filter(|directory| directory.is_dir())
+    }
+
+    /// Lists the cached assets of a genome, sorted by name
+    /// # Errors
+    /// * if the genome name is invalid
+    /// * any directory reading errors other than the genome not being cached
+    pub fn cached_assets(&self, genome: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        validate_name(genome)?;
+        let entries = match std::fs::read_dir(self.cache_root.join(genome)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into())
+        };
+        let mut assets: Vec<String> = vec![];
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type()?.is_dir() && !name.starts_with('.') {
+                assets.push(name);
+            }
+        }
+        assets.sort();
+        Ok(assets)
+    }
+
+    /// Downloads an asset from the remote registry into the cache, verifying any published digests.
+    /// Files are downloaded into a hidden directory owned by this process that is renamed into place once complete,
+    /// so other tools never see a partial asset, and processes fetching the same asset at once do not write to the same files.
+    /// A failed download resumes on the next call from the same process; if another process installs the asset first, its copy is used.
+    /// # Errors
+    /// * if there is no remote registry, or it does not offer the asset
+    /// * see `download_resumable(...)`
+    #[cfg(feature = "download")]
+    fn fetch(&self, genome: &str, asset: &str) -> Result<PathBuf, Box<dyn Error>> {
+        use crate::download::{download_resumable, fetch_text, DownloadOptions};
+
+        let directory = self.asset_directory(genome, asset)?;
+        let Some(base_url) = self.remote.as_ref() else {
+            bail!("Asset {}/{} is not cached in {:?} and no remote registry is configured", genome, asset, self.cache_root);
+        };
+        let manifest_url = format!("{base_url}/{genome}/assets.tsv");
+        let manifest = fetch_text(&manifest_url, &DownloadOptions::default())?;
+        let Some(remote_asset) = parse_asset_manifest(&manifest)?.into_iter().find(|a| a.asset == asset) else {
+            bail!("Remote registry {} does not offer asset {}/{}", base_url, genome, asset);
+        };
+
+        let partial_directory = self.cache_root.join(genome).join(format!(".{asset}.partial.{}", std::process::id()));
+        std::fs::create_dir_all(&partial_directory)?;
+        for (url, md5) in remote_asset.urls.iter().zip(remote_asset.md5s.iter()) {
+            let file_name = url.rsplit('/').next().unwrap_or_default();
+            validate_name(file_name)?;
+            let options = DownloadOptions { expected_md5: md5.clone(), ..Default::default() };
+            download_resumable(url, &partial_directory.join(file_name), &options)?;
+        }
+        install_partial(&partial_directory, &directory)?;
+        Ok(directory)
+    }
+
+    /// Resolves an asset to its local directory, fetching it from the remote registry if it is not cached
+    /// # Arguments
+    /// * `genome` - the genome name, e.g. "hg38"
+    /// * `asset` - the asset name, e.g. "fasta"
+    /// # Errors
+    /// * if the asset is not cached and cannot be fetched, including when the `download` feature is disabled
+    pub fn resolve(&self, genome: &str, asset: &str) -> Result<PathBuf, Box<dyn Error>> {
+        if let Some(directory) = self.locate(genome, asset) {
+            return Ok(directory);
+        }
+        #[cfg(feature = "download")] {
+            self.fetch(genome, asset)
+        }
+        #[cfg(not(feature = "download"))] {
+            self.asset_directory(genome, asset)?;
+            bail!("Asset {}/{} is not cached in {:?}; fetching from a remote registry requires the \"download\" feature", genome, asset, self.cache_root)
+        }
+    }
+
+    /// Loads the `fasta` asset of a genome, fetching it first if needed; every FASTA file in the asset directory is loaded
+    /// # Arguments
+    /// * `genome` - the genome name
+    /// # Errors
+    /// * see `resolve(...)` and `ReferenceGenome::from_fasta(...)`
+    pub fn load_genome(&self, genome: &str) -> Result<ReferenceGenome, Box<dyn Error>> {
+        ReferenceGenome::from_fasta(&self.resolve(genome, FASTA_ASSET)?)
+    }
+}
+
+/// Renames a completed partial directory into place.
+/// If another process installed the same asset first, the rename fails because the target is not empty;
+/// that copy is kept and the partial directory is removed.
+/// # Errors
+/// * if the rename fails and the target directory does not exist
+#[cfg_attr(not(feature = "download"), allow(dead_code))]
+fn install_partial(partial_directory: &Path, directory: &Path) -> std::io::Result<()> {
+    match std::fs::rename(partial_directory, directory) {
+        Ok(()) => Ok(()),
+        Err(_) if directory.is_dir() => std::fs::remove_dir_all(partial_directory),
+        Err(e) => Err(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_asset_manifest() {
+        let manifest = "# hg38 assets\nfasta\thttps://example.org/hg38/hg38.fa.gz\tABCDEF\nbwa_index\thttps://example.org/hg38/hg38.bwt\nbwa_index\thttps://example.org/hg38/hg38.sa\t\n";
+        let assets = parse_asset_manifest(manifest).unwrap();
+        assert_eq!(assets.len(), 2);
+        assert_eq!(assets[0].md5s, vec![Some("abcdef".to_string())]);
+        assert_eq!(assets[1].urls.len(), 2);
+        assert_eq!(assets[1].md5s, vec![None, None]);
+        assert!(parse_asset_manifest("fasta\n").is_err());
+    }
+
+    #[test]
+    fn test_asset_registry() {
+        let cache_root = std::env::temp_dir().join(format!("refgenome_assets_{}", std::process::id()));
+        let registry = AssetRegistry::new(&cache_root);
+        assert!(registry.locate("test", FASTA_ASSET).is_none());
+        assert!(registry.cached_assets("test").unwrap().is_empty());
+        assert!(registry.load_genome("test").is_err());
+        assert!(registry.asset_directory("../test", FASTA_ASSET).is_err());
+
+        let directory = registry.asset_directory("test", FASTA_ASSET).unwrap();
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::copy("./test_data/test_reference.fa", directory.join("test_reference.fa")).unwrap();
+        std::fs::create_dir_all(cache_root.join("test").join(".bwa_index.partial")).unwrap();
+        assert_eq!(registry.locate("test", FASTA_ASSET), Some(directory.clone()));
+        assert_eq!(registry.cached_assets("test").unwrap(), vec!["fasta"]);
+        assert_eq!(registry.load_genome("test").unwrap().contig_keys(), &["chr1", "chr2"]);
+
+        // a fetch that finishes after another process installed the asset keeps the installed copy
+        let partial_directory = cache_root.join("test").join(".fasta.partial.1");
+        std::fs::create_dir_all(&partial_directory).unwrap();
+        std::fs::write(partial_directory.join("other.fa"), ">chr3\nA\n").unwrap();
+        install_partial(&partial_directory, &directory).unwrap();
+        assert!(!partial_directory.exists());
+        assert!(!directory.join("other.fa").exists());
+        let partial_directory = cache_root.join("test").join(".bwa_index.partial.1");
+        std::fs::create_dir_all(&partial_directory).unwrap();
+        install_partial(&partial_directory, &cache_root.join("test").join("bwa_index")).unwrap();
+        assert_eq!(registry.cached_assets("test").unwrap(), vec!["bwa_index", "fasta"]);
+        std::fs::remove_dir_all(&cache_root).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+use crate::builder::{decompress, is_gzip};
+use simple_error::bail;
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A BED interval, keeping any optional columns as text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BedRecord {
+    /// The contig name
+    pub contig: String,
+    /// The 0-based start (included)
+    pub start: usize,
+    /// The 0-based end (excluded)
+    pub end: usize,
+    /// The columns after the end, such as name, score, and strand
+    pub extra: Vec<String>
+}
+
+impl BedRecord {
+    /// The name column, or `None` if it is missing or `.`
+    pub fn name(&self) -> Option<&str> {
+        self.extra.first().map(|n| n.as_str()).filter(|n| *n != ".")
+    }
+
+    /// The strand column as `+` or `-`, or `None` if it is missing or unstranded
+    pub fn strand(&self) -> Option<char> {
+        match self.extra.get(2).map(|s| s.as_str()) {
+            Some("+") => Some('+'),
+            Some("-") => Some('-'),
+            _ => None
+        }
+    }
+
+    /// The interval length
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns true if the interval is empty
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Reads the records of a BED file, skipping blank, comment, track, and browser lines
+/// # Arguments
+/// * `reader` - the uncompressed BED content
+/// # Errors
+/// * any reading errors
+/// * if a line does not have a contig, start, and end, or the start is after the end
+pub fn read_bed_records<R: BufRead>(reader: R) -> Result<Vec<BedRecord>, Box<dyn Error>> {
+    let mut records: Vec<BedRecord> = vec![];
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') || line.starts_with("track") || line.starts_with("browser") {
+            continue;
+        }
+        let mut fields = line.trim_end_matches('\r').split('\t');
+        let (Some(contig), Some(start), Some(end)) = (fields.next(), fields.next(), fields.next()) else {
+            bail!("Expected at least 3 columns on line {} of the BED: {:?}", line_index + 1, line);
+        };
+        let (start, end) = match (start.parse::<usize>(), end.parse::<usize>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end),
+            _ => bail!("Invalid interval {}-{} on line {} of the BED", start, end, line_index + 1)
+        };
+        records.push(BedRecord {
+            contig: contig.to_string(),
+            start,
+            end,
+            extra: fields.map(|f| f.to_string()).collect()
+        });
+    }
+    Ok(records)
+}
+
+/// Reads the records of a BED file, see `read_bed_records(...)`
+/// # Arguments
+/// * `bed_fn` - the BED filename; gzip and bgzip compression are detected from a `.gz` extension
+/// # Errors
+/// * any file reading errors
+/// * see `read_bed_records(...)`
+pub fn read_bed_file(bed_fn: &Path) -> Result<Vec<BedRecord>, Box<dyn Error>> {
+    let bed_file = std::fs::File::open(bed_fn)?;
+    let reader = decompress(Box::new(BufReader::new(bed_file)), is_gzip(bed_fn), &Arc::default())?;
+    read_bed_records(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_bed_records() {
+        let bed = b"track name=targets\n# comment\nchr1\t10\t20\tBRCA1_ex1\t0\t-\nchr2\t0\t5\r\n\nchr2\t5\t8\t.\n";
+        let records = read_bed_records(&bed[..]).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!((records[0].name(), records[0].strand(), records[0].len()), (Some("BRCA1_ex1"), Some('-'), 10));
+        assert_eq!(records[1], BedRecord { contig: "chr2".to_string(), start: 0, end: 5, extra: vec![] });
+        assert_eq!((records[2].name(), records[2].strand()), (None, None));
+
+        assert!(read_bed_records(&b"chr1\t10\n"[..]).is_err());
+        assert!(read_bed_records(&b"chr1\t10\t5\n"[..]).is_err());
+        assert!(read_bed_records(&b"chr1\tx\t5\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,401 @@
+use crate::reference_genome::ReferenceGenome;
+use crate::tracks::ContigTrack;
+use flate2::{write::ZlibEncoder, Compression};
+use rustc_hash::FxHashMap as HashMap;
+use simple_error::bail;
+use std::io::{Seek, SeekFrom, Write};
+use std::ops::Range;
+
+/// Magic number at the start and end of a bigWig file
+const BIGWIG_MAGIC: u32 = 0x888F_FC26;
+/// Magic number of the chromosome B+ tree
+const CHROM_TREE_MAGIC: u32 = 0x78CA_8C91;
+/// Magic number of the R-tree index over data sections
+const R_TREE_MAGIC: u32 = 0x2468_ACE0;
+/// The maximum intervals per data section, matching `bedGraphToBigWig`
+const ITEMS_PER_SLOT: usize = 1024;
+/// The maximum items per node in both trees, matching `bedGraphToBigWig`
+const BLOCK_SIZE: usize = 256;
+/// The byte size of the fixed header
+const HEADER_SIZE: u64 = 64;
+/// The byte size of the total summary that follows the header
+const SUMMARY_SIZE: u64 = 40;
+/// The bedGraph section type, where each item has its own start and end
+const BEDGRAPH_SECTION: u8 = 1;
+
+/// A compressed data section and its location in the file
+struct DataSection {
+    /// The chromosome id, in the numbering of the chromosome tree
+    chrom_id: u32,
+    /// The start of the first interval
+    start: u32,
+    /// The end of the last interval
+    end: u32,
+    /// The file offset of the compressed section
+    offset: u64,
+    /// The compressed size
+    size: u64
+}
+
+/// Groups `count` items into tree levels, bottom-up, with at most `BLOCK_SIZE` items per node.
+/// Each level lists its nodes as ranges over the level below (or the items, for the first level); the last level is the single root.
+fn tree_levels(count: usize) -> Vec<Vec<Range<usize>>> {
+    let chunk = |n: usize| -> Vec<Range<usize>> {
+        (0..n.max(1)).step_by(BLOCK_SIZE).map(|s| s..(s + BLOCK_SIZE).min(n)).collect()
+    };
+    let mut levels = vec![chunk(count)];
+    while levels.last().unwrap().len() > 1 {
+        levels.push(chunk(levels.last().unwrap().len()));
+    }
+    levels
+}
+
+/// The file offset of each node of each level when the levels are written root first, starting at `start`
+/// # Arguments
+/// * `levels` - from `tree_levels(...)`
+/// * `start` - the offset of the root node
+/// * `leaf_item_size` - the bytes per leaf item
+/// * `branch_item_size` - the bytes per non-leaf item
+fn node_offsets(levels: &[Vec<Range<usize>>], start: u64, leaf_item_size: u64, branch_item_size: u64) -> Vec<Vec<u64>> {
+    let mut offsets: Vec<Vec<u64>> = vec![vec![]; levels.len()];
+    let mut offset = start;
+    for (level_index, level) in levels.iter().enumerate().rev() {
+        let item_size = if level_index == 0 { leaf_item_size } else { branch_item_size };
+        for node in level.iter() {
+            offsets[level_index].push(offset);
+            offset += 4 + item_size * node.len() as u64;
+        }
+    }
+    offsets
+}
+
+/// The index of the first item under a node, following the first child down to the items
+fn first_item(levels: &[Vec<Range<usize>>], level_index: usize, node_index: usize) -> usize {
+    let mut index = levels[level_index][node_index].start;
+    for level in levels[..level_index].iter().rev() {
+        index = level[index].start;
+    }
+    index
+}
+
+/// The index of the last item under a node, following the last child down to the items
+fn last_item(levels: &[Vec<Range<usize>>], level_index: usize, node_index: usize) -> usize {
+    let mut index = levels[level_index][node_index].end - 1;
+    for level in levels[..level_index].iter().rev() {
+        index = level[index].end - 1;
+    }
+    index
+}
+
+/// Serializes the chromosome B+ tree that maps names to ids and sizes
+/// # Arguments
+/// * `chroms` - (name, size) pairs sorted by name, where the position is the id
+/// * `start` - the file offset of the tree
+fn chrom_tree(chroms: &[(&str, u32)], start: u64) -> Vec<u8> {
+    let key_size = chroms.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max(1);
+    let key = |name: &str| -> Vec<u8> {
+        let mut key = name.as_bytes().to_vec();
+        key.resize(key_size, 0);
+        key
+    };
+    let levels = tree_levels(chroms.len());
+    let item_size = (key_size + 8) as u64;
+    let offsets = node_offsets(&levels, start + 32, item_size, item_size);
+
+    let mut buffer: Vec<u8> = vec![];
+    buffer.extend(CHROM_TREE_MAGIC.to_le_bytes());
+    buffer.extend((chroms.len().clamp(1, BLOCK_SIZE) as u32).to_le_bytes());
+    buffer.extend((key_size as u32).to_le_bytes());
+    buffer.extend(8u32.to_le_bytes());
+    buffer.extend((chroms.len() as u64).to_le_bytes());
+    buffer.extend(0u64.to_le_bytes());
+    for (level_index, level) in levels.iter().enumerate().rev() {
+        for node in level.iter() {
+            buffer.push(u8::from(level_index == 0));
+            buffer.push(0);
+            buffer.extend((node.len() as u16).to_le_bytes());
+            for child in node.clone() {
+                if level_index == 0 {
+                    let (name, size) = chroms[child];
+                    buffer.extend(key(name));
+                    buffer.extend((child as u32).to_le_bytes());
+                    buffer.extend(size.to_le_bytes());
+                } else {
+                    buffer.extend(key(chroms[first_item(&levels, level_index - 1, child)].0));
+                    buffer.extend(offsets[level_index - 1][child].to_le_bytes());
+                }
+            }
+        }
+    }
+    buffer
+}
+
+/// Serializes the R-tree index over the data sections
+/// # Arguments
+/// * `sections` - the data sections, sorted by chromosome id and start
+/// * `start` - the file offset of the index
+/// * `data_end` - the file offset just past the data sections
+fn r_tree(sections: &[DataSection], start: u64, data_end: u64) -> Vec<u8> {
+    let levels = tree_levels(sections.len());
+    let offsets = node_offsets(&levels, start + 48, 32, 24);
+    let (first, last) = (sections.first(), sections.last());
+
+    let mut buffer: Vec<u8> = vec![];
+    buffer.extend(R_TREE_MAGIC.to_le_bytes());
+    buffer.extend((BLOCK_SIZE as u32).to_le_bytes());
+    buffer.extend((sections.len() as u64).to_le_bytes());
+    buffer.extend(first.map(|s| s.chrom_id).unwrap_or(0).to_le_bytes());
+    buffer.extend(first.map(|s| s.start).unwrap_or(0).to_le_bytes());
+    buffer.extend(last.map(|s| s.chrom_id).unwrap_or(0).to_le_bytes());
+    buffer.extend(last.map(|s| s.end).unwrap_or(0).to_le_bytes());
+    buffer.extend(data_end.to_le_bytes());
+    buffer.extend((ITEMS_PER_SLOT as u32).to_le_bytes());
+    buffer.extend(0u32.to_le_bytes());
+    for (level_index, level) in levels.iter().enumerate().rev() {
+        for node in level.iter() {
+            buffer.push(u8::from(level_index == 0));
+            buffer.push(0);
+            buffer.extend((node.len() as u16).to_le_bytes());
+            for child in node.clone() {
+                // sections are sorted and non-overlapping, so a subtree spans from its first to its last section
+                let (first, last) = if level_index == 0 {
+                    (&sections[child], &sections[child])
+                } else {
+                    (&sections[first_item(&levels, level_index - 1, child)], &sections[last_item(&levels, level_index - 1, child)])
+                };
+                buffer.extend(first.chrom_id.to_le_bytes());
+                buffer.extend(first.start.to_le_bytes());
+                buffer.extend(last.chrom_id.to_le_bytes());
+                buffer.extend(last.end.to_le_bytes());
+                if level_index == 0 {
+                    buffer.extend(first.offset.to_le_bytes());
+                    buffer.extend(first.size.to_le_bytes());
+                } else {
+                    buffer.extend(offsets[level_index - 1][child].to_le_bytes());
+                }
+            }
+        }
+    }
+    buffer
+}
+
+impl ReferenceGenome {
+    /// Writes tracks (e.g. from `gc_skew(...)`) as a bigWig file for genome browsers, using every contig in the genome as the chromosome list.
+    /// Data is stored as zlib-compressed bedGraph sections, the same layout as `bedGraphToBigWig`, and values are stored as 32-bit floats.
+    /// Zoom levels are not written, so browsers read the full-resolution data at every scale.
+    /// # Arguments
+    /// * `writer` - the output to write to, which must be seekable to fill in the header
+    /// * `tracks` - the tracks to write, at most one per contig
+    /// # Errors
+    /// * if a track is for a contig that is not in the reference genome, or there are two tracks for the same contig
+    /// * if a track's intervals are empty, unsorted, overlapping, or extend past the contig
+    /// * if a contig is longer than the 32-bit coordinates of the format allow
+    /// * any errors from the underlying writer
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn write_bigwig<W: Write + Seek>(&self, mut writer: W, tracks: &[ContigTrack]) -> Result<(), Box<dyn std::error::Error>> {
+        // chromosome ids follow the sorted order of the names, as in the B+ tree
+        let mut chroms: Vec<(&str, u32)> = Vec::with_capacity(self.contig_keys().len());
+        for contig in self.contig_keys().iter() {
+            let length = self.contig_length(contig).unwrap_or_default();
+            let Ok(length) = u32::try_from(length) else {
+                bail!("Contig \"{contig}\" has length {length}, which is too long for bigWig");
+            };
+            chroms.push((contig, length));
+        }
+        chroms.sort_unstable_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+        let chrom_ids: HashMap<&str, usize> = chroms.iter().enumerate().map(|(id, (name, _))| (*name, id)).collect();
+
+        let mut sorted_tracks: Vec<(usize, &ContigTrack)> = Vec::with_capacity(tracks.len());
+        for track in tracks.iter() {
+            let Some(&chrom_id) = chrom_ids.get(track.contig.as_str()) else {
+                bail!("{}", self.missing_contig_message(&track.contig));
+            };
+            let length = chroms[chrom_id].1 as usize;
+            let mut previous_end = 0;
+            for interval in track.intervals.iter() {
+                if interval.start >= interval.end || interval.start < previous_end || interval.end > length {
+                    bail!("Track for contig \"{}\" has an empty, unsorted, overlapping, or out-of-range interval at {}-{}", track.contig, interval.start, interval.end);
+                }
+                previous_end = interval.end;
+            }
+            sorted_tracks.push((chrom_id, track));
+        }
+        sorted_tracks.sort_by_key(|(chrom_id, _)| *chrom_id);
+        if let Some(pair) = sorted_tracks.windows(2).find(|p| p[0].0 == p[1].0) {
+            bail!("There is more than one track for contig \"{}\"", pair[0].1.contig);
+        }
+
+        // the header and summary are written last, once the offsets are known
+        writer.write_all(&[0; (HEADER_SIZE + SUMMARY_SIZE) as usize])?;
+        let chrom_tree_offset = HEADER_SIZE + SUMMARY_SIZE;
+        let chrom_tree_bytes = chrom_tree(&chroms, chrom_tree_offset);
+        writer.write_all(&chrom_tree_bytes)?;
+
+        let data_offset = chrom_tree_offset + chrom_tree_bytes.len() as u64;
+        let section_count: usize = sorted_tracks.iter().map(|(_, t)| t.intervals.len().div_ceil(ITEMS_PER_SLOT)).sum();
+        writer.write_all(&(section_count as u64).to_le_bytes())?;
+        let mut offset = data_offset + 8;
+        let mut sections: Vec<DataSection> = Vec::with_capacity(section_count);
+        let mut max_section_size = 0;
+        let (mut bases_covered, mut min_value, mut max_value, mut sum_data, mut sum_squares) = (0u64, f64::INFINITY, f64::NEG_INFINITY, 0.0, 0.0);
+        for (chrom_id, track) in sorted_tracks.iter() {
+            for items in track.intervals.chunks(ITEMS_PER_SLOT) {
+                let (start, end) = (items[0].start as u32, items[items.len() - 1].end as u32);
+                let mut section: Vec<u8> = Vec::with_capacity(24 + 12 * items.len());
+                section.extend((*chrom_id as u32).to_le_bytes());
+                section.extend(start.to_le_bytes());
+                section.extend(end.to_le_bytes());
+                section.extend(0u32.to_le_bytes());
+                section.extend(0u32.to_le_bytes());
+                section.push(BEDGRAPH_SECTION);
+                section.push(0);
+                section.extend((items.len() as u16).to_le_bytes());
+                for item in items.iter() {
+                    let value = item.value as f32;
+                    section.extend((item.start as u32).to_le_bytes());
+                    section.extend((item.end as u32).to_le_bytes());
+                    section.extend(value.to_le_bytes());
+
+                    let (bases, value) = ((item.end - item.start) as u64, value as f64);
+                    bases_covered += bases;
+                    min_value = min_value.min(value);
+                    max_value = max_value.max(value);
+                    sum_data += value * bases as f64;
+                    sum_squares += value * value * bases as f64;
+                }
+                max_section_size = max_section_size.max(section.len());
+
+                let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+                encoder.write_all(&section)?;
+                let compressed = encoder.finish()?;
+                writer.write_all(&compressed)?;
+                sections.push(DataSection {
+                    chrom_id: *chrom_id as u32,
+                    start,
+                    end,
+                    offset,
+                    size: compressed.len() as u64
+                });
+                offset += compressed.len() as u64;
+            }
+        }
+
+        let index_offset = offset;
+        writer.write_all(&r_tree(&sections, index_offset, offset))?;
+        writer.write_all(&BIGWIG_MAGIC.to_le_bytes())?;
+
+        let mut header: Vec<u8> = Vec::with_capacity((HEADER_SIZE + SUMMARY_SIZE) as usize);
+        header.extend(BIGWIG_MAGIC.to_le_bytes());
+        header.extend(4u16.to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        header.extend(chrom_tree_offset.to_le_bytes());
+        header.extend(data_offset.to_le_bytes());
+        header.extend(index_offset.to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        header.extend(0u64.to_le_bytes());
+        header.extend(HEADER_SIZE.to_le_bytes());
+        header.extend((max_section_size as u32).to_le_bytes());
+        header.extend(0u64.to_le_bytes());
+        if bases_covered == 0 {
+            (min_value, max_value) = (0.0, 0.0);
+        }
+        header.extend(bases_covered.to_le_bytes());
+        for value in [min_value, max_value, sum_data, sum_squares] {
+            header.extend(value.to_le_bytes());
+        }
+        writer.seek(SeekFrom::Start(0))?;
+        writer.write_all(&header)?;
+        writer.seek(SeekFrom::End(0))?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracks::TrackInterval;
+    use flate2::read::ZlibDecoder;
+    use std::io::{Cursor, Read};
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64_at(data: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_write_bigwig() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr2".to_string(), &"ACGT".repeat(1000)).unwrap();
+        reference_genome.add_contig("chr10".to_string(), "ACGT").unwrap();
+        // enough intervals for two sections on chr2
+        let chr2 = ContigTrack {
+            contig: "chr2".to_string(),
+            intervals: (0..1500).map(|i| TrackInterval { start: 2 * i, end: 2 * i + 1, value: i as f64 }).collect()
+        };
+        let chr10 = ContigTrack {
+            contig: "chr10".to_string(),
+            intervals: vec![TrackInterval { start: 0, end: 4, value: 0.5 }]
+        };
+        let mut output = Cursor::new(vec![]);
+        reference_genome.write_bigwig(&mut output, &[chr2, chr10]).unwrap();
+        let data = output.into_inner();
+
+        assert_eq!(u32_at(&data, 0), BIGWIG_MAGIC);
+        assert_eq!(u32_at(&data, data.len() - 4), BIGWIG_MAGIC);
+        // bases covered and the maximum value from the summary
+        assert_eq!(u64_at(&data, 64), 1504);
+        assert_eq!(f64::from_le_bytes(data[80..88].try_into().unwrap()), 1499.0);
+
+        // the chromosome tree is a single leaf with names in sorted order
+        let chrom_tree_offset = u64_at(&data, 8) as usize;
+        assert_eq!(u32_at(&data, chrom_tree_offset), CHROM_TREE_MAGIC);
+        let key_size = u32_at(&data, chrom_tree_offset + 8) as usize;
+        let leaf = chrom_tree_offset + 32;
+        assert_eq!(&data[leaf + 4..leaf + 4 + key_size], b"chr10");
+        assert_eq!(u32_at(&data, leaf + 4 + key_size + 4), 4);
+
+        // follow the index to the last section, which holds the remaining chr2 intervals
+        let index_offset = u64_at(&data, 24) as usize;
+        assert_eq!(u32_at(&data, index_offset), R_TREE_MAGIC);
+        assert_eq!(u64_at(&data, index_offset + 8), 3);
+        let node = index_offset + 48;
+        assert_eq!(u16::from_le_bytes([data[node + 2], data[node + 3]]), 3);
+        let item = node + 4 + 2 * 32;
+        assert_eq!(u32_at(&data, item), 1);
+        let (section_offset, section_size) = (u64_at(&data, item + 16) as usize, u64_at(&data, item + 24) as usize);
+        let mut section: Vec<u8> = vec![];
+        ZlibDecoder::new(&data[section_offset..section_offset + section_size]).read_to_end(&mut section).unwrap();
+        assert_eq!(u16::from_le_bytes([section[22], section[23]]), 476);
+        assert_eq!(u32_at(&section, 24), 2048);
+        assert_eq!(f32::from_le_bytes(section[32..36].try_into().unwrap()), 1024.0);
+    }
+
+    #[test]
+    fn test_tree_levels() {
+        assert_eq!(tree_levels(0), vec![vec![0..0]]);
+        let levels = tree_levels(BLOCK_SIZE * BLOCK_SIZE + 1);
+        assert_eq!(levels.iter().map(|l| l.len()).collect::<Vec<usize>>(), vec![BLOCK_SIZE + 1, 2, 1]);
+        assert_eq!(last_item(&levels, 2, 0), BLOCK_SIZE * BLOCK_SIZE);
+        assert_eq!(first_item(&levels, 1, 1), BLOCK_SIZE * BLOCK_SIZE);
+    }
+
+    #[test]
+    fn test_write_bigwig_errors() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), "ACGT").unwrap();
+        let track = |contig: &str, start: usize, end: usize| ContigTrack {
+            contig: contig.to_string(),
+            intervals: vec![TrackInterval { start, end, value: 1.0 }]
+        };
+        for tracks in [vec![track("chr2", 0, 1)], vec![track("chr1", 0, 5)], vec![track("chr1", 2, 2)], vec![track("chr1", 0, 1), track("chr1", 1, 2)]] {
+            assert!(reference_genome.write_bigwig(Cursor::new(vec![]), &tracks).is_err());
+        }
+        // a genome without tracks is still a valid file
+        reference_genome.write_bigwig(Cursor::new(vec![]), &[]).unwrap();
+    }
+}
@@ -0,0 +1,313 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use rust_lib_reference_genome::assembly::{KnownAssembly, NamingScheme};
+use rust_lib_reference_genome::bed::{read_bed_file, BedRecord};
+use rust_lib_reference_genome::builder::{Backend, ReferenceGenomeBuilder};
+use rust_lib_reference_genome::fingerprint::fingerprint_fasta;
+use rust_lib_reference_genome::reference_genome::ReferenceGenome;
+use rust_lib_reference_genome::writer::{write_fasta_record, DEFAULT_LINE_WIDTH};
+use rustc_hash::FxHashMap as HashMap;
+use simple_error::bail;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Command line tools for reference genome FASTA files
+#[derive(Parser)]
+#[command(name = "refgenome", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Reports the length, GC fraction, and N count of each contig as a TSV
+    Stats {
+        /// The FASTA file, directory, or wildcard pattern
+        fasta: PathBuf
+    },
+    /// Extracts regions as FASTA records
+    Extract {
+        /// The FASTA file, directory, or wildcard pattern
+        fasta: PathBuf,
+        /// Regions as `contig`, `contig:start`, or `contig:start-end` with 1-based inclusive coordinates
+        #[arg(required = true)]
+        regions: Vec<String>,
+        /// Output file, default is stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>
+    },
+    /// Reports the length and MD5 digest of each contig as a TSV
+    Digest {
+        /// The FASTA file, directory, or wildcard pattern
+        fasta: PathBuf
+    },
+    /// Streams a FASTA file without loading it and reports the digests, length, and gaps of each contig as a TSV,
+    /// preceded by a comment line with the sequence collection digest
+    Fingerprint {
+        /// The FASTA file
+        fasta: PathBuf,
+        /// The minimum run of Ns that counts as a gap
+        #[arg(long, default_value_t = 1)]
+        min_gap_length: usize
+    },
+    /// Writes a FASTA containing only the listed contigs
+    Subset {
+        /// The FASTA file, directory, or wildcard pattern
+        fasta: PathBuf,
+        /// Comma-separated contig names to keep
+        #[arg(short, long, value_delimiter = ',', required = true)]
+        contigs: Vec<String>,
+        /// Output file, default is stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>
+    },
+    /// Writes a FASTA with the regions of a BED file masked
+    Mask {
+        /// The FASTA file, directory, or wildcard pattern
+        fasta: PathBuf,
+        /// BED file of regions to mask, 0-based half-open
+        #[arg(short, long)]
+        bed: PathBuf,
+        /// Lower-case the masked regions instead of replacing them with N
+        #[arg(long)]
+        soft: bool,
+        /// Output file, default is stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>
+    },
+    /// Writes a FASTA with contigs renamed to a different naming scheme
+    Convert {
+        /// The FASTA file, directory, or wildcard pattern
+        fasta: PathBuf,
+        /// The assembly the FASTA is from
+        #[arg(short, long)]
+        assembly: AssemblyArg,
+        /// The target naming scheme
+        #[arg(short, long)]
+        naming: NamingArg,
+        /// Output file, default is stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>
+    }
+}
+
+/// Command line names for `KnownAssembly`
+#[derive(Clone, Copy, ValueEnum)]
+enum AssemblyArg {
+    Grch38,
+    Grch37,
+    Hg19,
+    T2tChm13,
+    Grcm39
+}
+
+impl From<AssemblyArg> for KnownAssembly {
+    fn from(value: AssemblyArg) -> Self {
+        match value {
+            AssemblyArg::Grch38 => KnownAssembly::GRCh38,
+            AssemblyArg::Grch37 => KnownAssembly::GRCh37,
+            AssemblyArg::Hg19 => KnownAssembly::Hg19,
+            AssemblyArg::T2tChm13 => KnownAssembly::T2tChm13,
+            AssemblyArg::Grcm39 => KnownAssembly::GRCm39
+        }
+    }
+}
+
+/// Command line names for `NamingScheme`
+#[derive(Clone, Copy, ValueEnum)]
+enum NamingArg {
+    Ucsc,
+    Ensembl,
+    Refseq
+}
+
+impl From<NamingArg> for NamingScheme {
+    fn from(value: NamingArg) -> Self {
+        match value {
+            NamingArg::Ucsc => NamingScheme::Ucsc,
+            NamingArg::Ensembl => NamingScheme::Ensembl,
+            NamingArg::Refseq => NamingScheme::RefSeq
+        }
+    }
+}
+
+/// Loads a reference, memory-mapping a single uncompressed file so that only the contigs used are read
+fn load_reference(fasta_fn: &Path) -> Result<ReferenceGenome, Box<dyn std::error::Error>> {
+    let backend = if fasta_fn.is_file() && fasta_fn.extension().unwrap_or_default() != "gz" {
+        Backend::Mmap
+    } else {
+        Backend::InMemory
+    };
+    ReferenceGenomeBuilder::new(fasta_fn).backend(backend).build()
+}
+
+/// Opens the output file, or stdout if none was given
+fn open_output(output: &Option<PathBuf>) -> Result<Box<dyn Write>, Box<dyn std::error::Error>> {
+    match output {
+        Some(path) => Ok(Box::new(BufWriter::new(std::fs::File::create(path)?))),
+        None => Ok(Box::new(BufWriter::new(std::io::stdout().lock())))
+    }
+}
+
+/// A region as (contig, 0-based start, 0-based exclusive end)
+type Region = (String, usize, usize);
+
+/// Parses a samtools-style region into the contig and 0-based half-open coordinates.
+/// A missing end extends to the end of the contig, represented as `usize::MAX`.
+/// # Errors
+/// * if the coordinates are not positive integers or the start is after the end
+fn parse_region(region: &str) -> Result<Region, Box<dyn std::error::Error>> {
+    let (contig, range) = match region.rsplit_once(':') {
+        Some((contig, range)) => (contig, range),
+        None => return Ok((region.to_string(), 0, usize::MAX))
+    };
+    let range = range.replace(',', "");
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (start.parse::<usize>()?, end.parse::<usize>()?),
+        None => (range.parse::<usize>()?, usize::MAX)
+    };
+    if start == 0 || start > end {
+        bail!("Invalid region {region:?}, expected 1-based coordinates with start <= end");
+    }
+    Ok((contig.to_string(), start - 1, end))
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    match cli.command {
+        Command::Stats { fasta } => {
+            let reference_genome = load_reference(&fasta)?;
+            let mut writer = open_output(&None)?;
+            writeln!(writer, "contig\tlength\tgc\tn")?;
+            let (mut total_length, mut total_gc, mut total_n) = (0, 0, 0);
+            for contig in reference_genome.contig_keys().iter() {
+                let sequence = reference_genome.get_full_chromosome_shared(contig);
+                let gc = sequence.iter().filter(|&&c| matches!(c, b'G' | b'C' | b'g' | b'c')).count();
+                let n = sequence.iter().filter(|&&c| matches!(c, b'N' | b'n')).count();
+                writeln!(writer, "{}\t{}\t{:.4}\t{}", contig, sequence.len(), gc_fraction(gc, sequence.len() - n), n)?;
+                total_length += sequence.len();
+                total_gc += gc;
+                total_n += n;
+            }
+            writeln!(writer, "total\t{}\t{:.4}\t{}", total_length, gc_fraction(total_gc, total_length - total_n), total_n)?;
+            writer.flush()?;
+        },
+        Command::Extract { fasta, regions, output } => {
+            let reference_genome = load_reference(&fasta)?;
+            let mut writer = open_output(&output)?;
+            for region in regions.iter() {
+                // like samtools, a full contig name takes priority over parsing coordinates, e.g. for "HLA-A*01:01"
+                let (contig, start, end) = match reference_genome.contig_length(region) {
+                    Some(_) => (region.clone(), 0, usize::MAX),
+                    None => parse_region(region)?
+                };
+                let Some(length) = reference_genome.contig_length(&contig) else {
+                    bail!("{}", reference_genome.missing_contig_message(&contig));
+                };
+                let end = end.min(length);
+                if start >= end {
+                    bail!("Region {region:?} is outside of contig {contig:?} with length {length}");
+                }
+                let sequence = reference_genome.get_slice_shared(&contig, start, end);
+                write_fasta_record(&mut writer, region, &sequence, DEFAULT_LINE_WIDTH)?;
+            }
+            writer.flush()?;
+        },
+        Command::Digest { fasta } => {
+            let reference_genome = load_reference(&fasta)?;
+            let mut writer = open_output(&None)?;
+            writeln!(writer, "contig\tlength\tmd5")?;
+            for contig in reference_genome.contig_keys().iter() {
+                let Some(digests) = reference_genome.contig_digests(contig) else {
+                    bail!("Failed to load contig {:?}", contig);
+                };
+                writeln!(writer, "{}\t{}\t{}", contig, reference_genome.contig_length(contig).unwrap(), digests.md5)?;
+            }
+            writer.flush()?;
+        },
+        Command::Fingerprint { fasta, min_gap_length } => {
+            let fingerprint = fingerprint_fasta(&fasta, min_gap_length)?;
+            let mut writer = open_output(&None)?;
+            writeln!(writer, "# seqcol digest: {}", fingerprint.collection.digest)?;
+            writeln!(writer, "contig\tlength\tmd5\tsha512t24u\tn_count\tgaps\tgap_bases\tlargest_gap")?;
+            for contig in fingerprint.contigs.iter() {
+                writeln!(writer, "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}", contig.name, contig.length, contig.digests.md5, contig.digests.sha512t24u,
+                    contig.n_count, contig.gap_count, contig.gap_bases, contig.largest_gap)?;
+            }
+            writer.flush()?;
+        },
+        Command::Subset { fasta, contigs, output } => {
+            let reference_genome = load_reference(&fasta)?;
+            for contig in contigs.iter() {
+                if reference_genome.contig_length(contig).is_none() {
+                    bail!("{}", reference_genome.missing_contig_message(contig));
+                }
+            }
+            let subset = reference_genome.subset(|c| contigs.iter().any(|k| k == c));
+            subset.write_fasta(open_output(&output)?, DEFAULT_LINE_WIDTH)?;
+        },
+        Command::Mask { fasta, bed, soft, output } => {
+            let reference_genome = load_reference(&fasta)?;
+            let records = read_bed_file(&bed)?;
+            // group the intervals once, rather than filtering them for every contig
+            let mut intervals: HashMap<&str, Vec<&BedRecord>> = Default::default();
+            for record in records.iter() {
+                intervals.entry(record.contig.as_str()).or_default().push(record);
+            }
+            let mut writer = open_output(&output)?;
+            for contig in reference_genome.contig_keys().iter() {
+                let mut sequence = reference_genome.get_full_chromosome_shared(contig).to_vec();
+                for record in intervals.get(contig.as_str()).into_iter().flatten() {
+                    let end = record.end.min(sequence.len());
+                    let masked = &mut sequence[record.start.min(end)..end];
+                    if soft {
+                        masked.make_ascii_lowercase();
+                    } else {
+                        masked.fill(b'N');
+                    }
+                }
+                write_fasta_record(&mut writer, contig, &sequence, DEFAULT_LINE_WIDTH)?;
+            }
+            writer.flush()?;
+        },
+        Command::Convert { fasta, assembly, naming, output } => {
+            let mut reference_genome = load_reference(&fasta)?;
+            reference_genome.convert_naming(assembly.into(), naming.into())?;
+            reference_genome.write_fasta(open_output(&output)?, DEFAULT_LINE_WIDTH)?;
+        }
+    };
+    Ok(())
+}
+
+/// The fraction of G/C bases among the non-N bases, or 0 for an all-N sequence
+fn gc_fraction(gc: usize, called: usize) -> f64 {
+    if called == 0 { 0.0 } else { gc as f64 / called as f64 }
+}
+
+fn main() {
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_region() {
+        assert_eq!(parse_region("chr1").unwrap(), ("chr1".to_string(), 0, usize::MAX));
+        assert_eq!(parse_region("chr1:1,001-2,000").unwrap(), ("chr1".to_string(), 1000, 2000));
+        assert_eq!(parse_region("chr1:5").unwrap(), ("chr1".to_string(), 4, usize::MAX));
+        assert_eq!(parse_region("HLA-A*01:01:1-3").unwrap(), ("HLA-A*01:01".to_string(), 0, 3));
+        assert!(parse_region("chr1:0-5").is_err());
+        assert!(parse_region("chr1:6-5").is_err());
+        assert!(parse_region("chr1:a-b").is_err());
+    }
+
+    #[test]
+    fn test_cli_parses() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+}
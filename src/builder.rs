@@ -0,0 +1,1089 @@
+use crate::alphabet::Alphabet;
+use crate::cache::ContigCache;
+#[cfg(feature = "gzip")]
+use crate::contig_store::encode_compressed;
+use crate::contig_store::{encode_packed, lazy_encoded_contig, ContigEncoder, ContigStore, EncodeFn};
+use crate::digest::Md5Manifest;
+use crate::direct::UncachedFile;
+use crate::mapped::index_mapped_fasta;
+#[cfg(feature = "gzip")]
+use crate::metrics::TimedReader;
+use crate::metrics::{CountingReader, LoadCounters};
+use crate::multi_file::expand_fasta_paths;
+use crate::parser::{read_records_preserving, FastaReader, Parser};
+use crate::reference_genome::{ContigSequence, ReferenceGenome};
+use crate::warnings::{check_symbols, Warning, WarningChannel};
+use crate::writer::RecordFormat;
+use bytes::Bytes;
+#[cfg(feature = "gzip")]
+use flate2::bufread::MultiGzDecoder;
+use log::debug;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use simple_error::{bail, SimpleError};
+use std::io::{BufRead, BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Controls where contig sequences are stored after loading
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// The full FASTA is decoded into memory during the load
+    #[default]
+    InMemory,
+    /// The full FASTA is decoded during the load and stored with 4 bits per symbol, about half the memory of `InMemory`.
+    /// Content is kept exactly, including lower-case runs, but only the 16 symbols `=ACMGRSVTWYHKDBN` are supported (in either case).
+    /// Each contig is unpacked the first time it is accessed; use `lru_cache(...)` to bound the memory of unpacked contigs.
+    /// The `memory_limit(...)` option does not apply.
+    Packed,
+    /// The full FASTA is decoded during the load and each contig is stored DEFLATE-compressed, typically a quarter of the memory
+    /// of `InMemory` for assembled genomes. Content is kept exactly and any symbols are supported. Each contig is decompressed
+    /// the first time it is accessed; use `lru_cache(...)` to bound the memory of decompressed contigs.
+    /// The `memory_limit(...)` option does not apply. Requires the `gzip` feature.
+    #[cfg(feature = "gzip")]
+    Compressed,
+    /// The FASTA is memory-mapped and each contig is decoded into memory the first time it is accessed.
+    /// This makes loading nearly instant and only uses memory for the contigs that are actually used.
+    /// Gzip-compressed files are not supported, and the file must not be modified while the genome is in use.
+    Mmap,
+    /// The FASTA is opened with htslib's faidx and each contig is read into memory the first time it is accessed.
+    /// This supports bgzip-compressed files, and the `.fai`/`.gzi` indices are built if they do not exist.
+    /// Alphabet validation is not supported since it would require reading every contig. Requires the `htslib` feature.
+    #[cfg(feature = "htslib")]
+    Faidx
+}
+
+impl Backend {
+    /// Returns true if the backend decodes the full FASTA during the load, rather than reading contigs from the file on access
+    fn decodes_during_load(&self) -> bool {
+        match self {
+            Backend::InMemory | Backend::Packed => true,
+            #[cfg(feature = "gzip")]
+            Backend::Compressed => true,
+            Backend::Mmap => false,
+            #[cfg(feature = "htslib")]
+            Backend::Faidx => false
+        }
+    }
+}
+
+/// Controls what happens when the projected in-memory size of a genome exceeds the limit from `memory_limit(...)`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MemoryLimitPolicy {
+    /// Loading fails with an error
+    #[default]
+    Error,
+    /// Loading switches to `Backend::Mmap`, which only decodes contigs when they are accessed.
+    /// Gzip-compressed files cannot be memory-mapped, so they still fail with an error.
+    FallbackToMmap
+}
+
+/// Controls what happens when a record ID appears more than once in the input
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DuplicatePolicy {
+    /// Loading fails with an error
+    #[default]
+    Error,
+    /// Repeated IDs are renamed with numeric suffixes in load order (`name`, `name_2`, `name_3`, ...), skipping any suffix
+    /// that is already a record ID. The renames are reported by `ReferenceGenome::duplicate_renames()`.
+    Rename
+}
+
+/// A filter on contig names, returning true for contigs that should be loaded
+pub type ContigFilter = Box<dyn Fn(&str) -> bool>;
+
+/// Builder for loading a `ReferenceGenome` with non-default options
+/// # Examples
+/// ```
+/// use rust_lib_reference_genome::alphabet::Alphabet;
+/// use rust_lib_reference_genome::builder::{Backend, ReferenceGenomeBuilder};
+/// use std::path::PathBuf;
+///
+/// let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+///     .uppercase(false)
+///     .contig_filter(|c| c == "chr2")
+///     .validate(Alphabet::Dna)
+///     .backend(Backend::Mmap)
+///     .build()
+///     .unwrap();
+/// assert_eq!(reference_genome.get_full_chromosome("chr2"), b"AccATGTA");
+/// ```
+pub struct ReferenceGenomeBuilder {
+    /// The FASTA filename
+    fasta_fn: PathBuf,
+    /// If true, sequences are upper-cased
+    uppercase: bool,
+    /// Optional filter on contig names, only matching contigs are loaded
+    contig_filter: Option<ContigFilter>,
+    /// The alphabet that sequences are validated against
+    alphabet: Alphabet,
+    /// The storage backend
+    backend: Backend,
+    /// The FASTA parser for the in-memory backend
+    parser: Parser,
+    /// Optional LRU cache limits for lazy backends as (max contigs, max bytes)
+    lru_cache: Option<(usize, usize)>,
+    /// Optional limit on the sequence bytes loaded by the in-memory backend
+    memory_limit: Option<(usize, MemoryLimitPolicy)>,
+    /// FASTA content to load instead of reading `fasta_fn`
+    data: Option<Bytes>,
+    /// If true, record descriptions and line widths are captured for `write_fasta_preserved(...)`
+    preserve_format: bool,
+    /// If true, FASTA files are read without filling the page cache
+    bypass_page_cache: bool,
+    /// If true, FASTA files are read ahead of parsing through io_uring
+    #[cfg(feature = "io-uring")]
+    io_uring: bool,
+    /// Optional expected digests that the loaded contigs are verified against
+    expected_md5: Option<Md5Manifest>,
+    /// Counters for the `load_metrics()` of the genome being built
+    counters: Arc<LoadCounters>,
+    /// How repeated record IDs are handled
+    duplicate_policy: DuplicatePolicy,
+    /// Optional custom encoding for contigs decoded during the load
+    contig_encoder: Option<ContigEncoder>,
+    /// Receives the load warnings, and is handed over to the genome being built
+    warnings: WarningChannel
+}
+
+impl ReferenceGenomeBuilder {
+    /// Creates a builder with the default options, which matches `ReferenceGenome::from_fasta(...)`.
+    /// A reference split across multiple files can be loaded from a directory, which loads every FASTA file it contains,
+    /// or from a pattern with `*`/`?`/`[...]` wildcards in the file name (e.g. `genome/chr*.fa.gz`).
+    /// Files are loaded in natural file name order (e.g. "chr2.fa" before "chr10.fa"), and contigs keep their order within each file.
+    /// # Arguments
+    /// * `fasta_fn` - the FASTA filename, directory, or wildcard pattern; gzip is allowed for the in-memory backend
+    pub fn new(fasta_fn: &Path) -> Self {
+        Self {
+            fasta_fn: fasta_fn.to_path_buf(),
+            uppercase: true,
+            contig_filter: None,
+            alphabet: Alphabet::Any,
+            backend: Backend::InMemory,
+            parser: Parser::Native,
+            lru_cache: None,
+            memory_limit: None,
+            data: None,
+            preserve_format: false,
+            bypass_page_cache: false,
+            #[cfg(feature = "io-uring")]
+            io_uring: false,
+            expected_md5: None,
+            counters: Default::default(),
+            duplicate_policy: DuplicatePolicy::Error,
+            contig_encoder: None,
+            warnings: Default::default()
+        }
+    }
+
+    /// Creates a builder that loads FASTA content from memory instead of a file, such as a download in a browser.
+    /// Gzip-compressed content is detected from its magic bytes and decompressed with a pure-Rust decoder.
+    /// Only `Backend::InMemory` is supported, and the genome's `filename()` is empty.
+    /// # Arguments
+    /// * `data` - the FASTA content
+    pub fn from_bytes(data: impl Into<Bytes>) -> Self {
+        let mut builder = Self::new(Path::new(""));
+        builder.data = Some(data.into());
+        builder
+    }
+
+    /// Sets whether sequences are upper-cased, default is true
+    pub fn uppercase(mut self, uppercase: bool) -> Self {
+        self.uppercase = uppercase;
+        self
+    }
+
+    /// Sets a filter on contig names such that only contigs where the filter returns true are loaded
+    pub fn contig_filter<F>(mut self, contig_filter: F) -> Self where F: Fn(&str) -> bool + 'static {
+        self.contig_filter = Some(Box::new(contig_filter));
+        self
+    }
+
+    /// Sets the alphabet that sequences are validated against during the load, default is `Alphabet::Any`
+    pub fn validate(mut self, alphabet: Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// Sets the storage backend, default is `Backend::InMemory`
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Stores each contig with a custom encoding (see `ContigStore`) instead of plain ASCII, such as a 2-bit or reference-based encoding.
+    /// The encoder receives every contig as it is loaded, after upper-casing (if enabled), and the contig is decoded on first access.
+    /// Requires `Backend::InMemory`, which then behaves like a lazy backend, so `lru_cache(...)` can be used.
+    /// # Arguments
+    /// * `encoder` - encodes a contig given its name and sequence, e.g. `encode_packed`; an error fails the load
+    pub fn contig_store<F>(mut self, encoder: F) -> Self where F: Fn(&str, Vec<u8>) -> Result<Box<dyn ContigStore>, SimpleError> + 'static {
+        self.contig_encoder = Some(Box::new(encoder));
+        self
+    }
+
+    /// Sets the FASTA parser used by the in-memory backend, default is `Parser::Native`
+    pub fn parser(mut self, parser: Parser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Enables a least-recently-used cache for lazy backends (e.g. `Backend::Mmap`), bounding the memory used by decoded contigs.
+    /// Only the shared accessors (`get_full_chromosome_shared(...)` and `get_slice_shared(...)`) go through the cache, so they
+    /// are the ones to use with it. The borrowing accessors (e.g. `get_slice(...)`) must keep the contig in memory for the
+    /// lifetime of the genome, which the cache cannot evict; each contig kept this way raises a `Warning::CacheBypassed`.
+    /// # Arguments
+    /// * `max_contigs` - the maximum number of decoded contigs to keep, at least 1
+    /// * `max_bytes` - the maximum total bytes of decoded contigs to keep; the most recent contig is always kept
+    pub fn lru_cache(mut self, max_contigs: usize, max_bytes: usize) -> Self {
+        self.lru_cache = Some((max_contigs, max_bytes));
+        self
+    }
+
+    /// Limits the sequence bytes that `Backend::InMemory` may load, so an oversized genome fails early instead of exhausting memory.
+    /// Before loading, the size is projected from the `.fai` index next to the FASTA if present, otherwise from the size of an uncompressed file.
+    /// If no projection is possible (e.g. a filtered or gzip-compressed file without a `.fai`), loading stops with an error once the limit is crossed.
+    /// Lazy backends do not load sequences up front, so the limit does not apply to them; see `lru_cache(...)` instead.
+    /// # Arguments
+    /// * `max_bytes` - the maximum total bytes of loaded sequence
+    /// * `policy` - what to do if the projected size is over the limit
+    pub fn memory_limit(mut self, max_bytes: usize, policy: MemoryLimitPolicy) -> Self {
+        self.memory_limit = Some((max_bytes, policy));
+        self
+    }
+
+    /// Preserves the original formatting, such that `ReferenceGenome::write_fasta_preserved(...)` reproduces the loaded FASTA
+    /// byte for byte and `write_fai_preserved(...)` matches its `.fai` index, default is false.
+    /// Record descriptions and line widths are kept (see `ReferenceGenome::record_format(...)`) and contig order is unchanged.
+    /// Enabling this also disables upper-casing. Loading fails if a record cannot be reproduced, such as one with irregular
+    /// line lengths, blank lines, or Windows line endings. Requires `Backend::InMemory` and `Parser::Native`.
+    pub fn preserve_format(mut self, preserve_format: bool) -> Self {
+        self.preserve_format = preserve_format;
+        if preserve_format {
+            self.uppercase = false;
+        }
+        self
+    }
+
+    /// Reads the FASTA without filling the page cache, default is false. This suits very large references that are read once
+    /// into memory on shared nodes, where a buffered read would evict the rest of the pipeline's working set.
+    /// On Linux the file is read with `O_DIRECT`, or dropped from the page cache as it is read where the file system does not support it;
+    /// other platforms read normally. Only backends that decode during the load (`InMemory`, `Packed`, `Compressed`) support this,
+    /// and `MemoryLimitPolicy::FallbackToMmap` does not fall back, since mapped reads go through the page cache.
+    pub fn bypass_page_cache(mut self, bypass_page_cache: bool) -> Self {
+        self.bypass_page_cache = bypass_page_cache;
+        self
+    }
+
+    /// Reads the FASTA through io_uring, default is false. Several large reads are kept in flight ahead of the parser, which
+    /// shortens the load of multi-gigabyte uncompressed references on fast storage such as NVMe.
+    /// On Linux kernels without io_uring (before 5.1, or where it is blocked), and on other platforms, the file is read normally.
+    /// Only backends that decode during the load (`InMemory`, `Packed`, `Compressed`) support this, and it cannot be
+    /// combined with `bypass_page_cache(true)`. Requires the `io-uring` feature.
+    #[cfg(feature = "io-uring")]
+    pub fn io_uring(mut self, io_uring: bool) -> Self {
+        self.io_uring = io_uring;
+        self
+    }
+
+    /// Verifies the loaded contigs against expected MD5 digests (e.g. from `Md5Manifest::from_dict(...)`), failing the load on any mismatch.
+    /// Manifest contigs that are excluded by `contig_filter(...)` are not required; otherwise see `ReferenceGenome::verify_md5(...)`.
+    /// With a lazy backend, every contig is read once to compute its digest.
+    pub fn verify_md5(mut self, manifest: Md5Manifest) -> Self {
+        self.expected_md5 = Some(manifest);
+        self
+    }
+
+    /// Sets how record IDs that appear more than once are handled, default is `DuplicatePolicy::Error`.
+    /// Duplicates are detected after `contig_filter(...)`, across all files of a multi-file reference.
+    pub fn duplicate_policy(mut self, duplicate_policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = duplicate_policy;
+        self
+    }
+
+    /// Projects the bytes of sequence that the in-memory backend would load, or `None` if it cannot be determined up front.
+    /// The `.fai` index gives exact lengths; the size of an uncompressed file is an upper bound.
+    fn projected_size(&self, fasta_fn: &Path) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+        let mut fai_fn = fasta_fn.as_os_str().to_os_string();
+        fai_fn.push(".fai");
+        let fai_fn = PathBuf::from(fai_fn);
+        if fai_fn.exists() {
+            let mut total = 0;
+            for line in BufReader::new(std::fs::File::open(&fai_fn)?).lines() {
+                let line = line?;
+                let mut fields = line.split('\t');
+                let (name, length) = match (fields.next(), fields.next()) {
+                    (Some(name), Some(length)) => (name, length),
+                    _ => bail!("Failed to parse {:?}, expected tab-separated name and length: {:?}", fai_fn, line)
+                };
+                if self.contig_filter.as_ref().map(|f| f(name)).unwrap_or(true) {
+                    total += length.parse::<usize>()?;
+                }
+            }
+            Ok(Some(total))
+        } else if !is_gzip(fasta_fn) && self.contig_filter.is_none() {
+            Ok(Some(std::fs::metadata(fasta_fn)?.len() as usize))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Opens the FASTA file, decompressing it if it has a gzip extension
+    fn open_reader(&self, fasta_fn: &Path) -> Result<FastaReader, Box<dyn std::error::Error>> {
+        #[cfg(feature = "io-uring")]
+        if self.io_uring {
+            let reader: FastaReader = Box::new(CountingReader::new(crate::uring::open(fasta_fn)?, self.counters.clone()));
+            return decompress(reader, is_gzip(fasta_fn), &self.counters);
+        }
+        let reader: FastaReader = if self.bypass_page_cache {
+            Box::new(CountingReader::new(UncachedFile::open(fasta_fn)?, self.counters.clone()))
+        } else {
+            let fasta_file: std::fs::File = std::fs::File::open(fasta_fn)?;
+            Box::new(CountingReader::new(BufReader::new(fasta_file), self.counters.clone()))
+        };
+        decompress(reader, is_gzip(fasta_fn), &self.counters)
+    }
+
+    /// Loads the reference genome with the configured options
+    /// # Errors
+    /// * any file reading and/or record reading errors
+    /// * if a sequence fails alphabet validation
+    /// * if a contig name is present more than once
+    /// * if `Backend::Mmap` is used with a gzip-compressed file
+    /// * if `Backend::Faidx` is used with alphabet validation
+    /// * if an LRU cache is requested with `Backend::InMemory` and no `contig_store(...)` encoding
+    /// * if `contig_store(...)` is used with a backend other than `Backend::InMemory`
+    /// * if the genome is over the memory limit and the policy cannot fall back to `Backend::Mmap`
+    /// * if content from `from_bytes(...)` is used with a lazy backend
+    /// * if `preserve_format(true)` is combined with upper-casing, a lazy backend, a parser other than `Parser::Native`, or `DuplicatePolicy::Rename`
+    /// * if `bypass_page_cache(true)` is used with a lazy backend
+    /// * if `io_uring(true)` is used with a lazy backend or with `bypass_page_cache(true)`
+    /// * if the contigs do not match the digests from `verify_md5(...)`
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "load", skip_all, err, fields(path = ?self.fasta_fn, backend = ?self.backend)))]
+    pub fn build(mut self) -> Result<ReferenceGenome, Box<dyn std::error::Error>> {
+        let start = Instant::now();
+        if self.lru_cache.is_some() && self.backend == Backend::InMemory && self.contig_encoder.is_none() {
+            bail!("An LRU cache requires a lazy backend, but the InMemory backend was selected");
+        }
+        if self.contig_encoder.is_some() && self.backend != Backend::InMemory {
+            bail!("A custom contig store requires the InMemory backend, but the {:?} backend was selected", self.backend);
+        }
+        if self.bypass_page_cache && !self.backend.decodes_during_load() {
+            bail!("Bypassing the page cache requires a backend that decodes during the load, such as InMemory, but the {:?} backend was selected", self.backend);
+        }
+        #[cfg(feature = "io-uring")]
+        if self.io_uring {
+            if !self.backend.decodes_during_load() {
+                bail!("Reading with io_uring requires a backend that decodes during the load, such as InMemory, but the {:?} backend was selected", self.backend);
+            }
+            if self.bypass_page_cache {
+                bail!("Reading with io_uring is incompatible with bypassing the page cache");
+            }
+        }
+        if self.preserve_format {
+            if self.uppercase {
+                bail!("Preserving the format is incompatible with upper-casing sequences");
+            }
+            if self.backend != Backend::InMemory || self.parser != Parser::Native {
+                bail!("Preserving the format requires the InMemory backend and Native parser, but {:?} and {:?} were selected", self.backend, self.parser);
+            }
+            if self.duplicate_policy == DuplicatePolicy::Rename {
+                bail!("Preserving the format is incompatible with renaming duplicate record IDs");
+            }
+        }
+        if let Some(data) = self.data.take() {
+            return self.build_from_bytes(data, start);
+        }
+        let fasta_fns = expand_fasta_paths(&self.fasta_fn)?;
+        let any_gzip = fasta_fns.iter().any(|f| is_gzip(f));
+
+        let max_bytes = match self.memory_limit {
+            Some((max_bytes, policy)) if self.backend == Backend::InMemory => {
+                let mut projected = Some(0);
+                for fasta_fn in fasta_fns.iter() {
+                    projected = match (projected, self.projected_size(fasta_fn)?) {
+                        (Some(total), Some(size)) => Some(total + size),
+                        _ => None
+                    };
+                }
+                if let Some(projected) = projected {
+                    if projected > max_bytes {
+                        if policy == MemoryLimitPolicy::FallbackToMmap && !any_gzip && !self.preserve_format && !self.bypass_page_cache {
+                            self.warnings.emit(Warning::MmapFallback { projected_bytes: projected, max_bytes });
+                            self.backend = Backend::Mmap;
+                        } else {
+                            bail!("Projected size of {:?} is {} bytes, over the memory limit of {} bytes", self.fasta_fn, projected, max_bytes);
+                        }
+                    }
+                }
+                max_bytes
+            },
+            _ => usize::MAX
+        };
+
+        let mut contigs: Vec<(String, ContigSequence)> = vec![];
+        let mut loaded_bytes: usize = 0;
+        let mut record_formats: HashMap<String, RecordFormat> = Default::default();
+        for fasta_fn in fasta_fns.iter() {
+            contigs.extend(self.load_file(fasta_fn, max_bytes, &mut loaded_bytes, &mut record_formats)?);
+        }
+        debug!("Finished loading {} contigs.", contigs.len());
+
+        let mut contigs = match self.lru_cache {
+            Some((max_contigs, max_bytes)) => {
+                let cache = Arc::new(ContigCache::new(max_contigs, max_bytes));
+                contigs.into_iter()
+                    .map(|(seq_id, sequence)| match sequence {
+                        ContigSequence::Lazy(contig) => (seq_id, ContigSequence::Lazy(contig.with_cache(cache.clone()))),
+                        loaded => (seq_id, loaded)
+                    })
+                    .collect()
+            },
+            None => contigs
+        };
+
+        let renames = self.apply_duplicate_policy(&mut contigs);
+        let mut reference_genome = ReferenceGenome::from_contigs(self.fasta_fn.clone(), contigs)?;
+        reference_genome.set_record_formats(record_formats);
+        reference_genome.set_duplicate_renames(renames);
+        reference_genome.set_warning_channel(self.warnings.clone());
+        self.check_md5(&reference_genome)?;
+        reference_genome.set_load_metrics(self.counters.finish(start, &reference_genome));
+        Ok(reference_genome)
+    }
+
+    /// Sets a callback that receives each load warning as it is raised, and later warnings from the genome, see `ReferenceGenome::warnings()`.
+    /// A handler that needs to fail the load can record the warning and the caller can check it after `build()`.
+    pub fn on_warning<F>(mut self, handler: F) -> Self where F: Fn(&Warning) + Send + Sync + 'static {
+        self.warnings.set_handler(Arc::new(handler));
+        self
+    }
+
+    /// Renames repeated record IDs if the policy is `DuplicatePolicy::Rename`, otherwise leaves them to fail when the genome is assembled
+    /// # Returns
+    /// * (original ID, new name) for each renamed record, in load order
+    fn apply_duplicate_policy(&self, contigs: &mut [(String, ContigSequence)]) -> Vec<(String, String)> {
+        if self.duplicate_policy != DuplicatePolicy::Rename {
+            return vec![];
+        }
+        let mut used: HashSet<String> = contigs.iter().map(|(seq_id, _)| seq_id.clone()).collect();
+        let mut seen: HashSet<String> = Default::default();
+        let mut next_suffix: HashMap<String, usize> = Default::default();
+        let mut renames: Vec<(String, String)> = vec![];
+        for (seq_id, _) in contigs.iter_mut() {
+            if seen.insert(seq_id.clone()) {
+                continue;
+            }
+            let suffix = next_suffix.entry(seq_id.clone()).or_insert(2);
+            let mut new_name = format!("{seq_id}_{suffix}");
+            while used.contains(&new_name) {
+                *suffix += 1;
+                new_name = format!("{seq_id}_{suffix}");
+            }
+            *suffix += 1;
+            self.warnings.emit(Warning::DuplicateRenamed { original: seq_id.clone(), renamed: new_name.clone() });
+            used.insert(new_name.clone());
+            renames.push((std::mem::replace(seq_id, new_name), seq_id.clone()));
+        }
+        renames
+    }
+
+    /// Verifies a loaded genome against the digests from `verify_md5(...)`, if any
+    fn check_md5(&self, reference_genome: &ReferenceGenome) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(manifest) = self.expected_md5.as_ref() {
+            let mut manifest = manifest.clone();
+            if let Some(contig_filter) = self.contig_filter.as_ref() {
+                manifest.retain(contig_filter);
+            }
+            reference_genome.verify_md5(&manifest)?;
+            debug!("Verified the MD5 digests of {} contigs.", manifest.len());
+        }
+        Ok(())
+    }
+
+    /// Loads the reference genome from FASTA content in memory, see `from_bytes(...)`
+    fn build_from_bytes(self, data: Bytes, start: Instant) -> Result<ReferenceGenome, Box<dyn std::error::Error>> {
+        if !self.backend.decodes_during_load() {
+            bail!("Loading from bytes requires a backend that decodes during the load, such as InMemory, but the {:?} backend was selected", self.backend);
+        }
+        let is_gzip = data.starts_with(&[0x1f, 0x8b]);
+        let max_bytes = match self.memory_limit {
+            Some((max_bytes, _)) => {
+                // uncompressed content is an upper bound on the sequence bytes
+                if !is_gzip && self.contig_filter.is_none() && data.len() > max_bytes {
+                    bail!("Projected size of the FASTA content is {} bytes, over the memory limit of {} bytes", data.len(), max_bytes);
+                }
+                max_bytes
+            },
+            None => usize::MAX
+        };
+        let reader = CountingReader::new(Cursor::new(data), self.counters.clone());
+        let reader = decompress(Box::new(reader), is_gzip, &self.counters)?;
+        let mut record_formats: HashMap<String, RecordFormat> = Default::default();
+        let mut contigs = self.load_records(reader, max_bytes, &mut 0, &mut record_formats)?;
+        debug!("Finished loading {} contigs.", contigs.len());
+        let renames = self.apply_duplicate_policy(&mut contigs);
+        let mut reference_genome = ReferenceGenome::from_contigs(PathBuf::new(), contigs)?;
+        reference_genome.set_record_formats(record_formats);
+        reference_genome.set_duplicate_renames(renames);
+        reference_genome.set_warning_channel(self.warnings.clone());
+        self.check_md5(&reference_genome)?;
+        reference_genome.set_load_metrics(self.counters.finish(start, &reference_genome));
+        Ok(reference_genome)
+    }
+
+    /// The encoding of contigs that are decoded during the load, or `None` to keep them as plain ASCII
+    fn encoder(&self) -> Option<&EncodeFn> {
+        match (self.contig_encoder.as_ref(), self.backend) {
+            (Some(encoder), _) => Some(encoder.as_ref()),
+            (None, Backend::Packed) => Some(&encode_packed),
+            #[cfg(feature = "gzip")]
+            (None, Backend::Compressed) => Some(&encode_compressed),
+            _ => None
+        }
+    }
+
+    /// Parses FASTA records into memory with the configured parser and options
+    /// # Arguments
+    /// * `reader` - the decompressed FASTA content
+    /// * `max_bytes` - the memory limit
+    /// * `loaded_bytes` - the bytes loaded so far, which is updated with the bytes loaded from this reader
+    /// * `record_formats` - receives the format of each loaded record if `preserve_format(true)` is set
+    fn load_records(&self, reader: FastaReader, max_bytes: usize, loaded_bytes: &mut usize, record_formats: &mut HashMap<String, RecordFormat>) -> Result<Vec<(String, ContigSequence)>, Box<dyn std::error::Error>> {
+        let is_loaded = |seq_id: &str| self.contig_filter.as_ref().map(|f| f(seq_id)).unwrap_or(true);
+        let encoder = self.encoder();
+        let mut contigs = vec![];
+        let mut add_record = |seq_id: String, mut sequence: Vec<u8>| -> Result<(), Box<dyn std::error::Error>> {
+            *loaded_bytes += sequence.len();
+            if *loaded_bytes > max_bytes {
+                bail!("Loading contig \"{}\" exceeded the memory limit of {} bytes", seq_id, max_bytes);
+            }
+            self.alphabet.validate(&seq_id, &sequence)?;
+            if self.alphabet == Alphabet::Any {
+                if let Some(warning) = check_symbols(&seq_id, &sequence) {
+                    self.warnings.emit(warning);
+                }
+            }
+            if self.uppercase {
+                sequence.make_ascii_uppercase();
+            }
+            if let Some(encode) = encoder {
+                let buffer_bytes = sequence.capacity();
+                let contig = lazy_encoded_contig(&seq_id, encode(&seq_id, sequence)?);
+                self.counters.add_record(buffer_bytes, contig.heap_bytes());
+                contigs.push((seq_id, ContigSequence::Lazy(contig)));
+            } else {
+                self.counters.add_record(sequence.capacity(), sequence.len());
+                // parsers grow the buffer as they go, so release the unused capacity
+                sequence.shrink_to_fit();
+                contigs.push((seq_id, ContigSequence::Loaded(Bytes::from(sequence))));
+            }
+            Ok(())
+        };
+        if self.preserve_format {
+            read_records_preserving(reader, |seq_id, format, sequence| {
+                if is_loaded(&seq_id) {
+                    record_formats.insert(seq_id.clone(), format);
+                    add_record(seq_id, sequence)?;
+                }
+                Ok(())
+            })?;
+        } else {
+            self.parser.read_records(reader, |seq_id, sequence| {
+                if is_loaded(&seq_id) {
+                    add_record(seq_id, sequence)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(contigs)
+    }
+
+    /// Loads the contigs from a single FASTA file with the configured backend
+    /// # Arguments
+    /// * `fasta_fn` - the FASTA filename
+    /// * `max_bytes` - the memory limit for the in-memory backend
+    /// * `loaded_bytes` - the bytes loaded so far, which is updated with the bytes loaded from this file
+    /// * `record_formats` - receives the format of each loaded record if `preserve_format(true)` is set
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = ?fasta_fn)))]
+    fn load_file(&self, fasta_fn: &Path, max_bytes: usize, loaded_bytes: &mut usize, record_formats: &mut HashMap<String, RecordFormat>) -> Result<Vec<(String, ContigSequence)>, Box<dyn std::error::Error>> {
+        debug!("Loading {:?} with {:?} backend...", fasta_fn, self.backend);
+        let contig_filter = self.contig_filter.as_deref();
+        let contigs: Vec<(String, ContigSequence)> = match self.backend {
+            Backend::InMemory | Backend::Packed => self.load_records(self.open_reader(fasta_fn)?, max_bytes, loaded_bytes, record_formats)?,
+            #[cfg(feature = "gzip")]
+            Backend::Compressed => self.load_records(self.open_reader(fasta_fn)?, max_bytes, loaded_bytes, record_formats)?,
+            Backend::Mmap => {
+                if is_gzip(fasta_fn) {
+                    bail!("The Mmap backend does not support gzip-compressed files: {:?}", fasta_fn);
+                }
+                // indexing scans the whole file
+                self.counters.add_bytes_read(std::fs::metadata(fasta_fn)?.len());
+                index_mapped_fasta(fasta_fn, self.uppercase, contig_filter, self.alphabet)?
+                    .into_iter()
+                    .map(|(seq_id, contig)| (seq_id, ContigSequence::Lazy(contig)))
+                    .collect()
+            },
+            #[cfg(feature = "htslib")]
+            Backend::Faidx => {
+                if self.alphabet != Alphabet::Any {
+                    bail!("The Faidx backend does not support alphabet validation");
+                }
+                crate::htslib::index_faidx_fasta(fasta_fn, self.uppercase, contig_filter)?
+                    .into_iter()
+                    .map(|(seq_id, contig)| (seq_id, ContigSequence::Lazy(contig)))
+                    .collect()
+            }
+        };
+        Ok(contigs)
+    }
+}
+
+/// Wraps a reader with a gzip decoder if needed, timing the decoder with the load counters
+pub(crate) fn decompress(reader: FastaReader, is_gzip: bool, counters: &Arc<LoadCounters>) -> Result<FastaReader, Box<dyn std::error::Error>> {
+    if is_gzip {
+        #[cfg(feature = "gzip")] {
+            debug!("Detected gzip input, loading reference with MultiGzDecoder...");
+            Ok(Box::new(BufReader::new(TimedReader::new(MultiGzDecoder::new(reader), counters.clone()))))
+        }
+        #[cfg(not(feature = "gzip"))] {
+            let _ = counters;
+            bail!("Loading gzip-compressed data requires the \"gzip\" feature");
+        }
+    } else {
+        debug!("Loading reference as plain-text...");
+        Ok(reader)
+    }
+}
+
+/// Returns true if a filename has a gzip extension
+pub(crate) fn is_gzip(fasta_fn: &Path) -> bool {
+    fasta_fn.extension().unwrap_or_default() == "gz"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_options() {
+        for backend in [Backend::InMemory, Backend::Mmap] {
+            let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+                .backend(backend)
+                .build()
+                .unwrap();
+            assert_eq!(reference_genome.contig_keys(), &["chr1".to_string(), "chr2".to_string()]);
+            assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGTACGT");
+            assert_eq!(reference_genome.get_slice("chr2", 2, 6), b"CATG");
+
+            let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+                .uppercase(false)
+                .contig_filter(|c| c != "chr2")
+                .backend(backend)
+                .build()
+                .unwrap();
+            assert_eq!(reference_genome.contig_keys(), &["chr1".to_string()]);
+            assert_eq!(reference_genome.get_full_chromosome("chr1"), b"acgtACGT");
+        }
+    }
+
+    #[test]
+    fn test_builder_bypass_page_cache() {
+        let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .bypass_page_cache(true)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["chr1".to_string(), "chr2".to_string()]);
+        assert_eq!(reference_genome.get_full_chromosome("chr2"), b"ACCATGTA");
+        assert_eq!(reference_genome.load_metrics().unwrap().bytes_read, std::fs::metadata("./test_data/test_reference.fa").unwrap().len());
+
+        // compressed files are decompressed after the uncached read
+        #[cfg(feature = "gzip")]
+        {
+            let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa.gz"))
+                .bypass_page_cache(true)
+                .build()
+                .unwrap();
+            assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGTACGT");
+        }
+
+        assert!(ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .backend(Backend::Mmap)
+            .bypass_page_cache(true)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "io-uring")]
+    fn test_builder_io_uring() {
+        let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .io_uring(true)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["chr1".to_string(), "chr2".to_string()]);
+        assert_eq!(reference_genome.get_full_chromosome("chr2"), b"ACCATGTA");
+        assert_eq!(reference_genome.load_metrics().unwrap().bytes_read, std::fs::metadata("./test_data/test_reference.fa").unwrap().len());
+
+        #[cfg(feature = "gzip")]
+        {
+            let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa.gz"))
+                .io_uring(true)
+                .build()
+                .unwrap();
+            assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGTACGT");
+        }
+
+        assert!(ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .backend(Backend::Mmap)
+            .io_uring(true)
+            .build()
+            .is_err());
+        assert!(ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .bypass_page_cache(true)
+            .io_uring(true)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "htslib")]
+    fn test_builder_faidx() {
+        let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .backend(Backend::Faidx)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["chr1".to_string(), "chr2".to_string()]);
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGTACGT");
+        assert_eq!(reference_genome.get_slice("chr2", 2, 6), b"CATG");
+
+        let result = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .backend(Backend::Faidx)
+            .validate(Alphabet::Dna)
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_builder_lru_cache() {
+        let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .backend(Backend::Mmap)
+            .lru_cache(1, 1000)
+            .build()
+            .unwrap();
+
+        let chr1 = reference_genome.get_full_chromosome_shared("chr1");
+        assert_eq!(reference_genome.memory_usage().sequence_bytes(), 8);
+        // loading chr2 evicts chr1 from the cache, but our handle is still valid
+        assert_eq!(&reference_genome.get_slice_shared("chr2", 0, 4)[..], b"ACCA");
+        let usage = reference_genome.memory_usage();
+        assert_eq!(usage.contigs[0].sequence_bytes, 0);
+        assert_eq!(usage.contigs[1].sequence_bytes, 8);
+        assert_eq!(&chr1[..], b"ACGTACGT");
+
+        // borrowed access keeps the contig permanently, with a warning the first time
+        assert_eq!(reference_genome.warning_count(), 0);
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGTACGT");
+        assert_eq!(reference_genome.get_slice("chr1", 0, 4), b"ACGT");
+        assert_eq!(reference_genome.warnings(), vec![Warning::CacheBypassed { contig: "chr1".to_string(), bytes: 8 }]);
+        reference_genome.get_full_chromosome_shared("chr2");
+        assert_eq!(reference_genome.memory_usage().sequence_bytes(), 16);
+
+        let result = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .lru_cache(1, 1000)
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_builder_unload() {
+        let mut reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .backend(Backend::Mmap)
+            .build()
+            .unwrap();
+
+        // lengths are available without loading
+        assert_eq!(reference_genome.contig_length("chr1"), Some(8));
+        assert_eq!(reference_genome.memory_usage().sequence_bytes(), 0);
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGTACGT");
+        assert_eq!(reference_genome.memory_usage().sequence_bytes(), 8);
+
+        // lazy contigs are reloaded from the file after unloading
+        reference_genome.unload_contig("chr1").unwrap();
+        assert_eq!(reference_genome.memory_usage().sequence_bytes(), 0);
+        assert_eq!(reference_genome.contig_length("chr1"), Some(8));
+        assert_eq!(reference_genome.get("chr1"), Some(&b"ACGTACGT"[..]));
+    }
+
+    #[test]
+    fn test_builder_memory_limit() {
+        let fasta_fn = PathBuf::from("./test_data/test_reference.fa");
+
+        // projected from the .fai, respecting the contig filter
+        assert!(ReferenceGenomeBuilder::new(&fasta_fn)
+            .memory_limit(15, MemoryLimitPolicy::Error)
+            .build()
+            .is_err());
+        let reference_genome = ReferenceGenomeBuilder::new(&fasta_fn)
+            .memory_limit(8, MemoryLimitPolicy::Error)
+            .contig_filter(|c| c == "chr1")
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.memory_usage().sequence_bytes(), 8);
+
+        // falling back to mmap loads nothing up front
+        let reference_genome = ReferenceGenomeBuilder::new(&fasta_fn)
+            .memory_limit(15, MemoryLimitPolicy::FallbackToMmap)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.memory_usage().sequence_bytes(), 0);
+        assert_eq!(reference_genome.get_full_chromosome("chr2"), b"ACCATGTA");
+
+        // without a .fai, a filtered load stops once the limit is crossed
+        let iupac_fn = PathBuf::from("./test_data/test_iupac.fa");
+        let result = ReferenceGenomeBuilder::new(&iupac_fn)
+            .memory_limit(8, MemoryLimitPolicy::FallbackToMmap)
+            .contig_filter(|_| true)
+            .build();
+        assert!(result.err().unwrap().to_string().contains("exceeded the memory limit"));
+        assert!(ReferenceGenomeBuilder::new(&iupac_fn)
+            .memory_limit(9, MemoryLimitPolicy::Error)
+            .contig_filter(|_| true)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn test_builder_multi_file() {
+        let reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/split")).unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["chr2", "chr10", "chrM", "chrM_alt"]);
+        assert_eq!(reference_genome.get_full_chromosome("chr10"), b"TTTT");
+        assert_eq!(reference_genome.filename(), PathBuf::from("./test_data/split"));
+
+        let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/split/chr*.fa"))
+            .backend(Backend::Mmap)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["chr2", "chrM", "chrM_alt"]);
+        assert_eq!(reference_genome.get_full_chromosome("chrM"), b"GGCC");
+
+        // mmap cannot load the gzip file in the directory
+        assert!(ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/split"))
+            .backend(Backend::Mmap)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_builder_from_bytes() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nacgt\nAC\n>chr2\nGG\n").unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["chr1", "chr2"]);
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGTAC");
+        assert_eq!(reference_genome.filename(), Path::new(""));
+
+        #[cfg(feature = "gzip")] {
+            let gzipped = std::fs::read("./test_data/test_reference.fa.gz").unwrap();
+            let reference_genome = ReferenceGenomeBuilder::from_bytes(gzipped)
+                .contig_filter(|c| c == "chr2")
+                .build()
+                .unwrap();
+            assert_eq!(reference_genome.get_full_chromosome("chr2"), b"ACCATGTA");
+        }
+
+        assert!(ReferenceGenomeBuilder::from_bytes(&b">chr1\nACGT\n"[..])
+            .backend(Backend::Mmap)
+            .build()
+            .is_err());
+        assert!(ReferenceGenomeBuilder::from_bytes(&b">chr1\nACGT\n"[..])
+            .memory_limit(4, MemoryLimitPolicy::Error)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_builder_parsers() {
+        for parser in Parser::available() {
+            let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+                .parser(parser)
+                .build()
+                .unwrap();
+            assert_eq!(reference_genome.contig_keys(), &["chr1".to_string(), "chr2".to_string()]);
+            assert_eq!(reference_genome.get_full_chromosome("chr2"), b"ACCATGTA");
+        }
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        for backend in [Backend::InMemory, Backend::Mmap] {
+            let result = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_iupac.fa"))
+                .validate(Alphabet::Dna)
+                .backend(backend)
+                .build();
+            assert_eq!(
+                result.err().unwrap().to_string(),
+                "Contig \"iupac\" contains invalid character 'r' at position 5 for alphabet Dna"
+            );
+
+            let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_iupac.fa"))
+                .validate(Alphabet::Iupac)
+                .backend(backend)
+                .build()
+                .unwrap();
+            assert_eq!(reference_genome.get_full_chromosome("iupac"), b"ACGTNRYKM");
+        }
+    }
+
+    #[test]
+    fn test_builder_preserve_format() {
+        let fasta_fn = PathBuf::from("./test_data/test_reference.fa");
+        let reference_genome = ReferenceGenomeBuilder::new(&fasta_fn).preserve_format(true).build().unwrap();
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"acgtACGT");
+        assert_eq!(reference_genome.record_format("chr1").unwrap().line_width, 4);
+        assert!(ReferenceGenome::from_fasta(&fasta_fn).unwrap().record_format("chr1").is_none());
+
+        assert!(ReferenceGenomeBuilder::new(&fasta_fn).preserve_format(true).backend(Backend::Mmap).build().is_err());
+        assert!(ReferenceGenomeBuilder::new(&fasta_fn).preserve_format(true).uppercase(true).build().is_err());
+        let result = ReferenceGenomeBuilder::from_bytes(&b">chr1\nAC\nA\nC\n"[..]).preserve_format(true).build();
+        assert!(result.err().unwrap().to_string().contains("irregular line lengths"));
+    }
+
+    #[test]
+    fn test_builder_verify_md5() {
+        let fasta_fn = PathBuf::from("./test_data/test_reference.fa");
+        let manifest = Md5Manifest::from_md5sum(&PathBuf::from("./test_data/test_reference.md5")).unwrap();
+        for backend in [Backend::InMemory, Backend::Mmap] {
+            // lower-case input still matches, and filtered contigs are not required
+            ReferenceGenomeBuilder::new(&fasta_fn).backend(backend).uppercase(false).verify_md5(manifest.clone()).build().unwrap();
+            ReferenceGenomeBuilder::new(&fasta_fn).backend(backend).contig_filter(|c| c == "chr2").verify_md5(manifest.clone()).build().unwrap();
+        }
+
+        let result = ReferenceGenomeBuilder::from_bytes(&b">chr1\nACGTACGA\n>chr2\nACCATGTA\n"[..]).verify_md5(manifest).build();
+        assert!(result.err().unwrap().to_string().starts_with("MD5 mismatch for contig \"chr1\""));
+    }
+
+    #[test]
+    fn test_builder_packed() {
+        let iupac_fn = PathBuf::from("./test_data/test_iupac.fa");
+        let reference_genome = ReferenceGenomeBuilder::new(&iupac_fn)
+            .uppercase(false)
+            .backend(Backend::Packed)
+            .lru_cache(1, 100)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.get_full_chromosome_shared("iupac"), &b"ACGTNrykm"[..]);
+        assert_eq!(reference_genome.memory_usage().sequence_bytes(), 5 + 9 + std::mem::size_of::<std::ops::Range<usize>>());
+
+        let reference_genome = ReferenceGenomeBuilder::from_bytes(&b">chr1\nACGTACGT\n>chr2\n"[..]).backend(Backend::Packed).build().unwrap();
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGTACGT");
+        assert_eq!(reference_genome.get_full_chromosome("chr2"), b"");
+        let result = ReferenceGenomeBuilder::from_bytes(&b">chr1\nACGU\n"[..]).backend(Backend::Packed).build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_builder_contig_store() {
+        let fasta_fn = PathBuf::from("./test_data/test_reference.fa");
+        let reference_genome = ReferenceGenomeBuilder::new(&fasta_fn)
+            .contig_store(|seq_id, sequence| {
+                if seq_id == "chr2" {
+                    crate::contig_store::encode_ascii(seq_id, sequence)
+                } else {
+                    encode_packed(seq_id, sequence)
+                }
+            })
+            .lru_cache(1, 100)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.get_full_chromosome_shared("chr1"), &b"ACGTACGT"[..]);
+        assert_eq!(reference_genome.get_slice("chr2", 0, 4), b"ACCA");
+
+        let result = ReferenceGenomeBuilder::new(&fasta_fn)
+            .contig_store(|seq_id, _| bail!("Cannot encode {}", seq_id))
+            .build();
+        assert_eq!(result.err().unwrap().to_string(), "Cannot encode chr1");
+        let result = ReferenceGenomeBuilder::new(&fasta_fn).backend(Backend::Mmap).contig_store(encode_packed).build();
+        assert!(result.is_err());
+
+        #[cfg(feature = "gzip")] {
+            let reference_genome = ReferenceGenomeBuilder::from_bytes(&b">chr1\nACGTacgtNNNN\n"[..])
+                .uppercase(false)
+                .backend(Backend::Compressed)
+                .build()
+                .unwrap();
+            assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGTacgtNNNN");
+        }
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        // Mmap cannot handle compression regardless of the gzip feature
+        let result = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa.gz"))
+            .backend(Backend::Mmap)
+            .build();
+        assert!(result.is_err());
+
+        for backend in [Backend::InMemory, Backend::Mmap] {
+            let result = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_duplicate.fa"))
+                .backend(backend)
+                .build();
+            assert_eq!(
+                result.err().unwrap().to_string(),
+                "Contig key \"chr1\" is already in the reference genome"
+            );
+        }
+    }
+
+    #[test]
+    fn test_builder_warnings() {
+        let seen: Arc<std::sync::Mutex<Vec<String>>> = Default::default();
+        let handler_seen = seen.clone();
+        let reference_genome = ReferenceGenomeBuilder::from_bytes(&b">a\nAC-GT\n>a\nACGT\n"[..])
+            .duplicate_policy(DuplicatePolicy::Rename)
+            .on_warning(move |w| handler_seen.lock().unwrap().push(w.to_string()))
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.warnings(), vec![
+            Warning::SuspiciousSymbols { contig: "a".to_string(), symbol: b'-', position: 2, count: 1 },
+            Warning::DuplicateRenamed { original: "a".to_string(), renamed: "a_2".to_string() }
+        ]);
+
+        // reads keep reporting to the same handler and collection
+        assert_eq!(reference_genome.get_slice("a_2", 2, 10), b"GT");
+        assert_eq!(reference_genome.take_warnings().len(), 3);
+        assert!(reference_genome.warnings().is_empty());
+        assert_eq!(reference_genome.warning_count(), 3);
+        assert_eq!(seen.lock().unwrap()[2], "Received get_slice(\"a_2\", 2, 10), truncated to the contig length 4");
+
+        // clean records raise no warnings
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">a\nACGT\n").unwrap();
+        assert_eq!(reference_genome.warning_count(), 0);
+    }
+
+    #[test]
+    fn test_builder_duplicate_rename() {
+        for backend in [Backend::InMemory, Backend::Mmap] {
+            let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_duplicate.fa"))
+                .backend(backend)
+                .duplicate_policy(DuplicatePolicy::Rename)
+                .build()
+                .unwrap();
+            assert_eq!(reference_genome.contig_keys(), &["chr1".to_string(), "chr2".to_string(), "chr1_2".to_string()]);
+            assert_eq!(reference_genome.get_full_chromosome("chr1_2"), b"CCCC");
+            assert_eq!(reference_genome.duplicate_renames(), &[("chr1".to_string(), "chr1_2".to_string())]);
+        }
+
+        // suffixes skip names that are already taken, even by later records
+        let reference_genome = ReferenceGenomeBuilder::from_bytes(&b">a\nA\n>a\nC\n>a_2\nG\n>a\nT\n"[..])
+            .duplicate_policy(DuplicatePolicy::Rename)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["a".to_string(), "a_3".to_string(), "a_2".to_string(), "a_4".to_string()]);
+        assert!(ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap().duplicate_renames().is_empty());
+
+        let result = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_duplicate.fa"))
+            .preserve_format(true)
+            .duplicate_policy(DuplicatePolicy::Rename)
+            .build();
+        assert!(result.is_err());
+    }
+}
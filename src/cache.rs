@@ -0,0 +1,85 @@
+use bytes::Bytes;
+use std::sync::Mutex;
+
+/// Identifies a contig in the cache by its loader and index within that loader
+pub(crate) type CacheKey = (usize, usize);
+
+/// Least-recently-used cache of loaded contig sequences, bounded by both a contig count and a byte budget
+pub(crate) struct ContigCache {
+    /// Maximum number of contigs to keep
+    max_contigs: usize,
+    /// Maximum total sequence bytes to keep
+    max_bytes: usize,
+    /// Cached entries, least recently used first, and their total size
+    state: Mutex<(Vec<(CacheKey, Bytes)>, usize)>
+}
+
+impl ContigCache {
+    /// Creates an empty cache; at least one contig is always kept, even if it alone exceeds the byte budget
+    pub(crate) fn new(max_contigs: usize, max_bytes: usize) -> Self {
+        Self {
+            max_contigs: max_contigs.max(1),
+            max_bytes,
+            state: Mutex::new((vec![], 0))
+        }
+    }
+
+    /// Retrieves a cached sequence and marks it as most recently used
+    pub(crate) fn get(&self, key: CacheKey) -> Option<Bytes> {
+        let mut state = self.state.lock().unwrap();
+        let position = state.0.iter().position(|(k, _)| *k == key)?;
+        let entry = state.0.remove(position);
+        let sequence = entry.1.clone();
+        state.0.push(entry);
+        Some(sequence)
+    }
+
+    /// Adds a sequence as the most recently used entry, evicting the least recently used entries until within limits
+    pub(crate) fn insert(&self, key: CacheKey, sequence: Bytes) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(position) = state.0.iter().position(|(k, _)| *k == key) {
+            let (_, previous) = state.0.remove(position);
+            state.1 -= previous.len();
+        }
+        state.1 += sequence.len();
+        state.0.push((key, sequence));
+        while state.0.len() > 1 && (state.0.len() > self.max_contigs || state.1 > self.max_bytes) {
+            // callers holding a shared handle keep the evicted sequence alive until they drop it
+            let (_, evicted) = state.0.remove(0);
+            state.1 -= evicted.len();
+        }
+    }
+
+    /// The size of a cached sequence, or 0 if it is not cached
+    pub(crate) fn cached_bytes(&self, key: CacheKey) -> usize {
+        let state = self.state.lock().unwrap();
+        state.0.iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, s)| s.len())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contig_cache() {
+        let cache = ContigCache::new(2, 10);
+        cache.insert((0, 0), Bytes::from_static(b"AAAA"));
+        cache.insert((0, 1), Bytes::from_static(b"CCCC"));
+        assert_eq!(cache.get((0, 0)), Some(Bytes::from_static(b"AAAA")));
+
+        // (0, 1) is now least recently used and exceeds the contig limit
+        cache.insert((0, 2), Bytes::from_static(b"GG"));
+        assert_eq!(cache.get((0, 1)), None);
+        assert_eq!(cache.cached_bytes((0, 0)), 4);
+
+        // the byte budget evicts everything but the newest entry
+        cache.insert((1, 0), Bytes::from_static(b"TTTTTTTTTTTT"));
+        assert_eq!(cache.get((0, 0)), None);
+        assert_eq!(cache.get((0, 2)), None);
+        assert_eq!(cache.cached_bytes((1, 0)), 12);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::completeness::find_gaps;
+use crate::contig_class::classify_contig_name;
+use crate::reference_genome::ReferenceGenome;
+use std::error::Error;
+use std::io::Write;
+
+/// Quotes a value as an SQL string literal
+fn sql_text(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Quotes an optional value as an SQL string literal, or `NULL`
+fn sql_optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| sql_text(&v.to_string())).unwrap_or_else(|| "NULL".to_string())
+}
+
+impl ReferenceGenome {
+    /// Writes the contig table as an SQL dump, for ad-hoc querying and LIMS integration.
+    /// This is a text script of SQL statements, not a database file; import it with e.g. `sqlite3 catalog.db < catalog.sql`.
+    /// The script (re)creates two tables in one transaction:
+    /// * `contigs` - one row per contig in load order: `id` (the load index), `name`, `length`, `md5` and `sha512t24u` (of the
+    ///   upper-cased sequence), `class` (see `ContigClass::name()`), and `gap_count`, `gap_bases`, and `largest_gap` for runs of N
+    /// * `assembly` - one row with the assembly metadata (`species`, `assembly_name`, `source`, `release_date`, `taxonomy_id`), NULL where unset
+    ///
+    /// Digests come from the cache of `contig_digests(...)`; lazily loaded contigs are not kept in memory.
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// * `min_gap_length` - the shortest run of N counted as a gap
+    /// # Returns
+    /// * the number of contig rows written
+    /// # Errors
+    /// * if a contig was unloaded or fails to load
+    /// * any errors from the underlying writer
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn write_catalog_sql_dump<W: Write>(&self, mut writer: W, min_gap_length: usize) -> Result<usize, Box<dyn Error>> {
+        writeln!(writer, "BEGIN TRANSACTION;")?;
+        writeln!(writer, "DROP TABLE IF EXISTS contigs;")?;
+        writeln!(writer, "CREATE TABLE contigs (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE, length INTEGER NOT NULL, \
+            md5 TEXT NOT NULL, sha512t24u TEXT NOT NULL, class TEXT NOT NULL, gap_count INTEGER NOT NULL, gap_bases INTEGER NOT NULL, \
+            largest_gap INTEGER NOT NULL);")?;
+        for (id, contig) in self.contig_keys().iter().enumerate() {
+            let digests = self.try_contig_digests(contig)?;
+            let sequence = self.try_sequence_unkept(contig)?;
+            let gaps = find_gaps(&sequence, min_gap_length);
+            writeln!(writer, "INSERT INTO contigs VALUES ({}, {}, {}, '{}', '{}', '{}', {}, {}, {});",
+                id, sql_text(contig), sequence.len(), digests.md5, digests.sha512t24u,
+                classify_contig_name(contig).name(), gaps.len(), gaps.iter().map(|g| g.len()).sum::<usize>(),
+                gaps.iter().map(|g| g.len()).max().unwrap_or(0)
+            )?;
+        }
+
+        let metadata = self.assembly_metadata();
+        writeln!(writer, "DROP TABLE IF EXISTS assembly;")?;
+        writeln!(writer, "CREATE TABLE assembly (species TEXT, assembly_name TEXT, source TEXT, release_date TEXT, taxonomy_id INTEGER);")?;
+        writeln!(writer, "INSERT INTO assembly VALUES ({}, {}, {}, {}, {});",
+            sql_optional(metadata.species.as_ref()), sql_optional(metadata.assembly_name.as_ref()), sql_optional(metadata.source.as_ref()),
+            sql_optional(metadata.release_date.as_ref()), metadata.taxonomy_id.map(|t| t.to_string()).unwrap_or_else(|| "NULL".to_string())
+        )?;
+        writeln!(writer, "COMMIT;")?;
+        writer.flush()?;
+        Ok(self.contig_keys().len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::AssemblyMetadata;
+
+    #[test]
+    fn test_write_catalog_sql_dump() {
+        let mut reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGTNNNNACNN\n>it's_decoy\nACGT\n").unwrap();
+        reference_genome.set_assembly_metadata(AssemblyMetadata { species: Some("Homo sapiens".to_string()), taxonomy_id: Some(9606), ..Default::default() });
+        let mut sql: Vec<u8> = vec![];
+        assert_eq!(reference_genome.write_catalog_sql_dump(&mut sql, 1).unwrap(), 2);
+        let sql = String::from_utf8(sql).unwrap();
+        let lines: Vec<&str> = sql.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[4], "INSERT INTO contigs VALUES (1, 'it''s_decoy', 4, 'f1f8f4bf413b16ad135722aa4591043e', 'aKF498dAxcJAqme6QYQ7EZ07-fiw8Kw2', 'decoy', 0, 0, 0);");
+        assert!(lines[3].ends_with(", 'autosome', 2, 6, 4);"));
+        assert_eq!(lines[7], "INSERT INTO assembly VALUES ('Homo sapiens', NULL, NULL, NULL, 9606);");
+        assert_eq!(lines[8], "COMMIT;");
+
+        // gaps shorter than the minimum are not counted
+        let mut sql: Vec<u8> = vec![];
+        reference_genome.write_catalog_sql_dump(&mut sql, 3).unwrap();
+        assert!(String::from_utf8(sql).unwrap().contains(", 'autosome', 1, 4, 4);"));
+    }
+}
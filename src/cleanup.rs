@@ -0,0 +1,139 @@
+use crate::reference_genome::ReferenceGenome;
+use rustc_hash::FxHashSet as HashSet;
+use simple_error::SimpleError;
+
+/// Result of `ReferenceGenome::filter_min_length(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LengthFilterReport {
+    /// The minimum length that was applied
+    pub min_length: usize,
+    /// The number of contigs that were kept
+    pub kept: usize,
+    /// The (name, length) of each dropped contig, in load order
+    pub dropped: Vec<(String, usize)>
+}
+
+impl LengthFilterReport {
+    /// The total length of the dropped contigs
+    pub fn dropped_bases(&self) -> usize {
+        self.dropped.iter().map(|(_, length)| length).sum()
+    }
+}
+
+/// The N bases trimmed from the ends of a contig by `ReferenceGenome::trim_terminal_ns()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrimmedContig {
+    /// The contig name
+    pub contig: String,
+    /// The number of N bases removed from the start; original coordinates are this much larger than trimmed coordinates
+    pub leading: usize,
+    /// The number of N bases removed from the end
+    pub trailing: usize
+}
+
+/// Returns the lengths of the leading and trailing N runs of a sequence; an all-N sequence is counted entirely as leading
+fn terminal_n_runs(sequence: &[u8]) -> (usize, usize) {
+    let is_n = |c: &u8| c.eq_ignore_ascii_case(&b'N');
+    let leading = sequence.iter().take_while(|c| is_n(c)).count();
+    let trailing = sequence[leading..].iter().rev().take_while(|c| is_n(c)).count();
+    (leading, trailing)
+}
+
+impl ReferenceGenome {
+    /// Creates a new reference genome without the contigs shorter than a minimum length, such as the many tiny fragments of a draft assembly.
+    /// Sequences are shared rather than copied (see `subset(...)`), and lengths are checked without loading any sequence.
+    /// # Arguments
+    /// * `min_length` - the minimum contig length to keep, in bp
+    /// # Returns
+    /// * the filtered genome and a report of what was dropped
+    pub fn filter_min_length(&self, min_length: usize) -> (ReferenceGenome, LengthFilterReport) {
+        let mut report = LengthFilterReport {
+            min_length,
+            ..Default::default()
+        };
+        for contig in self.contig_keys().iter() {
+            let length = self.contig_length(contig).unwrap();
+            if length < min_length {
+                report.dropped.push((contig.clone(), length));
+            } else {
+                report.kept += 1;
+            }
+        }
+        let dropped: HashSet<&str> = report.dropped.iter().map(|(contig, _)| contig.as_str()).collect();
+        let filtered = self.subset(|contig| !dropped.contains(contig));
+        (filtered, report)
+    }
+
+    /// Removes the runs of N (or n) bases at the start and end of every contig, such as the padding of draft assemblies,
+    /// which would otherwise inflate length statistics. Contigs that are entirely N become empty.
+    /// Trimmed contigs are held in memory as described in `modify_contig(...)`; contigs without terminal Ns are left as they are.
+    /// # Returns
+    /// * the amount trimmed from each contig that changed, in load order
+    /// # Errors
+    /// * if a contig was unloaded or fails to load; contigs before it have already been trimmed in this case
+    pub fn trim_terminal_ns(&mut self) -> Result<Vec<TrimmedContig>, SimpleError> {
+        let mut trimmed: Vec<TrimmedContig> = vec![];
+        for contig in self.contig_keys().to_vec().iter() {
+            // check first, so lazily loaded contigs without any padding are not pulled into memory
+            let (leading, trailing) = terminal_n_runs(&self.try_sequence_unkept(contig)?);
+            if leading == 0 && trailing == 0 {
+                continue;
+            }
+            self.modify_contig(contig, |sequence| {
+                sequence.truncate(sequence.len() - trailing);
+                sequence.drain(..leading);
+            })?;
+            trimmed.push(TrimmedContig {
+                contig: contig.clone(),
+                leading,
+                trailing
+            });
+        }
+        Ok(trimmed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ReferenceGenomeBuilder;
+
+    #[test]
+    fn test_filter_min_length() {
+        let reference_genome = ReferenceGenomeBuilder::from_bytes(&b">chr1\nACGTACGT\n>frag1\nAC\n>chr2\nACGT\n>frag2\nA\n"[..])
+            .build()
+            .unwrap();
+        let (filtered, report) = reference_genome.filter_min_length(4);
+        assert_eq!(filtered.contig_keys(), &["chr1", "chr2"]);
+        assert_eq!(filtered.get_full_chromosome("chr2"), b"ACGT");
+        assert_eq!(report.kept, 2);
+        assert_eq!(report.dropped, vec![("frag1".to_string(), 2), ("frag2".to_string(), 1)]);
+        assert_eq!(report.dropped_bases(), 3);
+
+        let (unchanged, report) = reference_genome.filter_min_length(0);
+        assert_eq!(unchanged.contig_keys(), reference_genome.contig_keys());
+        assert!(report.dropped.is_empty());
+    }
+
+    #[test]
+    fn test_trim_terminal_ns() {
+        assert_eq!(terminal_n_runs(b"NNACGTnN"), (2, 2));
+        assert_eq!(terminal_n_runs(b"ACGNT"), (0, 0));
+        assert_eq!(terminal_n_runs(b"NNN"), (3, 0));
+        assert_eq!(terminal_n_runs(b""), (0, 0));
+
+        let mut reference_genome = ReferenceGenomeBuilder::from_bytes(&b">chr1\nNNACGTNNACN\n>chr2\nACGT\n>chr3\nnnnn\n"[..])
+            .uppercase(false)
+            .build()
+            .unwrap();
+        let trimmed = reference_genome.trim_terminal_ns().unwrap();
+        assert_eq!(trimmed, vec![
+            TrimmedContig { contig: "chr1".to_string(), leading: 2, trailing: 1 },
+            TrimmedContig { contig: "chr3".to_string(), leading: 4, trailing: 0 }
+        ]);
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGTNNAC");
+        assert_eq!(reference_genome.get_full_chromosome("chr2"), b"ACGT");
+        assert_eq!(reference_genome.contig_length("chr3"), Some(0));
+        assert!(reference_genome.trim_terminal_ns().unwrap().is_empty());
+    }
+}
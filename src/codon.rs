@@ -0,0 +1,315 @@
+use crate::alphabet::complement;
+use crate::builder::{decompress, is_gzip};
+use crate::coordinates::Strand;
+use crate::promoter::attribute;
+use crate::reference_genome::ReferenceGenome;
+use rustc_hash::FxHashMap as HashMap;
+use simple_error::{bail, SimpleError};
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// The amino acids of the standard genetic code, indexed by codon with the bases ordered T, C, A, G
+const STANDARD_CODE: &[u8; 64] = b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+
+/// Translates a codon with the standard genetic code, ignoring case and reading U as T
+/// # Arguments
+/// * `codon` - the three bases on the coding strand
+/// # Returns
+/// * the one-letter amino acid, `*` for a stop codon, or `X` if the codon is not 3 unambiguous bases
+pub fn translate_codon(codon: &[u8]) -> u8 {
+    if codon.len() != 3 {
+        return b'X';
+    }
+    let mut index = 0;
+    for base in codon.iter() {
+        let value = match base.to_ascii_uppercase() {
+            b'T' | b'U' => 0,
+            b'C' => 1,
+            b'A' => 2,
+            b'G' => 3,
+            _ => return b'X'
+        };
+        index = index * 4 + value;
+    }
+    STANDARD_CODE[index]
+}
+
+/// The coding segments of one transcript, from `read_cds_models(...)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CdsModel {
+    /// The transcript ID (`transcript_id` in GTF, `Parent` in GFF3)
+    pub transcript: String,
+    /// The contig name
+    pub contig: String,
+    /// The strand of the transcript
+    pub strand: Strand,
+    /// The CDS segments as 0-based, half-open intervals in transcript order, i.e. descending for the reverse strand
+    pub segments: Vec<(usize, usize)>,
+    /// The bases before the first complete codon, from the phase of the first segment; non-zero for 5' incomplete CDS
+    pub start_phase: usize
+}
+
+impl CdsModel {
+    /// The total coding length
+    pub fn cds_length(&self) -> usize {
+        self.segments.iter().map(|(start, end)| end - start).sum()
+    }
+
+    /// Converts a genomic position to its 0-based offset in the CDS, or `None` if it is not in a coding segment
+    pub fn cds_offset(&self, position: usize) -> Option<usize> {
+        let mut offset = 0;
+        for &(start, end) in self.segments.iter() {
+            if (start..end).contains(&position) {
+                return Some(offset + match self.strand {
+                    Strand::Forward => position - start,
+                    Strand::Reverse => end - 1 - position
+                });
+            }
+            offset += end - start;
+        }
+        None
+    }
+
+    /// Converts a 0-based CDS offset to its genomic position, or `None` if it is past the end of the CDS
+    pub fn genomic_position(&self, cds_offset: usize) -> Option<usize> {
+        let mut remaining = cds_offset;
+        for &(start, end) in self.segments.iter() {
+            if remaining < end - start {
+                return Some(match self.strand {
+                    Strand::Forward => start + remaining,
+                    Strand::Reverse => end - 1 - remaining
+                });
+            }
+            remaining -= end - start;
+        }
+        None
+    }
+}
+
+/// Reads the `CDS` features of a GFF3 or GTF annotation and groups them by transcript, using `transcript_id` for GTF
+/// and `Parent` for GFF3 (a CDS with several parents is added to each); other features are ignored.
+/// # Arguments
+/// * `reader` - the uncompressed annotation
+/// # Returns
+/// * the models in order of their first CDS line
+/// # Errors
+/// * any reading errors
+/// * if a CDS line has fewer than 9 columns, invalid coordinates, an unknown strand, or no transcript attribute
+/// * if the segments of a transcript are on different contigs or strands
+pub fn read_cds_models<R: BufRead>(reader: R) -> Result<Vec<CdsModel>, Box<dyn Error>> {
+    let mut models: Vec<CdsModel> = vec![];
+    let mut model_index: HashMap<String, usize> = Default::default();
+    // the phase of each segment, to pick the phase of the first one in transcript order
+    let mut phases: Vec<Vec<usize>> = vec![];
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.starts_with("##FASTA") {
+            break;
+        }
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let columns: Vec<&str> = line.trim_end_matches('\r').split('\t').collect();
+        if columns.get(2) != Some(&"CDS") {
+            continue;
+        }
+        if columns.len() < 9 {
+            bail!("Expected 9 columns on line {} of the annotation, found {}", line_index + 1, columns.len());
+        }
+        let (start, end) = match (columns[3].parse::<usize>(), columns[4].parse::<usize>()) {
+            (Ok(start), Ok(end)) if start > 0 && start <= end => (start - 1, end),
+            _ => bail!("Invalid CDS {}-{} on line {} of the annotation", columns[3], columns[4], line_index + 1)
+        };
+        let Some(strand) = columns[6].chars().next().filter(|_| columns[6].len() == 1).and_then(Strand::from_symbol) else {
+            bail!("Invalid CDS strand {:?} on line {} of the annotation", columns[6], line_index + 1);
+        };
+        let phase = columns[7].parse::<usize>().unwrap_or(0) % 3;
+        let Some(transcripts) = attribute(columns[8], "transcript_id").or_else(|| attribute(columns[8], "Parent")) else {
+            bail!("Missing transcript_id or Parent on line {} of the annotation", line_index + 1);
+        };
+        for transcript in transcripts.split(',') {
+            let index = *model_index.entry(transcript.to_string()).or_insert_with(|| {
+                models.push(CdsModel {
+                    transcript: transcript.to_string(),
+                    contig: columns[0].to_string(),
+                    strand,
+                    segments: vec![],
+                    start_phase: 0
+                });
+                phases.push(vec![]);
+                models.len() - 1
+            });
+            let model = &mut models[index];
+            if model.contig != columns[0] || model.strand != strand {
+                bail!("Transcript {:?} has CDS segments on different contigs or strands (line {})", transcript, line_index + 1);
+            }
+            model.segments.push((start, end));
+            phases[index].push(phase);
+        }
+    }
+    for (model, phases) in models.iter_mut().zip(phases) {
+        let mut order: Vec<usize> = (0..model.segments.len()).collect();
+        order.sort_by_key(|&i| model.segments[i].0);
+        if model.strand == Strand::Reverse {
+            order.reverse();
+        }
+        model.start_phase = phases[order[0]];
+        model.segments = order.iter().map(|&i| model.segments[i]).collect();
+    }
+    Ok(models)
+}
+
+/// Reads the CDS models of a GFF3 or GTF file, see `read_cds_models(...)`
+/// # Arguments
+/// * `annotation_fn` - the annotation filename; gzip compression is detected from a `.gz` extension
+/// # Errors
+/// * any file reading errors
+/// * see `read_cds_models(...)`
+pub fn read_cds_file(annotation_fn: &Path) -> Result<Vec<CdsModel>, Box<dyn Error>> {
+    let annotation_file = std::fs::File::open(annotation_fn)?;
+    let reader = decompress(Box::new(BufReader::new(annotation_file)), is_gzip(annotation_fn), &Arc::default())?;
+    read_cds_models(reader)
+}
+
+/// The reference codon containing a position, from `ReferenceGenome::codon_at(...)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodonLookup {
+    /// The transcript of the CDS model
+    pub transcript: String,
+    /// The 0-based codon number in the CDS, counting a leading partial codon as codon 0
+    pub codon_index: usize,
+    /// The position of the base within its codon (0, 1, or 2)
+    pub frame: usize,
+    /// The codon on the transcript strand, upper-cased; bases of a partial codon that are outside the CDS are N
+    pub codon: [u8; 3],
+    /// The 0-based genomic positions of the codon bases in codon order, or `None` for bases outside the CDS
+    pub positions: [Option<usize>; 3],
+    /// The reference amino acid, `*` for a stop codon, or `X` for a partial or ambiguous codon
+    pub amino_acid: u8
+}
+
+impl ReferenceGenome {
+    /// Finds the reference codon, frame, and amino acid at a genomic position in a CDS model, strand aware and across exon boundaries,
+    /// such as for lightweight variant-effect annotation; the codon is translated with the standard genetic code.
+    /// # Arguments
+    /// * `model` - the CDS model, e.g. from `read_cds_file(...)`
+    /// * `position` - the 0-based genomic position on the contig of the model
+    /// # Returns
+    /// * the codon, or `None` if the position is not in a coding segment of the model
+    /// # Errors
+    /// * if the contig is not in the reference genome or was unloaded
+    /// * if a segment of the codon is past the end of the contig
+    pub fn codon_at(&self, model: &CdsModel, position: usize) -> Result<Option<CodonLookup>, SimpleError> {
+        let Some(cds_offset) = model.cds_offset(position) else {
+            return Ok(None);
+        };
+        let sequence = self.position_query(&model.contig, position)?;
+        // the offset from the start of the first (possibly partial) codon
+        let shifted = cds_offset + (3 - model.start_phase) % 3;
+        let (codon_index, frame) = (shifted / 3, shifted % 3);
+        let mut codon = [b'N'; 3];
+        let mut positions = [None; 3];
+        for i in 0..3 {
+            let Some(offset) = (shifted - frame + i).checked_sub((3 - model.start_phase) % 3) else {
+                continue;
+            };
+            let Some(genomic) = model.genomic_position(offset) else {
+                continue;
+            };
+            let Some(&base) = sequence.get(genomic) else {
+                bail!("Codon position {} of transcript {:?} is past the end of contig \"{}\" ({} bp)", genomic, model.transcript, model.contig, sequence.len());
+            };
+            codon[i] = match model.strand {
+                Strand::Forward => base.to_ascii_uppercase(),
+                Strand::Reverse => complement(base.to_ascii_uppercase())
+            };
+            positions[i] = Some(genomic);
+        }
+        Ok(Some(CodonLookup {
+            transcript: model.transcript.clone(),
+            codon_index,
+            frame,
+            codon,
+            positions,
+            amino_acid: translate_codon(&codon)
+        }))
+    }
+
+    /// Finds the reference codon at a genomic position in every CDS model that covers it, see `codon_at(...)`
+    /// # Arguments
+    /// * `models` - the CDS models, e.g. from `read_cds_file(...)`
+    /// * `contig` - the contig name, matched exactly against the models
+    /// * `position` - the 0-based genomic position
+    /// # Errors
+    /// * see `codon_at(...)`
+    pub fn codons_at(&self, models: &[CdsModel], contig: &str, position: usize) -> Result<Vec<CodonLookup>, SimpleError> {
+        let mut codons: Vec<CodonLookup> = vec![];
+        for model in models.iter().filter(|m| m.contig == contig) {
+            codons.extend(self.codon_at(model, position)?);
+        }
+        Ok(codons)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate_codon() {
+        assert_eq!(translate_codon(b"ATG"), b'M');
+        assert_eq!(translate_codon(b"tgg"), b'W');
+        assert_eq!(translate_codon(b"UAA"), b'*');
+        assert_eq!(translate_codon(b"GGG"), b'G');
+        assert_eq!(translate_codon(b"ANG"), b'X');
+        assert_eq!(translate_codon(b"AT"), b'X');
+    }
+
+    #[test]
+    fn test_read_cds_models() {
+        let gtf = b"chr1\tsrc\tCDS\t11\t12\t.\t+\t1\ttranscript_id \"tx1\";\nchr1\tsrc\texon\t1\t20\t.\t+\t.\ttranscript_id \"tx1\";\n\
+            chr1\tsrc\tCDS\t2\t5\t.\t+\t0\ttranscript_id \"tx1\";\nchr1\tsrc\tCDS\t2\t5\t.\t-\t2\tParent=tx2,tx3\n";
+        let models = read_cds_models(&gtf[..]).unwrap();
+        assert_eq!(models.len(), 3);
+        assert_eq!(models[0].segments, vec![(1, 5), (10, 12)]);
+        assert_eq!((models[0].start_phase, models[0].cds_length()), (0, 6));
+        assert_eq!((models[2].transcript.as_str(), models[2].start_phase), ("tx3", 2));
+
+        assert_eq!(models[0].cds_offset(10), Some(4));
+        assert_eq!(models[0].cds_offset(6), None);
+        assert_eq!(models[0].genomic_position(5), Some(11));
+        assert_eq!(models[1].cds_offset(4), Some(0));
+
+        assert!(read_cds_models(&b"chr1\tsrc\tCDS\t2\t5\t.\t+\t0\tgene_id \"g1\";\n"[..]).is_err());
+        assert!(read_cds_models(&b"chr1\tsrc\tCDS\t2\t5\t.\t+\t0\tParent=tx1\nchr1\tsrc\tCDS\t8\t9\t.\t-\t0\tParent=tx1\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_codon_at() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nCATGGCaaaaTTAAC\n").unwrap();
+        // ATG GC|T TAA split by an intron at 6..10
+        let forward = CdsModel { transcript: "tx1".to_string(), contig: "chr1".to_string(), strand: Strand::Forward, segments: vec![(1, 6), (10, 14)], start_phase: 0 };
+        let codon = reference_genome.codon_at(&forward, 10).unwrap().unwrap();
+        assert_eq!((codon.codon_index, codon.frame, &codon.codon, codon.amino_acid), (1, 2, b"GCT", b'A'));
+        assert_eq!(codon.positions, [Some(4), Some(5), Some(10)]);
+        assert_eq!(reference_genome.codon_at(&forward, 13).unwrap().unwrap().amino_acid, b'*');
+        assert_eq!(reference_genome.codon_at(&forward, 7).unwrap(), None);
+
+        // the reverse strand of 1..7 (ATGGCa) is TGC CAT
+        let reverse = CdsModel { transcript: "tx2".to_string(), strand: Strand::Reverse, segments: vec![(1, 7)], ..forward.clone() };
+        let codon = reference_genome.codon_at(&reverse, 1).unwrap().unwrap();
+        assert_eq!((codon.codon_index, codon.frame, &codon.codon, codon.amino_acid), (1, 2, b"CAT", b'H'));
+
+        // a 5' incomplete CDS with phase 1 starts mid-codon
+        let partial = CdsModel { start_phase: 1, ..forward.clone() };
+        let codon = reference_genome.codon_at(&partial, 1).unwrap().unwrap();
+        assert_eq!((codon.codon_index, codon.frame, &codon.codon, codon.amino_acid), (0, 2, b"NNA", b'X'));
+        assert_eq!(reference_genome.codon_at(&partial, 2).unwrap().unwrap().codon, *b"TGG");
+
+        assert_eq!(reference_genome.codons_at(&[forward.clone(), reverse], "chr1", 4).unwrap().len(), 2);
+        let missing = CdsModel { contig: "chr2".to_string(), ..forward };
+        assert!(reference_genome.codon_at(&missing, 1).is_err());
+    }
+}
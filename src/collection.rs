@@ -0,0 +1,245 @@
+use crate::alphabet::complement;
+use crate::coordinates::Strand;
+use crate::genome_comparison::GenomeComparison;
+use crate::liftover::{LiftedPosition, Liftover};
+use crate::reference_genome::ReferenceGenome;
+use crate::registry::GenomeRegistry;
+use rustc_hash::FxHashMap as HashMap;
+use simple_error::{bail, SimpleError};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Several loaded genomes keyed by build name (e.g. "hg19" and "hg38"), for tools that handle inputs from different builds in one process.
+/// Files are loaded through the process-wide `GenomeRegistry`, so a file already held by another component (or another collection) is shared
+/// rather than loaded again. Cloning a collection is cheap and shares the genomes.
+/// # Examples
+/// ```
+/// use rust_lib_reference_genome::collection::ReferenceCollection;
+/// use std::path::Path;
+///
+/// let mut collection = ReferenceCollection::new();
+/// collection.load("test", Path::new("./test_data/test_reference.fa")).unwrap();
+/// assert_eq!(collection.find_build([("chr1", 8), ("chr2", 8)]), Some("test"));
+/// assert_eq!(collection.get("test").unwrap().get_slice("chr2", 0, 3), b"ACC");
+/// ```
+#[derive(Clone, Default)]
+pub struct ReferenceCollection {
+    /// Genomes by build name, in insertion order
+    genomes: Vec<(String, Arc<ReferenceGenome>)>,
+    /// Liftovers by (from build, to build)
+    liftovers: HashMap<(String, String), Arc<Liftover>>
+}
+
+impl ReferenceCollection {
+    /// Creates an empty collection
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Loads a genome through the process-wide `GenomeRegistry` and adds it under a build name
+    /// # Arguments
+    /// * `build` - the build name, e.g. "hg38"
+    /// * `fasta_fn` - the FASTA filename or directory
+    /// # Errors
+    /// * if `build` is already in the collection
+    /// * see `GenomeRegistry::get_or_load(...)`
+    pub fn load(&mut self, build: &str, fasta_fn: &Path) -> Result<Arc<ReferenceGenome>, Box<dyn std::error::Error>> {
+        if self.get(build).is_some() {
+            bail!("Build {:?} is already in the reference collection", build);
+        }
+        let genome = GenomeRegistry::global().get_or_load(fasta_fn)?;
+        self.genomes.push((build.to_string(), genome.clone()));
+        Ok(genome)
+    }
+
+    /// Adds an already loaded genome under a build name
+    /// # Arguments
+    /// * `build` - the build name, e.g. "hg38"
+    /// * `genome` - the genome, which may also be held elsewhere
+    /// # Errors
+    /// * if `build` is already in the collection
+    pub fn insert(&mut self, build: &str, genome: Arc<ReferenceGenome>) -> Result<(), SimpleError> {
+        if self.get(build).is_some() {
+            bail!("Build {:?} is already in the reference collection", build);
+        }
+        self.genomes.push((build.to_string(), genome));
+        Ok(())
+    }
+
+    /// Removes a genome and any liftovers from or to it
+    /// # Returns
+    /// * the removed genome, or `None` if `build` is not in the collection
+    pub fn remove(&mut self, build: &str) -> Option<Arc<ReferenceGenome>> {
+        let index = self.genomes.iter().position(|(b, _)| b == build)?;
+        self.liftovers.retain(|(from, to), _| from != build && to != build);
+        Some(self.genomes.remove(index).1)
+    }
+
+    /// The genome for a build, or `None` if it is not in the collection
+    pub fn get(&self, build: &str) -> Option<&Arc<ReferenceGenome>> {
+        self.genomes.iter().find(|(b, _)| b == build).map(|(_, genome)| genome)
+    }
+
+    /// The build names in insertion order
+    pub fn builds(&self) -> Vec<&str> {
+        self.genomes.iter().map(|(build, _)| build.as_str()).collect()
+    }
+
+    /// The number of genomes
+    pub fn len(&self) -> usize {
+        self.genomes.len()
+    }
+
+    /// Returns true if the collection has no genomes
+    pub fn is_empty(&self) -> bool {
+        self.genomes.is_empty()
+    }
+
+    /// Picks the build that an input was aligned or called against, from its contig names and lengths (e.g. a BAM or VCF header)
+    /// # Arguments
+    /// * `contigs` - the (name, length) pairs of the input
+    /// # Returns
+    /// * the build with the most contigs matching in both name and length, the earliest on ties, or `None` if no contig matches any build
+    pub fn find_build<'a>(&self, contigs: impl IntoIterator<Item = (&'a str, usize)>) -> Option<&str> {
+        let contigs: Vec<(&str, usize)> = contigs.into_iter().collect();
+        let mut best: Option<(&str, usize)> = None;
+        for (build, genome) in self.genomes.iter() {
+            let matched = contigs.iter()
+                .filter(|&&(name, length)| genome.contig_length(name) == Some(length))
+                .count();
+            if matched > best.map(|(_, m)| m).unwrap_or(0) {
+                best = Some((build, matched));
+            }
+        }
+        best.map(|(build, _)| build)
+    }
+
+    /// Registers a liftover between two builds in the collection
+    /// # Arguments
+    /// * `from` - the build that the chain converts from (the chain target)
+    /// * `to` - the build that the chain converts to (the chain query)
+    /// * `liftover` - the chains, e.g. from `Liftover::from_file(...)`
+    /// # Errors
+    /// * if either build is not in the collection
+    pub fn add_liftover(&mut self, from: &str, to: &str, liftover: Liftover) -> Result<(), SimpleError> {
+        for build in [from, to] {
+            if self.get(build).is_none() {
+                bail!("Build {:?} is not in the reference collection", build);
+            }
+        }
+        self.liftovers.insert((from.to_string(), to.to_string()), Arc::new(liftover));
+        Ok(())
+    }
+
+    /// Converts a position between builds with the liftover from `add_liftover(...)`
+    /// # Arguments
+    /// * `from` - the build of the position
+    /// * `to` - the build to convert to
+    /// * `contig` - the contig in `from`
+    /// * `position` - the 0-based position on `contig`
+    /// # Returns
+    /// * the converted position, or `None` if the position is not aligned between the builds
+    /// # Errors
+    /// * if no liftover is registered from `from` to `to`
+    pub fn lift_position(&self, from: &str, to: &str, contig: &str, position: usize) -> Result<Option<LiftedPosition>, SimpleError> {
+        let Some(liftover) = self.liftovers.get(&(from.to_string(), to.to_string())) else {
+            bail!("No liftover from {:?} to {:?} in the reference collection", from, to);
+        };
+        Ok(liftover.lift_position(contig, position))
+    }
+
+    /// Converts a position between builds and reads the base there, on the strand that the original forward strand maps to.
+    /// This checks reference alleles across builds, e.g. a base that differs between `from` and the returned base changed between builds.
+    /// # Arguments
+    /// * `from` - the build of the position
+    /// * `to` - the build to convert to
+    /// * `contig` - the contig in `from`
+    /// * `position` - the 0-based position on `contig`
+    /// # Returns
+    /// * the converted position and its base, or `None` if the position is not aligned or the lifted contig is not in `to`
+    /// # Errors
+    /// * if no liftover is registered from `from` to `to`
+    pub fn lift_base(&self, from: &str, to: &str, contig: &str, position: usize) -> Result<Option<(LiftedPosition, u8)>, SimpleError> {
+        let Some(lifted) = self.lift_position(from, to, contig, position)? else {
+            return Ok(None);
+        };
+        let genome = self.get(to).expect("liftovers are removed with their builds");
+        let Some(base) = genome.try_get_full_chromosome_shared(&lifted.contig).and_then(|s| s.get(lifted.position).copied()) else {
+            return Ok(None);
+        };
+        let base = match lifted.strand {
+            Strand::Forward => base,
+            Strand::Reverse => complement(base)
+        };
+        Ok(Some((lifted, base)))
+    }
+
+    /// Compares two builds in the collection, see `ReferenceGenome::compare(...)`
+    /// # Errors
+    /// * if either build is not in the collection
+    /// * if a contig of either build was unloaded or fails to load
+    pub fn compare(&self, first: &str, second: &str) -> Result<GenomeComparison, SimpleError> {
+        let (Some(first_genome), Some(second_genome)) = (self.get(first), self.get(second)) else {
+            bail!("Builds {:?} and {:?} must both be in the reference collection", first, second);
+        };
+        first_genome.compare(second_genome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn collection() -> ReferenceCollection {
+        let mut collection = ReferenceCollection::new();
+        collection.load("old", &PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        let new_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGTAACGT\n>chr2\nTACATGGT\n").unwrap();
+        collection.insert("new", Arc::new(new_genome)).unwrap();
+        collection
+    }
+
+    #[test]
+    fn test_collection() {
+        let mut collection = collection();
+        assert_eq!(collection.builds(), vec!["old", "new"]);
+        assert!(collection.insert("old", collection.get("new").unwrap().clone()).is_err());
+        assert!(collection.load("new", &PathBuf::from("./test_data/test_iupac.fa")).is_err());
+
+        // loads share the process-wide registry
+        let shared = ReferenceGenome::from_fasta_shared(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        assert!(Arc::ptr_eq(&shared, collection.get("old").unwrap()));
+
+        assert_eq!(collection.find_build([("chr1", 9), ("chr2", 8)]), Some("new"));
+        assert_eq!(collection.find_build([("chr1", 8)]), Some("old"));
+        assert_eq!(collection.find_build([("chr3", 8)]), None);
+
+        let comparison = collection.compare("old", "new").unwrap();
+        assert_eq!(comparison.length_mismatches.len(), 1);
+        assert!(collection.compare("old", "missing").is_err());
+
+        assert!(collection.remove("new").is_some());
+        assert_eq!((collection.len(), collection.remove("new").is_none()), (1, true));
+    }
+
+    #[test]
+    fn test_collection_liftover() {
+        let mut collection = collection();
+        // old chr1 gets a 1-base insertion after position 4 in new, and old chr2 is reverse complemented
+        let chain = b"chain 100 chr1 8 + 0 8 chr1 9 + 0 9 1\n4\t0\t1\n4\n\nchain 50 chr2 8 + 0 8 chr2 8 - 0 8 2\n8\n";
+        let liftover = Liftover::from_reader(&chain[..]).unwrap();
+        assert!(collection.add_liftover("old", "missing", liftover.clone()).is_err());
+        collection.add_liftover("old", "new", liftover).unwrap();
+
+        assert_eq!(collection.lift_position("old", "new", "chr1", 5).unwrap().unwrap().position, 6);
+        let (lifted, base) = collection.lift_base("old", "new", "chr1", 5).unwrap().unwrap();
+        assert_eq!((lifted.contig.as_str(), base), ("chr1", b'C'));
+        // old chr2 position 0 (A) is new chr2 position 7 on the reverse strand (T, complemented to A)
+        let (lifted, base) = collection.lift_base("old", "new", "chr2", 0).unwrap().unwrap();
+        assert_eq!((lifted.position, lifted.strand, base), (7, Strand::Reverse, b'A'));
+        assert!(collection.lift_position("new", "old", "chr1", 0).is_err());
+
+        collection.remove("new");
+        assert!(collection.lift_position("old", "new", "chr1", 5).is_err());
+    }
+}
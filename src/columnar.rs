@@ -0,0 +1,108 @@
+use crate::tracks::WindowStats;
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+use std::sync::Arc;
+
+#[cfg(feature = "parquet")]
+use parquet::arrow::ArrowWriter;
+#[cfg(feature = "parquet")]
+use parquet::basic::Compression;
+#[cfg(feature = "parquet")]
+use parquet::file::properties::WriterProperties;
+#[cfg(feature = "parquet")]
+use std::io::Write;
+
+/// The Arrow schema of a per-window statistics table: `contig`, `start`, `end`, `gc_fraction`, `n_fraction`,
+/// `masked_fraction`, and `complexity`, where `gc_fraction` and `complexity` are nullable
+pub fn window_stats_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("contig", DataType::Utf8, false),
+        Field::new("start", DataType::UInt64, false),
+        Field::new("end", DataType::UInt64, false),
+        Field::new("gc_fraction", DataType::Float64, true),
+        Field::new("n_fraction", DataType::Float64, false),
+        Field::new("masked_fraction", DataType::Float64, false),
+        Field::new("complexity", DataType::Float64, true)
+    ]))
+}
+
+/// Converts per-window statistics into an Arrow record batch with one row per window, see `window_stats_schema()`
+/// # Arguments
+/// * `stats` - the statistics, usually from `ReferenceGenome::window_stats(...)`
+/// # Errors
+/// * if the batch cannot be assembled
+pub fn window_stats_record_batch(stats: &[WindowStats]) -> Result<RecordBatch, ArrowError> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(stats.iter().map(|s| s.contig.as_str()))),
+        Arc::new(UInt64Array::from_iter_values(stats.iter().map(|s| s.start as u64))),
+        Arc::new(UInt64Array::from_iter_values(stats.iter().map(|s| s.end as u64))),
+        Arc::new(Float64Array::from_iter(stats.iter().map(|s| s.gc_fraction))),
+        Arc::new(Float64Array::from_iter_values(stats.iter().map(|s| s.n_fraction))),
+        Arc::new(Float64Array::from_iter_values(stats.iter().map(|s| s.masked_fraction))),
+        Arc::new(Float64Array::from_iter(stats.iter().map(|s| s.complexity)))
+    ];
+    RecordBatch::try_new(window_stats_schema(), columns)
+}
+
+/// Writes per-window statistics as a Snappy-compressed Parquet file, readable with `polars.read_parquet` or `pandas.read_parquet`
+/// # Arguments
+/// * `writer` - the output to write to
+/// * `stats` - the statistics, usually from `ReferenceGenome::window_stats(...)`
+/// # Errors
+/// * if the table cannot be encoded
+/// * any errors from the underlying writer
+#[cfg(feature = "parquet")]
+pub fn write_window_stats_parquet<W: Write + Send>(writer: W, stats: &[WindowStats]) -> Result<(), Box<dyn std::error::Error>> {
+    let batch = window_stats_record_batch(stats)?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut parquet_writer = ArrowWriter::try_new(writer, batch.schema(), Some(properties))?;
+    parquet_writer.write(&batch)?;
+    parquet_writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reference_genome::ReferenceGenome;
+    use arrow_array::Array;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_window_stats_record_batch() {
+        let reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        let stats = reference_genome.window_stats(10).unwrap();
+        let batch = window_stats_record_batch(&stats).unwrap();
+        assert_eq!(batch.num_rows(), stats.len());
+        assert_eq!(batch.schema(), window_stats_schema());
+
+        let contigs = batch.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(contigs.value(0), stats[0].contig);
+        let gc = batch.column(3).as_any().downcast_ref::<Float64Array>().unwrap();
+        for (index, window) in stats.iter().enumerate() {
+            assert_eq!(gc.is_null(index), window.gc_fraction.is_none());
+        }
+        assert_eq!(window_stats_record_batch(&[]).unwrap().num_rows(), 0);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_write_window_stats_parquet() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        let stats = reference_genome.window_stats(10).unwrap();
+        let mut output: Vec<u8> = vec![];
+        write_window_stats_parquet(&mut output, &stats).unwrap();
+        assert_eq!(&output[..4], b"PAR1");
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(output)).unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(|b| b.unwrap()).collect();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0], window_stats_record_batch(&stats).unwrap());
+    }
+}
@@ -0,0 +1,204 @@
+use crate::alphabet::reverse_complement;
+use crate::reference_genome::ReferenceGenome;
+use memchr::memmem;
+use simple_error::{bail, SimpleError};
+use std::io::Write;
+use std::ops::Range;
+
+/// Options for `ReferenceGenome::assess_completeness(...)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompletenessOptions {
+    /// The telomere repeat unit, matched in either orientation and case; default is the vertebrate `TTAGGG`
+    pub telomere_motif: Vec<u8>,
+    /// How far from each contig end to search for telomere repeats, default is 10,000 bases
+    pub search_length: usize,
+    /// The minimum number of consecutive motif copies that count as a telomere, default is 10
+    pub min_repeats: usize,
+    /// The minimum length of an `N` run that counts as a gap, default is 1
+    pub min_gap_length: usize
+}
+
+impl Default for CompletenessOptions {
+    fn default() -> Self {
+        Self {
+            telomere_motif: b"TTAGGG".to_vec(),
+            search_length: 10_000,
+            min_repeats: 10,
+            min_gap_length: 1
+        }
+    }
+}
+
+/// Completeness of a single contig
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContigCompleteness {
+    /// The contig name
+    pub contig: String,
+    /// The contig length
+    pub length: usize,
+    /// The number of gaps
+    pub gaps: usize,
+    /// The total bases in gaps
+    pub gap_bases: usize,
+    /// True if there is a telomere at the start of the contig
+    pub telomere_start: bool,
+    /// True if there is a telomere at the end of the contig
+    pub telomere_end: bool
+}
+
+impl ContigCompleteness {
+    /// Returns true if the contig is gapless with telomeres at both ends, i.e. telomere-to-telomere
+    pub fn is_t2t(&self) -> bool {
+        self.gaps == 0 && self.telomere_start && self.telomere_end
+    }
+}
+
+/// Completeness of every contig in a genome, from `ReferenceGenome::assess_completeness(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompletenessReport {
+    /// Per-contig results, in load order
+    pub contigs: Vec<ContigCompleteness>
+}
+
+impl CompletenessReport {
+    /// The number of telomere-to-telomere contigs
+    pub fn t2t_count(&self) -> usize {
+        self.contigs.iter().filter(|c| c.is_t2t()).count()
+    }
+
+    /// Writes the report as a tab-separated table with a header line and one row per contig:
+    /// `contig`, `length`, `gaps`, `gap_bases`, `telomere_start`, `telomere_end`, and `t2t`, with `yes`/`no` flags
+    /// # Errors
+    /// * any errors from the underlying writer
+    pub fn write_table<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let flag = |value: bool| if value { "yes" } else { "no" };
+        writeln!(writer, "contig\tlength\tgaps\tgap_bases\ttelomere_start\ttelomere_end\tt2t")?;
+        for contig in self.contigs.iter() {
+            writeln!(
+                writer, "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                contig.contig, contig.length, contig.gaps, contig.gap_bases,
+                flag(contig.telomere_start), flag(contig.telomere_end), flag(contig.is_t2t())
+            )?;
+        }
+        writer.flush()
+    }
+}
+
+/// Finds the runs of `N`/`n` in a sequence
+/// # Arguments
+/// * `sequence` - the ASCII sequence
+/// * `min_length` - the minimum run length to report
+pub fn find_gaps(sequence: &[u8], min_length: usize) -> Vec<Range<usize>> {
+    let mut gaps: Vec<Range<usize>> = vec![];
+    let mut position = 0;
+    while position < sequence.len() {
+        if sequence[position].eq_ignore_ascii_case(&b'N') {
+            let start = position;
+            while position < sequence.len() && sequence[position].eq_ignore_ascii_case(&b'N') {
+                position += 1;
+            }
+            if position - start >= min_length.max(1) {
+                gaps.push(start..position);
+            }
+        } else {
+            position += 1;
+        }
+    }
+    gaps
+}
+
+/// Returns true if a region contains at least `min_repeats` consecutive, exactly adjacent copies of an upper-case motif
+fn has_tandem_repeat(region: &[u8], motif: &[u8], min_repeats: usize) -> bool {
+    let mut run = 0;
+    let mut expected = None;
+    for position in memmem::find_iter(region, motif) {
+        run = if expected == Some(position) { run + 1 } else { 1 };
+        if run >= min_repeats {
+            return true;
+        }
+        expected = Some(position + motif.len());
+    }
+    false
+}
+
+impl ReferenceGenome {
+    /// Assesses whether each contig is telomere-to-telomere: gapless, with a telomere at both ends.
+    /// A telomere is a run of `min_repeats` adjacent copies of the motif, or its reverse complement, within `search_length` of the end.
+    /// Lazily loaded contigs are read once and are not kept in memory.
+    /// # Arguments
+    /// * `options` - the telomere motif and thresholds, see `CompletenessOptions::default()`
+    /// # Errors
+    /// * if the motif is empty
+    /// * if a contig was unloaded or fails to load
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn assess_completeness(&self, options: &CompletenessOptions) -> Result<CompletenessReport, SimpleError> {
+        if options.telomere_motif.is_empty() {
+            bail!("The telomere motif must not be empty");
+        }
+        let forward = options.telomere_motif.to_ascii_uppercase();
+        let reverse = reverse_complement(&forward);
+        let has_telomere = |region: &[u8]| {
+            let region = region.to_ascii_uppercase();
+            has_tandem_repeat(&region, &forward, options.min_repeats) || has_tandem_repeat(&region, &reverse, options.min_repeats)
+        };
+
+        let mut contigs = Vec::with_capacity(self.contig_keys().len());
+        for contig in self.contig_keys().iter() {
+            let sequence = self.try_sequence_unkept(contig)?;
+            let gaps = find_gaps(&sequence, options.min_gap_length);
+            let search_length = options.search_length.min(sequence.len());
+            contigs.push(ContigCompleteness {
+                contig: contig.clone(),
+                length: sequence.len(),
+                gaps: gaps.len(),
+                gap_bases: gaps.iter().map(|g| g.len()).sum(),
+                telomere_start: has_telomere(&sequence[..search_length]),
+                telomere_end: has_telomere(&sequence[sequence.len() - search_length..])
+            });
+        }
+        Ok(CompletenessReport { contigs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_gaps() {
+        assert_eq!(find_gaps(b"NNACGTnNnAN", 1), vec![0..2, 6..9, 10..11]);
+        assert_eq!(find_gaps(b"NNACGTnNnAN", 3), vec![6..9]);
+        assert!(find_gaps(b"", 1).is_empty());
+    }
+
+    #[test]
+    fn test_assess_completeness() {
+        let start = "CCCTAA".repeat(3);
+        let end = "ttaggg".repeat(3);
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("complete".to_string(), &format!("{start}ACGTACGT{end}")).unwrap();
+        reference_genome.add_contig("gapped".to_string(), &format!("{start}ACGTNNNNACGT{end}")).unwrap();
+        reference_genome.add_contig("open_end".to_string(), &format!("{start}ACGTACGTTTAGGGTTAGGG")).unwrap();
+        reference_genome.add_contig("empty".to_string(), "").unwrap();
+
+        let options = CompletenessOptions {
+            search_length: 20,
+            min_repeats: 3,
+            ..Default::default()
+        };
+        let report = reference_genome.assess_completeness(&options).unwrap();
+        let t2t: Vec<bool> = report.contigs.iter().map(|c| c.is_t2t()).collect();
+        assert_eq!(t2t, vec![true, false, false, false]);
+        assert_eq!((report.contigs[1].gaps, report.contigs[1].gap_bases), (1, 4));
+        assert!(report.contigs[2].telomere_start && !report.contigs[2].telomere_end);
+        assert_eq!(report.t2t_count(), 1);
+
+        let mut table: Vec<u8> = vec![];
+        report.write_table(&mut table).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        assert_eq!(table.lines().nth(1).unwrap(), "complete\t44\t0\t0\tyes\tyes\tyes");
+
+        let empty_motif = CompletenessOptions { telomere_motif: vec![], ..Default::default() };
+        assert!(reference_genome.assess_completeness(&empty_motif).is_err());
+    }
+}
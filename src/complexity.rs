@@ -0,0 +1,224 @@
+use crate::kmer::Kmers;
+use crate::reference_genome::ReferenceGenome;
+use simple_error::{bail, SimpleError};
+
+/// Options for `ReferenceGenome::kmer_complexity(...)` and `ReferenceGenome::kmer_complexity_report(...)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KmerComplexityOptions {
+    /// The k-mer length, from 1 to 32; default is 21
+    pub k: usize,
+    /// If true, a k-mer and its reverse complement count as the same k-mer; default is true
+    pub canonical: bool,
+    /// The HyperLogLog precision, from 4 to 18; the counter uses 2^precision bytes and has a relative error of about
+    /// 1.04 / sqrt(2^precision), so the default of 14 uses 16 KiB per counter for about 0.8% error
+    pub precision: u8
+}
+
+impl Default for KmerComplexityOptions {
+    fn default() -> Self {
+        Self {
+            k: 21,
+            canonical: true,
+            precision: 14
+        }
+    }
+}
+
+/// The k-mer complexity of a contig or genome
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KmerComplexity {
+    /// The number of k-mers with only A, C, G, and T
+    pub total_kmers: u64,
+    /// The estimated number of distinct k-mers, at most `total_kmers`
+    pub distinct_kmers: f64
+}
+
+impl KmerComplexity {
+    /// The fraction of k-mers that are distinct, near 1 for unique sequence and lower for repetitive sequence; 0 if there are no k-mers
+    pub fn distinct_fraction(&self) -> f64 {
+        if self.total_kmers == 0 {
+            0.0
+        } else {
+            self.distinct_kmers / self.total_kmers as f64
+        }
+    }
+}
+
+/// The k-mer complexity of every contig and of the whole genome, from `ReferenceGenome::kmer_complexity_report(...)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComplexityReport {
+    /// The whole genome, where k-mers shared between contigs count once
+    pub genome: KmerComplexity,
+    /// Each contig in load order
+    pub contigs: Vec<(String, KmerComplexity)>
+}
+
+/// A HyperLogLog distinct counter over 64-bit hashes
+struct HyperLogLog {
+    /// The index bits of a hash
+    precision: u8,
+    /// The maximum rank seen per register
+    registers: Vec<u8>
+}
+
+impl HyperLogLog {
+    /// Creates an empty counter with 2^precision registers
+    fn new(precision: u8) -> Self {
+        Self { precision, registers: vec![0; 1 << precision] }
+    }
+
+    /// Adds a hashed item
+    fn insert(&mut self, hash: u64) {
+        let index = (hash >> (64 - self.precision)) as usize;
+        // the guard bit caps the rank when the remaining bits are all zero
+        let rank = ((hash << self.precision) | (1 << (self.precision - 1))).leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    /// Adds every item of another counter with the same precision
+    fn merge(&mut self, other: &HyperLogLog) {
+        for (register, other) in self.registers.iter_mut().zip(other.registers.iter()) {
+            *register = (*register).max(*other);
+        }
+    }
+
+    /// Estimates the number of distinct items, with the linear counting correction for small counts
+    fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m)
+        };
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            estimate
+        }
+    }
+}
+
+/// Mixes a value, such as a 2-bit encoded k-mer, into a well-distributed hash (the splitmix64 finalizer)
+pub(crate) fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Adds the k-mers of a sequence to a counter, skipping k-mers that contain a base other than A, C, G, or T (in either case)
+/// # Returns
+/// * the number of k-mers added
+fn count_kmers(sequence: &[u8], options: &KmerComplexityOptions, counter: &mut HyperLogLog) -> u64 {
+    let mut total = 0;
+    for kmer in Kmers::new(sequence, options.k) {
+        counter.insert(mix(if options.canonical { kmer.canonical() } else { kmer.forward }));
+        total += 1;
+    }
+    total
+}
+
+impl ReferenceGenome {
+    /// Checks the k-mer length and counter precision
+    fn check_complexity_options(options: &KmerComplexityOptions) -> Result<(), SimpleError> {
+        if !(1..=32).contains(&options.k) {
+            bail!("The k-mer length must be from 1 to 32, found {}", options.k);
+        }
+        if !(4..=18).contains(&options.precision) {
+            bail!("The HyperLogLog precision must be from 4 to 18, found {}", options.precision);
+        }
+        Ok(())
+    }
+
+    /// Estimates the fraction of distinct k-mers of a contig, a quick repetitiveness estimate, with a HyperLogLog counter of bounded memory.
+    /// K-mers containing a base other than A, C, G, or T are skipped; case is ignored.
+    /// # Arguments
+    /// * `chromosome` - the contig name
+    /// * `options` - the k-mer length, strandedness, and counter precision, see `KmerComplexityOptions::default()`
+    /// # Errors
+    /// * if the k-mer length or precision is out of range
+    /// * if the contig is not in the reference genome, was unloaded, or fails to load
+    pub fn kmer_complexity(&self, chromosome: &str, options: &KmerComplexityOptions) -> Result<KmerComplexity, SimpleError> {
+        Self::check_complexity_options(options)?;
+        let mut counter = HyperLogLog::new(options.precision);
+        let total_kmers = count_kmers(&self.try_sequence_unkept(chromosome)?, options, &mut counter);
+        Ok(KmerComplexity { total_kmers, distinct_kmers: counter.estimate().min(total_kmers as f64) })
+    }
+
+    /// Estimates the fraction of distinct k-mers of every contig and of the whole genome, see `kmer_complexity(...)`.
+    /// The genome counter merges the contig counters, so memory stays at two counters regardless of genome size.
+    /// Lazily loaded contigs are read once and are not kept in memory.
+    /// # Arguments
+    /// * `options` - the k-mer length, strandedness, and counter precision, see `KmerComplexityOptions::default()`
+    /// # Errors
+    /// * if the k-mer length or precision is out of range
+    /// * if a contig was unloaded or fails to load
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn kmer_complexity_report(&self, options: &KmerComplexityOptions) -> Result<ComplexityReport, SimpleError> {
+        Self::check_complexity_options(options)?;
+        let mut genome_counter = HyperLogLog::new(options.precision);
+        let mut genome_total = 0;
+        let mut contigs: Vec<(String, KmerComplexity)> = vec![];
+        for contig in self.contig_keys().iter() {
+            let mut counter = HyperLogLog::new(options.precision);
+            let total_kmers = count_kmers(&self.try_sequence_unkept(contig)?, options, &mut counter);
+            genome_counter.merge(&counter);
+            genome_total += total_kmers;
+            contigs.push((contig.clone(), KmerComplexity { total_kmers, distinct_kmers: counter.estimate().min(total_kmers as f64) }));
+        }
+        Ok(ComplexityReport {
+            genome: KmerComplexity { total_kmers: genome_total, distinct_kmers: genome_counter.estimate().min(genome_total as f64) },
+            contigs
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_sequences::random_sequence;
+
+    #[test]
+    fn test_kmer_complexity() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("repeat".to_string(), &"A".repeat(100)).unwrap();
+        reference_genome.add_contig("random".to_string(), &random_sequence(20_000, 1)).unwrap();
+        reference_genome.add_contig("gapped".to_string(), "ACGTNACGTa").unwrap();
+
+        let options = KmerComplexityOptions { k: 5, ..Default::default() };
+        let repeat = reference_genome.kmer_complexity("repeat", &options).unwrap();
+        assert_eq!(repeat.total_kmers, 96);
+        assert!((repeat.distinct_kmers - 1.0).abs() < 0.01);
+
+        let random = reference_genome.kmer_complexity("random", &KmerComplexityOptions::default()).unwrap();
+        assert_eq!(random.total_kmers, 20_000 - 20);
+        assert!(random.distinct_fraction() > 0.97);
+
+        // k-mers do not span the N, and ACGT is its own reverse complement
+        let gapped = reference_genome.kmer_complexity("gapped", &KmerComplexityOptions { k: 4, ..Default::default() }).unwrap();
+        assert_eq!(gapped.total_kmers, 3);
+        assert!((gapped.distinct_kmers - 2.0).abs() < 0.01);
+
+        assert!(reference_genome.kmer_complexity("repeat", &KmerComplexityOptions { k: 33, ..Default::default() }).is_err());
+        assert!(reference_genome.kmer_complexity("repeat", &KmerComplexityOptions { precision: 3, ..Default::default() }).is_err());
+        assert!(reference_genome.kmer_complexity("missing", &options).is_err());
+    }
+
+    #[test]
+    fn test_kmer_complexity_report() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        let sequence = random_sequence(5_000, 7);
+        reference_genome.add_contig("copy1".to_string(), &sequence).unwrap();
+        reference_genome.add_contig("copy2".to_string(), &sequence).unwrap();
+        let report = reference_genome.kmer_complexity_report(&KmerComplexityOptions::default()).unwrap();
+        assert_eq!(report.contigs.len(), 2);
+        assert!(report.contigs[0].1.distinct_fraction() > 0.95);
+        // the second copy adds no new k-mers, so half of the genome k-mers are distinct
+        assert_eq!(report.genome.total_kmers, 2 * report.contigs[0].1.total_kmers);
+        assert!((report.genome.distinct_fraction() - 0.5).abs() < 0.03);
+    }
+}
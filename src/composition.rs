@@ -0,0 +1,211 @@
+use crate::reference_genome::ReferenceGenome;
+use bytes::Bytes;
+use rustc_hash::FxHashMap as HashMap;
+use simple_error::{bail, SimpleError};
+
+/// The number of each base in a region, ignoring case
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BaseCounts {
+    /// The number of A bases
+    pub a: usize,
+    /// The number of C bases
+    pub c: usize,
+    /// The number of G bases
+    pub g: usize,
+    /// The number of T bases
+    pub t: usize,
+    /// The number of N bases
+    pub n: usize,
+    /// The number of other symbols, such as IUPAC ambiguity codes
+    pub other: usize
+}
+
+impl BaseCounts {
+    /// The total number of bases
+    pub fn len(&self) -> usize {
+        self.a + self.c + self.g + self.t + self.n + self.other
+    }
+
+    /// Returns true if there are no bases
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The fraction of G/C among the non-N bases, as in `WindowStats::gc_fraction`, or `None` if every base is N
+    pub fn gc_fraction(&self) -> Option<f64> {
+        let called = self.len() - self.n;
+        (called > 0).then(|| (self.c + self.g) as f64 / called as f64)
+    }
+}
+
+/// The index of a base in a checkpoint, with other symbols in the last slot
+fn base_slot(symbol: u8) -> usize {
+    match symbol.to_ascii_uppercase() {
+        b'A' => 0,
+        b'C' => 1,
+        b'G' => 2,
+        b'T' => 3,
+        b'N' => 4,
+        _ => 5
+    }
+}
+
+/// The cumulative base counts of one contig at every `stride` bases
+struct ContigComposition {
+    /// The contig sequence, to count between checkpoints
+    sequence: Bytes,
+    /// The counts of A, C, G, T, N, and other symbols before each multiple of the stride
+    checkpoints: Vec<[u64; 6]>
+}
+
+impl ContigComposition {
+    /// Builds the checkpoints of a contig
+    fn new(sequence: Bytes, stride: usize) -> Self {
+        let mut checkpoints: Vec<[u64; 6]> = Vec::with_capacity(sequence.len() / stride + 1);
+        let mut counts = [0u64; 6];
+        checkpoints.push(counts);
+        for block in sequence.chunks(stride) {
+            for &symbol in block.iter() {
+                counts[base_slot(symbol)] += 1;
+            }
+            if block.len() == stride {
+                checkpoints.push(counts);
+            }
+        }
+        Self { sequence, checkpoints }
+    }
+
+    /// The counts of the bases before a position: the nearest checkpoint, plus a scan of fewer than `stride` bases
+    fn counts_before(&self, position: usize, stride: usize) -> [u64; 6] {
+        let checkpoint = position / stride;
+        let mut counts = self.checkpoints[checkpoint];
+        for &symbol in self.sequence[checkpoint * stride..position].iter() {
+            counts[base_slot(symbol)] += 1;
+        }
+        counts
+    }
+}
+
+/// Cumulative base counts of every contig, from `ReferenceGenome::composition_index(...)`, for bias-correction code that
+/// asks for the composition of millions of intervals. Counts are kept at every `stride` bases, so each query costs at most
+/// `2 * stride` base reads regardless of the interval length, and the index takes `48 / stride` bytes per base on top of the sequences.
+/// The index keeps its own handle to every sequence, including lazily loaded ones, so it is unaffected by later changes to the genome.
+pub struct CompositionIndex {
+    /// The bases between checkpoints
+    stride: usize,
+    /// The checkpoints by contig name
+    contigs: HashMap<String, ContigComposition>
+}
+
+impl CompositionIndex {
+    /// Counts the bases of a region in constant time
+    /// # Arguments
+    /// * `chromosome` - the contig name; no lookup normalization is applied
+    /// * `start` - the 0-based start index (included)
+    /// * `end` - the 0-based end index (excluded)
+    /// # Errors
+    /// * if the contig is not in the index
+    /// * if `start` > `end`, or `end` is past the contig end
+    pub fn counts(&self, chromosome: &str, start: usize, end: usize) -> Result<BaseCounts, SimpleError> {
+        let Some(contig) = self.contigs.get(chromosome) else {
+            bail!("Contig key {:?} was not found in the composition index", chromosome);
+        };
+        if start > end {
+            bail!("start > end: {} > {}", start, end);
+        }
+        if end > contig.sequence.len() {
+            bail!("Region {:?}:{}-{} extends past the contig length {}", chromosome, start, end, contig.sequence.len());
+        }
+        let before = contig.counts_before(start, self.stride);
+        let through = contig.counts_before(end, self.stride);
+        let count = |slot: usize| (through[slot] - before[slot]) as usize;
+        Ok(BaseCounts { a: count(0), c: count(1), g: count(2), t: count(3), n: count(4), other: count(5) })
+    }
+
+    /// Computes the fraction of G/C among the non-N bases of a region in constant time, see `counts(...)`
+    /// # Returns
+    /// * the GC fraction, or `None` if the region is empty or every base is N
+    /// # Errors
+    /// * see `counts(...)`
+    pub fn gc_fraction(&self, chromosome: &str, start: usize, end: usize) -> Result<Option<f64>, SimpleError> {
+        Ok(self.counts(chromosome, start, end)?.gc_fraction())
+    }
+
+    /// The number of bases between checkpoints
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+}
+
+impl ReferenceGenome {
+    /// Precomputes cumulative base counts for every contig, so the composition of any interval is found in constant time,
+    /// see `CompositionIndex`. A smaller stride makes queries faster and the index larger; 64 is a reasonable default.
+    /// Lazily loaded contigs are loaded and held by the index.
+    /// # Arguments
+    /// * `stride` - the bases between checkpoints, at least 1
+    /// # Errors
+    /// * if `stride` is 0
+    /// * if a contig was unloaded or fails to load
+    pub fn composition_index(&self, stride: usize) -> Result<CompositionIndex, SimpleError> {
+        if stride == 0 {
+            bail!("The stride must be at least 1");
+        }
+        let mut contigs: HashMap<String, ContigComposition> = Default::default();
+        for contig in self.contig_keys().iter() {
+            let sequence = self.try_sequence_unkept(contig)?;
+            contigs.insert(contig.clone(), ContigComposition::new(sequence, stride));
+        }
+        Ok(CompositionIndex { stride, contigs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ReferenceGenomeBuilder;
+
+    /// Counts the bases of a region by scanning it
+    fn scan_counts(sequence: &[u8]) -> BaseCounts {
+        let mut counts = BaseCounts::default();
+        for &symbol in sequence.iter() {
+            match symbol.to_ascii_uppercase() {
+                b'A' => counts.a += 1,
+                b'C' => counts.c += 1,
+                b'G' => counts.g += 1,
+                b'T' => counts.t += 1,
+                b'N' => counts.n += 1,
+                _ => counts.other += 1
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn test_composition_index() {
+        let sequence = b"ACGTNNacgtRYGGGCCCATATnGCGCAAAT";
+        let mut fasta = b">chr1\n".to_vec();
+        fasta.extend_from_slice(sequence);
+        fasta.extend_from_slice(b"\n>empty\n");
+        let reference_genome = ReferenceGenomeBuilder::from_bytes(fasta).uppercase(false).build().unwrap();
+        for stride in [1, 3, 8, 64] {
+            let index = reference_genome.composition_index(stride).unwrap();
+            assert_eq!(index.stride(), stride);
+            for start in 0..=sequence.len() {
+                for end in start..=sequence.len() {
+                    assert_eq!(index.counts("chr1", start, end).unwrap(), scan_counts(&sequence[start..end]), "{stride} {start}-{end}");
+                }
+            }
+            assert!(index.counts("empty", 0, 0).unwrap().is_empty());
+        }
+
+        let index = reference_genome.composition_index(4).unwrap();
+        assert_eq!(index.gc_fraction("chr1", 0, 4).unwrap(), Some(0.5));
+        assert_eq!(index.gc_fraction("chr1", 4, 6).unwrap(), None);
+        assert_eq!(index.gc_fraction("chr1", 12, 18).unwrap(), Some(1.0));
+        assert_eq!(index.counts("chr1", 0, sequence.len()).unwrap().len(), sequence.len());
+        assert!(index.counts("chr1", 5, 4).is_err());
+        assert!(index.counts("chr1", 0, sequence.len() + 1).is_err());
+        assert!(index.counts("chr2", 0, 1).is_err());
+        assert!(reference_genome.composition_index(0).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use crate::kmer::Kmers;
+use crate::reference_genome::ReferenceGenome;
+use rustc_hash::FxHashMap as HashMap;
+use simple_error::{bail, SimpleError};
+
+/// Options for `ReferenceGenome::screen_contaminants(...)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContaminantScreenOptions {
+    /// The k-mer length, from 1 to 32; default is 25, long enough that random matches against a bacterial genome are rare
+    pub k: usize,
+    /// The largest distance between matching k-mers that are merged into one region; default is 25
+    pub max_gap: usize,
+    /// The shortest region that is reported, measured from the first matching k-mer start to the last matching k-mer end; default is 50
+    pub min_region_length: usize
+}
+
+impl Default for ContaminantScreenOptions {
+    fn default() -> Self {
+        Self {
+            k: 25,
+            max_gap: 25,
+            min_region_length: 50
+        }
+    }
+}
+
+/// A region of a contig that shares k-mers with a contaminant
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContaminantHit {
+    /// The contig name
+    pub contig: String,
+    /// 0-based start of the region
+    pub start: usize,
+    /// 0-based, exclusive end of the region
+    pub end: usize,
+    /// The number of k-mers in the region that occur in a contaminant, on either strand
+    pub matching_kmers: usize,
+    /// The contaminant record that most of the matching k-mers come from
+    pub contaminant: String
+}
+
+/// A contig with at least one contaminant hit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SuspectContig {
+    /// The contig name
+    pub contig: String,
+    /// The contig length
+    pub length: usize,
+    /// The bases covered by the contig's hits
+    pub contaminated_bases: usize,
+    /// The contaminant record that most of the contig's matching k-mers come from
+    pub top_contaminant: String
+}
+
+impl SuspectContig {
+    /// The fraction of the contig covered by hits; a contig that is mostly contaminant is likely a whole foreign sequence
+    /// rather than a chimeric join, so it would be removed rather than trimmed
+    pub fn contaminated_fraction(&self) -> f64 {
+        if self.length == 0 {
+            0.0
+        } else {
+            self.contaminated_bases as f64 / self.length as f64
+        }
+    }
+}
+
+/// Result of `ReferenceGenome::screen_contaminants(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContaminationReport {
+    /// Every reported region, by contig in load order and then by position
+    pub hits: Vec<ContaminantHit>,
+    /// Every contig with at least one hit, in load order
+    pub contigs: Vec<SuspectContig>
+}
+
+impl ContaminationReport {
+    /// Returns true if no contaminant hits were found
+    pub fn is_clean(&self) -> bool {
+        self.hits.is_empty()
+    }
+}
+
+/// The matching k-mers of a region that is still being extended
+struct OpenRegion {
+    /// 0-based start of the region
+    start: usize,
+    /// 0-based, exclusive end of the region
+    end: usize,
+    /// The matching k-mers per contaminant ID
+    votes: HashMap<u32, usize>
+}
+
+/// Returns the ID with the most votes, preferring the lowest ID on ties so results do not depend on hash order
+fn top_vote(votes: &HashMap<u32, usize>) -> Option<u32> {
+    votes.iter().max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0))).map(|(&id, _)| id)
+}
+
+impl ReferenceGenome {
+    /// Screens the genome for contamination, such as cloning vectors (e.g. UniVec) or common bacteria, by looking up every k-mer
+    /// in the k-mers of a contaminant genome on both strands. Matching k-mers at most `max_gap` bases apart are merged into regions, and
+    /// regions of at least `min_region_length` are reported, so assembly ingest can flag contigs to trim or drop without external tools.
+    /// The contaminant k-mers are held in memory, about 16 bytes per distinct k-mer; lazily loaded contigs of either genome are read
+    /// once and are not kept in memory. K-mers containing a base other than A, C, G, or T are skipped; case is ignored.
+    /// # Arguments
+    /// * `contaminants` - the contaminant sequences, e.g. loaded with `ReferenceGenome::from_fasta(...)`
+    /// * `options` - the k-mer length and region thresholds, see `ContaminantScreenOptions::default()`
+    /// # Errors
+    /// * if the k-mer length is out of range
+    /// * if a contig of either genome was unloaded or fails to load
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn screen_contaminants(&self, contaminants: &ReferenceGenome, options: &ContaminantScreenOptions) -> Result<ContaminationReport, SimpleError> {
+        if !(1..=32).contains(&options.k) {
+            bail!("The k-mer length must be from 1 to 32, found {}", options.k);
+        }
+        // each k-mer maps to the first contaminant record that contains it
+        let contaminant_names = contaminants.contig_keys();
+        let mut contaminant_kmers: HashMap<u64, u32> = Default::default();
+        for (id, name) in contaminant_names.iter().enumerate() {
+            for kmer in Kmers::new(&contaminants.try_sequence_unkept(name)?, options.k) {
+                contaminant_kmers.entry(kmer.canonical()).or_insert(id as u32);
+            }
+        }
+
+        let mut report = ContaminationReport::default();
+        for contig in self.contig_keys().iter() {
+            let sequence = self.try_sequence_unkept(contig)?;
+            let mut regions: Vec<OpenRegion> = vec![];
+            for kmer in Kmers::new(&sequence, options.k) {
+                let Some(&id) = contaminant_kmers.get(&kmer.canonical()) else {
+                    continue;
+                };
+                let start = kmer.start;
+                match regions.last_mut() {
+                    Some(region) if start <= region.end + options.max_gap => region.end = start + options.k,
+                    _ => regions.push(OpenRegion { start, end: start + options.k, votes: Default::default() })
+                }
+                *regions.last_mut().unwrap().votes.entry(id).or_default() += 1;
+            }
+
+            let mut contig_votes: HashMap<u32, usize> = Default::default();
+            let mut contaminated_bases = 0;
+            for region in regions.into_iter().filter(|r| r.end - r.start >= options.min_region_length) {
+                for (&id, &count) in region.votes.iter() {
+                    *contig_votes.entry(id).or_default() += count;
+                }
+                contaminated_bases += region.end - region.start;
+                report.hits.push(ContaminantHit {
+                    contig: contig.clone(),
+                    start: region.start,
+                    end: region.end,
+                    matching_kmers: region.votes.values().sum(),
+                    contaminant: contaminant_names[top_vote(&region.votes).unwrap() as usize].clone()
+                });
+            }
+            if let Some(id) = top_vote(&contig_votes) {
+                report.contigs.push(SuspectContig {
+                    contig: contig.clone(),
+                    length: sequence.len(),
+                    contaminated_bases,
+                    top_contaminant: contaminant_names[id as usize].clone()
+                });
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alphabet::reverse_complement;
+    use crate::test_sequences::random_sequence;
+
+    #[test]
+    fn test_screen_contaminants() {
+        let vector = random_sequence(300, 1);
+        let bacterium = random_sequence(2_000, 2);
+        let mut contaminants = ReferenceGenome::empty_reference();
+        contaminants.add_contig("vector".to_string(), &vector).unwrap();
+        contaminants.add_contig("bacterium".to_string(), &bacterium).unwrap();
+
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("clean".to_string(), &random_sequence(5_000, 3)).unwrap();
+        // a vector insert on the reverse strand in the middle of a contig
+        let chimera = format!("{}{}{}", random_sequence(1_000, 4), std::str::from_utf8(&reverse_complement(&vector.as_bytes()[50..250])).unwrap(), random_sequence(1_000, 5));
+        reference_genome.add_contig("chimera".to_string(), &chimera).unwrap();
+        reference_genome.add_contig("foreign".to_string(), &bacterium[..1_500]).unwrap();
+        // a short match is below the minimum region length
+        let short = format!("{}{}{}", random_sequence(500, 6), &bacterium[1_600..1_630], random_sequence(500, 7));
+        reference_genome.add_contig("short".to_string(), &short).unwrap();
+
+        let report = reference_genome.screen_contaminants(&contaminants, &ContaminantScreenOptions::default()).unwrap();
+        assert!(!report.is_clean());
+        assert_eq!(report.hits.len(), 2);
+        // a flanking base can match the next vector base by chance, which extends the region
+        let vector_hit = &report.hits[0];
+        assert_eq!((vector_hit.contig.as_str(), vector_hit.contaminant.as_str()), ("chimera", "vector"));
+        assert!((990..=1_000).contains(&vector_hit.start) && (1_200..=1_210).contains(&vector_hit.end), "{vector_hit:?}");
+        assert!(vector_hit.matching_kmers > 200 - 25);
+        let bacterium_hit = &report.hits[1];
+        assert_eq!((bacterium_hit.contig.as_str(), bacterium_hit.start, bacterium_hit.end), ("foreign", 0, 1_500));
+        assert_eq!(bacterium_hit.contaminant, "bacterium");
+
+        let suspects: Vec<&str> = report.contigs.iter().map(|c| c.contig.as_str()).collect();
+        assert_eq!(suspects, vec!["chimera", "foreign"]);
+        assert_eq!(report.contigs[0].contaminated_bases, vector_hit.end - vector_hit.start);
+        assert!(report.contigs[0].contaminated_fraction() < 0.1);
+        assert_eq!(report.contigs[1].contaminated_fraction(), 1.0);
+        assert_eq!(report.contigs[1].top_contaminant, "bacterium");
+
+        let options = ContaminantScreenOptions { min_region_length: 25, ..Default::default() };
+        let report = reference_genome.screen_contaminants(&contaminants, &options).unwrap();
+        let short_hit = report.hits.last().unwrap();
+        assert_eq!(short_hit.contig, "short");
+        assert!((490..=500).contains(&short_hit.start) && (530..=540).contains(&short_hit.end), "{short_hit:?}");
+
+        assert!(reference_genome.screen_contaminants(&ReferenceGenome::empty_reference(), &Default::default()).unwrap().is_clean());
+        let options = ContaminantScreenOptions { k: 33, ..Default::default() };
+        assert!(reference_genome.screen_contaminants(&contaminants, &options).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::alphabet::reverse_complement;
+use crate::reference_genome::ReferenceGenome;
+use bytes::Bytes;
+use simple_error::{bail, SimpleError};
+
+/// The homopolymer containing a position, from `ReferenceGenome::homopolymer_run(...)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HomopolymerRun {
+    /// The repeated base, upper-cased
+    pub base: u8,
+    /// The 0-based start of the run (included)
+    pub start: usize,
+    /// The 0-based end of the run (excluded)
+    pub end: usize
+}
+
+impl HomopolymerRun {
+    /// The number of bases in the run, at least 1
+    pub fn length(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// The strand-normalized context of a position, from `ReferenceGenome::context(...)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequenceContext {
+    /// The upper-cased k-mer centered on the position, with a pyrimidine (C or T) at the center
+    pub kmer: Vec<u8>,
+    /// True if the k-mer was reverse complemented because the reference base is a purine;
+    /// alternate alleles must then be complemented too, e.g. a G>T substitution is reported as C>A
+    pub reverse_complemented: bool
+}
+
+impl ReferenceGenome {
+    /// Retrieves a contig for a single-position query, going through the LRU cache of lazy backends
+    /// # Errors
+    /// * if the contig is not in the reference genome or was unloaded
+    /// * if `position` is past the end of the contig
+    pub(crate) fn position_query(&self, chromosome: &str, position: usize) -> Result<Bytes, SimpleError> {
+        let Some(sequence) = self.try_get_full_chromosome_shared(chromosome) else {
+            if self.resolve_contig_name(chromosome).is_none() {
+                bail!("{}", self.missing_contig_message(chromosome));
+            }
+            bail!("Contig key \"{chromosome}\" has been unloaded");
+        };
+        if position >= sequence.len() {
+            bail!("Position {} is past the end of contig \"{}\" ({} bp)", position, chromosome, sequence.len());
+        }
+        Ok(sequence)
+    }
+
+    /// Finds the homopolymer run containing a position, such as for filtering long-read indel candidates.
+    /// Bases are compared ignoring case, so soft-masking does not split a run; a base that differs from both neighbors is a run of length 1.
+    /// # Arguments
+    /// * `chromosome` - the contig name
+    /// * `position` - the 0-based position
+    /// # Errors
+    /// * if the contig is not in the reference genome or was unloaded
+    /// * if `position` is past the end of the contig
+    pub fn homopolymer_run(&self, chromosome: &str, position: usize) -> Result<HomopolymerRun, SimpleError> {
+        let sequence = self.position_query(chromosome, position)?;
+        let base = sequence[position].to_ascii_uppercase();
+        let same = |c: &u8| c.to_ascii_uppercase() == base;
+        let start = position - sequence[..position].iter().rev().take_while(|c| same(c)).count();
+        let end = position + sequence[position..].iter().take_while(|c| same(c)).count();
+        Ok(HomopolymerRun { base, start, end })
+    }
+
+    /// Extracts the pyrimidine-normalized k-mer context of a position, such as the trinucleotide context (k = 3) of SBS96 mutational signatures.
+    /// If the reference base is A or G, the reverse complement is returned so the center is always C or T; other center bases are left as they are.
+    /// Positions past either end of the contig are padded with N.
+    /// # Arguments
+    /// * `chromosome` - the contig name
+    /// * `position` - the 0-based position of the center base
+    /// * `k` - the context length, which must be odd
+    /// # Errors
+    /// * if `k` is even
+    /// * if the contig is not in the reference genome or was unloaded
+    /// * if `position` is past the end of the contig
+    pub fn context(&self, chromosome: &str, position: usize, k: usize) -> Result<SequenceContext, SimpleError> {
+        if k.is_multiple_of(2) {
+            bail!("The context length must be odd, found {}", k);
+        }
+        let sequence = self.position_query(chromosome, position)?;
+        let flank = k / 2;
+        let start = position.saturating_sub(flank);
+        let end = (position + flank + 1).min(sequence.len());
+        let mut kmer: Vec<u8> = vec![b'N'; flank - (position - start)];
+        kmer.extend(sequence[start..end].iter().map(|c| c.to_ascii_uppercase()));
+        kmer.resize(k, b'N');
+
+        let reverse_complemented = matches!(kmer[flank], b'A' | b'G');
+        if reverse_complemented {
+            kmer = reverse_complement(&kmer);
+        }
+        Ok(SequenceContext { kmer, reverse_complemented })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ReferenceGenomeBuilder;
+
+    #[test]
+    fn test_homopolymer_run() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nAAACCCCGT\n").unwrap();
+        let run = reference_genome.homopolymer_run("chr1", 4).unwrap();
+        assert_eq!(run, HomopolymerRun { base: b'C', start: 3, end: 7 });
+        assert_eq!(run.length(), 4);
+        assert_eq!(reference_genome.homopolymer_run("chr1", 0).unwrap(), HomopolymerRun { base: b'A', start: 0, end: 3 });
+        assert_eq!(reference_genome.homopolymer_run("chr1", 8).unwrap().length(), 1);
+        assert!(reference_genome.homopolymer_run("chr1", 9).is_err());
+        assert!(reference_genome.homopolymer_run("chr2", 0).is_err());
+
+        // soft-masked bases continue a run
+        let reference_genome = ReferenceGenomeBuilder::from_bytes(&b">chr1\nGTTttTA\n"[..])
+            .uppercase(false)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.homopolymer_run("chr1", 3).unwrap(), HomopolymerRun { base: b'T', start: 1, end: 6 });
+    }
+
+    #[test]
+    fn test_context() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGTTGCA\n").unwrap();
+        assert_eq!(reference_genome.context("chr1", 1, 3).unwrap(), SequenceContext { kmer: b"ACG".to_vec(), reverse_complemented: false });
+        // G is a purine, so TGC becomes GCA
+        assert_eq!(reference_genome.context("chr1", 5, 3).unwrap(), SequenceContext { kmer: b"GCA".to_vec(), reverse_complemented: true });
+        assert_eq!(reference_genome.context("chr1", 3, 5).unwrap().kmer, b"CGTTG");
+        assert_eq!(reference_genome.context("chr1", 2, 1).unwrap().kmer, b"C");
+
+        // padded past the contig ends
+        assert_eq!(reference_genome.context("chr1", 0, 5).unwrap().kmer, b"CGTNN");
+        assert_eq!(reference_genome.context("chr1", 6, 5).unwrap().kmer, b"TGCAN");
+
+        assert!(reference_genome.context("chr1", 1, 4).is_err());
+        assert!(reference_genome.context("chr1", 8, 3).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use crate::reference_genome::ReferenceGenome;
+
+/// The role of a contig within a reference assembly
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContigClass {
+    /// A numbered nuclear chromosome, e.g. `chr1` or `1`
+    Autosome,
+    /// A sex chromosome, e.g. `chrX`, `chrY`, `Z`, or `W`
+    SexChromosome,
+    /// The mitochondrial genome, e.g. `chrM` or `MT`
+    Mitochondrial,
+    /// Unlocalized or unplaced scaffolds, e.g. `chr1_KI270706v1_random`, `chrUn_KI270302v1`, or `GL000192.1`
+    Unplaced,
+    /// Alternate loci, HLA alleles, and patch scaffolds, e.g. `chr6_GL000250v2_alt`, `HLA-A*01:01:01:01`, or `chr1_KN196472v1_fix`
+    Alt,
+    /// Decoy sequences, e.g. `chrUn_JTFH01000001v1_decoy` or `hs37d5`
+    Decoy,
+    /// The Epstein-Barr virus sequence, e.g. `chrEBV` or `NC_007605.1`
+    Ebv,
+    /// Anything that did not match a known naming convention
+    Other
+}
+
+impl ContigClass {
+    /// Returns true for the standard chromosomes: autosomes, sex chromosomes, and the mitochondrial genome
+    pub fn is_primary_chromosome(&self) -> bool {
+        matches!(self, ContigClass::Autosome | ContigClass::SexChromosome | ContigClass::Mitochondrial)
+    }
+
+    /// A lower-case label for reports and exports, e.g. "sex_chromosome"
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContigClass::Autosome => "autosome",
+            ContigClass::SexChromosome => "sex_chromosome",
+            ContigClass::Mitochondrial => "mitochondrial",
+            ContigClass::Unplaced => "unplaced",
+            ContigClass::Alt => "alt",
+            ContigClass::Decoy => "decoy",
+            ContigClass::Ebv => "ebv",
+            ContigClass::Other => "other"
+        }
+    }
+}
+
+/// Curated RefSeq accessions (without version) for EBV
+const EBV_ACCESSIONS: [&str; 1] = ["NC_007605"];
+/// Curated RefSeq accessions (without version) for mitochondrial genomes; human and mouse
+const MITO_ACCESSIONS: [&str; 3] = ["NC_012920", "NC_001807", "NC_005089"];
+/// Curated RefSeq accession ranges (without "NC_" or version) for autosomes and sex chromosomes.
+/// Each entry is (first autosome, last autosome, first sex chromosome, last sex chromosome).
+const CHROMOSOME_ACCESSION_RANGES: [(u32, u32, u32, u32); 3] = [
+    // human GRCh37/GRCh38
+    (1, 22, 23, 24),
+    // mouse GRCm38/GRCm39
+    (67, 85, 86, 87),
+    // human T2T-CHM13
+    (60925, 60946, 60947, 60948)
+];
+/// Curated decoy contig names that do not follow the `_decoy` suffix convention
+const DECOY_NAMES: [&str; 1] = ["hs37d5"];
+/// Scaffold accession prefixes used for unplaced/unlocalized sequence by GRC/Ensembl naming
+const UNPLACED_PREFIXES: [&str; 5] = ["GL", "KI", "JH", "KB", "MU"];
+
+/// Classifies a contig by name using a combination of curated names and common naming heuristics.
+/// UCSC (`chr1`), Ensembl (`1`), and RefSeq (`NC_000001.11`) styles are supported for the standard chromosomes.
+/// # Arguments
+/// * `name` - the contig name to classify
+/// # Examples
+/// ```
+/// use rust_lib_reference_genome::contig_class::{classify_contig_name, ContigClass};
+/// assert_eq!(classify_contig_name("chr1"), ContigClass::Autosome);
+/// assert_eq!(classify_contig_name("MT"), ContigClass::Mitochondrial);
+/// assert_eq!(classify_contig_name("chr6_GL000250v2_alt"), ContigClass::Alt);
+/// ```
+pub fn classify_contig_name(name: &str) -> ContigClass {
+    let accession = name.split('.').next().unwrap_or_default();
+
+    // check the suffix-based and curated classes first since they often embed other patterns
+    if name.ends_with("_decoy") || DECOY_NAMES.contains(&name) {
+        return ContigClass::Decoy;
+    }
+    if name == "chrEBV" || name == "EBV" || EBV_ACCESSIONS.contains(&accession) {
+        return ContigClass::Ebv;
+    }
+    if name.ends_with("_alt") || name.ends_with("_fix") || name.starts_with("HLA-") {
+        return ContigClass::Alt;
+    }
+    if name.ends_with("_random") || name.starts_with("chrUn") {
+        return ContigClass::Unplaced;
+    }
+    if MITO_ACCESSIONS.contains(&accession) {
+        return ContigClass::Mitochondrial;
+    }
+    if let Some(number) = accession.strip_prefix("NC_").and_then(|n| n.parse::<u32>().ok()) {
+        for &(auto_start, auto_end, sex_start, sex_end) in CHROMOSOME_ACCESSION_RANGES.iter() {
+            if (auto_start..=auto_end).contains(&number) {
+                return ContigClass::Autosome;
+            }
+            if (sex_start..=sex_end).contains(&number) {
+                return ContigClass::SexChromosome;
+            }
+        }
+    }
+
+    // strip any chr prefix, e.g. "chr1" or "Chr1"
+    let stripped = match name.get(..3) {
+        Some(prefix) if name.len() > 3 && prefix.eq_ignore_ascii_case("chr") => &name[3..],
+        _ => name
+    };
+    if stripped == "M" || stripped == "MT" {
+        ContigClass::Mitochondrial
+    } else if matches!(stripped, "X" | "Y" | "Z" | "W") {
+        ContigClass::SexChromosome
+    } else if !stripped.is_empty() && stripped.bytes().all(|b| b.is_ascii_digit()) && !stripped.starts_with('0') {
+        ContigClass::Autosome
+    } else if UNPLACED_PREFIXES.iter().any(|p| name.starts_with(p)) && name.contains('.') {
+        ContigClass::Unplaced
+    } else {
+        ContigClass::Other
+    }
+}
+
+impl ReferenceGenome {
+    /// Classifies a contig by name, see `classify_contig_name(...)` for details
+    /// # Arguments
+    /// * `chromosome` - the contig to classify; with normalized lookup, the name it resolves to is classified
+    /// # Panics
+    /// * if `chromosome` was not in the reference genome
+    pub fn contig_class(&self, chromosome: &str) -> ContigClass {
+        match self.resolve_contig_name(chromosome) {
+            Some(name) => classify_contig_name(name),
+            None => panic!("{}", self.missing_contig_message(chromosome))
+        }
+    }
+
+    /// Returns the classification of every contig, in load order
+    pub fn contig_classes(&self) -> Vec<(&str, ContigClass)> {
+        self.contig_keys().iter()
+            .map(|c| (c.as_str(), classify_contig_name(c)))
+            .collect()
+    }
+
+    /// Returns the contigs matching a given classification, in load order
+    /// # Arguments
+    /// * `class` - the classification to filter for
+    pub fn contigs_of_class(&self, class: ContigClass) -> Vec<&str> {
+        self.contig_classes().into_iter()
+            .filter_map(|(c, cc)| if cc == class { Some(c) } else { None })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_contig_name() {
+        let expected = [
+            ("chr1", ContigClass::Autosome),
+            ("22", ContigClass::Autosome),
+            ("Chr5", ContigClass::Autosome),
+            ("NC_000001.11", ContigClass::Autosome),
+            ("NC_060930.1", ContigClass::Autosome),
+            ("chrX", ContigClass::SexChromosome),
+            ("Y", ContigClass::SexChromosome),
+            ("NC_000087.8", ContigClass::SexChromosome),
+            ("chrM", ContigClass::Mitochondrial),
+            ("MT", ContigClass::Mitochondrial),
+            ("NC_012920.1", ContigClass::Mitochondrial),
+            ("chr1_KI270706v1_random", ContigClass::Unplaced),
+            ("chrUn_KI270302v1", ContigClass::Unplaced),
+            ("GL000192.1", ContigClass::Unplaced),
+            ("chr6_GL000250v2_alt", ContigClass::Alt),
+            ("chr1_KN196472v1_fix", ContigClass::Alt),
+            ("HLA-A*01:01:01:01", ContigClass::Alt),
+            ("chrUn_JTFH01000001v1_decoy", ContigClass::Decoy),
+            ("hs37d5", ContigClass::Decoy),
+            ("chrEBV", ContigClass::Ebv),
+            ("NC_007605.1", ContigClass::Ebv),
+            ("contig_00012", ContigClass::Other),
+            ("chr", ContigClass::Other),
+            ("007", ContigClass::Other)
+        ];
+        for (name, class) in expected.iter() {
+            assert_eq!(classify_contig_name(name), *class, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_contig_classes() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), "A").unwrap();
+        reference_genome.add_contig("chrX".to_string(), "A").unwrap();
+        reference_genome.add_contig("chr2".to_string(), "A").unwrap();
+        reference_genome.add_contig("chrEBV".to_string(), "A").unwrap();
+
+        assert_eq!(reference_genome.contig_class("chrX"), ContigClass::SexChromosome);
+        assert_eq!(reference_genome.contigs_of_class(ContigClass::Autosome), vec!["chr1", "chr2"]);
+        assert_eq!(reference_genome.contig_classes().len(), 4);
+        assert!(reference_genome.contig_classes().iter().all(|(c, cc)| {
+            cc.is_primary_chromosome() == (*c != "chrEBV")
+        }));
+    }
+
+    #[test]
+    fn test_contig_class_normalized_lookup() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chrX".to_string(), "A").unwrap();
+        reference_genome.add_contig("chr1_KI270706v1_random".to_string(), "A").unwrap();
+        reference_genome.set_normalized_lookup(true);
+        // the resolved names are classified, not the queries
+        assert_eq!(reference_genome.contig_class("x"), ContigClass::SexChromosome);
+        assert_eq!(reference_genome.contig_class("CHR1_KI270706V1_RANDOM"), ContigClass::Unplaced);
+    }
+}
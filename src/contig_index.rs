@@ -0,0 +1,155 @@
+use hashbrown::HashTable;
+use rustc_hash::{FxHashMap as HashMap, FxHasher};
+use simple_error::{bail, SimpleError};
+use crate::complexity::mix;
+use crate::lookup::loose_name;
+use crate::reference_genome::ContigId;
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
+/// Interned contig names with a hash index from name to `ContigId`.
+/// Each name is stored once; the index only holds 4-byte ids, so assemblies with millions of contigs do not pay for a second copy of every name.
+#[derive(Clone, Default)]
+pub(crate) struct ContigIndex {
+    /// Contig names, indexed by id
+    names: Vec<String>,
+    /// Ids hashed by the name they refer to
+    table: HashTable<ContigId>,
+    /// Ids by loose name (see `loose_name(...)`), or `None` if several contigs share it; built on the first loose lookup
+    /// and reset by `insert(...)`, so genomes without normalized lookup never pay for it
+    loose: OnceLock<HashMap<String, Option<ContigId>>>
+}
+
+/// Hashes a contig name for the index table.
+/// The table picks buckets from the low bits, which `FxHasher` barely changes for names that only differ in their last
+/// characters (e.g. "scaffold_1" to "scaffold_9999999"), so the hash is finalized to spread them; see `benches/contig_index.rs`.
+fn hash_name(name: &str) -> u64 {
+    let mut hasher = FxHasher::default();
+    name.hash(&mut hasher);
+    mix(hasher.finish())
+}
+
+impl ContigIndex {
+    /// Creates an empty index with room for `capacity` contigs
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            names: Vec::with_capacity(capacity),
+            table: HashTable::with_capacity(capacity),
+            loose: OnceLock::new()
+        }
+    }
+
+    /// Builds an index from names in id order
+    /// # Errors
+    /// * if a name is present more than once
+    /// * if there are more names than fit in a `ContigId`
+    pub(crate) fn from_names(names: Vec<String>) -> Result<Self, SimpleError> {
+        let mut index = Self::with_capacity(names.len());
+        for name in names.into_iter() {
+            index.insert(name)?;
+        }
+        Ok(index)
+    }
+
+    /// Adds a new name, returning its id
+    /// # Errors
+    /// * if the name is already in the index
+    /// * if there are more names than fit in a `ContigId`
+    pub(crate) fn insert(&mut self, name: String) -> Result<ContigId, SimpleError> {
+        if self.get(&name).is_some() {
+            bail!("Contig key \"{name}\" is already in the reference genome");
+        }
+        let id = match ContigId::try_from(self.names.len()) {
+            Ok(id) => id,
+            Err(_) => bail!("Cannot add contig key \"{name}\", the reference genome is limited to {} contigs", ContigId::MAX)
+        };
+        let names = &self.names;
+        self.table.insert_unique(hash_name(&name), id, |&i| hash_name(&names[i as usize]));
+        self.names.push(name);
+        self.loose.take();
+        Ok(id)
+    }
+
+    /// Looks up the id for a name
+    pub(crate) fn get(&self, name: &str) -> Option<ContigId> {
+        self.table.find(hash_name(name), |&i| self.names[i as usize] == name).copied()
+    }
+
+    /// Looks up the id of the single contig whose loose name matches that of a given name, see `loose_name(...)`
+    /// # Returns
+    /// * the id, or `None` if zero or multiple contigs match
+    pub(crate) fn get_loose(&self, name: &str) -> Option<ContigId> {
+        let loose = self.loose.get_or_init(|| {
+            let mut loose: HashMap<String, Option<ContigId>> = Default::default();
+            loose.reserve(self.names.len());
+            for (id, name) in self.names.iter().enumerate() {
+                loose.entry(loose_name(name))
+                    .and_modify(|e| *e = None)
+                    .or_insert(Some(id as ContigId));
+            }
+            loose
+        });
+        loose.get(&loose_name(name)).copied().flatten()
+    }
+
+    /// The names in id order
+    pub(crate) fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Estimates the heap bytes used by the names and the hash table
+    pub(crate) fn heap_bytes(&self) -> usize {
+        let name_bytes: usize = self.names.iter().map(|n| n.capacity()).sum();
+        let names_vec_bytes = self.names.capacity() * std::mem::size_of::<String>();
+        // hashbrown stores each entry inline plus one control byte per bucket
+        let table_bytes = self.table.capacity() * (std::mem::size_of::<ContigId>() + 1);
+        let loose_bytes: usize = self.loose.get()
+            .map(|l| l.keys().map(|k| k.capacity()).sum::<usize>() + l.capacity() * (std::mem::size_of::<(String, Option<ContigId>)>() + 1))
+            .unwrap_or_default();
+        name_bytes + names_vec_bytes + table_bytes + loose_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contig_index() {
+        let mut index = ContigIndex::default();
+        assert_eq!(index.insert("chr1".to_string()).unwrap(), 0);
+        assert_eq!(index.insert("chr2".to_string()).unwrap(), 1);
+        assert!(index.insert("chr1".to_string()).is_err());
+        assert_eq!(index.get("chr2"), Some(1));
+        assert_eq!(index.get("chr3"), None);
+        assert_eq!(index.names(), &["chr1".to_string(), "chr2".to_string()]);
+
+        assert!(ContigIndex::from_names(vec!["a".to_string(), "a".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_contig_index_loose() {
+        let mut index = ContigIndex::from_names(vec!["chr1".to_string(), "chrM".to_string(), "2".to_string(), "chr2".to_string()]).unwrap();
+        assert_eq!(index.get_loose("1"), Some(0));
+        assert_eq!(index.get_loose(" CHR1 "), Some(0));
+        assert_eq!(index.get_loose("MT"), Some(1));
+        // "2" and "chr2" are ambiguous
+        assert_eq!(index.get_loose("chr2"), None);
+        assert_eq!(index.get_loose("3"), None);
+
+        // inserting resets the loose names
+        index.insert("chr3".to_string()).unwrap();
+        assert_eq!(index.get_loose("3"), Some(4));
+        index.insert("Chr3".to_string()).unwrap();
+        assert_eq!(index.get_loose("3"), None);
+    }
+
+    #[test]
+    fn test_contig_index_many() {
+        let names: Vec<String> = (0..100_000).map(|i| format!("contig_{i}")).collect();
+        let index = ContigIndex::from_names(names).unwrap();
+        assert_eq!(index.get("contig_0"), Some(0));
+        assert_eq!(index.get("contig_99999"), Some(99_999));
+        assert!(index.heap_bytes() < 100_000 * 64);
+    }
+}
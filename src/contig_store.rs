@@ -0,0 +1,165 @@
+use crate::lazy::{ContigLoader, LazyContig};
+use crate::packed::pack_sequence;
+use simple_error::SimpleError;
+use std::error::Error;
+use std::sync::Arc;
+
+/// An encoded contig held in memory and decoded when it is accessed, e.g. 4-bit packed or compressed.
+/// Encodings are selected with `ReferenceGenomeBuilder::backend(...)` for the built-in ones, or `ReferenceGenomeBuilder::contig_store(...)`
+/// for custom ones; the sequence accessors of `ReferenceGenome` are the same for every encoding.
+/// Decoded contigs are kept in memory after the first access, or held in the LRU cache from `ReferenceGenomeBuilder::lru_cache(...)`.
+pub trait ContigStore: Send + Sync {
+    /// The number of symbols in the decoded sequence
+    fn len(&self) -> usize;
+
+    /// Returns true if the decoded sequence is empty
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Decodes the full ASCII sequence; it is used as-is, so any case conversion must already be applied by the encoder
+    /// # Errors
+    /// * if the stored data cannot be decoded
+    fn decode(&self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+
+    /// The heap bytes held by the encoded contig, reported by `ReferenceGenome::memory_usage()`
+    fn stored_bytes(&self) -> usize;
+}
+
+/// A function that encodes a contig during the load, given the contig name and the ASCII sequence after upper-casing (if enabled)
+pub type EncodeFn = dyn Fn(&str, Vec<u8>) -> Result<Box<dyn ContigStore>, SimpleError>;
+
+/// A boxed `EncodeFn`, see `ReferenceGenomeBuilder::contig_store(...)`
+pub type ContigEncoder = Box<EncodeFn>;
+
+/// Plain ASCII storage, one byte per symbol
+impl ContigStore for Vec<u8> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn decode(&self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        Ok(self.clone())
+    }
+
+    fn stored_bytes(&self) -> usize {
+        self.capacity()
+    }
+}
+
+/// Stores a contig as plain ASCII, one byte per symbol. This only differs from `Backend::InMemory` in that accesses copy the sequence,
+/// so it mainly serves as a baseline for custom encodings.
+/// # Errors
+/// * never fails; the signature matches `ContigEncoder`
+pub fn encode_ascii(_seq_id: &str, mut sequence: Vec<u8>) -> Result<Box<dyn ContigStore>, SimpleError> {
+    sequence.shrink_to_fit();
+    Ok(Box::new(sequence))
+}
+
+/// Stores a contig with 4 bits per symbol, as used by `Backend::Packed`
+/// # Errors
+/// * if the sequence contains a symbol other than `=ACMGRSVTWYHKDBN` (in either case), reporting the first 0-based position
+pub fn encode_packed(seq_id: &str, sequence: Vec<u8>) -> Result<Box<dyn ContigStore>, SimpleError> {
+    Ok(Box::new(pack_sequence(seq_id, &sequence)?))
+}
+
+/// A contig compressed with raw DEFLATE
+#[cfg(feature = "gzip")]
+struct CompressedSequence {
+    /// The number of symbols
+    length: usize,
+    /// The compressed symbols
+    compressed: Vec<u8>
+}
+
+#[cfg(feature = "gzip")]
+impl ContigStore for CompressedSequence {
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn decode(&self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        use std::io::Read;
+        let mut sequence: Vec<u8> = Vec::with_capacity(self.length);
+        flate2::read::DeflateDecoder::new(&self.compressed[..]).read_to_end(&mut sequence)?;
+        if sequence.len() != self.length {
+            return Err(format!("Decompressed {} bytes, expected {}", sequence.len(), self.length).into());
+        }
+        Ok(sequence)
+    }
+
+    fn stored_bytes(&self) -> usize {
+        self.compressed.capacity()
+    }
+}
+
+/// Stores a contig compressed with DEFLATE, as used by `Backend::Compressed`. Any symbols are supported and case is kept exactly.
+/// Requires the `gzip` feature.
+/// # Errors
+/// * if compression fails
+#[cfg(feature = "gzip")]
+pub fn encode_compressed(seq_id: &str, sequence: Vec<u8>) -> Result<Box<dyn ContigStore>, SimpleError> {
+    use std::io::Write;
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut compressed = encoder.write_all(&sequence)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| SimpleError::new(format!("Failed to compress contig {seq_id:?}: {e}")))?;
+    compressed.shrink_to_fit();
+    Ok(Box::new(CompressedSequence { length: sequence.len(), compressed }))
+}
+
+/// Adapts a single encoded contig to the lazy contig machinery
+struct EncodedContig {
+    /// The contig name, used for error messages
+    name: String,
+    /// The encoded sequence
+    store: Box<dyn ContigStore>
+}
+
+impl ContigLoader for EncodedContig {
+    fn contig_name(&self, _index: usize) -> &str {
+        &self.name
+    }
+
+    fn contig_length(&self, _index: usize) -> usize {
+        self.store.len()
+    }
+
+    fn load_contig(&self, _index: usize) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        self.store.decode()
+    }
+
+    fn stored_bytes(&self, _index: usize) -> usize {
+        self.store.stored_bytes()
+    }
+}
+
+/// Wraps an encoded contig into a contig that is decoded on first access
+pub(crate) fn lazy_encoded_contig(seq_id: &str, store: Box<dyn ContigStore>) -> LazyContig {
+    let loader = EncodedContig { name: seq_id.to_string(), store };
+    // case is already applied before encoding
+    LazyContig::new(Arc::new(loader), 0, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodings() {
+        let sequence = b"ACGTacgtNNNNacgtRYKM".to_vec();
+        let mut encoders: Vec<&EncodeFn> = vec![&encode_ascii, &encode_packed];
+        #[cfg(feature = "gzip")]
+        encoders.push(&encode_compressed);
+        for encoder in encoders {
+            let store = encoder("chr1", sequence.clone()).unwrap();
+            assert_eq!((store.len(), store.is_empty()), (sequence.len(), false));
+            assert_eq!(store.decode().unwrap(), sequence);
+
+            let contig = lazy_encoded_contig("chr1", store);
+            assert_eq!(contig.len(), sequence.len());
+            assert_eq!(contig.try_sequence().unwrap(), sequence);
+        }
+        assert!(encode_packed("chr1", b"ACGU".to_vec()).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::alphabet::reverse_complement;
+use crate::reference_genome::ReferenceGenome;
+use bytes::Bytes;
+use simple_error::{bail, SimpleError};
+use std::ops::Range;
+
+/// A 0-based position on a contig. Values from 1-based sources (VCF, GFF, samtools regions) must go through
+/// `Position::from_one_based(...)`, so a coordinate system mix-up is a type error rather than an off-by-one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position(usize);
+
+impl Position {
+    /// Creates a position from a 0-based value
+    pub fn zero_based(position: usize) -> Self {
+        Self(position)
+    }
+
+    /// Creates a position from a 1-based value
+    /// # Errors
+    /// * if `position` is 0, which does not exist in 1-based coordinates
+    pub fn from_one_based(position: usize) -> Result<Self, SimpleError> {
+        match position.checked_sub(1) {
+            Some(position) => Ok(Self(position)),
+            None => bail!("1-based position must be at least 1")
+        }
+    }
+
+    /// The 0-based value
+    pub fn get(&self) -> usize {
+        self.0
+    }
+
+    /// The 1-based value
+    pub fn one_based(&self) -> usize {
+        self.0 + 1
+    }
+}
+
+/// The strand of a feature relative to the reference
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Strand {
+    /// The reference (plus) strand
+    #[default]
+    Forward,
+    /// The reverse complement (minus) strand
+    Reverse
+}
+
+impl Strand {
+    /// Parses a `+` or `-` strand symbol, e.g. from a BED or GFF column, or `None` for any other symbol
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '+' => Some(Strand::Forward),
+            '-' => Some(Strand::Reverse),
+            _ => None
+        }
+    }
+
+    /// The strand symbol, `+` or `-`
+    pub fn symbol(&self) -> char {
+        match self {
+            Strand::Forward => '+',
+            Strand::Reverse => '-'
+        }
+    }
+}
+
+/// A 0-based, half-open interval on a contig, with the start at or before the end
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval {
+    /// The first position (included)
+    start: Position,
+    /// The end position (excluded)
+    end: Position
+}
+
+impl Interval {
+    /// Creates an interval from its start (included) and end (excluded)
+    /// # Errors
+    /// * if `start` is after `end`
+    pub fn new(start: Position, end: Position) -> Result<Self, SimpleError> {
+        if start > end {
+            bail!("Interval start {} is after the end {}", start.get(), end.get());
+        }
+        Ok(Self { start, end })
+    }
+
+    /// Creates an interval from 0-based, half-open values, as used by BED and this crate's `usize` APIs
+    /// # Errors
+    /// * if `start` > `end`
+    pub fn zero_based(start: usize, end: usize) -> Result<Self, SimpleError> {
+        Self::new(Position::zero_based(start), Position::zero_based(end))
+    }
+
+    /// Creates an interval from 1-based, fully closed values, as used by VCF, GFF, and samtools regions (e.g. `chr1:100-200`)
+    /// # Errors
+    /// * if `start` is 0 or the interval ends before it starts; an empty interval is written as `end = start - 1`
+    pub fn from_one_based_closed(start: usize, end: usize) -> Result<Self, SimpleError> {
+        Self::new(Position::from_one_based(start)?, Position::zero_based(end))
+    }
+
+    /// The first position (included)
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    /// The end position (excluded)
+    pub fn end(&self) -> Position {
+        self.end
+    }
+
+    /// The number of positions in the interval
+    pub fn len(&self) -> usize {
+        self.end.get() - self.start.get()
+    }
+
+    /// Returns true if the interval is empty
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns true if the interval contains a position
+    pub fn contains(&self, position: Position) -> bool {
+        self.start <= position && position < self.end
+    }
+
+    /// The interval as 1-based, fully closed values (start, end)
+    pub fn one_based_closed(&self) -> (usize, usize) {
+        (self.start.one_based(), self.end.get())
+    }
+}
+
+impl From<Interval> for Range<usize> {
+    fn from(interval: Interval) -> Self {
+        interval.start.get()..interval.end.get()
+    }
+}
+
+impl ReferenceGenome {
+    /// Retrieves the base at a position, or `None` if the contig is not in the reference genome, was unloaded, or is shorter than the position
+    /// # Arguments
+    /// * `chromosome` - the contig to read from
+    /// * `position` - the position of the base
+    pub fn base_at(&self, chromosome: &str, position: Position) -> Option<u8> {
+        self.try_get_full_chromosome_shared(chromosome)
+            .and_then(|sequence| sequence.get(position.get()).copied())
+    }
+
+    /// Fetches an interval on either strand as a shared handle; the reverse strand is reverse complemented.
+    /// Unlike `get_slice(...)`, intervals past the end of the contig are an error rather than being truncated.
+    /// # Arguments
+    /// * `chromosome` - the contig to fetch from
+    /// * `interval` - the interval to fetch
+    /// * `strand` - the strand to read
+    /// # Errors
+    /// * if the contig is not in the reference genome, was unloaded, or fails to load
+    /// * if the interval ends past the end of the contig
+    pub fn fetch_interval(&self, chromosome: &str, interval: Interval, strand: Strand) -> Result<Bytes, SimpleError> {
+        let Some(sequence) = self.try_get_full_chromosome_shared(chromosome) else {
+            bail!("{}", self.missing_contig_message(chromosome));
+        };
+        if interval.end().get() > sequence.len() {
+            bail!("Interval {}-{} is past the end of contig {:?} with length {}", interval.start().get(), interval.end().get(), chromosome, sequence.len());
+        }
+        let slice = sequence.slice(Range::from(interval));
+        match strand {
+            Strand::Forward => Ok(slice),
+            Strand::Reverse => Ok(Bytes::from(reverse_complement(&slice)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coordinates() {
+        let position = Position::from_one_based(10).unwrap();
+        assert_eq!((position.get(), position.one_based()), (9, 10));
+        assert_eq!(position, Position::zero_based(9));
+        assert!(Position::from_one_based(0).is_err());
+
+        let interval = Interval::from_one_based_closed(3, 6).unwrap();
+        assert_eq!(interval, Interval::zero_based(2, 6).unwrap());
+        assert_eq!((interval.len(), interval.one_based_closed()), (4, (3, 6)));
+        assert!(interval.contains(Position::zero_based(5)) && !interval.contains(Position::zero_based(6)));
+        assert!(Interval::from_one_based_closed(3, 2).unwrap().is_empty());
+        assert!(Interval::from_one_based_closed(3, 1).is_err());
+        assert_eq!(Range::from(interval), 2..6);
+
+        assert_eq!(Strand::from_symbol('-'), Some(Strand::Reverse));
+        assert_eq!(Strand::from_symbol('.'), None);
+        assert_eq!(Strand::Forward.symbol(), '+');
+    }
+
+    #[test]
+    fn test_fetch_interval() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nAACCGGTT\n").unwrap();
+        let interval = Interval::from_one_based_closed(2, 4).unwrap();
+        assert_eq!(reference_genome.fetch_interval("chr1", interval, Strand::Forward).unwrap(), &b"ACC"[..]);
+        assert_eq!(reference_genome.fetch_interval("chr1", interval, Strand::Reverse).unwrap(), &b"GGT"[..]);
+        assert!(reference_genome.fetch_interval("chr1", Interval::zero_based(6, 9).unwrap(), Strand::Forward).is_err());
+        assert!(reference_genome.fetch_interval("chr2", interval, Strand::Forward).is_err());
+
+        assert_eq!(reference_genome.base_at("chr1", Position::from_one_based(3).unwrap()), Some(b'C'));
+        assert_eq!(reference_genome.base_at("chr1", Position::zero_based(8)), None);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::builder::{decompress, is_gzip};
+use crate::reference_genome::ReferenceGenome;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use simple_error::{bail, SimpleError};
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A single `@SQ` line of a sequence dictionary
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DictEntry {
+    /// The contig name (`SN`)
+    pub name: String,
+    /// The contig length (`LN`)
+    pub length: usize,
+    /// The lower-case MD5 digest of the upper-cased sequence (`M5`), if present
+    pub md5: Option<String>,
+    /// The remaining tags in file order as (tag, value), such as `UR`, `AS`, and `SP`
+    pub other_tags: Vec<(String, String)>
+}
+
+/// The `@SQ` entries of a Picard/samtools sequence dictionary (`.dict`) or SAM header, in file order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SequenceDictionary {
+    /// The entries in file order
+    pub entries: Vec<DictEntry>
+}
+
+impl SequenceDictionary {
+    /// Parses the `@SQ` lines of a sequence dictionary or SAM header; other lines are ignored
+    /// # Arguments
+    /// * `reader` - the uncompressed dictionary
+    /// # Errors
+    /// * any reading errors
+    /// * if an `@SQ` line is missing `SN` or `LN`, has an invalid length or digest, or repeats a contig name
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut entries: Vec<DictEntry> = vec![];
+        let mut seen: HashMap<String, usize> = Default::default();
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let Some(tags) = line.trim_end_matches('\r').strip_prefix("@SQ\t") else {
+                continue;
+            };
+            let (mut name, mut length, mut md5) = (None, None, None);
+            let mut other_tags: Vec<(String, String)> = vec![];
+            for field in tags.split('\t') {
+                let Some((tag, value)) = field.split_once(':') else {
+                    bail!("Invalid tag {:?} on line {} of the dictionary", field, line_index + 1);
+                };
+                match tag {
+                    "SN" => name = Some(value.to_string()),
+                    "LN" => match value.parse::<usize>() {
+                        Ok(value) => length = Some(value),
+                        Err(_) => bail!("Invalid length {:?} on line {} of the dictionary", value, line_index + 1)
+                    },
+                    "M5" => {
+                        let value = value.to_ascii_lowercase();
+                        if value.len() != 32 || !value.bytes().all(|c| c.is_ascii_hexdigit()) {
+                            bail!("Invalid MD5 digest {:?} on line {} of the dictionary", value, line_index + 1);
+                        }
+                        md5 = Some(value);
+                    },
+                    _ => other_tags.push((tag.to_string(), value.to_string()))
+                }
+            }
+            let (Some(name), Some(length)) = (name, length) else {
+                bail!("Expected SN and LN tags on line {} of the dictionary: {:?}", line_index + 1, line);
+            };
+            if let Some(previous) = seen.insert(name.clone(), line_index + 1) {
+                bail!("Contig {:?} is on lines {} and {} of the dictionary", name, previous, line_index + 1);
+            }
+            entries.push(DictEntry { name, length, md5, other_tags });
+        }
+        Ok(Self { entries })
+    }
+
+    /// Parses the `@SQ` lines of a sequence dictionary or SAM header, see `from_reader(...)`
+    /// # Arguments
+    /// * `dict_fn` - the dictionary filename; gzip compression is detected from a `.gz` extension
+    /// # Errors
+    /// * any file reading errors
+    /// * see `from_reader(...)`
+    pub fn from_file(dict_fn: &Path) -> Result<Self, Box<dyn Error>> {
+        let dict_file = std::fs::File::open(dict_fn)?;
+        let reader = decompress(Box::new(BufReader::new(dict_file)), is_gzip(dict_fn), &Arc::default())?;
+        Self::from_reader(reader)
+    }
+
+    /// The entry for a contig, or `None` if it is not in the dictionary
+    pub fn get(&self, name: &str) -> Option<&DictEntry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+}
+
+/// A contig with a different length in the dictionary and the genome
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DictLengthMismatch {
+    /// The contig name
+    pub contig: String,
+    /// The length in the dictionary
+    pub dict_length: usize,
+    /// The length in the genome
+    pub genome_length: usize
+}
+
+/// A contig whose `M5` digest in the dictionary does not match the genome sequence
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DictMd5Mismatch {
+    /// The contig name
+    pub contig: String,
+    /// The digest in the dictionary
+    pub dict_md5: String,
+    /// The digest of the genome sequence
+    pub genome_md5: String
+}
+
+/// The result of `ReferenceGenome::validate_dict(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DictValidation {
+    /// Dictionary contigs that are not in the genome, in dictionary order
+    pub missing_from_genome: Vec<String>,
+    /// Genome contigs that are not in the dictionary, in load order
+    pub missing_from_dict: Vec<String>,
+    /// Shared contigs with different lengths, in dictionary order
+    pub length_mismatches: Vec<DictLengthMismatch>,
+    /// Shared, same-length contigs with different digests, in dictionary order
+    pub md5_mismatches: Vec<DictMd5Mismatch>,
+    /// True if the shared contigs are in a different order, which GATK also rejects
+    pub order_differs: bool
+}
+
+impl DictValidation {
+    /// Returns true if the dictionary matches the genome in contigs, order, lengths, and digests
+    pub fn is_valid(&self) -> bool {
+        self.missing_from_genome.is_empty() &&
+            self.missing_from_dict.is_empty() &&
+            self.length_mismatches.is_empty() &&
+            self.md5_mismatches.is_empty() &&
+            !self.order_differs
+    }
+
+    /// One line per problem, e.g. for an error message or a log; empty if the dictionary is valid
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = vec![];
+        problems.extend(self.missing_from_genome.iter().map(|c| format!("{c}: in the dictionary but not in the genome")));
+        problems.extend(self.missing_from_dict.iter().map(|c| format!("{c}: in the genome but not in the dictionary")));
+        problems.extend(self.length_mismatches.iter().map(|m| format!("{}: length {} in the dictionary, {} in the genome", m.contig, m.dict_length, m.genome_length)));
+        problems.extend(self.md5_mismatches.iter().map(|m| format!("{}: M5 {} in the dictionary, {} in the genome", m.contig, m.dict_md5, m.genome_md5)));
+        if self.order_differs {
+            problems.push("the contig order differs between the dictionary and the genome".to_string());
+        }
+        problems
+    }
+}
+
+impl ReferenceGenome {
+    /// Compares a sequence dictionary against the genome, reporting contigs missing from either side, contig order differences,
+    /// length mismatches, and `M5` digest mismatches; a stale dictionary next to a regenerated FASTA is a common cause of GATK failures.
+    /// Digests are only computed for same-length contigs that have an `M5` tag, reading lazily loaded contigs without keeping them.
+    /// # Arguments
+    /// * `dictionary` - the dictionary to check, e.g. from `SequenceDictionary::from_file(...)`
+    /// # Errors
+    /// * if a contig that needs a digest was unloaded or fails to load
+    pub fn validate_dict(&self, dictionary: &SequenceDictionary) -> Result<DictValidation, SimpleError> {
+        let mut validation = DictValidation::default();
+        let mut shared_order: Vec<usize> = vec![];
+        for entry in dictionary.entries.iter() {
+            let Some(id) = self.contig_id(&entry.name) else {
+                validation.missing_from_genome.push(entry.name.clone());
+                continue;
+            };
+            shared_order.push(id as usize);
+            let genome_length = self.contig_length(&entry.name).unwrap_or_default();
+            if genome_length != entry.length {
+                validation.length_mismatches.push(DictLengthMismatch {
+                    contig: entry.name.clone(),
+                    dict_length: entry.length,
+                    genome_length
+                });
+                continue;
+            }
+            if let Some(dict_md5) = entry.md5.as_ref() {
+                let genome_md5 = self.try_contig_digests(&entry.name)?.md5.clone();
+                if &genome_md5 != dict_md5 {
+                    validation.md5_mismatches.push(DictMd5Mismatch { contig: entry.name.clone(), dict_md5: dict_md5.clone(), genome_md5 });
+                }
+            }
+        }
+        let dict_names: HashSet<&str> = dictionary.entries.iter().map(|e| e.name.as_str()).collect();
+        validation.missing_from_dict = self.contig_keys().iter()
+            .filter(|c| !dict_names.contains(c.as_str()))
+            .cloned()
+            .collect();
+        validation.order_differs = shared_order.windows(2).any(|w| w[0] > w[1]);
+        Ok(validation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_read_dict() {
+        let dictionary = SequenceDictionary::from_file(&PathBuf::from("./test_data/test_reference.dict")).unwrap();
+        assert_eq!(dictionary.entries.len(), 2);
+        assert_eq!(dictionary.get("chr2").unwrap(), &DictEntry {
+            name: "chr2".to_string(),
+            length: 8,
+            md5: Some("0f4a16fa40a62460a647beef79a0ec45".to_string()),
+            other_tags: vec![("UR".to_string(), "file:test_reference.fa".to_string())]
+        });
+
+        assert!(SequenceDictionary::from_reader(&b"@SQ\tSN:chr1\n"[..]).is_err());
+        assert!(SequenceDictionary::from_reader(&b"@SQ\tSN:chr1\tLN:x\n"[..]).is_err());
+        assert!(SequenceDictionary::from_reader(&b"@SQ\tSN:chr1\tLN:4\tM5:abc\n"[..]).is_err());
+        assert!(SequenceDictionary::from_reader(&b"@SQ\tSN:chr1\tLN:4\n@SQ\tSN:chr1\tLN:4\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_validate_dict() {
+        let reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        let dictionary = SequenceDictionary::from_file(&PathBuf::from("./test_data/test_reference.dict")).unwrap();
+        let validation = reference_genome.validate_dict(&dictionary).unwrap();
+        assert!(validation.is_valid() && validation.problems().is_empty());
+
+        // the genome's own dictionary is valid
+        let mut written: Vec<u8> = vec![];
+        reference_genome.write_dict(&mut written).unwrap();
+        assert!(reference_genome.validate_dict(&SequenceDictionary::from_reader(&written[..]).unwrap()).unwrap().is_valid());
+
+        let stale = b"@SQ\tSN:chr2\tLN:8\tM5:cc0af3a4fedb18378b4b57b98068e69f\n@SQ\tSN:chr1\tLN:9\n@SQ\tSN:chrM\tLN:16569\n";
+        let validation = reference_genome.validate_dict(&SequenceDictionary::from_reader(&stale[..]).unwrap()).unwrap();
+        assert_eq!(validation.missing_from_genome, vec!["chrM".to_string()]);
+        assert!(validation.missing_from_dict.is_empty());
+        assert_eq!(validation.length_mismatches[0], DictLengthMismatch { contig: "chr1".to_string(), dict_length: 9, genome_length: 8 });
+        assert_eq!(validation.md5_mismatches[0].genome_md5, "0f4a16fa40a62460a647beef79a0ec45");
+        assert!(validation.order_differs);
+        assert_eq!(validation.problems().len(), 4);
+    }
+}
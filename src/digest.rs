@@ -0,0 +1,675 @@
+use crate::reference_genome::{ContigId, ContigSequence, OutOfBoundsPolicy, ReferenceGenome};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use rustc_hash::FxHashMap as HashMap;
+use simple_error::{bail, SimpleError};
+use std::path::Path;
+
+/// Computes the lower-case hexadecimal MD5 digest of a sequence, matching the `M5` tag of a SAM/`.dict` header
+/// # Arguments
+/// * `sequence` - the sequence to digest
+pub fn md5_hex(sequence: &[u8]) -> String {
+    format!("{:x}", md5::compute(sequence))
+}
+
+/// Computes the MD5 digest of a sequence as if it were upper-cased, which is how SAM/`.dict` `M5` tags are defined
+/// # Arguments
+/// * `sequence` - the sequence to digest
+pub(crate) fn md5_hex_uppercase(sequence: &[u8]) -> String {
+    if !sequence.iter().any(|c| c.is_ascii_lowercase()) {
+        return md5_hex(sequence);
+    }
+    // upper-case in chunks to avoid copying the whole contig
+    let mut context = md5::Context::new();
+    let mut buffer = [0u8; 8192];
+    for chunk in sequence.chunks(buffer.len()) {
+        let buffer = &mut buffer[..chunk.len()];
+        buffer.copy_from_slice(chunk);
+        buffer.make_ascii_uppercase();
+        context.consume(buffer);
+    }
+    format!("{:x}", context.finalize())
+}
+
+/// The SHA-512 round constants
+const SHA512_K: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817
+];
+
+/// The SHA-512 initial hash value
+const SHA512_INITIAL: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179
+];
+
+/// A streaming SHA-512 (FIPS 180-4) context, only used for GA4GH `sha512t24u` digests
+struct Sha512 {
+    /// The running hash value
+    state: [u64; 8],
+    /// The bytes of a partial block
+    buffer: Vec<u8>,
+    /// The number of bytes consumed
+    length: u128
+}
+
+impl Sha512 {
+    /// Creates a context for an empty message
+    fn new() -> Self {
+        Self { state: SHA512_INITIAL, buffer: Vec::with_capacity(128), length: 0 }
+    }
+
+    /// Appends bytes to the message
+    fn consume(&mut self, data: &[u8]) {
+        self.length += data.len() as u128;
+        let mut data = data;
+        if !self.buffer.is_empty() {
+            let take = (128 - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buffer.len() < 128 {
+                return;
+            }
+            let block: [u8; 128] = self.buffer[..].try_into().unwrap();
+            self.compress(&block);
+            self.buffer.clear();
+        }
+        let mut blocks = data.chunks_exact(128);
+        for block in blocks.by_ref() {
+            self.compress(block.try_into().unwrap());
+        }
+        self.buffer.extend_from_slice(blocks.remainder());
+    }
+
+    /// Applies the compression function to one 128-byte block
+    fn compress(&mut self, block: &[u8; 128]) {
+        let mut w = [0u64; 80];
+        for (i, word) in block.chunks_exact(8).enumerate() {
+            w[i] = u64::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..80 {
+            let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+            let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for (k, w) in SHA512_K.iter().zip(w.iter()) {
+            let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(*k).wrapping_add(*w);
+            let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+        }
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    /// Pads the message and returns the 64-byte digest
+    fn finalize(mut self) -> [u8; 64] {
+        let bit_length = self.length * 8;
+        let mut padding = vec![0x80u8];
+        padding.resize((239 - self.buffer.len()) % 128 + 1, 0);
+        padding.extend_from_slice(&bit_length.to_be_bytes());
+        self.consume(&padding);
+        let mut digest = [0u8; 64];
+        for (bytes, value) in digest.chunks_exact_mut(8).zip(self.state.iter()) {
+            bytes.copy_from_slice(&value.to_be_bytes());
+        }
+        digest
+    }
+}
+
+/// Encodes bytes as unpadded, URL-safe base64
+fn base64url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let value = chunk.iter().enumerate().fold(0u32, |v, (i, &b)| v | (b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(value >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// Computes the GA4GH `sha512t24u` digest of a sequence (the URL-safe base64 of the first 24 bytes of its SHA-512), as used by refget
+/// and sequence collections; refget identifiers add an `SQ.` prefix
+/// # Arguments
+/// * `sequence` - the sequence to digest, which refget expects to be upper-cased
+pub fn sha512t24u(sequence: &[u8]) -> String {
+    let mut context = Sha512::new();
+    context.consume(sequence);
+    base64url(&context.finalize()[..24])
+}
+
+/// Computes the `sha512t24u` digest of a sequence as if it were upper-cased, see `sha512t24u(...)`
+/// # Arguments
+/// * `sequence` - the sequence to digest
+pub(crate) fn sha512t24u_uppercase(sequence: &[u8]) -> String {
+    let mut context = Sha512::new();
+    let mut buffer = [0u8; 8192];
+    for chunk in sequence.chunks(buffer.len()) {
+        let buffer = &mut buffer[..chunk.len()];
+        buffer.copy_from_slice(chunk);
+        buffer.make_ascii_uppercase();
+        context.consume(buffer);
+    }
+    base64url(&context.finalize()[..24])
+}
+
+/// The digests of a contig's upper-cased sequence, from `ReferenceGenome::contig_digests(...)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContigDigests {
+    /// The lower-case hexadecimal MD5 digest, as in SAM/`.dict` `M5` tags
+    pub md5: String,
+    /// The GA4GH `sha512t24u` digest, as used by refget and sequence collections
+    pub sha512t24u: String
+}
+
+impl ContigDigests {
+    /// Computes both digests of a sequence as if it were upper-cased
+    fn of(sequence: &[u8]) -> Self {
+        Self {
+            md5: md5_hex_uppercase(sequence),
+            sha512t24u: sha512t24u_uppercase(sequence)
+        }
+    }
+}
+
+/// Computes `ContigDigests` incrementally over a sequence that arrives in pieces, e.g. line by line from a FASTA stream
+pub(crate) struct StreamingDigests {
+    /// The running MD5 context
+    md5: md5::Context,
+    /// The running SHA-512 context
+    sha512: Sha512,
+    /// Scratch space for upper-casing
+    buffer: Vec<u8>
+}
+
+impl StreamingDigests {
+    /// Creates digests of an empty sequence
+    pub(crate) fn new() -> Self {
+        Self { md5: md5::Context::new(), sha512: Sha512::new(), buffer: vec![] }
+    }
+
+    /// Appends a piece of sequence, which is digested as if it were upper-cased
+    pub(crate) fn consume(&mut self, sequence: &[u8]) {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(sequence);
+        self.buffer.make_ascii_uppercase();
+        self.md5.consume(&self.buffer);
+        self.sha512.consume(&self.buffer);
+    }
+
+    /// Returns the digests of everything consumed
+    pub(crate) fn finalize(self) -> ContigDigests {
+        ContigDigests {
+            md5: format!("{:x}", self.md5.finalize()),
+            sha512t24u: base64url(&self.sha512.finalize()[..24])
+        }
+    }
+}
+
+/// The digests of every contig of a genome, cached on the genome by `ReferenceGenome::digest_cache()`
+pub(crate) struct DigestCache {
+    /// The digests of each contig by ID, or `None` for contigs that are unloaded or fail to load
+    contigs: Vec<Option<ContigDigests>>,
+    /// Contig IDs by MD5 digest; for identical sequences, the first contig in load order
+    pub(crate) md5_index: HashMap<String, ContigId>
+}
+
+impl DigestCache {
+    /// Digests every contig, in parallel with the `rayon` feature. Lazily loaded contigs are read once and are not kept in memory.
+    /// # Arguments
+    /// * `names` - the contig names by ID
+    /// * `sequences` - the contig sequences by ID
+    pub(crate) fn compute(names: &[String], sequences: &[ContigSequence]) -> Self {
+        let digest = |(name, sequence): (&String, &ContigSequence)| {
+            sequence.try_as_bytes_unkept(name).ok().map(|bytes| ContigDigests::of(&bytes))
+        };
+        #[cfg(feature = "rayon")]
+        let contigs: Vec<Option<ContigDigests>> = names.par_iter().zip(sequences.par_iter()).map(digest).collect();
+        #[cfg(not(feature = "rayon"))]
+        let contigs: Vec<Option<ContigDigests>> = names.iter().zip(sequences.iter()).map(digest).collect();
+
+        let mut md5_index: HashMap<String, ContigId> = Default::default();
+        for (id, digests) in contigs.iter().enumerate() {
+            if let Some(digests) = digests {
+                md5_index.entry(digests.md5.clone()).or_insert(id as ContigId);
+            }
+        }
+        Self { contigs, md5_index }
+    }
+}
+
+/// Expected per-contig MD5 digests, used to verify a genome as it is loaded (see `ReferenceGenomeBuilder::verify_md5(...)`).
+/// Digests follow the SAM `M5` convention: lower-case hexadecimal over the upper-cased sequence.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Md5Manifest {
+    /// Expected digests by contig name
+    digests: HashMap<String, String>
+}
+
+impl Md5Manifest {
+    /// Creates an empty manifest
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds or replaces the expected digest of a contig
+    /// # Arguments
+    /// * `contig` - the contig name
+    /// * `md5` - the hexadecimal MD5 digest, in either case
+    /// # Errors
+    /// * if `md5` is not 32 hexadecimal characters
+    pub fn insert(&mut self, contig: String, md5: &str) -> Result<(), SimpleError> {
+        if md5.len() != 32 || !md5.bytes().all(|c| c.is_ascii_hexdigit()) {
+            bail!("Invalid MD5 digest for contig \"{}\": {:?}", contig, md5);
+        }
+        self.digests.insert(contig, md5.to_ascii_lowercase());
+        Ok(())
+    }
+
+    /// The expected digest of a contig, if present
+    pub fn get(&self, contig: &str) -> Option<&str> {
+        self.digests.get(contig).map(|d| d.as_str())
+    }
+
+    /// The number of contigs in the manifest
+    pub fn len(&self) -> usize {
+        self.digests.len()
+    }
+
+    /// Returns true if the manifest has no contigs
+    pub fn is_empty(&self) -> bool {
+        self.digests.is_empty()
+    }
+
+    /// Removes contigs whose names do not match a predicate, such as a load filter
+    pub(crate) fn retain<F>(&mut self, predicate: F) where F: Fn(&str) -> bool {
+        self.digests.retain(|contig, _| predicate(contig));
+    }
+
+    /// Loads the `SN` and `M5` tags of each `@SQ` line in a Picard/samtools sequence dictionary (`.dict`) or SAM header
+    /// # Errors
+    /// * if the file cannot be read
+    /// * if an `@SQ` line is missing either tag or has an invalid digest
+    pub fn from_dict(dict_fn: &Path) -> Result<Md5Manifest, Box<dyn std::error::Error>> {
+        let mut manifest = Md5Manifest::new();
+        for line in std::fs::read_to_string(dict_fn)?.lines() {
+            if !line.starts_with("@SQ\t") {
+                continue;
+            }
+            let tag = |name: &str| line.split('\t').find_map(|f| f.strip_prefix(name));
+            match (tag("SN:"), tag("M5:")) {
+                (Some(contig), Some(md5)) => manifest.insert(contig.to_string(), md5)?,
+                _ => bail!("Failed to parse {:?}, expected SN and M5 tags: {:?}", dict_fn, line)
+            };
+        }
+        Ok(manifest)
+    }
+
+    /// Loads an `md5sum`-style manifest, where each line is a digest and a contig name separated by whitespace
+    /// (e.g. `cc0af3a4fedb18378b4b57b98068e69f  chr1`); blank lines are ignored
+    /// # Errors
+    /// * if the file cannot be read
+    /// * if a line does not have both fields or has an invalid digest
+    pub fn from_md5sum(md5sum_fn: &Path) -> Result<Md5Manifest, Box<dyn std::error::Error>> {
+        let mut manifest = Md5Manifest::new();
+        for line in std::fs::read_to_string(md5sum_fn)?.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match line.split_once(char::is_whitespace) {
+                // md5sum marks binary mode with a `*` before the name
+                Some((md5, contig)) => {
+                    let contig = contig.trim_start();
+                    manifest.insert(contig.strip_prefix('*').unwrap_or(contig).to_string(), md5)?
+                },
+                None => bail!("Failed to parse {:?}, expected a digest and contig name: {:?}", md5sum_fn, line)
+            };
+        }
+        Ok(manifest)
+    }
+
+    /// Loads a level 2 sequence collection (seqcol) JSON object, pairing its `names` and `sequences` arrays.
+    /// Only MD5 sequence digests (e.g. legacy refget identifiers, optionally prefixed with `md5:`) can be verified;
+    /// collections that use `SQ.` (sha512t24u) digests are rejected. Requires the `seqcol` feature.
+    /// # Errors
+    /// * if the file cannot be read or is not valid JSON
+    /// * if the arrays are missing, have different lengths, or contain digests that are not MD5
+    #[cfg(feature = "seqcol")]
+    pub fn from_seqcol(seqcol_fn: &Path) -> Result<Md5Manifest, Box<dyn std::error::Error>> {
+        let collection: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(seqcol_fn)?)?;
+        let array = |name: &str| -> Result<Vec<String>, SimpleError> {
+            let Some(values) = collection.get(name).and_then(|v| v.as_array()) else {
+                bail!("Failed to parse {:?}, expected a \"{}\" array", seqcol_fn, name);
+            };
+            values.iter()
+                .map(|v| v.as_str().map(|s| s.to_string()).ok_or_else(|| SimpleError::new(format!("Failed to parse {:?}, expected strings in \"{}\"", seqcol_fn, name))))
+                .collect()
+        };
+        let names = array("names")?;
+        let sequences = array("sequences")?;
+        if names.len() != sequences.len() {
+            bail!("Failed to parse {:?}, \"names\" and \"sequences\" have different lengths", seqcol_fn);
+        }
+
+        let mut manifest = Md5Manifest::new();
+        for (contig, digest) in names.into_iter().zip(sequences.iter()) {
+            let md5 = digest.strip_prefix("md5:").unwrap_or(digest);
+            if md5.starts_with("SQ.") {
+                bail!("Contig \"{}\" has a sha512t24u digest ({}), but only MD5 digests can be verified", contig, digest);
+            }
+            manifest.insert(contig, md5)?;
+        }
+        Ok(manifest)
+    }
+}
+
+impl ReferenceGenome {
+    /// Retrieves the MD5 digest of a contig, or `None` if the contig is not in the reference genome, was unloaded, or fails to load.
+    /// The digest is over the upper-cased sequence, so it matches the `M5` tag computed by samtools/Picard whether or not the genome
+    /// was upper-cased at load; it comes from the cache of `contig_digests(...)`, so lazily loaded contigs are not kept in memory.
+    /// # Arguments
+    /// * `chromosome` - the contig to digest
+    pub fn contig_md5(&self, chromosome: &str) -> Option<String> {
+        let name = self.resolve_contig_name(chromosome)?;
+        self.contig_digests(name).map(|d| d.md5.clone())
+    }
+
+    /// Retrieves the MD5 and GA4GH digests of a contig's upper-cased sequence, or `None` if the contig is not in the reference genome,
+    /// was unloaded, or fails to load. The first call digests every contig (in parallel with the `rayon` feature, reading lazily loaded
+    /// contigs without keeping them), and the digests are cached on the genome for `.dict` generation, header validation, and
+    /// `contig_by_md5(...)`, shared with clones made afterwards, and recomputed after any change to the sequences.
+    /// # Arguments
+    /// * `chromosome` - the contig to look up; no lookup normalization is applied
+    pub fn contig_digests(&self, chromosome: &str) -> Option<&ContigDigests> {
+        let id = self.contig_id(chromosome)?;
+        self.digest_cache().contigs[id as usize].as_ref()
+    }
+
+    /// Retrieves the cached digests of a contig, see `contig_digests(...)`
+    /// # Errors
+    /// * if the contig is not in the reference genome, was unloaded, or fails to load
+    pub(crate) fn try_contig_digests(&self, chromosome: &str) -> Result<&ContigDigests, SimpleError> {
+        match self.contig_digests(chromosome) {
+            Some(digests) => Ok(digests),
+            // reading the contig again reports why it could not be digested
+            None => match self.try_sequence_unkept(chromosome) {
+                Err(e) => Err(e),
+                Ok(_) => bail!("Failed to digest contig {:?}", chromosome)
+            }
+        }
+    }
+
+    /// Finds the contig with a given sequence digest, so sequences that CRAM/SAM headers reference by their `M5` tag can be resolved by content.
+    /// The digest index is computed on the first call, reading every contig (lazily loaded contigs are not kept in memory),
+    /// and reused by later calls and clones until the sequences change.
+    /// # Arguments
+    /// * `digest` - the hexadecimal MD5 digest of the upper-cased sequence, in either case
+    /// # Returns
+    /// * the contig name, or `None` if no contig has the digest; for identical sequences, the first contig in load order
+    pub fn contig_by_md5(&self, digest: &str) -> Option<&str> {
+        let id = *self.md5_index().get(&digest.to_ascii_lowercase())?;
+        self.contig_name(id)
+    }
+
+    /// Computes the MD5 digest of a region, such as for checking that two pipelines extracted exactly the same target sequence.
+    /// The digest is over the upper-cased bases, like `M5` tags, so it does not depend on soft-masking.
+    /// # Arguments
+    /// * `chromosome` - the contig name
+    /// * `start` - the 0-based start index (included)
+    /// * `end` - the 0-based end index (excluded)
+    /// # Errors
+    /// * if `chromosome` is not in the reference genome, was unloaded, or fails to load
+    /// * if `start` > `end`, or `end` is past the contig end; regions are never clamped or padded
+    pub fn region_md5(&self, chromosome: &str, start: usize, end: usize) -> Result<String, SimpleError> {
+        let region = self.get_slice_checked(chromosome, start, end, OutOfBoundsPolicy::Error)?;
+        Ok(md5_hex_uppercase(&region))
+    }
+
+    /// Computes the GA4GH `sha512t24u` digest of a region over its upper-cased bases, see `region_md5(...)` and `sha512t24u(...)`
+    /// # Arguments
+    /// * `chromosome` - the contig name
+    /// * `start` - the 0-based start index (included)
+    /// * `end` - the 0-based end index (excluded)
+    /// # Errors
+    /// * if `chromosome` is not in the reference genome, was unloaded, or fails to load
+    /// * if `start` > `end`, or `end` is past the contig end; regions are never clamped or padded
+    pub fn region_sha512t24u(&self, chromosome: &str, start: usize, end: usize) -> Result<String, SimpleError> {
+        let region = self.get_slice_checked(chromosome, start, end, OutOfBoundsPolicy::Error)?;
+        Ok(sha512t24u_uppercase(&region))
+    }
+
+    /// Verifies that the contigs match a manifest of expected digests, which protects against corrupted or swapped files.
+    /// Digests are computed over the upper-cased sequence, so this holds whether or not the genome was upper-cased at load.
+    /// Lazily loaded contigs are read to compute their digests, but are not kept in memory.
+    /// # Arguments
+    /// * `manifest` - the expected digests
+    /// # Errors
+    /// * if a contig's digest differs from the manifest
+    /// * if a contig is not in the manifest, or a manifest contig is not in the reference genome
+    /// * if a contig was unloaded or fails to load
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn verify_md5(&self, manifest: &Md5Manifest) -> Result<(), SimpleError> {
+        for contig in self.contig_keys().iter() {
+            let Some(expected) = manifest.get(contig) else {
+                bail!("Contig \"{}\" is not in the expected MD5 manifest", contig);
+            };
+            let found = &self.try_contig_digests(contig)?.md5;
+            if found != expected {
+                bail!("MD5 mismatch for contig \"{}\": expected {}, found {}", contig, expected, found);
+            }
+        }
+        let mut missing: Vec<&str> = manifest.digests.keys()
+            .filter(|c| self.contig_id(c).is_none())
+            .map(|c| c.as_str())
+            .collect();
+        if !missing.is_empty() {
+            missing.sort_unstable();
+            bail!("Contigs in the expected MD5 manifest are missing from the reference genome: {}", missing.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Identifies contigs that have identical sequence content under different names, ignoring case (soft-masking).
+    /// Each returned group contains two or more contig names in load order, and groups are ordered by their first contig.
+    /// The comparison uses the cached digests of `contig_digests(...)`; contigs that were unloaded or fail to load are skipped.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn find_duplicate_sequences(&self) -> Vec<Vec<String>> {
+        let mut group_index: HashMap<&str, usize> = Default::default();
+        let mut groups: Vec<Vec<String>> = vec![];
+        let cache = self.digest_cache();
+        for (contig, digests) in self.contig_keys().iter().zip(cache.contigs.iter()) {
+            let Some(digests) = digests else {
+                continue;
+            };
+            let index = *group_index.entry(&digests.md5).or_insert_with(|| {
+                groups.push(vec![]);
+                groups.len() - 1
+            });
+            groups[index].push(contig.clone());
+        }
+        groups.retain(|g| g.len() > 1);
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ReferenceGenomeBuilder;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_md5() {
+        // empty sequence digest is well known
+        assert_eq!(md5_hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("test".to_string(), "acgt").unwrap();
+        assert_eq!(reference_genome.contig_md5("test"), Some(md5_hex(b"ACGT")));
+        assert_eq!(reference_genome.contig_md5("missing"), None);
+
+        // the digest is over the upper-cased sequence even when soft-masking is kept
+        let mut reference_genome = ReferenceGenomeBuilder::from_bytes(&b">chr1\nacgt\n"[..]).uppercase(false).build().unwrap();
+        assert_eq!(reference_genome.contig_md5("chr1"), Some(md5_hex(b"ACGT")));
+        assert_eq!(reference_genome.contig_md5("chr1"), Some(reference_genome.contig_digests("chr1").unwrap().md5.clone()));
+        reference_genome.set_normalized_lookup(true);
+        assert_eq!(reference_genome.contig_md5("1"), Some(md5_hex(b"ACGT")));
+    }
+
+    #[test]
+    fn test_contig_digests() {
+        let mut reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        let digests = reference_genome.contig_digests("chr1").unwrap().clone();
+        assert_eq!(digests.md5, md5_hex(b"ACGTACGT"));
+        assert_eq!(digests.sha512t24u, sha512t24u(b"ACGTACGT"));
+        assert_eq!(reference_genome.contig_digests("chr3"), None);
+
+        // clones share the cache, and changing a sequence recomputes it
+        let clone = reference_genome.clone();
+        reference_genome.modify_contig("chr1", |s| s.truncate(4)).unwrap();
+        assert_eq!(reference_genome.contig_digests("chr1").unwrap().sha512t24u, "aKF498dAxcJAqme6QYQ7EZ07-fiw8Kw2");
+        assert_eq!(reference_genome.contig_by_md5(&md5_hex(b"ACGT")), Some("chr1"));
+        assert_eq!(clone.contig_digests("chr1"), Some(&digests));
+
+        // unloaded contigs have no digests
+        reference_genome.add_contig("chr3".to_string(), "GG").unwrap();
+        reference_genome.unload_contig("chr3").unwrap();
+        assert_eq!(reference_genome.contig_digests("chr3"), None);
+        assert!(reference_genome.try_contig_digests("chr3").is_err());
+        assert!(reference_genome.try_contig_digests("chr2").is_ok());
+    }
+
+    #[test]
+    fn test_md5_hex_uppercase() {
+        assert_eq!(md5_hex_uppercase(b"acgtACGT"), md5_hex(b"ACGTACGT"));
+        let long: Vec<u8> = b"acgtn".iter().cycle().take(20000).copied().collect();
+        assert_eq!(md5_hex_uppercase(&long), md5_hex(&long.to_ascii_uppercase()));
+    }
+
+    #[test]
+    fn test_sha512t24u() {
+        // refget reference digests
+        assert_eq!(sha512t24u(b""), "z4PhNX7vuL3xVChQ1m2AB9Yg5AULVxXc");
+        assert_eq!(sha512t24u(b"ACGT"), "aKF498dAxcJAqme6QYQ7EZ07-fiw8Kw2");
+        // padding that spills into a second block, and input spanning several blocks
+        assert_eq!(sha512t24u(&[b'A'; 112]), "GgCLBICk62TSkttnHU9D9G_Ffgd7cq0-");
+        assert_eq!(sha512t24u(&b"ACGT".repeat(50)), "G7NZKHMQmFk_64I1o--4fdSUvygPMxjX");
+        assert_eq!(sha512t24u_uppercase(&b"acgt".repeat(50)), "G7NZKHMQmFk_64I1o--4fdSUvygPMxjX");
+    }
+
+    #[test]
+    fn test_region_digests() {
+        let reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        // chr1 is acgtACGT, so 1..5 is CGTA
+        assert_eq!(reference_genome.region_md5("chr1", 1, 5).unwrap(), "7d38bb5e5acb319c84e5d41bea1fe604");
+        assert_eq!(reference_genome.region_sha512t24u("chr1", 1, 5).unwrap(), "2thoaJ5bbA2l1e3r_-1KT2ZcO_BACJgf");
+        assert_eq!(reference_genome.region_md5("chr1", 0, 8).unwrap(), "cc0af3a4fedb18378b4b57b98068e69f");
+        assert!(reference_genome.region_md5("chr1", 4, 9).is_err());
+        assert!(reference_genome.region_sha512t24u("chr1", 5, 4).is_err());
+        assert!(reference_genome.region_md5("chr3", 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_md5_manifest() {
+        let dict = Md5Manifest::from_dict(&PathBuf::from("./test_data/test_reference.dict")).unwrap();
+        let md5sum = Md5Manifest::from_md5sum(&PathBuf::from("./test_data/test_reference.md5")).unwrap();
+        assert_eq!(dict.len(), 2);
+        assert_eq!(dict.get("chr1"), Some("cc0af3a4fedb18378b4b57b98068e69f"));
+        assert_eq!(dict, md5sum);
+        #[cfg(feature = "seqcol")]
+        assert_eq!(Md5Manifest::from_seqcol(&PathBuf::from("./test_data/test_reference.seqcol.json")).unwrap(), dict);
+
+        let mut manifest = Md5Manifest::new();
+        assert!(manifest.insert("chr1".to_string(), "not-a-digest").is_err());
+        assert!(manifest.is_empty());
+        assert!(Md5Manifest::from_md5sum(&PathBuf::from("./test_data/test_reference.fa")).is_err());
+    }
+
+    #[test]
+    fn test_verify_md5() {
+        let manifest = Md5Manifest::from_dict(&PathBuf::from("./test_data/test_reference.dict")).unwrap();
+        let reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        reference_genome.verify_md5(&manifest).unwrap();
+
+        let mut swapped = Md5Manifest::new();
+        swapped.insert("chr1".to_string(), manifest.get("chr2").unwrap()).unwrap();
+        swapped.insert("chr2".to_string(), manifest.get("chr1").unwrap()).unwrap();
+        assert_eq!(
+            reference_genome.verify_md5(&swapped).unwrap_err().to_string(),
+            "MD5 mismatch for contig \"chr1\": expected 0f4a16fa40a62460a647beef79a0ec45, found cc0af3a4fedb18378b4b57b98068e69f"
+        );
+
+        let subset = reference_genome.subset(|c| c == "chr2");
+        assert!(subset.verify_md5(&manifest).unwrap_err().to_string().contains("missing from the reference genome: chr1"));
+        let mut partial = manifest.clone();
+        partial.retain(|c| c == "chr2");
+        assert!(reference_genome.verify_md5(&partial).unwrap_err().to_string().contains("\"chr1\" is not in the expected MD5 manifest"));
+    }
+
+    #[test]
+    fn test_contig_by_md5() {
+        let mut reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        assert_eq!(reference_genome.contig_by_md5("cc0af3a4fedb18378b4b57b98068e69f"), Some("chr1"));
+        assert_eq!(reference_genome.contig_by_md5("0F4A16FA40A62460A647BEEF79A0EC45"), Some("chr2"));
+        assert_eq!(reference_genome.contig_by_md5(&md5_hex(b"ACGT")), None);
+
+        // renames keep the index, while sequence changes rebuild it
+        reference_genome.rename_contigs(&[("chr1".to_string(), "1".to_string())]).unwrap();
+        assert_eq!(reference_genome.contig_by_md5("cc0af3a4fedb18378b4b57b98068e69f"), Some("1"));
+        reference_genome.add_contig("chr3".to_string(), "ACGT").unwrap();
+        assert_eq!(reference_genome.contig_by_md5(&md5_hex(b"ACGT")), Some("chr3"));
+        reference_genome.modify_contig("chr3", |s| s[0] = b'T').unwrap();
+        assert_eq!(reference_genome.contig_by_md5(&md5_hex(b"ACGT")), None);
+        reference_genome.sort_contigs_by(|a, b| b.cmp(a));
+        assert_eq!(reference_genome.contig_by_md5(&md5_hex(b"TCGT")), Some("chr3"));
+    }
+
+    #[test]
+    fn test_find_duplicate_sequences() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("a".to_string(), "ACGT").unwrap();
+        reference_genome.add_contig("b".to_string(), "TTTT").unwrap();
+        reference_genome.add_contig("c".to_string(), "acgt").unwrap();
+        reference_genome.add_contig("d".to_string(), "GGGG").unwrap();
+        reference_genome.add_contig("e".to_string(), "TTTT").unwrap();
+        reference_genome.add_contig("f".to_string(), "ACGT").unwrap();
+
+        assert_eq!(reference_genome.find_duplicate_sequences(), vec![
+            vec!["a".to_string(), "c".to_string(), "f".to_string()],
+            vec!["b".to_string(), "e".to_string()]
+        ]);
+
+        // soft-masking does not hide a duplicate, and unloaded contigs are skipped
+        let mut reference_genome = ReferenceGenomeBuilder::from_bytes(&b">a\nACGT\n>b\nacgt\n>c\nTTTT\n>d\nTTTT\n"[..])
+            .uppercase(false)
+            .build()
+            .unwrap();
+        reference_genome.unload_contig("d").unwrap();
+        assert_eq!(reference_genome.find_duplicate_sequences(), vec![vec!["a".to_string(), "b".to_string()]]);
+    }
+}
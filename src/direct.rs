@@ -0,0 +1,175 @@
+use std::fs::File;
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+
+/// The alignment of O_DIRECT buffers, which covers the logical block size of common devices
+const ALIGNMENT: usize = 4096;
+
+/// The bytes requested per read, large enough that direct reads stream at device speed
+const BLOCK_SIZE: usize = 4 << 20;
+
+/// A buffered reader over a file that bypasses the page cache, so loading a file that is read once does not evict
+/// the rest of the working set. On Linux the file is opened with `O_DIRECT` and read in large aligned blocks; if the
+/// file system rejects `O_DIRECT` (e.g. tmpfs), the file is read normally and each block is dropped from the page cache
+/// once it has been read. On other platforms the file is read normally.
+pub(crate) struct UncachedFile {
+    /// The file path, to reopen it if direct reads are rejected
+    path: PathBuf,
+    /// The open file
+    file: File,
+    /// If true, blocks are dropped from the page cache after they are read, since the file was not opened with `O_DIRECT`
+    drop_behind: bool,
+    /// The backing allocation, over-allocated so that an aligned block fits
+    buffer: Vec<u8>,
+    /// The offset of the aligned block within `buffer`
+    aligned_start: usize,
+    /// The size of each read
+    block_size: usize,
+    /// The consumed position in the current block
+    position: usize,
+    /// The valid bytes in the current block
+    filled: usize,
+    /// The file offset of the end of the current block
+    file_offset: u64,
+    /// Set once a read returns fewer bytes than requested, which only happens at the end of the file
+    at_end: bool
+}
+
+impl UncachedFile {
+    /// Opens a file for reading without filling the page cache
+    /// # Arguments
+    /// * `path` - the file to read
+    /// # Errors
+    /// * if the file cannot be opened
+    pub(crate) fn open(path: &Path) -> std::io::Result<Self> {
+        Self::with_block_size(path, BLOCK_SIZE)
+    }
+
+    /// Opens a file for reading without filling the page cache, with a block size that is a multiple of `ALIGNMENT`
+    fn with_block_size(path: &Path, block_size: usize) -> std::io::Result<Self> {
+        let (file, drop_behind) = open_direct(path)?;
+        let buffer = vec![0; block_size + ALIGNMENT];
+        let aligned_start = buffer.as_ptr().align_offset(ALIGNMENT);
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            drop_behind,
+            buffer,
+            aligned_start,
+            block_size,
+            position: 0,
+            filled: 0,
+            file_offset: 0,
+            at_end: false
+        })
+    }
+}
+
+/// Opens a file with `O_DIRECT`, falling back to a normal open if the file system does not support it
+/// # Returns
+/// * the file, and true if it was opened normally and read blocks should be dropped from the page cache
+#[cfg(target_os = "linux")]
+fn open_direct(path: &Path) -> std::io::Result<(File, bool)> {
+    use std::os::unix::fs::OpenOptionsExt;
+    match std::fs::OpenOptions::new().read(true).custom_flags(libc::O_DIRECT).open(path) {
+        Ok(file) => Ok((file, false)),
+        Err(e) if e.raw_os_error() == Some(libc::EINVAL) => Ok((File::open(path)?, true)),
+        Err(e) => Err(e)
+    }
+}
+
+/// Opens a file normally, as there is no portable way to bypass the page cache
+#[cfg(not(target_os = "linux"))]
+fn open_direct(path: &Path) -> std::io::Result<(File, bool)> {
+    Ok((File::open(path)?, false))
+}
+
+/// Drops a range of a file from the page cache; failures are ignored since this is only a hint
+#[cfg(target_os = "linux")]
+fn drop_from_page_cache(file: &File, start: u64, length: u64) {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: posix_fadvise only reads its arguments, and the descriptor is valid for the lifetime of `file`
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), start as libc::off_t, length as libc::off_t, libc::POSIX_FADV_DONTNEED);
+    }
+}
+
+/// Nothing to drop on platforms without `posix_fadvise`
+#[cfg(not(target_os = "linux"))]
+fn drop_from_page_cache(_file: &File, _start: u64, _length: u64) {}
+
+impl Read for UncachedFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.fill_buf()?;
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl BufRead for UncachedFile {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.position == self.filled && !self.at_end {
+            // direct reads must keep the file offset aligned, so every block is filled completely unless the file ends
+            let block = &mut self.buffer[self.aligned_start..self.aligned_start + self.block_size];
+            let mut filled = 0;
+            while filled < block.len() {
+                match self.file.read(&mut block[filled..]) {
+                    Ok(0) => break,
+                    Ok(count) => filled += count,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {},
+                    // some file systems accept O_DIRECT on open but reject the reads
+                    Err(e) if e.kind() == std::io::ErrorKind::InvalidInput && !self.drop_behind && self.file_offset == 0 && filled == 0 => {
+                        self.file = File::open(&self.path)?;
+                        self.drop_behind = true;
+                    },
+                    Err(e) => return Err(e)
+                }
+            }
+            if self.drop_behind && filled > 0 {
+                drop_from_page_cache(&self.file, self.file_offset, filled as u64);
+            }
+            self.file_offset += filled as u64;
+            self.at_end = filled < block.len();
+            self.position = 0;
+            self.filled = filled;
+        }
+        Ok(&self.buffer[self.aligned_start + self.position..self.aligned_start + self.filled])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.filled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uncached_file() {
+        let directory = std::env::temp_dir().join(format!("refgenome_direct_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let file_fn = directory.join("data.txt");
+        // several blocks plus a partial one
+        let content: Vec<u8> = (0..3 * ALIGNMENT + 123).map(|i| b"ACGT\n"[i % 5]).collect();
+        std::fs::write(&file_fn, &content).unwrap();
+
+        let mut data: Vec<u8> = vec![];
+        UncachedFile::with_block_size(&file_fn, ALIGNMENT).unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, content);
+
+        let lines = UncachedFile::open(&file_fn).unwrap().lines().count();
+        assert_eq!(lines, content.iter().filter(|&&c| c == b'\n').count() + 1);
+
+        // a file that ends on a block boundary
+        std::fs::write(&file_fn, &content[..2 * ALIGNMENT]).unwrap();
+        let mut data: Vec<u8> = vec![];
+        UncachedFile::with_block_size(&file_fn, ALIGNMENT).unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, &content[..2 * ALIGNMENT]);
+
+        assert!(UncachedFile::open(&directory.join("missing.txt")).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
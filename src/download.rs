@@ -0,0 +1,495 @@
+use crate::assembly::KnownAssembly;
+use crate::reference_genome::ReferenceGenome;
+use crate::user_dirs::default_cache_dir;
+use simple_error::bail;
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Options for `download_resumable(...)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DownloadOptions {
+    /// The expected lower-case hexadecimal MD5 digest of the downloaded file (not of the sequences); default is no check
+    pub expected_md5: Option<String>,
+    /// The maximum number of requests, each resuming where the previous one stopped; default is 5
+    pub max_attempts: usize,
+    /// The delay before the first retry, doubled for each further retry; default is 1 second
+    pub retry_delay: Duration
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            expected_md5: None,
+            max_attempts: 5,
+            retry_delay: Duration::from_secs(1)
+        }
+    }
+}
+
+/// A failed request, split by whether resuming could succeed
+enum FetchError {
+    /// A dropped connection, throttling, or server error; the partial file is kept and the request can be retried
+    Transient(Box<dyn Error>),
+    /// Any other failure, such as a missing file on the server
+    Fatal(Box<dyn Error>)
+}
+
+/// Appends a suffix to a file name, e.g. `genome.fa` to `genome.fa.part`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+/// The contents of the marker recording that a file was verified against an MD5 digest: the digest, file size, and modification time.
+/// A changed size or modification time invalidates the marker.
+fn verified_marker(path: &Path, md5: &str) -> std::io::Result<String> {
+    let metadata = std::fs::metadata(path)?;
+    let modified = metadata.modified()?.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    Ok(format!("{}\t{}\t{}\n", md5.to_ascii_lowercase(), metadata.len(), modified.as_nanos()))
+}
+
+/// Computes the lower-case hexadecimal MD5 digest of a file without reading it into memory
+fn file_md5(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut context = md5::Context::new();
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        let count = file.read(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+        context.consume(&buffer[..count]);
+    }
+    Ok(format!("{:x}", context.finalize()))
+}
+
+/// Parses the total length from a `Content-Range` header, e.g. `bytes 100-199/1000` or `bytes */1000`
+fn content_range_total(content_range: &str) -> Option<u64> {
+    content_range.rsplit_once('/').and_then(|(_, total)| total.trim().parse().ok())
+}
+
+/// Classifies a failed request: throttling, server errors, and connection failures are transient, other statuses are fatal
+fn request_error(url: &str, error: ureq::Error) -> FetchError {
+    match error {
+        ureq::Error::Status(status, _) if status == 408 || status == 429 || status >= 500 => {
+            FetchError::Transient(format!("Request for {url} failed with status {status}").into())
+        },
+        ureq::Error::Status(status, _) => FetchError::Fatal(format!("Request for {url} failed with status {status}").into()),
+        error => FetchError::Transient(error.into())
+    }
+}
+
+/// Runs a request until it succeeds, retrying transient failures with the exponential backoff of `options`
+/// # Errors
+/// * the first fatal error, or the last transient error once `max_attempts` requests have failed
+fn with_retries<T, F>(options: &DownloadOptions, mut request: F) -> Result<T, Box<dyn Error>> where F: FnMut() -> Result<T, FetchError> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match request() {
+            Ok(value) => return Ok(value),
+            Err(FetchError::Transient(error)) if attempt < options.max_attempts => {
+                log::warn!("{error}; retrying ({attempt} of {} attempts used)", options.max_attempts);
+                std::thread::sleep(options.retry_delay * 2u32.saturating_pow(attempt as u32 - 1));
+            },
+            Err(FetchError::Transient(error)) | Err(FetchError::Fatal(error)) => return Err(error)
+        }
+    }
+}
+
+/// Fetches a small text resource, such as a registry manifest, into memory, retrying transient failures like `download_resumable(...)`
+/// # Arguments
+/// * `url` - the remote file
+/// * `options` - the retry settings; `expected_md5` is ignored
+/// # Errors
+/// * if the server reports a non-transient error, such as a missing file
+/// * if the request does not succeed within `max_attempts` tries
+pub(crate) fn fetch_text(url: &str, options: &DownloadOptions) -> Result<String, Box<dyn Error>> {
+    with_retries(options, || {
+        let response = ureq::get(url).call().map_err(|e| request_error(url, e))?;
+        response.into_string().map_err(|e| FetchError::Transient(e.into()))
+    })
+}
+
+/// Requests the part of a file that is not yet in the partial file and appends it.
+/// If the server ignores the range, or the remote file changed since the partial file was started, the partial file is restarted.
+/// # Arguments
+/// * `url` - the remote file
+/// * `partial_fn` - the partial download
+/// * `validator_fn` - holds the `ETag` or `Last-Modified` value of the remote file when the partial file was started
+fn fetch_remaining(url: &str, partial_fn: &Path, validator_fn: &Path) -> Result<(), FetchError> {
+    let offset = std::fs::metadata(partial_fn).map(|m| m.len()).unwrap_or(0);
+    let mut request = ureq::get(url);
+    if offset > 0 {
+        request = request.set("Range", &format!("bytes={offset}-"));
+        if let Ok(validator) = std::fs::read_to_string(validator_fn) {
+            request = request.set("If-Range", validator.trim());
+        }
+    }
+
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(416, response)) => {
+            // the partial file may already be complete, e.g. if the process stopped before renaming it
+            let total = response.header("Content-Range").and_then(content_range_total);
+            if total == Some(offset) {
+                return Ok(());
+            }
+            let _ = std::fs::remove_file(partial_fn);
+            return Err(FetchError::Transient(format!("Range request for {url} was not satisfiable, restarting").into()));
+        },
+        Err(error) => return Err(request_error(url, error))
+    };
+
+    let resumed = response.status() == 206;
+    let expected_length = if resumed {
+        let content_range = response.header("Content-Range").unwrap_or_default();
+        if !content_range.starts_with(&format!("bytes {offset}-")) {
+            let _ = std::fs::remove_file(partial_fn);
+            return Err(FetchError::Transient(format!("Unexpected Content-Range {content_range:?} from {url}, restarting").into()));
+        }
+        content_range_total(content_range)
+    } else {
+        response.header("Content-Length").and_then(|l| l.parse().ok())
+    };
+    if !resumed {
+        let validator = response.header("ETag").or_else(|| response.header("Last-Modified"));
+        let saved = match validator {
+            Some(validator) => std::fs::write(validator_fn, validator),
+            None => std::fs::remove_file(validator_fn).or(Ok(()))
+        };
+        saved.map_err(|e| FetchError::Fatal(e.into()))?;
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(partial_fn)
+        .map_err(|e| FetchError::Fatal(e.into()))?;
+    let mut writer = BufWriter::new(file);
+    let copied = std::io::copy(&mut response.into_reader(), &mut writer);
+    // keep whatever arrived, even if the connection dropped
+    writer.flush().map_err(|e| FetchError::Fatal(e.into()))?;
+    copied.map_err(|e| FetchError::Transient(e.into()))?;
+
+    let length = std::fs::metadata(partial_fn).map_err(|e| FetchError::Fatal(e.into()))?.len();
+    match expected_length {
+        Some(expected) if expected != length => {
+            Err(FetchError::Transient(format!("Download of {url} stopped at {length} of {expected} bytes").into()))
+        },
+        _ => Ok(())
+    }
+}
+
+/// Downloads a remote file over HTTP(S), resuming after dropped connections instead of starting over.
+/// Data is written to `<destination>.part`, which also survives process restarts, and a later call resumes it with a range request.
+/// Resuming is guarded by the remote `ETag`/`Last-Modified`, so a remote file that changed is downloaded again from the start.
+/// The completed file is verified against `expected_md5`, if provided, before it is renamed to `destination`.
+/// # Arguments
+/// * `url` - the remote file
+/// * `destination` - the local filename; an existing file is assumed complete and is only verified.
+///   A successful verification is recorded in `<destination>.verified`, so later calls skip hashing an unchanged file.
+/// * `options` - the expected digest and retry settings
+/// # Errors
+/// * if the server reports a non-transient error, such as a missing file
+/// * if the download does not complete within `max_attempts` requests
+/// * if the MD5 digest does not match, in which case the partial file is removed
+/// * any local file errors
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err, fields(url = url)))]
+pub fn download_resumable(url: &str, destination: &Path, options: &DownloadOptions) -> Result<(), Box<dyn Error>> {
+    let partial_fn = with_suffix(destination, ".part");
+    let validator_fn = with_suffix(destination, ".part.validator");
+    let verified_fn = with_suffix(destination, ".verified");
+    // the marker is only a shortcut, so failing to write it is not an error
+    let record_verified = || {
+        if let Some(expected_md5) = options.expected_md5.as_ref() {
+            let _ = verified_marker(destination, expected_md5).and_then(|marker| std::fs::write(&verified_fn, marker));
+        }
+    };
+    let verify = |path: &Path| -> Result<(), Box<dyn Error>> {
+        if let Some(expected_md5) = options.expected_md5.as_ref() {
+            let md5 = file_md5(path)?;
+            if !md5.eq_ignore_ascii_case(expected_md5) {
+                bail!("MD5 mismatch for {}: expected {}, found {}", url, expected_md5, md5);
+            }
+        }
+        Ok(())
+    };
+    if destination.is_file() {
+        if let Some(expected_md5) = options.expected_md5.as_ref() {
+            let marker = verified_marker(destination, expected_md5)?;
+            if std::fs::read_to_string(&verified_fn).is_ok_and(|recorded| recorded == marker) {
+                return Ok(());
+            }
+        }
+        verify(destination)?;
+        record_verified();
+        return Ok(());
+    }
+
+    with_retries(options, || fetch_remaining(url, &partial_fn, &validator_fn))?;
+
+    if let Err(error) = verify(&partial_fn) {
+        let _ = std::fs::remove_file(&partial_fn);
+        let _ = std::fs::remove_file(&validator_fn);
+        return Err(error);
+    }
+    std::fs::rename(&partial_fn, destination)?;
+    let _ = std::fs::remove_file(&validator_fn);
+    record_verified();
+    Ok(())
+}
+
+/// The official download location of a canonical reference build
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReferenceSource {
+    /// The gzip-compressed FASTA
+    pub url: &'static str,
+    /// The `md5sum`-style checksum file published next to the FASTA
+    pub md5_url: &'static str
+}
+
+impl KnownAssembly {
+    /// The official UCSC download of the canonical build of this assembly, which matches its UCSC-style contig names:
+    /// the GRCh38 analysis set (`hg38.analysisSet`), T2T-CHM13 v2.0 (`hs1`), and GRCm39 (`mm39`).
+    /// # Returns
+    /// * the source, or `None` if there is no built-in source for this assembly
+    pub fn download_source(&self) -> Option<ReferenceSource> {
+        match self {
+            KnownAssembly::GRCh38 => Some(ReferenceSource {
+                url: "https://hgdownload.soe.ucsc.edu/goldenPath/hg38/bigZips/analysisSet/hg38.analysisSet.fa.gz",
+                md5_url: "https://hgdownload.soe.ucsc.edu/goldenPath/hg38/bigZips/analysisSet/md5sum.txt"
+            }),
+            KnownAssembly::T2tChm13 => Some(ReferenceSource {
+                url: "https://hgdownload.soe.ucsc.edu/goldenPath/hs1/bigZips/hs1.fa.gz",
+                md5_url: "https://hgdownload.soe.ucsc.edu/goldenPath/hs1/bigZips/md5sum.txt"
+            }),
+            KnownAssembly::GRCm39 => Some(ReferenceSource {
+                url: "https://hgdownload.soe.ucsc.edu/goldenPath/mm39/bigZips/mm39.fa.gz",
+                md5_url: "https://hgdownload.soe.ucsc.edu/goldenPath/mm39/bigZips/md5sum.txt"
+            }),
+            KnownAssembly::GRCh37 | KnownAssembly::Hg19 => None
+        }
+    }
+}
+
+/// Returns the default cache directory for `fetch_known_reference(...)`: `refgenome` under `$XDG_CACHE_HOME` or `$HOME/.cache`
+/// # Returns
+/// * the directory, or `None` if neither environment variable is set
+pub fn default_download_cache() -> Option<PathBuf> {
+    default_cache_dir("refgenome")
+}
+
+/// Finds the digest of a file in `md5sum` output, where each line is a digest and a file name (optionally prefixed with `*` or `./`)
+fn find_md5(md5sum: &str, file_name: &str) -> Option<String> {
+    md5sum.lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, name)| {
+            let name = name.trim().trim_start_matches('*');
+            name.strip_prefix("./").unwrap_or(name) == file_name
+        })
+        .map(|(md5, _)| md5.to_ascii_lowercase())
+}
+
+/// Downloads a FASTA and its published checksum file into a directory, verifying the FASTA against the checksum
+/// unless `options` already has an expected digest. The checksum file is kept as `<file name>.md5sum.txt` so later calls work offline.
+fn fetch_source(url: &str, md5_url: &str, cache_directory: &Path, options: &DownloadOptions) -> Result<PathBuf, Box<dyn Error>> {
+    let file_name = url.rsplit('/').next().unwrap_or_default();
+    if file_name.is_empty() {
+        bail!("Cannot determine a file name from {}", url);
+    }
+    std::fs::create_dir_all(cache_directory)?;
+    let mut options = options.clone();
+    if options.expected_md5.is_none() {
+        let md5_fn = cache_directory.join(format!("{file_name}.md5sum.txt"));
+        download_resumable(md5_url, &md5_fn, &DownloadOptions { expected_md5: None, ..options.clone() })?;
+        match find_md5(&std::fs::read_to_string(&md5_fn)?, file_name) {
+            Some(md5) => options.expected_md5 = Some(md5),
+            None => bail!("{} does not list a checksum for {}", md5_url, file_name)
+        }
+    }
+    let destination = cache_directory.join(file_name);
+    download_resumable(url, &destination, &options)?;
+    Ok(destination)
+}
+
+/// Downloads the canonical build of a well-known assembly (see `KnownAssembly::download_source()`) into a cache directory,
+/// verified against the official checksum, unless it is already there. Interrupted downloads resume on the next call.
+/// # Arguments
+/// * `assembly` - the assembly to download
+/// * `cache_directory` - where downloads are kept, e.g. from `default_download_cache()`
+/// * `options` - retry settings; an `expected_md5` pins the digest instead of using the published checksum file
+/// # Returns
+/// * the local FASTA filename
+/// # Errors
+/// * if there is no built-in source for the assembly
+/// * if the checksum file does not list the FASTA
+/// * see `download_resumable(...)`
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err, fields(assembly = ?assembly)))]
+pub fn fetch_known_reference(assembly: KnownAssembly, cache_directory: &Path, options: &DownloadOptions) -> Result<PathBuf, Box<dyn Error>> {
+    let Some(source) = assembly.download_source() else {
+        bail!("There is no built-in download source for {:?}", assembly);
+    };
+    fetch_source(source.url, source.md5_url, cache_directory, options)
+}
+
+impl ReferenceGenome {
+    /// Downloads the canonical build of a well-known assembly with `fetch_known_reference(...)`, unless it is already cached, and loads it
+    /// # Arguments
+    /// * `assembly` - the assembly to load
+    /// * `cache_directory` - where downloads are kept, e.g. from `default_download_cache()`
+    /// * `options` - retry settings and an optional pinned digest
+    /// # Errors
+    /// * see `fetch_known_reference(...)` and `ReferenceGenome::from_fasta(...)`
+    pub fn from_known_assembly(assembly: KnownAssembly, cache_directory: &Path, options: &DownloadOptions) -> Result<ReferenceGenome, Box<dyn Error>> {
+        ReferenceGenome::from_fasta(&fetch_known_reference(assembly, cache_directory, options)?)
+    }
+
+    /// Downloads a remote FASTA with `download_resumable(...)`, unless it is already present, and loads it
+    /// # Arguments
+    /// * `url` - the remote FASTA, which may be gzip-compressed
+    /// * `destination` - the local filename
+    /// * `options` - the expected file digest and retry settings
+    /// # Errors
+    /// * see `download_resumable(...)` and `ReferenceGenome::from_fasta(...)`
+    pub fn from_fasta_url(url: &str, destination: &Path, options: &DownloadOptions) -> Result<ReferenceGenome, Box<dyn Error>> {
+        download_resumable(url, destination, options)?;
+        ReferenceGenome::from_fasta(destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest::md5_hex;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    /// Serves `body` with range support, cutting the first response off halfway through
+    fn serve(body: Vec<u8>, connections: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for (index, stream) in listener.incoming().take(connections).enumerate() {
+                let mut stream = stream.unwrap();
+                let mut offset = 0;
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(range) = line.strip_prefix("Range: bytes=").or_else(|| line.strip_prefix("range: bytes=")) {
+                        offset = range.trim().trim_end_matches('-').parse().unwrap();
+                    }
+                }
+                let status = if offset > 0 {
+                    format!("206 Partial Content\r\nContent-Range: bytes {}-{}/{}", offset, body.len() - 1, body.len())
+                } else {
+                    "200 OK".to_string()
+                };
+                let header = format!("HTTP/1.1 {status}\r\nContent-Length: {}\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n", body.len() - offset);
+                let end = if index == 0 { body.len() / 2 } else { body.len() };
+                let _ = stream.write_all(header.as_bytes()).and_then(|_| stream.write_all(&body[offset..end]));
+            }
+        });
+        format!("http://{address}/genome.fa")
+    }
+
+    #[test]
+    fn test_download_resumable() {
+        let body = b">chr1\nACGTACGTACGTACGTACGT\n>chr2\nGGGGCCCCAAAATTTT\n".to_vec();
+        let directory = std::env::temp_dir().join(format!("refgenome_download_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let destination = directory.join("genome.fa");
+        let options = DownloadOptions {
+            expected_md5: Some(md5_hex(&body)),
+            retry_delay: Duration::ZERO,
+            ..Default::default()
+        };
+
+        // the first connection drops halfway, the second resumes from there
+        let url = serve(body.clone(), 2);
+        let reference_genome = ReferenceGenome::from_fasta_url(&url, &destination, &options).unwrap();
+        assert_eq!(std::fs::read(&destination).unwrap(), body);
+        assert_eq!(reference_genome.contig_keys().len(), 2);
+        assert!(!with_suffix(&destination, ".part").exists());
+
+        // an unchanged file is not hashed again, but a modified one is
+        let verified_fn = with_suffix(&destination, ".verified");
+        assert!(verified_fn.is_file());
+        let modified = std::fs::metadata(&destination).unwrap().modified().unwrap();
+        let mut corrupted = body.clone();
+        corrupted[0] = b'@';
+        std::fs::write(&destination, &corrupted).unwrap();
+        std::fs::File::options().write(true).open(&destination).unwrap().set_modified(modified).unwrap();
+        download_resumable("http://127.0.0.1:9/genome.fa", &destination, &options).unwrap();
+        std::fs::File::options().write(true).open(&destination).unwrap()
+            .set_modified(modified + Duration::from_secs(1)).unwrap();
+        assert!(download_resumable("http://127.0.0.1:9/genome.fa", &destination, &options).err().unwrap().to_string().contains("MD5 mismatch"));
+        std::fs::write(&destination, &body).unwrap();
+        download_resumable("http://127.0.0.1:9/genome.fa", &destination, &options).unwrap();
+        std::fs::remove_file(&verified_fn).unwrap();
+        std::fs::remove_file(&destination).unwrap();
+
+        // a digest mismatch leaves nothing behind
+        let url = serve(body.clone(), 2);
+        let wrong = DownloadOptions { expected_md5: Some(md5_hex(b"")), ..options.clone() };
+        assert!(download_resumable(&url, &destination, &wrong).err().unwrap().to_string().contains("MD5 mismatch"));
+        assert!(!destination.exists() && !with_suffix(&destination, ".part").exists());
+
+        // giving up after one attempt keeps the partial file for a later call
+        let url = serve(body.clone(), 2);
+        let single = DownloadOptions { max_attempts: 1, ..options.clone() };
+        assert!(download_resumable(&url, &destination, &single).is_err());
+        assert_eq!(std::fs::metadata(with_suffix(&destination, ".part")).unwrap().len() as usize, body.len() / 2);
+        download_resumable(&url, &destination, &single).unwrap();
+        assert_eq!(std::fs::read(&destination).unwrap(), body);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_fetch_text() {
+        let options = DownloadOptions { retry_delay: Duration::ZERO, ..Default::default() };
+        // the first response is cut off, so the text is only complete after a retry
+        let url = serve(b"fasta\thttp://example.com/genome.fa\n".to_vec(), 2);
+        assert_eq!(fetch_text(&url, &options).unwrap(), "fasta\thttp://example.com/genome.fa\n");
+        let url = serve(b"0123456789".to_vec(), 1);
+        let single = DownloadOptions { max_attempts: 1, ..options };
+        assert!(fetch_text(&url, &single).is_err());
+    }
+
+    #[test]
+    fn test_fetch_source() {
+        assert_eq!(find_md5("0123abcd  ./mm39.fa.gz\nffff  mm39.fa.out.gz\n", "mm39.fa.gz"), Some("0123abcd".to_string()));
+        assert_eq!(find_md5("0123ABCD *hs1.fa.gz\n", "hs1.fa.gz"), Some("0123abcd".to_string()));
+        assert_eq!(find_md5("0123abcd  hs1.fa.gz.fai\n", "hs1.fa.gz"), None);
+        for assembly in [KnownAssembly::GRCh38, KnownAssembly::T2tChm13, KnownAssembly::GRCm39] {
+            assert!(assembly.download_source().unwrap().url.ends_with(".fa.gz"));
+        }
+        assert!(KnownAssembly::GRCh37.download_source().is_none());
+
+        let body = b">chr1\nACGTACGT\n".to_vec();
+        let directory = std::env::temp_dir().join(format!("refgenome_fetch_{}", std::process::id()));
+        let options = DownloadOptions { retry_delay: Duration::ZERO, ..Default::default() };
+        let url = serve(body.clone(), 2);
+        let md5_url = serve(format!("{}  ./genome.fa\n", md5_hex(&body)).into_bytes(), 2);
+        let fasta_fn = fetch_source(&url, &md5_url, &directory, &options).unwrap();
+        assert_eq!(std::fs::read(&fasta_fn).unwrap(), body);
+        // cached, so no requests are needed
+        assert_eq!(fetch_source("http://127.0.0.1:9/genome.fa", "http://127.0.0.1:9/md5sum.txt", &directory, &options).unwrap(), fasta_fn);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let md5_url = serve(format!("{}  ./genome.fa\n", md5_hex(b"")).into_bytes(), 2);
+        let url = serve(body.clone(), 2);
+        assert!(fetch_source(&url, &md5_url, &directory, &options).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
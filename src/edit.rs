@@ -0,0 +1,364 @@
+use crate::reference_genome::ReferenceGenome;
+use simple_error::{bail, SimpleError};
+use std::io::Write;
+
+/// A single edit to a contig sequence; positions are 0-based in the original (unedited) coordinates
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// Replaces the bases starting at `position` with `sequence`, keeping the contig length
+    Substitute { position: usize, sequence: Vec<u8> },
+    /// Inserts `sequence` immediately before `position`; a `position` equal to the contig length appends
+    Insert { position: usize, sequence: Vec<u8> },
+    /// Removes the half-open range `[start, end)`
+    Delete { start: usize, end: usize }
+}
+
+impl Edit {
+    /// The half-open range of original bases affected by the edit; insertions have an empty range
+    fn original_range(&self) -> (usize, usize) {
+        match self {
+            Edit::Substitute { position, sequence } => (*position, position + sequence.len()),
+            Edit::Insert { position, .. } => (*position, *position),
+            Edit::Delete { start, end } => (*start, *end)
+        }
+    }
+}
+
+/// An ungapped block of bases that align between the original and edited sequences
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MappedBlock {
+    /// 0-based start in the original sequence
+    pub original_start: usize,
+    /// 0-based start in the edited sequence
+    pub edited_start: usize,
+    /// The number of aligned bases
+    pub length: usize
+}
+
+/// Maps coordinates between an original contig and its edited version.
+/// Substituted bases stay aligned, so only insertions and deletions split the blocks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoordinateMap {
+    /// The length of the original sequence
+    original_length: usize,
+    /// The length of the edited sequence
+    edited_length: usize,
+    /// Aligned blocks, sorted and non-overlapping in both coordinate spaces
+    blocks: Vec<MappedBlock>
+}
+
+impl CoordinateMap {
+    /// Creates a map for a sequence without any edits
+    /// # Arguments
+    /// * `length` - the sequence length
+    pub fn identity(length: usize) -> Self {
+        let blocks = if length > 0 {
+            vec![MappedBlock { original_start: 0, edited_start: 0, length }]
+        } else {
+            vec![]
+        };
+        Self {
+            original_length: length,
+            edited_length: length,
+            blocks
+        }
+    }
+
+    /// The length of the original sequence
+    pub fn original_length(&self) -> usize {
+        self.original_length
+    }
+
+    /// The length of the edited sequence
+    pub fn edited_length(&self) -> usize {
+        self.edited_length
+    }
+
+    /// The aligned blocks, in order
+    pub fn blocks(&self) -> &[MappedBlock] {
+        &self.blocks
+    }
+
+    /// Appends an aligned segment, merging it into the previous block when contiguous in both coordinates
+    fn push_aligned(&mut self, original_start: usize, edited_start: usize, length: usize) {
+        if length == 0 {
+            return;
+        }
+        if let Some(last) = self.blocks.last_mut() {
+            if last.original_start + last.length == original_start && last.edited_start + last.length == edited_start {
+                last.length += length;
+                return;
+            }
+        }
+        self.blocks.push(MappedBlock { original_start, edited_start, length });
+    }
+
+    /// Converts an original position into the edited sequence
+    /// # Arguments
+    /// * `position` - 0-based original position
+    /// # Returns
+    /// * the edited position, or None if the base was deleted or is out of range
+    pub fn to_edited(&self, position: usize) -> Option<usize> {
+        let index = self.blocks.partition_point(|b| b.original_start + b.length <= position);
+        let block = self.blocks.get(index)?;
+        if block.original_start <= position {
+            Some(block.edited_start + position - block.original_start)
+        } else {
+            None
+        }
+    }
+
+    /// Converts an edited position back into the original sequence
+    /// # Arguments
+    /// * `position` - 0-based edited position
+    /// # Returns
+    /// * the original position, or None if the base was inserted or is out of range
+    pub fn to_original(&self, position: usize) -> Option<usize> {
+        let index = self.blocks.partition_point(|b| b.edited_start + b.length <= position);
+        let block = self.blocks.get(index)?;
+        if block.edited_start <= position {
+            Some(block.original_start + position - block.edited_start)
+        } else {
+            None
+        }
+    }
+
+    /// Combines this map with a map of later edits, giving the map from the original sequence to the final one.
+    /// This accumulates the coordinate changes from successive `edit_contig(...)` calls.
+    /// # Arguments
+    /// * `next` - a map whose original sequence is the edited sequence of `self`
+    /// # Errors
+    /// * if the edited length of `self` does not match the original length of `next`
+    pub fn then(&self, next: &CoordinateMap) -> Result<CoordinateMap, SimpleError> {
+        if self.edited_length != next.original_length {
+            bail!("Cannot combine coordinate maps: edited length {} does not match the next original length {}", self.edited_length, next.original_length);
+        }
+        let mut combined = CoordinateMap {
+            original_length: self.original_length,
+            edited_length: next.edited_length,
+            blocks: vec![]
+        };
+        // intersect the blocks in the shared intermediate coordinates
+        let (mut i, mut j) = (0, 0);
+        while i < self.blocks.len() && j < next.blocks.len() {
+            let first = &self.blocks[i];
+            let second = &next.blocks[j];
+            let first_end = first.edited_start + first.length;
+            let second_end = second.original_start + second.length;
+            let start = first.edited_start.max(second.original_start);
+            let end = first_end.min(second_end);
+            if start < end {
+                combined.push_aligned(
+                    first.original_start + start - first.edited_start,
+                    second.edited_start + start - second.original_start,
+                    end - start
+                );
+            }
+            if first_end <= second_end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Ok(combined)
+    }
+}
+
+/// Writes coordinate maps as a UCSC chain file, such that annotations on the original contigs can be lifted onto the edited contigs
+/// with standard tools (e.g. `liftOver`, `CrossMap`). The original sequence is the chain target and the edited sequence is the query,
+/// both on the `+` strand and with the same contig name. Each chain is scored by its number of aligned bases, and contigs without
+/// any aligned bases are omitted.
+/// # Arguments
+/// * `writer` - the output to write to
+/// * `maps` - contig names and their coordinate maps, in output order
+/// # Errors
+/// * any errors from the underlying writer
+pub fn write_chain<'a, W: Write>(mut writer: W, maps: impl IntoIterator<Item = (&'a str, &'a CoordinateMap)>) -> std::io::Result<()> {
+    let mut chain_id = 0;
+    for (contig, coordinate_map) in maps {
+        let (Some(first), Some(last)) = (coordinate_map.blocks.first(), coordinate_map.blocks.last()) else {
+            continue;
+        };
+        chain_id += 1;
+        let score: usize = coordinate_map.blocks.iter().map(|b| b.length).sum();
+        writeln!(
+            writer, "chain {} {} {} + {} {} {} {} + {} {} {}",
+            score,
+            contig, coordinate_map.original_length, first.original_start, last.original_start + last.length,
+            contig, coordinate_map.edited_length, first.edited_start, last.edited_start + last.length,
+            chain_id
+        )?;
+        for pair in coordinate_map.blocks.windows(2) {
+            let original_gap = pair[1].original_start - (pair[0].original_start + pair[0].length);
+            let edited_gap = pair[1].edited_start - (pair[0].edited_start + pair[0].length);
+            writeln!(writer, "{}\t{}\t{}", pair[0].length, original_gap, edited_gap)?;
+        }
+        writeln!(writer, "{}\n", last.length)?;
+    }
+    writer.flush()
+}
+
+/// Sorts edits by position and checks that they are within the sequence and do not overlap.
+/// Insertions at the same position are kept in their given order, and an insertion at the start of a deletion or substitution comes first.
+/// # Errors
+/// * if an edit extends past `length`, a deletion has `start` > `end`, or two edits overlap
+fn sorted_edits(edits: &[Edit], length: usize) -> Result<Vec<&Edit>, SimpleError> {
+    let mut sorted: Vec<&Edit> = edits.iter().collect();
+    for edit in sorted.iter() {
+        let (start, end) = edit.original_range();
+        if start > end {
+            bail!("Edit {:?} has start > end", edit);
+        }
+        if end > length {
+            bail!("Edit {:?} extends past the contig length of {}", edit, length);
+        }
+    }
+    sorted.sort_by_key(|e| e.original_range());
+    for pair in sorted.windows(2) {
+        if pair[1].original_range().0 < pair[0].original_range().1 {
+            bail!("Edits {:?} and {:?} overlap", pair[0], pair[1]);
+        }
+    }
+    Ok(sorted)
+}
+
+/// Applies sorted, validated edits to a sequence
+/// # Returns
+/// * the edited sequence and the coordinate map from the original
+fn apply_edits(sequence: &[u8], edits: &[&Edit]) -> (Vec<u8>, CoordinateMap) {
+    let mut edited: Vec<u8> = Vec::with_capacity(sequence.len());
+    let mut coordinate_map = CoordinateMap {
+        original_length: sequence.len(),
+        edited_length: 0,
+        blocks: vec![]
+    };
+    let mut original_position = 0;
+    for edit in edits.iter() {
+        let (start, end) = edit.original_range();
+        // unchanged bases before the edit
+        coordinate_map.push_aligned(original_position, edited.len(), start - original_position);
+        edited.extend_from_slice(&sequence[original_position..start]);
+        match edit {
+            Edit::Substitute { sequence: replacement, .. } => {
+                coordinate_map.push_aligned(start, edited.len(), replacement.len());
+                edited.extend_from_slice(replacement);
+            },
+            Edit::Insert { sequence: inserted, .. } => edited.extend_from_slice(inserted),
+            Edit::Delete { .. } => {}
+        };
+        original_position = end;
+    }
+    coordinate_map.push_aligned(original_position, edited.len(), sequence.len() - original_position);
+    edited.extend_from_slice(&sequence[original_position..]);
+    coordinate_map.edited_length = edited.len();
+    (edited, coordinate_map)
+}
+
+impl ReferenceGenome {
+    /// Applies substitutions, insertions, and deletions to a contig, returning the map between the original and edited coordinates.
+    /// All edits use original coordinates and are applied together, so their order does not matter except for multiple insertions at the same position.
+    /// Edited sequences are stored as given, without upper-casing; see `modify_contig(...)` for how clones are affected.
+    /// # Arguments
+    /// * `chromosome` - the contig to edit
+    /// * `edits` - the edits to apply, which must not overlap
+    /// # Errors
+    /// * if `chromosome` is not in the reference genome, was unloaded, or fails to load
+    /// * if an edit is out of range or overlaps another; the contig is unchanged in this case
+    pub fn edit_contig(&mut self, chromosome: &str, edits: &[Edit]) -> Result<CoordinateMap, SimpleError> {
+        let length = match self.contig_length(chromosome) {
+            Some(l) => l,
+            None => bail!("{}", self.missing_contig_message(chromosome))
+        };
+        let sorted = sorted_edits(edits, length)?;
+        let mut coordinate_map = CoordinateMap::identity(length);
+        self.modify_contig(chromosome, |sequence| {
+            let (edited, edit_map) = apply_edits(sequence, &sorted);
+            *sequence = edited;
+            coordinate_map = edit_map;
+        })?;
+        Ok(coordinate_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_contig() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), "ACGTACGTAC").unwrap();
+        let edits = vec![
+            Edit::Delete { start: 6, end: 8 },
+            Edit::Substitute { position: 1, sequence: b"TT".to_vec() },
+            Edit::Insert { position: 4, sequence: b"NNN".to_vec() },
+            Edit::Insert { position: 10, sequence: b"G".to_vec() }
+        ];
+        let coordinate_map = reference_genome.edit_contig("chr1", &edits).unwrap();
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ATTTNNNACACG");
+        assert_eq!(coordinate_map.original_length(), 10);
+        assert_eq!(coordinate_map.edited_length(), 12);
+        assert_eq!(coordinate_map.blocks(), &[
+            MappedBlock { original_start: 0, edited_start: 0, length: 4 },
+            MappedBlock { original_start: 4, edited_start: 7, length: 2 },
+            MappedBlock { original_start: 8, edited_start: 9, length: 2 }
+        ]);
+
+        // substituted bases stay aligned, deleted and inserted bases do not map
+        assert_eq!(coordinate_map.to_edited(2), Some(2));
+        assert_eq!(coordinate_map.to_edited(5), Some(8));
+        assert_eq!(coordinate_map.to_edited(6), None);
+        assert_eq!(coordinate_map.to_edited(9), Some(10));
+        assert_eq!(coordinate_map.to_edited(10), None);
+        assert_eq!(coordinate_map.to_original(5), None);
+        assert_eq!(coordinate_map.to_original(10), Some(9));
+        assert_eq!(coordinate_map.to_original(11), None);
+    }
+
+    #[test]
+    fn test_invalid_edits() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), "ACGTACGT").unwrap();
+        let overlapping = vec![
+            Edit::Delete { start: 2, end: 5 },
+            Edit::Insert { position: 3, sequence: b"A".to_vec() }
+        ];
+        assert!(reference_genome.edit_contig("chr1", &overlapping).is_err());
+        assert!(reference_genome.edit_contig("chr1", &[Edit::Substitute { position: 7, sequence: b"AA".to_vec() }]).is_err());
+        assert!(reference_genome.edit_contig("chr2", &[]).is_err());
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGTACGT");
+
+        // adjacent edits are allowed
+        let adjacent = vec![
+            Edit::Delete { start: 2, end: 4 },
+            Edit::Insert { position: 2, sequence: b"C".to_vec() },
+            Edit::Substitute { position: 4, sequence: b"T".to_vec() }
+        ];
+        reference_genome.edit_contig("chr1", &adjacent).unwrap();
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACCTCGT");
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), "ACGTACGTAC").unwrap();
+        reference_genome.add_contig("chr2".to_string(), "GG").unwrap();
+
+        let first = reference_genome.edit_contig("chr1", &[Edit::Delete { start: 2, end: 4 }]).unwrap();
+        let second = reference_genome.edit_contig("chr1", &[Edit::Insert { position: 6, sequence: b"TTT".to_vec() }]).unwrap();
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACACGTTTTAC");
+        let combined = first.then(&second).unwrap();
+        assert_eq!(combined.to_edited(4), Some(2));
+        assert_eq!(combined.to_edited(8), Some(9));
+        assert_eq!(combined.to_original(7), None);
+        assert!(second.then(&first).is_err());
+
+        let deleted = reference_genome.edit_contig("chr2", &[Edit::Delete { start: 0, end: 2 }]).unwrap();
+        let mut output: Vec<u8> = vec![];
+        write_chain(&mut output, [("chr1", &combined), ("chr2", &deleted)]).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "chain 8 chr1 10 + 0 10 chr1 11 + 0 11 1\n2\t2\t0\n4\t0\t3\n2\n\n"
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use crate::builder::{decompress, is_gzip};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use simple_error::bail;
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A single line of a samtools `.fai` index
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FaiEntry {
+    /// The contig name
+    pub name: String,
+    /// The contig length in bases
+    pub length: usize,
+    /// The byte offset of the first base in the (uncompressed) FASTA
+    pub offset: u64,
+    /// The bases per line
+    pub line_bases: usize,
+    /// The bytes per line, including the line terminator
+    pub line_width: usize
+}
+
+/// The entries of a samtools `.fai` index, in file order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FastaIndex {
+    /// The entries in file order
+    pub entries: Vec<FaiEntry>
+}
+
+impl FastaIndex {
+    /// Parses a `.fai` index; columns after the first five, such as the quality offset of a FASTQ index, are ignored
+    /// # Arguments
+    /// * `reader` - the uncompressed index
+    /// # Errors
+    /// * any reading errors
+    /// * if a line has fewer than five columns or an invalid number, or repeats a contig name
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut entries: Vec<FaiEntry> = vec![];
+        let mut seen: HashMap<String, usize> = Default::default();
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 5 {
+                bail!("Expected 5 tab-separated columns on line {} of the index: {:?}", line_index + 1, line);
+            }
+            let number = |column: usize| -> Result<u64, Box<dyn Error>> {
+                match fields[column].parse::<u64>() {
+                    Ok(value) => Ok(value),
+                    Err(_) => bail!("Invalid number {:?} in column {} on line {} of the index", fields[column], column + 1, line_index + 1)
+                }
+            };
+            let entry = FaiEntry {
+                name: fields[0].to_string(),
+                length: number(1)? as usize,
+                offset: number(2)?,
+                line_bases: number(3)? as usize,
+                line_width: number(4)? as usize
+            };
+            if let Some(previous) = seen.insert(entry.name.clone(), line_index + 1) {
+                bail!("Contig {:?} is on lines {} and {} of the index", entry.name, previous, line_index + 1);
+            }
+            entries.push(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    /// Parses a `.fai` index, see `from_reader(...)`
+    /// # Arguments
+    /// * `fai_fn` - the index filename
+    /// # Errors
+    /// * any file reading errors
+    /// * see `from_reader(...)`
+    pub fn from_file(fai_fn: &Path) -> Result<Self, Box<dyn Error>> {
+        Self::from_reader(BufReader::new(std::fs::File::open(fai_fn)?))
+    }
+
+    /// Computes the index of a FASTA the way `samtools faidx` does, recording records whose lines cannot be indexed
+    /// (lines of different lengths other than a shorter last line) instead of failing
+    /// # Arguments
+    /// * `reader` - the uncompressed FASTA
+    /// # Returns
+    /// * the index, and the names of records with irregular line lengths
+    /// # Errors
+    /// * any reading errors
+    /// * if sequence appears before the first header
+    pub fn from_fasta_reader<R: BufRead>(mut reader: R) -> Result<(Self, Vec<String>), Box<dyn Error>> {
+        let mut entries: Vec<FaiEntry> = vec![];
+        let mut irregular: Vec<String> = vec![];
+        // whether the current record has had a line shorter than its first line, which must be its last
+        let mut short_line_seen = false;
+        let mut current_irregular = false;
+        let mut offset: u64 = 0;
+        let mut line: Vec<u8> = vec![];
+        loop {
+            line.clear();
+            let line_width = reader.read_until(b'\n', &mut line)?;
+            if line_width == 0 {
+                break;
+            }
+            let line_start = offset;
+            offset += line_width as u64;
+            let content = line.strip_suffix(b"\n").unwrap_or(&line);
+            let content = content.strip_suffix(b"\r").unwrap_or(content);
+
+            if let Some(header) = content.strip_prefix(b">") {
+                if current_irregular {
+                    irregular.push(entries.last().unwrap().name.clone());
+                }
+                let header = String::from_utf8_lossy(header);
+                let name = header.split_whitespace().next().unwrap_or_default().to_string();
+                entries.push(FaiEntry { name, length: 0, offset, line_bases: 0, line_width: 0 });
+                short_line_seen = false;
+                current_irregular = false;
+                continue;
+            }
+            let Some(entry) = entries.last_mut() else {
+                if content.iter().all(|c| c.is_ascii_whitespace()) {
+                    continue;
+                }
+                bail!("Sequence found before the first FASTA header at byte {}", line_start);
+            };
+            if entry.length == 0 && entry.line_bases == 0 {
+                if content.is_empty() {
+                    // the sequence starts after any blank lines that follow the header
+                    entry.offset = offset;
+                    continue;
+                }
+                entry.line_bases = content.len();
+                entry.line_width = line_width;
+            } else if (short_line_seen && !content.is_empty()) || content.len() > entry.line_bases || line_width > entry.line_width {
+                current_irregular = true;
+            } else if content.len() < entry.line_bases {
+                short_line_seen = true;
+            }
+            entry.length += content.len();
+        }
+        if current_irregular {
+            irregular.push(entries.last().unwrap().name.clone());
+        }
+        Ok((Self { entries }, irregular))
+    }
+
+    /// The entry for a contig, or `None` if it is not in the index
+    pub fn get(&self, name: &str) -> Option<&FaiEntry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+}
+
+/// A contig whose `.fai` entry does not match the FASTA
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FaiMismatch {
+    /// The entry in the index
+    pub index: FaiEntry,
+    /// The entry computed from the FASTA
+    pub actual: FaiEntry
+}
+
+impl FaiMismatch {
+    /// Describes the differing fields, e.g. "offset 6 in the index, 7 in the FASTA"
+    fn describe(&self) -> String {
+        let fields = [
+            ("length", self.index.length as u64, self.actual.length as u64),
+            ("offset", self.index.offset, self.actual.offset),
+            ("line bases", self.index.line_bases as u64, self.actual.line_bases as u64),
+            ("line width", self.index.line_width as u64, self.actual.line_width as u64)
+        ];
+        fields.iter()
+            .filter(|(_, index, actual)| index != actual)
+            .map(|(field, index, actual)| format!("{field} {index} in the index, {actual} in the FASTA"))
+            .collect::<Vec<String>>()
+            .join("; ")
+    }
+}
+
+/// The result of `verify_index(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IndexVerification {
+    /// Index contigs that are not in the FASTA, in index order
+    pub missing_from_fasta: Vec<String>,
+    /// FASTA records that are not in the index, in file order
+    pub missing_from_index: Vec<String>,
+    /// Contigs whose length, offset, or line layout differ, in FASTA order
+    pub mismatches: Vec<FaiMismatch>,
+    /// FASTA records with irregular line lengths, which no `.fai` can describe, in file order
+    pub irregular_records: Vec<String>
+}
+
+impl IndexVerification {
+    /// Returns true if every index entry matches the FASTA, so faidx-style random access returns the right sequence
+    pub fn is_valid(&self) -> bool {
+        self.missing_from_fasta.is_empty() &&
+            self.missing_from_index.is_empty() &&
+            self.mismatches.is_empty() &&
+            self.irregular_records.is_empty()
+    }
+
+    /// One line per problem, e.g. for an error message or a log; empty if the index is valid
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = vec![];
+        problems.extend(self.missing_from_fasta.iter().map(|c| format!("{c}: in the index but not in the FASTA")));
+        problems.extend(self.missing_from_index.iter().map(|c| format!("{c}: in the FASTA but not in the index")));
+        problems.extend(self.mismatches.iter().map(|m| format!("{}: {}", m.index.name, m.describe())));
+        problems.extend(self.irregular_records.iter().map(|c| format!("{c}: the FASTA record has irregular line lengths")));
+        problems
+    }
+}
+
+/// Checks every entry of a `.fai` index against the bytes of its FASTA, reporting stale or corrupted indexes that would
+/// otherwise make faidx-style random access silently return the wrong sequence. The FASTA is read once, start to end,
+/// and indexed the way `samtools faidx` does; the entries are then compared field by field.
+/// A bgzip-compressed FASTA is supported, since `.fai` offsets refer to the uncompressed data.
+/// # Arguments
+/// * `fasta_fn` - the FASTA filename; gzip compression is detected from a `.gz` extension
+/// * `fai_fn` - the index filename, usually the FASTA filename with `.fai` appended
+/// # Errors
+/// * any file reading errors
+/// * if the index cannot be parsed or the FASTA has sequence before its first header
+#[cfg_attr(feature = "tracing", tracing::instrument)]
+pub fn verify_index(fasta_fn: &Path, fai_fn: &Path) -> Result<IndexVerification, Box<dyn Error>> {
+    let index = FastaIndex::from_file(fai_fn)?;
+    let fasta_file = std::fs::File::open(fasta_fn)?;
+    let reader = decompress(Box::new(BufReader::new(fasta_file)), is_gzip(fasta_fn), &Arc::default())?;
+    let (actual, irregular_records) = FastaIndex::from_fasta_reader(reader)?;
+
+    let mut verification = IndexVerification { irregular_records, ..Default::default() };
+    let actual_names: HashSet<&str> = actual.entries.iter().map(|e| e.name.as_str()).collect();
+    verification.missing_from_fasta = index.entries.iter()
+        .filter(|e| !actual_names.contains(e.name.as_str()))
+        .map(|e| e.name.clone())
+        .collect();
+    let index_entries: HashMap<&str, &FaiEntry> = index.entries.iter().map(|e| (e.name.as_str(), e)).collect();
+    for entry in actual.entries.iter() {
+        match index_entries.get(entry.name.as_str()) {
+            None => verification.missing_from_index.push(entry.name.clone()),
+            Some(&index_entry) => {
+                // single-line records may be indexed with any width past their length, and empty records with any layout
+                let layout_matches = entry.length == 0 ||
+                    (index_entry.line_bases == entry.line_bases && index_entry.line_width == entry.line_width) ||
+                    (entry.length <= index_entry.line_bases && entry.length == entry.line_bases &&
+                        index_entry.line_width.saturating_sub(index_entry.line_bases) == entry.line_width - entry.line_bases);
+                if index_entry.length != entry.length || index_entry.offset != entry.offset || !layout_matches {
+                    verification.mismatches.push(FaiMismatch { index: index_entry.clone(), actual: entry.clone() });
+                }
+            }
+        }
+    }
+    Ok(verification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reference_genome::ReferenceGenome;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_from_fasta_reader() {
+        let fasta = b">chr1 description\nACGT\nACGT\nAC\n>empty\n>chr2\r\nAAA\r\nA\r\n>bad\nAC\nACGT\nA\n";
+        let (index, irregular) = FastaIndex::from_fasta_reader(&fasta[..]).unwrap();
+        assert_eq!(index.entries[0], FaiEntry { name: "chr1".to_string(), length: 10, offset: 18, line_bases: 4, line_width: 5 });
+        assert_eq!(index.entries[1], FaiEntry { name: "empty".to_string(), length: 0, offset: 38, line_bases: 0, line_width: 0 });
+        assert_eq!(index.get("chr2").unwrap(), &FaiEntry { name: "chr2".to_string(), length: 4, offset: 45, line_bases: 3, line_width: 5 });
+        assert_eq!(irregular, vec!["bad".to_string()]);
+        assert!(FastaIndex::from_fasta_reader(&b"ACGT\n>chr1\nA\n"[..]).is_err());
+
+        // matches the index written alongside a FASTA
+        let reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        let mut fasta: Vec<u8> = vec![];
+        reference_genome.write_fasta(&mut fasta, 3).unwrap();
+        let mut fai: Vec<u8> = vec![];
+        reference_genome.write_fai(&mut fai, 3).unwrap();
+        assert_eq!(FastaIndex::from_fasta_reader(&fasta[..]).unwrap().0, FastaIndex::from_reader(&fai[..]).unwrap());
+    }
+
+    #[test]
+    fn test_verify_index() {
+        let directory = std::env::temp_dir().join(format!("refgenome_fai_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let fasta_fn = directory.join("reference.fa");
+        let fai_fn = directory.join("reference.fa.fai");
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGTACGTAC\n>chr2\nGGCC\n>chr3\nTTT\n").unwrap();
+        reference_genome.write_indexed_fasta(&fasta_fn, 4).unwrap();
+        let verification = verify_index(&fasta_fn, &fai_fn).unwrap();
+        assert!(verification.is_valid());
+        assert!(verification.problems().is_empty());
+
+        // the FASTA was regenerated with a different line width, and a contig was added, but the index was not
+        let mut updated = reference_genome.clone();
+        updated.add_contig("chr4".to_string(), "A").unwrap();
+        updated.write_fasta(std::fs::File::create(&fasta_fn).unwrap(), 5).unwrap();
+        let verification = verify_index(&fasta_fn, &fai_fn).unwrap();
+        assert!(!verification.is_valid());
+        assert_eq!(verification.missing_from_index, vec!["chr4".to_string()]);
+        assert_eq!(verification.mismatches.len(), 3);
+        assert_eq!(verification.problems()[1], "chr1: line bases 4 in the index, 5 in the FASTA; line width 5 in the index, 6 in the FASTA");
+        assert_eq!(verification.problems()[2], "chr2: offset 25 in the index, 24 in the FASTA");
+
+        std::fs::write(&fai_fn, b"chr1\t10\tnot_a_number\t4\t5\n").unwrap();
+        assert!(verify_index(&fasta_fn, &fai_fn).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,227 @@
+use crate::builder::{Backend, ReferenceGenomeBuilder};
+use crate::reference_genome::ReferenceGenome;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::path::PathBuf;
+use std::ptr;
+
+/// Opaque handle to a loaded reference genome for the C API.
+/// Functions that can fail return NULL or a negative status, and the error message for the calling thread is available
+/// from `refgenome_last_error()`. Sequence and name pointers are borrowed from the handle; they are not NUL-terminated
+/// and remain valid until the handle is freed. The header is generated with `cbindgen --config cbindgen.toml --output include/refgenome.h`.
+pub struct RefGenome {
+    /// The wrapped genome
+    inner: ReferenceGenome
+}
+
+thread_local! {
+    /// The most recent error message on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Records an error message for `refgenome_last_error()`
+fn set_last_error(message: String) {
+    // interior NUL bytes cannot be represented in a C string
+    let message = CString::new(message.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Converts a C string argument, recording an error if it is NULL or not UTF-8
+/// # Safety
+/// `value` must be NULL or a valid NUL-terminated string
+unsafe fn to_str<'a>(value: *const c_char, argument: &str) -> Option<&'a str> {
+    if value.is_null() {
+        set_last_error(format!("Argument {argument} is NULL"));
+        return None;
+    }
+    match CStr::from_ptr(value).to_str() {
+        Ok(s) => Some(s),
+        Err(e) => {
+            set_last_error(format!("Argument {argument} is not valid UTF-8: {e}"));
+            None
+        }
+    }
+}
+
+/// Returns the last error message on the calling thread, or NULL if there has been no error.
+/// The string is valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn refgenome_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map(|m| m.as_ptr()).unwrap_or(ptr::null()))
+}
+
+/// Loads a reference genome from a FASTA file, directory, or wildcard pattern.
+/// If `mmap` is non-zero, the FASTA is memory-mapped and contigs are decoded on first access.
+/// Returns NULL on failure; the handle must be released with `refgenome_free(...)`.
+/// # Safety
+/// `path` must be a valid NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn refgenome_load(path: *const c_char, mmap: i32) -> *mut RefGenome {
+    let Some(path) = to_str(path, "path") else {
+        return ptr::null_mut();
+    };
+    let backend = if mmap != 0 { Backend::Mmap } else { Backend::InMemory };
+    match ReferenceGenomeBuilder::new(&PathBuf::from(path)).backend(backend).build() {
+        Ok(inner) => Box::into_raw(Box::new(RefGenome { inner })),
+        Err(e) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Releases a handle from `refgenome_load(...)`; NULL is ignored
+/// # Safety
+/// `handle` must be NULL or a handle from `refgenome_load(...)` that has not been freed
+#[no_mangle]
+pub unsafe extern "C" fn refgenome_free(handle: *mut RefGenome) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Returns the number of contigs
+/// # Safety
+/// `handle` must be a valid handle
+#[no_mangle]
+pub unsafe extern "C" fn refgenome_contig_count(handle: *const RefGenome) -> usize {
+    (*handle).inner.contig_keys().len()
+}
+
+/// Returns the name of the contig at a 0-based index in load order and writes its length in bytes to `name_length`,
+/// or returns NULL if the index is out of range
+/// # Safety
+/// `handle` must be a valid handle and `name_length` must be a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn refgenome_contig_name(handle: *const RefGenome, index: usize, name_length: *mut usize) -> *const u8 {
+    match (*handle).inner.contig_keys().get(index) {
+        Some(name) => {
+            *name_length = name.len();
+            name.as_ptr()
+        },
+        None => {
+            set_last_error(format!("Contig index {index} is out of range"));
+            ptr::null()
+        }
+    }
+}
+
+/// Writes the length of a contig to `length`, returning 0 on success or -1 if the contig is not in the reference genome
+/// # Safety
+/// `handle` must be a valid handle, `contig` a valid NUL-terminated string, and `length` a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn refgenome_contig_length(handle: *const RefGenome, contig: *const c_char, length: *mut usize) -> i32 {
+    let genome = &(*handle).inner;
+    let Some(contig) = to_str(contig, "contig") else {
+        return -1;
+    };
+    match genome.contig_length(contig) {
+        Some(l) => {
+            *length = l;
+            0
+        },
+        None => {
+            set_last_error(genome.missing_contig_message(contig));
+            -1
+        }
+    }
+}
+
+/// Fetches the 0-based, half-open region `[start, end)` of a contig, truncated to the contig length.
+/// Returns a pointer to the sequence and writes its length to `sequence_length`, or returns NULL if the contig
+/// is not in the reference genome, has been unloaded, fails to load from a lazy backend, or `start` > `end`.
+/// # Safety
+/// `handle` must be a valid handle, `contig` a valid NUL-terminated string, and `sequence_length` a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn refgenome_fetch(handle: *const RefGenome, contig: *const c_char, start: usize, end: usize, sequence_length: *mut usize) -> *const u8 {
+    let genome = &(*handle).inner;
+    let Some(contig) = to_str(contig, "contig") else {
+        return ptr::null();
+    };
+    if genome.resolve_contig_name(contig).is_none() {
+        set_last_error(genome.missing_contig_message(contig));
+        return ptr::null();
+    }
+    if start > end {
+        set_last_error(format!("start > end: {start} > {end}"));
+        return ptr::null();
+    }
+    // checked rather than panicking, since panics cannot unwind across the C boundary
+    let sequence = match genome.try_full_chromosome(contig) {
+        Ok(sequence) => sequence,
+        Err(e) => {
+            set_last_error(e.to_string());
+            return ptr::null();
+        }
+    };
+    let slice = &sequence[start.min(sequence.len())..end.min(sequence.len())];
+    *sequence_length = slice.len();
+    slice.as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi() {
+        unsafe {
+            let path = CString::new("./test_data/test_reference.fa").unwrap();
+            let handle = refgenome_load(path.as_ptr(), 1);
+            assert!(!handle.is_null());
+            assert_eq!(refgenome_contig_count(handle), 2);
+
+            let mut length: usize = 0;
+            let name = refgenome_contig_name(handle, 1, &mut length);
+            assert_eq!(std::slice::from_raw_parts(name, length), b"chr2");
+            assert!(refgenome_contig_name(handle, 2, &mut length).is_null());
+
+            let chr1 = CString::new("chr1").unwrap();
+            assert_eq!(refgenome_contig_length(handle, chr1.as_ptr(), &mut length), 0);
+            assert_eq!(length, 8);
+            let sequence = refgenome_fetch(handle, chr1.as_ptr(), 2, 100, &mut length);
+            assert_eq!(std::slice::from_raw_parts(sequence, length), b"GTACGT");
+
+            let chr3 = CString::new("chr3").unwrap();
+            assert!(refgenome_fetch(handle, chr3.as_ptr(), 0, 1, &mut length).is_null());
+            let error = CStr::from_ptr(refgenome_last_error()).to_str().unwrap();
+            assert!(error.starts_with("Contig key \"chr3\" is not in the reference genome"));
+            refgenome_free(handle);
+
+            let missing = CString::new("./test_data/missing.fa").unwrap();
+            assert!(refgenome_load(missing.as_ptr(), 0).is_null());
+            assert!(!refgenome_last_error().is_null());
+        }
+    }
+
+    #[test]
+    fn test_ffi_load_error() {
+        /// A stored contig that always fails to decode
+        struct Corrupt;
+        impl crate::contig_store::ContigStore for Corrupt {
+            fn len(&self) -> usize {
+                8
+            }
+
+            fn decode(&self) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+                Err("corrupt".into())
+            }
+
+            fn stored_bytes(&self) -> usize {
+                0
+            }
+        }
+        let inner = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .contig_store(|_, _| Ok(Box::new(Corrupt)))
+            .build()
+            .unwrap();
+        let handle = RefGenome { inner };
+        unsafe {
+            let chr1 = CString::new("chr1").unwrap();
+            let mut length: usize = 0;
+            assert!(refgenome_fetch(&handle, chr1.as_ptr(), 0, 4, &mut length).is_null());
+            let error = CStr::from_ptr(refgenome_last_error()).to_str().unwrap();
+            assert!(error.starts_with("Failed to load contig \"chr1\""), "{error}");
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use crate::builder::{decompress, is_gzip};
+use crate::digest::{sha512t24u, ContigDigests, StreamingDigests};
+use crate::parser::header_id;
+use memchr::memchr;
+use rustc_hash::FxHashSet as HashSet;
+use simple_error::bail;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// The fingerprint of one contig, computed without holding its sequence
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContigFingerprint {
+    /// The contig name, everything before the first whitespace of the header
+    pub name: String,
+    /// The sequence length
+    pub length: usize,
+    /// The digests of the upper-cased sequence, identical to `ReferenceGenome::contig_digests(...)`
+    pub digests: ContigDigests,
+    /// The number of `N`/`n` bases
+    pub n_count: usize,
+    /// The number of `N`/`n` runs of at least the minimum gap length, as reported by `find_gaps(...)`
+    pub gap_count: usize,
+    /// The total length of those runs
+    pub gap_bases: usize,
+    /// The length of the longest of those runs, 0 without any
+    pub largest_gap: usize
+}
+
+/// GA4GH sequence collection (seqcol) digests of a FASTA: the `sha512t24u` digest of each attribute array in canonical JSON,
+/// and the top-level digest over the inherent attributes (`names` and `sequences`). Sequences are identified by refget `SQ.` digests of their upper-cased sequence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CollectionDigests {
+    /// The top-level (level 0) collection digest, which does not depend on `lengths` since they follow from `sequences`
+    pub digest: String,
+    /// The digest of the `names` array
+    pub names: String,
+    /// The digest of the `lengths` array
+    pub lengths: String,
+    /// The digest of the `sequences` array
+    pub sequences: String
+}
+
+/// Digests, lengths, and gap statistics of every record in a FASTA, from `fingerprint_fasta(...)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FastaFingerprint {
+    /// The contigs in file order
+    pub contigs: Vec<ContigFingerprint>,
+    /// The sequence collection digests of the whole file
+    pub collection: CollectionDigests
+}
+
+impl FastaFingerprint {
+    /// Looks up the fingerprint of a contig by name
+    /// # Arguments
+    /// * `name` - the contig name
+    pub fn get(&self, name: &str) -> Option<&ContigFingerprint> {
+        self.contigs.iter().find(|c| c.name == name)
+    }
+
+    /// The total length of all contigs
+    pub fn total_length(&self) -> usize {
+        self.contigs.iter().map(|c| c.length).sum()
+    }
+}
+
+/// Quotes a value as a JSON string, escaping as canonical JSON (RFC 8785) does
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\u{8}' => quoted.push_str("\\b"),
+            '\u{c}' => quoted.push_str("\\f"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl CollectionDigests {
+    /// Computes the collection digests from the contigs of a FASTA, in file order
+    fn of(contigs: &[ContigFingerprint]) -> Self {
+        let array = |values: Vec<String>| sha512t24u(format!("[{}]", values.join(",")).as_bytes());
+        let names = array(contigs.iter().map(|c| json_string(&c.name)).collect());
+        let lengths = array(contigs.iter().map(|c| c.length.to_string()).collect());
+        let sequences = array(contigs.iter().map(|c| json_string(&format!("SQ.{}", c.digests.sha512t24u))).collect());
+        // only the inherent attributes identify the collection
+        let digest = sha512t24u(format!("{{\"names\":{},\"sequences\":{}}}", json_string(&names), json_string(&sequences)).as_bytes());
+        Self { digest, names, lengths, sequences }
+    }
+}
+
+/// The running state of the contig being streamed
+struct ContigAccumulator {
+    /// The contig name
+    name: String,
+    /// The bases seen so far
+    length: usize,
+    /// The running digests
+    digests: StreamingDigests,
+    /// The `N`/`n` bases seen so far
+    n_count: usize,
+    /// The length of the `N`/`n` run at the end of what has been seen, which may continue on the next line
+    run: usize,
+    /// The gaps seen so far
+    gap_count: usize,
+    /// The bases in the gaps seen so far
+    gap_bases: usize,
+    /// The longest gap seen so far
+    largest_gap: usize,
+    /// The minimum run length that counts as a gap
+    min_gap_length: usize
+}
+
+impl ContigAccumulator {
+    /// Starts a contig
+    fn new(name: String, min_gap_length: usize) -> Self {
+        Self {
+            name,
+            length: 0,
+            digests: StreamingDigests::new(),
+            n_count: 0,
+            run: 0,
+            gap_count: 0,
+            gap_bases: 0,
+            largest_gap: 0,
+            min_gap_length: min_gap_length.max(1)
+        }
+    }
+
+    /// Counts a run of `N`/`n` that has ended
+    fn close_run(&mut self) {
+        if self.run >= self.min_gap_length {
+            self.gap_count += 1;
+            self.gap_bases += self.run;
+            self.largest_gap = self.largest_gap.max(self.run);
+        }
+        self.run = 0;
+    }
+
+    /// Adds a piece of sequence, ignoring carriage returns
+    fn consume(&mut self, piece: &[u8]) {
+        for part in piece.split(|&c| c == b'\r') {
+            self.length += part.len();
+            self.digests.consume(part);
+            for c in part.iter() {
+                if c.eq_ignore_ascii_case(&b'N') {
+                    self.n_count += 1;
+                    self.run += 1;
+                } else if self.run > 0 {
+                    self.close_run();
+                }
+            }
+        }
+    }
+
+    /// Completes the contig
+    fn finish(mut self) -> ContigFingerprint {
+        self.close_run();
+        ContigFingerprint {
+            name: self.name,
+            length: self.length,
+            digests: self.digests.finalize(),
+            n_count: self.n_count,
+            gap_count: self.gap_count,
+            gap_bases: self.gap_bases,
+            largest_gap: self.largest_gap
+        }
+    }
+}
+
+/// Completes the current contig, if any, and starts the one named by a header line (without the `>`)
+fn start_contig(header: &[u8], min_gap_length: usize, names: &mut HashSet<String>, current: &mut Option<ContigAccumulator>,
+    contigs: &mut Vec<ContigFingerprint>) -> Result<(), Box<dyn Error>> {
+    let name = header_id(header)?;
+    if !names.insert(name.clone()) {
+        bail!("Found duplicate contig name: {}", name);
+    }
+    if let Some(contig) = current.replace(ContigAccumulator::new(name, min_gap_length)) {
+        contigs.push(contig.finish());
+    }
+    Ok(())
+}
+
+/// Fingerprints a FASTA stream, see `fingerprint_fasta(...)`. The stream is read in buffer-sized chunks, so memory use does not
+/// depend on line or contig lengths.
+/// # Arguments
+/// * `reader` - the uncompressed FASTA content
+/// * `min_gap_length` - the minimum `N`/`n` run length that counts as a gap
+/// # Errors
+/// * if reading fails
+/// * if there is sequence before the first header, a header is not UTF-8, or a contig name is repeated
+pub fn fingerprint_reader<R: BufRead>(mut reader: R, min_gap_length: usize) -> Result<FastaFingerprint, Box<dyn Error>> {
+    let mut contigs: Vec<ContigFingerprint> = vec![];
+    let mut names: HashSet<String> = Default::default();
+    let mut current: Option<ContigAccumulator> = None;
+    let mut header: Vec<u8> = vec![];
+    let mut in_header = false;
+    let mut at_line_start = true;
+    loop {
+        let chunk = reader.fill_buf()?;
+        if chunk.is_empty() {
+            break;
+        }
+        let chunk_length = chunk.len();
+        let mut position = 0;
+        while position < chunk_length {
+            let line_end = memchr(b'\n', &chunk[position..]).map(|i| position + i);
+            let piece = &chunk[position..line_end.unwrap_or(chunk_length)];
+            if in_header {
+                header.extend_from_slice(piece);
+                if line_end.is_some() {
+                    in_header = false;
+                    start_contig(&header, min_gap_length, &mut names, &mut current, &mut contigs)?;
+                }
+            } else if at_line_start && piece.first() == Some(&b'>') {
+                header.clear();
+                in_header = true;
+                position += 1;
+                continue;
+            } else if let Some(contig) = current.as_mut() {
+                contig.consume(piece);
+            } else if piece.iter().any(|&c| c != b'\r') {
+                bail!("Expected > at record start.");
+            }
+            at_line_start = line_end.is_some();
+            position = line_end.map(|i| i + 1).unwrap_or(chunk_length);
+        }
+        reader.consume(chunk_length);
+    }
+    if in_header {
+        // a header on the last line without a newline
+        start_contig(&header, min_gap_length, &mut names, &mut current, &mut contigs)?;
+    }
+    if let Some(contig) = current.take() {
+        contigs.push(contig.finish());
+    }
+
+    let collection = CollectionDigests::of(&contigs);
+    Ok(FastaFingerprint { contigs, collection })
+}
+
+/// Streams a FASTA[.gz] file once and computes per-contig digests, lengths, and gap statistics, plus the sequence collection
+/// digests of the file, without ever holding a sequence in memory. This fingerprints references that are too large to load,
+/// e.g. on small machines; the results match loading the file and calling `contig_digests(...)` and `find_gaps(...)`.
+/// # Arguments
+/// * `fasta_fn` - the FASTA filename, gzip-compressed if it ends in `.gz`
+/// * `min_gap_length` - the minimum `N`/`n` run length that counts as a gap
+/// # Errors
+/// * if the file cannot be opened or read, or is not a valid FASTA, see `fingerprint_reader(...)`
+pub fn fingerprint_fasta(fasta_fn: &Path, min_gap_length: usize) -> Result<FastaFingerprint, Box<dyn Error>> {
+    let file = File::open(fasta_fn)?;
+    let reader = decompress(Box::new(BufReader::new(file)), is_gzip(fasta_fn), &Default::default())?;
+    fingerprint_reader(reader, min_gap_length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completeness::find_gaps;
+    use crate::reference_genome::ReferenceGenome;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_collection_digests() {
+        // the example collection from the GA4GH seqcol specification
+        let fingerprint = fingerprint_reader(&b">chrX\nTTGGGGAA\n>chr1\nGGAA\n>chr2\nGCGC\n"[..], 1).unwrap();
+        assert_eq!(fingerprint.collection, CollectionDigests {
+            digest: "XZlrcEGi6mlopZ2uD8ObHkQB1d0oDwKk".to_string(),
+            names: "Fw1r9eRxfOZD98KKrhlYQNEdSRHoVxAG".to_string(),
+            lengths: "cGRMZIb3AVgkcAfNv39RN7hnT5Chk7RX".to_string(),
+            sequences: "0uDQVLuHaOZi1u76LjV__yrVUIz9Bwhr".to_string()
+        });
+        assert_eq!(fingerprint.total_length(), 16);
+        assert_eq!(json_string("a\"b\\c\u{1}"), "\"a\\\"b\\\\c\\u0001\"");
+    }
+
+    #[test]
+    fn test_fingerprint_fasta() {
+        let fasta_fn = PathBuf::from("./test_data/test_reference.fa");
+        let fingerprint = fingerprint_fasta(&fasta_fn, 2).unwrap();
+        let reference_genome = ReferenceGenome::from_fasta(&fasta_fn).unwrap();
+        assert_eq!(fingerprint.contigs.iter().map(|c| c.name.clone()).collect::<Vec<String>>(), reference_genome.contig_keys());
+        for contig in fingerprint.contigs.iter() {
+            let sequence = reference_genome.get_full_chromosome(&contig.name);
+            let gaps = find_gaps(sequence, 2);
+            assert_eq!(contig.length, sequence.len());
+            assert_eq!(&contig.digests, reference_genome.contig_digests(&contig.name).unwrap());
+            assert_eq!(contig.n_count, sequence.iter().filter(|c| c.eq_ignore_ascii_case(&b'N')).count());
+            assert_eq!(contig.gap_count, gaps.len());
+            assert_eq!(contig.gap_bases, gaps.iter().map(|g| g.len()).sum::<usize>());
+            assert_eq!(contig.largest_gap, gaps.iter().map(|g| g.len()).max().unwrap_or(0));
+        }
+        assert!(fingerprint.get("chr1").is_some());
+        assert!(fingerprint.get("chr3").is_none());
+    }
+
+    #[test]
+    fn test_fingerprint_reader() {
+        // gaps span lines and chunk boundaries, and the layout is irregular
+        let fasta = b">chr1 description\r\nACnn\r\nNNgt\r\n\r\nNAAN\n>chr2\nNNNNN\n>empty\n>chr3";
+        let reader = BufReader::with_capacity(3, &fasta[..]);
+        let fingerprint = fingerprint_reader(reader, 2).unwrap();
+        let chr1 = fingerprint.get("chr1").unwrap();
+        assert_eq!(chr1.length, 12);
+        assert_eq!(chr1.digests, ContigDigests { md5: crate::digest::md5_hex(b"ACNNNNGTNAAN"), sha512t24u: sha512t24u(b"ACNNNNGTNAAN") });
+        assert_eq!((chr1.n_count, chr1.gap_count, chr1.gap_bases, chr1.largest_gap), (6, 1, 4, 4));
+        let chr2 = fingerprint.get("chr2").unwrap();
+        assert_eq!((chr2.length, chr2.n_count, chr2.gap_count, chr2.gap_bases, chr2.largest_gap), (5, 5, 1, 5, 5));
+        assert_eq!(fingerprint.get("empty").unwrap().length, 0);
+        assert_eq!(fingerprint.get("chr3").unwrap().length, 0);
+        assert_eq!(fingerprint.contigs.len(), 4);
+
+        assert!(fingerprint_reader(&b">chr1\nA\n>chr1\nC\n"[..], 1).is_err());
+        assert!(fingerprint_reader(&b"ACGT\n>chr1\nA\n"[..], 1).is_err());
+        assert!(fingerprint_fasta(&PathBuf::from("./test_data/missing.fa"), 1).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use crate::builder::{decompress, is_gzip};
+use crate::reference_genome::ReferenceGenome;
+use bytes::Bytes;
+use simple_error::{bail, SimpleError};
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A variant site, or a plain position, to extract flanking sequence around
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariantSite {
+    /// The contig name
+    pub contig: String,
+    /// The 0-based position of the first reference base
+    pub position: usize,
+    /// The reference allele; if empty, the site is the single base at `position`
+    pub reference: Vec<u8>,
+    /// The alternate alleles, in VCF order
+    pub alternates: Vec<Vec<u8>>
+}
+
+impl VariantSite {
+    /// Creates a site for a single position without any alleles
+    /// # Arguments
+    /// * `contig` - the contig name
+    /// * `position` - the 0-based position
+    pub fn at(contig: &str, position: usize) -> Self {
+        Self {
+            contig: contig.to_string(),
+            position,
+            reference: vec![],
+            alternates: vec![]
+        }
+    }
+
+    /// The number of reference bases covered by the site, at least 1
+    fn reference_length(&self) -> usize {
+        self.reference.len().max(1)
+    }
+}
+
+/// The flanking sequence around a variant site, from `ReferenceGenome::flanking_sequences(...)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlankingWindow {
+    /// The site the window was extracted for
+    pub site: VariantSite,
+    /// The 0-based start of the window (included), clipped to the contig start
+    pub start: usize,
+    /// The 0-based end of the window (excluded), clipped to the contig end
+    pub end: usize,
+    /// The reference sequence of the window
+    pub reference_sequence: Bytes,
+    /// False if the reference allele of the site does not match the genome, which usually means the VCF is for another assembly
+    pub reference_matches: bool,
+    /// The window with each alternate allele spliced in place of the reference allele, in VCF order, if requested.
+    /// Symbolic alleles (e.g. `<DEL>`, `*`, and breakends) are `None`, since they have no literal sequence.
+    pub alternate_sequences: Vec<Option<Vec<u8>>>
+}
+
+/// Returns true if an allele is a literal base sequence rather than a symbolic allele
+fn is_literal_allele(allele: &[u8]) -> bool {
+    !allele.is_empty() && allele.iter().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Reads the variant sites of a VCF, ignoring headers and every column after ALT
+/// # Arguments
+/// * `reader` - the uncompressed VCF content
+/// # Errors
+/// * any reading errors
+/// * if a record has fewer than 5 columns or an invalid position
+pub fn read_vcf_sites<R: BufRead>(reader: R) -> Result<Vec<VariantSite>, Box<dyn Error>> {
+    let mut sites: Vec<VariantSite> = vec![];
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() < 5 {
+            bail!("Expected at least 5 columns on line {} of the VCF, found {}", line_index + 1, columns.len());
+        }
+        let position = match columns[1].parse::<usize>() {
+            Ok(p) if p > 0 => p - 1,
+            _ => bail!("Invalid position {:?} on line {} of the VCF", columns[1], line_index + 1)
+        };
+        let alternates = if columns[4] == "." {
+            vec![]
+        } else {
+            columns[4].split(',').map(|a| a.as_bytes().to_vec()).collect()
+        };
+        sites.push(VariantSite {
+            contig: columns[0].to_string(),
+            position,
+            reference: columns[3].as_bytes().to_vec(),
+            alternates
+        });
+    }
+    Ok(sites)
+}
+
+/// Reads the variant sites of a VCF file, see `read_vcf_sites(...)`
+/// # Arguments
+/// * `vcf_fn` - the VCF filename; gzip and bgzip compression are detected from a `.gz` extension
+/// # Errors
+/// * any file reading errors
+/// * see `read_vcf_sites(...)`
+pub fn read_vcf_file(vcf_fn: &Path) -> Result<Vec<VariantSite>, Box<dyn Error>> {
+    let vcf_file = std::fs::File::open(vcf_fn)?;
+    let reader = decompress(Box::new(BufReader::new(vcf_file)), is_gzip(vcf_fn), &Arc::default())?;
+    read_vcf_sites(reader)
+}
+
+impl ReferenceGenome {
+    /// Extracts the reference sequence around each site, such as for probe design or model features.
+    /// Each window covers `padding` bases on either side of the reference allele, clipped at the contig ends.
+    /// Sites are processed in the given order; consecutive sites on the same contig share a single read of a lazily loaded contig.
+    /// # Arguments
+    /// * `sites` - the sites, e.g. from `read_vcf_file(...)` or `VariantSite::at(...)`
+    /// * `padding` - the number of flanking bases on each side
+    /// * `splice_alternates` - if true, each literal alternate allele is also spliced into its window
+    /// # Errors
+    /// * if a contig is not in the reference genome, was unloaded, or fails to load
+    /// * if a reference allele extends past the end of its contig
+    pub fn flanking_sequences(&self, sites: &[VariantSite], padding: usize, splice_alternates: bool) -> Result<Vec<FlankingWindow>, SimpleError> {
+        let mut windows: Vec<FlankingWindow> = Vec::with_capacity(sites.len());
+        let mut current: Option<(&str, Bytes)> = None;
+        for site in sites.iter() {
+            let sequence = match current.as_ref() {
+                Some((contig, sequence)) if *contig == site.contig => sequence.clone(),
+                _ => {
+                    if self.contig_id(&site.contig).is_none() {
+                        bail!("{}", self.missing_contig_message(&site.contig));
+                    }
+                    let sequence = self.try_sequence_unkept(&site.contig)?;
+                    current = Some((&site.contig, sequence.clone()));
+                    sequence
+                }
+            };
+
+            let allele_end = site.position + site.reference_length();
+            if allele_end > sequence.len() {
+                bail!("Site {}:{} extends past the end of the contig ({} bp)", site.contig, site.position + 1, sequence.len());
+            }
+            let start = site.position.saturating_sub(padding);
+            let end = (allele_end + padding).min(sequence.len());
+            let reference_matches = site.reference.is_empty()
+                || site.reference.eq_ignore_ascii_case(&sequence[site.position..allele_end]);
+            let alternate_sequences = if splice_alternates {
+                site.alternates.iter()
+                    .map(|alternate| {
+                        if !is_literal_allele(alternate) {
+                            return None;
+                        }
+                        let mut spliced = sequence[start..site.position].to_vec();
+                        spliced.extend_from_slice(alternate);
+                        spliced.extend_from_slice(&sequence[allele_end..end]);
+                        Some(spliced)
+                    })
+                    .collect()
+            } else {
+                vec![]
+            };
+            windows.push(FlankingWindow {
+                site: site.clone(),
+                start,
+                end,
+                reference_sequence: sequence.slice(start..end),
+                reference_matches,
+                alternate_sequences
+            });
+        }
+        Ok(windows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_vcf_sites() {
+        let vcf = b"##fileformat=VCFv4.3\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\nchr1\t3\t.\tG\tA,<DEL>\t.\tPASS\t.\nchr2\t1\trs1\tAC\t.\t.\t.\t.\n";
+        let sites = read_vcf_sites(&vcf[..]).unwrap();
+        assert_eq!(sites, vec![
+            VariantSite { contig: "chr1".to_string(), position: 2, reference: b"G".to_vec(), alternates: vec![b"A".to_vec(), b"<DEL>".to_vec()] },
+            VariantSite { contig: "chr2".to_string(), position: 0, reference: b"AC".to_vec(), alternates: vec![] }
+        ]);
+
+        assert!(read_vcf_sites(&b"chr1\t0\t.\tA\tC\n"[..]).is_err());
+        assert!(read_vcf_sites(&b"chr1\t1\t.\tA\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_flanking_sequences() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGTACGTAC\n>chr2\nGGCC\n").unwrap();
+        let sites = vec![
+            VariantSite { contig: "chr1".to_string(), position: 4, reference: b"AC".to_vec(), alternates: vec![b"T".to_vec(), b"*".to_vec()] },
+            VariantSite { contig: "chr1".to_string(), position: 1, reference: b"G".to_vec(), alternates: vec![b"GTT".to_vec()] },
+            VariantSite::at("chr2", 3)
+        ];
+        let windows = reference_genome.flanking_sequences(&sites, 2, true).unwrap();
+        assert_eq!((windows[0].start, windows[0].end), (2, 8));
+        assert_eq!(windows[0].reference_sequence, &b"GTACGT"[..]);
+        assert!(windows[0].reference_matches);
+        assert_eq!(windows[0].alternate_sequences, vec![Some(b"GTTGT".to_vec()), None]);
+
+        // clipped at the contig start, and the reference allele does not match
+        assert_eq!((windows[1].start, windows[1].end), (0, 4));
+        assert!(!windows[1].reference_matches);
+        assert_eq!(windows[1].alternate_sequences, vec![Some(b"AGTTGT".to_vec())]);
+
+        assert_eq!(windows[2].reference_sequence, &b"GCC"[..]);
+        assert!(windows[2].reference_matches);
+
+        let windows = reference_genome.flanking_sequences(&sites, 0, false).unwrap();
+        assert_eq!(windows[0].reference_sequence, &b"AC"[..]);
+        assert!(windows[0].alternate_sequences.is_empty());
+
+        assert!(reference_genome.flanking_sequences(&[VariantSite::at("chr3", 0)], 2, false).is_err());
+        assert!(reference_genome.flanking_sequences(&[VariantSite::at("chr2", 4)], 2, false).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::reference_genome::{ContigSequence, ReferenceGenome};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use simple_error::{bail, SimpleError};
+use std::sync::OnceLock;
+
+/// The sorted, disjoint runs of a contig whose bases have some property, such as being soft-masked, with prefix sums of
+/// the run lengths so the number of such bases in any region is found with one binary search instead of a rescan
+#[derive(Debug, Default)]
+pub(crate) struct RunIndex {
+    /// The run starts, ascending
+    starts: Vec<usize>,
+    /// The run ends (excluded), by run
+    ends: Vec<usize>,
+    /// The number of bases in all runs before each run
+    preceding: Vec<usize>
+}
+
+impl RunIndex {
+    /// Finds the runs of bases that match a predicate
+    /// # Arguments
+    /// * `sequence` - the ASCII sequence
+    /// * `predicate` - returns true for the bases to index
+    pub(crate) fn from_predicate<F>(sequence: &[u8], predicate: F) -> Self where F: Fn(u8) -> bool {
+        let mut index = Self::default();
+        let mut total = 0;
+        let mut position = 0;
+        while position < sequence.len() {
+            if predicate(sequence[position]) {
+                let start = position;
+                while position < sequence.len() && predicate(sequence[position]) {
+                    position += 1;
+                }
+                index.starts.push(start);
+                index.ends.push(position);
+                index.preceding.push(total);
+                total += position - start;
+            } else {
+                position += 1;
+            }
+        }
+        index
+    }
+
+    /// The number of indexed bases before a position
+    fn covered_before(&self, position: usize) -> usize {
+        match self.starts.partition_point(|&s| s < position) {
+            0 => 0,
+            runs => self.preceding[runs - 1] + self.ends[runs - 1].min(position) - self.starts[runs - 1]
+        }
+    }
+
+    /// The number of indexed bases in a region
+    /// # Arguments
+    /// * `start` - the 0-based start index (included)
+    /// * `end` - the 0-based end index (excluded), at least `start`
+    pub(crate) fn count(&self, start: usize, end: usize) -> usize {
+        self.covered_before(end) - self.covered_before(start)
+    }
+}
+
+/// Run indexes of every contig of a genome, cached on the genome and each built on first use
+#[derive(Default)]
+pub(crate) struct RunIndexCache {
+    /// The soft-masked (lower-case) runs of each contig by ID, or `None` for contigs that are unloaded or fail to load
+    masked: OnceLock<Vec<Option<RunIndex>>>,
+    /// The `N`/`n` runs (assembly gaps) of each contig by ID, or `None` for contigs that are unloaded or fail to load
+    gaps: OnceLock<Vec<Option<RunIndex>>>
+}
+
+impl RunIndexCache {
+    /// The soft-masked runs of each contig by ID, indexed on first use
+    /// # Arguments
+    /// * `names` - the contig names by ID
+    /// * `sequences` - the contig sequences by ID
+    pub(crate) fn masked(&self, names: &[String], sequences: &[ContigSequence]) -> &[Option<RunIndex>] {
+        self.masked.get_or_init(|| index_contigs(names, sequences, |c| c.is_ascii_lowercase()))
+    }
+
+    /// The `N`/`n` runs of each contig by ID, indexed on first use
+    /// # Arguments
+    /// * `names` - the contig names by ID
+    /// * `sequences` - the contig sequences by ID
+    pub(crate) fn gaps(&self, names: &[String], sequences: &[ContigSequence]) -> &[Option<RunIndex>] {
+        self.gaps.get_or_init(|| index_contigs(names, sequences, |c| c.eq_ignore_ascii_case(&b'N')))
+    }
+}
+
+/// Indexes the runs of every contig that match a predicate, in parallel with the `rayon` feature.
+/// Lazily loaded contigs are read once and are not kept in memory.
+fn index_contigs<F>(names: &[String], sequences: &[ContigSequence], predicate: F) -> Vec<Option<RunIndex>>
+    where F: Fn(u8) -> bool + Sync {
+    let index = |(name, sequence): (&String, &ContigSequence)| {
+        sequence.try_as_bytes_unkept(name).ok().map(|bytes| RunIndex::from_predicate(&bytes, &predicate))
+    };
+    #[cfg(feature = "rayon")]
+    let indexes = names.par_iter().zip(sequences.par_iter()).map(index).collect();
+    #[cfg(not(feature = "rayon"))]
+    let indexes = names.iter().zip(sequences.iter()).map(index).collect();
+    indexes
+}
+
+impl ReferenceGenome {
+    /// Looks up the run index of a contig and counts its indexed bases in a region, as a fraction of the region length
+    /// # Errors
+    /// * if the contig is not in the reference genome, was unloaded, or fails to load
+    /// * if `start` > `end`, or `end` is past the contig end
+    fn run_fraction(&self, indexes: &[Option<RunIndex>], chromosome: &str, start: usize, end: usize) -> Result<f64, SimpleError> {
+        let Some(name) = self.resolve_contig_name(chromosome) else {
+            bail!("{}", self.missing_contig_message(chromosome));
+        };
+        let id = self.contig_id(name).unwrap();
+        let Some(index) = indexes[id as usize].as_ref() else {
+            // reading the contig again reports why it could not be indexed
+            self.try_sequence_unkept(name)?;
+            bail!("Failed to index contig {:?}", name);
+        };
+        let length = self.contig_length(name).unwrap_or_default();
+        if start > end {
+            bail!("start > end: {} > {}", start, end);
+        }
+        if end > length {
+            bail!("Region {:?}:{}-{} extends past the contig length {}", chromosome, start, end, length);
+        }
+        if start == end {
+            return Ok(0.0);
+        }
+        Ok(index.count(start, end) as f64 / (end - start) as f64)
+    }
+
+    /// Computes the fraction of soft-masked (lower-case) bases in a region, e.g. to annotate the repeat content of many
+    /// candidate regions. The lower-case runs of every contig are indexed on the first call, reading every contig once
+    /// (lazily loaded contigs are not kept in memory), after which each query is a binary search rather than a rescan.
+    /// The index is shared with clones made afterwards and rebuilt after any change to the sequences.
+    /// Soft-masking is only kept when the genome is loaded with `ReferenceGenomeBuilder::uppercase(false)`; otherwise every fraction is 0.
+    /// # Arguments
+    /// * `chromosome` - the contig name
+    /// * `start` - the 0-based start index (included)
+    /// * `end` - the 0-based end index (excluded)
+    /// # Returns
+    /// * the fraction of lower-case bases, 0 for an empty region
+    /// # Errors
+    /// * if `chromosome` is not in the reference genome, was unloaded, or fails to load
+    /// * if `start` > `end`, or `end` is past the contig end; regions are never clamped
+    pub fn masked_fraction(&self, chromosome: &str, start: usize, end: usize) -> Result<f64, SimpleError> {
+        self.run_fraction(self.masked_runs(), chromosome, start, end)
+    }
+
+    /// Computes the fraction of `N`/`n` bases in a region, e.g. so coverage or copy-number tools can exclude or down-weight
+    /// windows that overlap assembly gaps. The gaps of every contig are indexed on the first call, like `masked_fraction(...)`,
+    /// so each query is a binary search rather than a rescan. Every `N` counts, including runs shorter than `find_gaps(...)` would report.
+    /// # Arguments
+    /// * `chromosome` - the contig name
+    /// * `start` - the 0-based start index (included)
+    /// * `end` - the 0-based end index (excluded)
+    /// # Returns
+    /// * the fraction of `N`/`n` bases, 0 for an empty region
+    /// # Errors
+    /// * if `chromosome` is not in the reference genome, was unloaded, or fails to load
+    /// * if `start` > `end`, or `end` is past the contig end; regions are never clamped
+    pub fn n_fraction(&self, chromosome: &str, start: usize, end: usize) -> Result<f64, SimpleError> {
+        self.run_fraction(self.gap_runs(), chromosome, start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ReferenceGenomeBuilder;
+
+    #[test]
+    fn test_run_index() {
+        let sequence = b"aaCGtttTacgtA";
+        let index = RunIndex::from_predicate(sequence, |c| c.is_ascii_lowercase());
+        for start in 0..=sequence.len() {
+            for end in start..=sequence.len() {
+                let expected = sequence[start..end].iter().filter(|c| c.is_ascii_lowercase()).count();
+                assert_eq!(index.count(start, end), expected, "{start}-{end}");
+            }
+        }
+        assert_eq!(RunIndex::from_predicate(b"", |c| c.is_ascii_lowercase()).count(0, 0), 0);
+    }
+
+    #[test]
+    fn test_masked_fraction() {
+        let reference_genome = ReferenceGenomeBuilder::from_bytes(&b">chr1\nACgtac\nGT\n>chr2\nACGT\n"[..])
+            .uppercase(false)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.masked_fraction("chr1", 0, 8).unwrap(), 0.5);
+        assert_eq!(reference_genome.masked_fraction("chr1", 2, 4).unwrap(), 1.0);
+        assert_eq!(reference_genome.masked_fraction("chr1", 1, 3).unwrap(), 0.5);
+        assert_eq!(reference_genome.masked_fraction("chr1", 3, 3).unwrap(), 0.0);
+        assert_eq!(reference_genome.masked_fraction("chr2", 0, 4).unwrap(), 0.0);
+        assert!(reference_genome.masked_fraction("chr1", 4, 2).is_err());
+        assert!(reference_genome.masked_fraction("chr1", 0, 9).is_err());
+        assert!(reference_genome.masked_fraction("chr3", 0, 1).is_err());
+
+        // the index is rebuilt after an edit
+        let mut edited = reference_genome.clone();
+        edited.modify_contig("chr2", |s| s.make_ascii_lowercase()).unwrap();
+        assert_eq!(edited.masked_fraction("chr2", 0, 4).unwrap(), 1.0);
+        assert_eq!(reference_genome.masked_fraction("chr2", 0, 4).unwrap(), 0.0);
+
+        let uppercased = ReferenceGenomeBuilder::from_bytes(&b">chr1\nACgtac\nGT\n"[..]).build().unwrap();
+        assert_eq!(uppercased.masked_fraction("chr1", 0, 8).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_n_fraction() {
+        let reference_genome = ReferenceGenomeBuilder::from_bytes(&b">chr1\nACNNnN\nGT\n>chr2\nNNNN\n"[..])
+            .uppercase(false)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.n_fraction("chr1", 0, 8).unwrap(), 0.5);
+        assert_eq!(reference_genome.n_fraction("chr1", 1, 5).unwrap(), 0.75);
+        assert_eq!(reference_genome.n_fraction("chr1", 6, 8).unwrap(), 0.0);
+        assert_eq!(reference_genome.n_fraction("chr2", 0, 4).unwrap(), 1.0);
+        assert_eq!(reference_genome.n_fraction("chr2", 2, 2).unwrap(), 0.0);
+        // a lower-case n is both a gap and soft-masked
+        assert_eq!(reference_genome.masked_fraction("chr1", 0, 8).unwrap(), 0.125);
+        assert!(reference_genome.n_fraction("chr1", 0, 9).is_err());
+        assert!(reference_genome.n_fraction("chr3", 0, 1).is_err());
+    }
+}
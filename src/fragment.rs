@@ -0,0 +1,401 @@
+use crate::complexity::mix;
+use crate::completeness::find_gaps;
+use crate::reference_genome::{ContigSequence, ReferenceGenome};
+use simple_error::{bail, SimpleError};
+use std::ops::Range;
+
+/// The N50 of an exponential length distribution as a multiple of its mean, i.e. the `x` where `(1 + x) * e^-x = 0.5`
+const EXPONENTIAL_N50_RATIO: f64 = 1.678;
+
+/// Where the breaks of a simulated fragmentation are placed, see `FragmentationBuilder::strategy(...)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakStrategy {
+    /// Breaks at random positions, and every base is kept
+    Random,
+    /// Breaks only at runs of `N` of at least a minimum length, and the `N` run at each break is removed, as when a scaffold
+    /// is split back into its contigs; such runs at either end of a source are always removed, sources without any are kept whole, and sources that are
+    /// entirely gap are dropped
+    GapGuided {
+        /// The minimum `N` run length to break at, at least 1
+        min_gap_length: usize
+    }
+}
+
+/// The source region of one simulated fragment
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FragmentOrigin {
+    /// The fragment name in the derived genome
+    pub fragment: String,
+    /// The source contig name
+    pub source: String,
+    /// The 0-based start of the fragment in the source contig; add it to fragment coordinates to get source coordinates
+    pub start: usize,
+    /// The 0-based, exclusive end of the fragment in the source contig
+    pub end: usize
+}
+
+/// Result of `FragmentationBuilder::build(...)`
+#[derive(Clone)]
+pub struct Fragmentation {
+    /// The fragments as a draft-like genome, all on the forward strand of their source
+    pub genome: ReferenceGenome,
+    /// The truth coordinates of each fragment, in the output order of the genome
+    pub origins: Vec<FragmentOrigin>
+}
+
+impl Fragmentation {
+    /// Finds the source region of a fragment, or `None` if it is not in the derived genome.
+    /// Origins are in the order of the genome, so the fragment is found by its contig ID rather than a scan.
+    /// # Arguments
+    /// * `fragment` - the fragment name
+    pub fn origin(&self, fragment: &str) -> Option<&FragmentOrigin> {
+        self.genome.contig_id(fragment)
+            .and_then(|id| self.origins.get(id as usize))
+            .filter(|o| o.fragment == fragment)
+    }
+
+    /// Converts a fragment coordinate to its source contig and coordinate
+    /// # Arguments
+    /// * `fragment` - the fragment name
+    /// * `position` - the 0-based position in the fragment
+    /// # Returns
+    /// * the source contig and 0-based source position, or `None` if the fragment is unknown or the position is past its end
+    pub fn to_source(&self, fragment: &str, position: usize) -> Option<(&str, usize)> {
+        let origin = self.origin(fragment)?;
+        (position < origin.end - origin.start).then_some((origin.source.as_str(), origin.start + position))
+    }
+
+    /// The N50 of the derived genome: the largest length such that fragments at least that long hold half of all bases, 0 without bases
+    pub fn n50(&self) -> usize {
+        let mut lengths: Vec<usize> = self.origins.iter().map(|o| o.end - o.start).collect();
+        lengths.sort_unstable_by(|a, b| b.cmp(a));
+        let total: usize = lengths.iter().sum();
+        let mut covered = 0;
+        for length in lengths.into_iter() {
+            covered += length;
+            if 2 * covered >= total {
+                return length;
+            }
+        }
+        0
+    }
+}
+
+/// A small deterministic pseudo-random generator (splitmix64), so fragmentations are reproducible from a seed
+struct SplitMix64 {
+    /// The generator state
+    state: u64
+}
+
+impl SplitMix64 {
+    /// Returns the next 64 random bits
+    fn next_u64(&mut self) -> u64 {
+        // `mix` adds the splitmix64 increment before finalizing, so this is the output for the advanced state
+        let value = mix(self.state);
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        value
+    }
+
+    /// Returns a uniform value in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a uniform value in `0..bound`, for a non-zero bound
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_f64() * bound as f64) as usize
+    }
+
+    /// Returns an exponentially distributed length with the given mean, at least 1
+    fn exponential(&mut self, mean: f64) -> usize {
+        ((-(1.0 - self.next_f64()).ln() * mean).ceil() as usize).max(1)
+    }
+}
+
+/// Builder that breaks a genome into a draft-like assembly with known truth coordinates, for benchmarking scaffolders and QC tools.
+/// Breaks are placed as a Poisson process along each source contig, with the rate chosen so fragments of long sources reach
+/// the target N50; sources shorter than the target are mostly kept whole, so the N50 of the result is approximate.
+/// # Examples
+/// ```
+/// use rust_lib_reference_genome::fragment::FragmentationBuilder;
+/// use rust_lib_reference_genome::reference_genome::ReferenceGenome;
+///
+/// let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGTACGTACGTACGTACGT\n").unwrap();
+/// let fragmentation = FragmentationBuilder::new(5)
+///     .seed(7)
+///     .rename("ctg")
+///     .build(&reference_genome)
+///     .unwrap();
+/// let origin = fragmentation.origin("ctg1").unwrap();
+/// assert_eq!(origin.source, "chr1");
+/// assert_eq!(fragmentation.genome.get_full_chromosome("ctg1"), reference_genome.get_slice("chr1", origin.start, origin.end));
+/// ```
+#[derive(Clone, Debug)]
+pub struct FragmentationBuilder {
+    /// The N50 the fragments should approach
+    target_n50: usize,
+    /// Where breaks are placed
+    strategy: BreakStrategy,
+    /// The shortest fragment a random break may create
+    min_fragment_length: usize,
+    /// If true, fragments are output in random order rather than source order
+    shuffle: bool,
+    /// If set, fragments are named with this prefix and their 1-based output index, hiding their source
+    rename_prefix: Option<String>,
+    /// The seed of the pseudo-random generator
+    seed: u64
+}
+
+impl FragmentationBuilder {
+    /// Creates a builder with random breaks, no shuffling, and fragments named after their source
+    /// # Arguments
+    /// * `target_n50` - the N50 the fragments should approach, in bp
+    pub fn new(target_n50: usize) -> Self {
+        Self {
+            target_n50,
+            strategy: BreakStrategy::Random,
+            min_fragment_length: 1,
+            shuffle: false,
+            rename_prefix: None,
+            seed: 0
+        }
+    }
+
+    /// Sets where breaks are placed, default is `BreakStrategy::Random`
+    pub fn strategy(mut self, strategy: BreakStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the shortest fragment that a break may create, default is 1; sources that are shorter are kept whole
+    pub fn min_fragment_length(mut self, min_fragment_length: usize) -> Self {
+        self.min_fragment_length = min_fragment_length;
+        self
+    }
+
+    /// Sets whether fragments are output in random order, default is false (source order)
+    pub fn shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Names fragments with a prefix and their 1-based output index (e.g. `ctg1`, `ctg2`, ...) instead of `<source>_<start>_<end>`
+    /// with 0-based, half-open source coordinates
+    pub fn rename(mut self, prefix: &str) -> Self {
+        self.rename_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Sets the seed of the pseudo-random generator, default is 0; the same seed and genome always give the same fragments
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Finds the fragments of one source contig
+    /// # Arguments
+    /// * `sequence` - the source sequence
+    /// * `mean_length` - the mean distance between breaks
+    /// * `random` - the pseudo-random generator
+    fn fragment_ranges(&self, sequence: &[u8], mean_length: f64, random: &mut SplitMix64) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = vec![];
+        let mut start = 0;
+        let mut end = sequence.len();
+        match self.strategy {
+            BreakStrategy::Random => {
+                let min_length = self.min_fragment_length.max(1);
+                loop {
+                    let next = start + random.exponential(mean_length).max(min_length);
+                    if next + min_length > end {
+                        break;
+                    }
+                    ranges.push(start..next);
+                    start = next;
+                }
+            },
+            BreakStrategy::GapGuided { min_gap_length } => {
+                let mut gaps = find_gaps(sequence, min_gap_length);
+                // terminal gaps are always trimmed, since draft contigs do not start or end in a gap
+                if gaps.last().is_some_and(|g| g.end == end) {
+                    end = gaps.pop().unwrap().start;
+                }
+                if gaps.first().is_some_and(|g| g.start == 0) {
+                    start = gaps.remove(0).end;
+                }
+                let mut gaps = gaps.iter();
+                loop {
+                    let target = start + random.exponential(mean_length);
+                    // break at the first gap that starts at or after the sampled position
+                    let Some(gap) = gaps.find(|g| g.start >= target) else {
+                        break;
+                    };
+                    ranges.push(start..gap.start);
+                    start = gap.end;
+                }
+            }
+        }
+        if start < end {
+            ranges.push(start..end);
+        }
+        ranges
+    }
+
+    /// Fragments every contig of a genome. Lazily loaded source contigs are read once, and the fragments share the read sequence.
+    /// # Arguments
+    /// * `reference_genome` - the genome to fragment
+    /// # Errors
+    /// * if the target N50 is 0, or a gap-guided strategy has a minimum gap length of 0
+    /// * if a contig was unloaded or fails to load
+    /// * if fragment names collide without `rename(...)`, which can only happen for source names that already look like fragment names
+    pub fn build(&self, reference_genome: &ReferenceGenome) -> Result<Fragmentation, SimpleError> {
+        if self.target_n50 == 0 {
+            bail!("The target N50 must be at least 1");
+        }
+        if self.strategy == (BreakStrategy::GapGuided { min_gap_length: 0 }) {
+            bail!("The minimum gap length must be at least 1");
+        }
+        let mean_length = self.target_n50 as f64 / EXPONENTIAL_N50_RATIO;
+        let mut random = SplitMix64 { state: self.seed };
+
+        let mut fragments: Vec<(FragmentOrigin, ContigSequence)> = vec![];
+        for source in reference_genome.contig_keys().iter() {
+            let sequence = reference_genome.try_sequence_unkept(source)?;
+            for range in self.fragment_ranges(&sequence, mean_length, &mut random).into_iter() {
+                let origin = FragmentOrigin {
+                    fragment: format!("{}_{}_{}", source, range.start, range.end),
+                    source: source.clone(),
+                    start: range.start,
+                    end: range.end
+                };
+                fragments.push((origin, ContigSequence::Loaded(sequence.slice(range))));
+            }
+        }
+
+        if self.shuffle {
+            // Fisher-Yates
+            for index in (1..fragments.len()).rev() {
+                fragments.swap(index, random.below(index + 1));
+            }
+        }
+        if let Some(prefix) = self.rename_prefix.as_ref() {
+            for (index, (origin, _)) in fragments.iter_mut().enumerate() {
+                origin.fragment = format!("{}{}", prefix, index + 1);
+            }
+        }
+
+        let origins: Vec<FragmentOrigin> = fragments.iter().map(|(o, _)| o.clone()).collect();
+        let contigs: Vec<(String, ContigSequence)> = fragments.into_iter().map(|(o, s)| (o.fragment, s)).collect();
+        let genome = ReferenceGenome::from_contigs(reference_genome.filename().to_path_buf(), contigs)?;
+        Ok(Fragmentation { genome, origins })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_sequences::random_sequence;
+
+    /// Checks that every fragment matches its source region
+    fn assert_truth(fragmentation: &Fragmentation, reference_genome: &ReferenceGenome) {
+        assert_eq!(fragmentation.genome.contig_keys().len(), fragmentation.origins.len());
+        for origin in fragmentation.origins.iter() {
+            assert_eq!(
+                fragmentation.genome.get_full_chromosome(&origin.fragment),
+                reference_genome.get_slice(&origin.source, origin.start, origin.end)
+            );
+            assert_eq!(fragmentation.origin(&origin.fragment), Some(origin));
+        }
+        assert_eq!(fragmentation.origin("missing"), None);
+    }
+
+    #[test]
+    fn test_random_fragmentation() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), &random_sequence(200_000, 1)).unwrap();
+        reference_genome.add_contig("chr2".to_string(), &random_sequence(100_000, 2)).unwrap();
+        reference_genome.add_contig("short".to_string(), "ACGT").unwrap();
+
+        let fragmentation = FragmentationBuilder::new(10_000).seed(3).build(&reference_genome).unwrap();
+        assert_truth(&fragmentation, &reference_genome);
+        let n50 = fragmentation.n50();
+        assert!((7_000..14_000).contains(&n50), "N50 {n50}");
+        // every base is kept, in source order
+        let total: usize = fragmentation.origins.iter().map(|o| o.end - o.start).sum();
+        assert_eq!(total, 300_004);
+        assert_eq!(fragmentation.origins[0].start, 0);
+        assert!(fragmentation.origins.windows(2).all(|w| w[0].source != w[1].source || w[0].end == w[1].start));
+        assert_eq!(fragmentation.origins.last().unwrap().fragment, "short_0_4");
+        let first = &fragmentation.origins[1];
+        assert_eq!(fragmentation.to_source(&first.fragment, 2), Some(("chr1", first.start + 2)));
+        assert_eq!(fragmentation.to_source(&first.fragment, first.end - first.start), None);
+
+        // the same seed gives the same fragments, a different seed different ones
+        let repeated = FragmentationBuilder::new(10_000).seed(3).build(&reference_genome).unwrap();
+        assert_eq!(repeated.origins, fragmentation.origins);
+        let reseeded = FragmentationBuilder::new(10_000).seed(4).build(&reference_genome).unwrap();
+        assert_ne!(reseeded.origins, fragmentation.origins);
+
+        let fragments = FragmentationBuilder::new(10_000).min_fragment_length(5_000).build(&reference_genome).unwrap();
+        assert!(fragments.origins.iter().filter(|o| o.source != "short").all(|o| o.end - o.start >= 5_000));
+
+        assert!(FragmentationBuilder::new(0).build(&reference_genome).is_err());
+    }
+
+    #[test]
+    fn test_gap_guided_fragmentation() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">scaffold1\nNNACGTNNNNGGCCNACNNNNTTAANNNN\n>ctg\nACGT\n>gap\nNNN\n").unwrap();
+        // a huge target only trims the terminal gaps
+        let unbroken = FragmentationBuilder::new(1_000_000)
+            .strategy(BreakStrategy::GapGuided { min_gap_length: 2 })
+            .build(&reference_genome)
+            .unwrap();
+        assert_truth(&unbroken, &reference_genome);
+        assert_eq!(unbroken.genome.contig_keys(), vec!["scaffold1_2_25".to_string(), "ctg_0_4".to_string()]);
+
+        let mut scaffold = String::new();
+        for index in 0..20 {
+            if index > 0 {
+                scaffold.push_str(&"N".repeat(10));
+            }
+            scaffold.push_str(&random_sequence(1_000, index));
+        }
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("scaffold1".to_string(), &scaffold).unwrap();
+        // with a tiny target, every gap is a break and is removed
+        let fragmentation = FragmentationBuilder::new(1)
+            .strategy(BreakStrategy::GapGuided { min_gap_length: 10 })
+            .build(&reference_genome)
+            .unwrap();
+        assert_truth(&fragmentation, &reference_genome);
+        let starts: Vec<usize> = fragmentation.origins.iter().map(|o| o.start).collect();
+        assert_eq!(starts, (0..20).map(|i| i * 1_010).collect::<Vec<usize>>());
+        assert!(fragmentation.origins.iter().all(|o| o.end - o.start == 1_000));
+        // gaps shorter than the minimum are never broken
+        let fragmentation = FragmentationBuilder::new(1)
+            .strategy(BreakStrategy::GapGuided { min_gap_length: 11 })
+            .build(&reference_genome)
+            .unwrap();
+        assert_eq!(fragmentation.origins.len(), 1);
+
+        assert!(FragmentationBuilder::new(1).strategy(BreakStrategy::GapGuided { min_gap_length: 0 }).build(&reference_genome).is_err());
+    }
+
+    #[test]
+    fn test_shuffle_and_rename() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), &random_sequence(50_000, 5)).unwrap();
+        let fragmentation = FragmentationBuilder::new(2_000)
+            .shuffle(true)
+            .rename("contig_")
+            .seed(11)
+            .build(&reference_genome)
+            .unwrap();
+        assert_truth(&fragmentation, &reference_genome);
+        let names: Vec<String> = (1..=fragmentation.origins.len()).map(|i| format!("contig_{i}")).collect();
+        assert_eq!(fragmentation.genome.contig_keys(), names);
+        assert!(fragmentation.origins.windows(2).any(|w| w[0].start > w[1].start));
+        let mut starts: Vec<usize> = fragmentation.origins.iter().map(|o| o.start).collect();
+        starts.sort_unstable();
+        assert_eq!(starts[0], 0);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::reference_genome::ReferenceGenome;
+use simple_error::SimpleError;
+
+/// Describes a contig that is present in both genomes but with different lengths
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LengthMismatch {
+    /// The contig name
+    pub contig: String,
+    /// Length of the contig in the genome `compare(...)` was called on
+    pub self_length: usize,
+    /// Length of the contig in the other genome
+    pub other_length: usize
+}
+
+/// Describes base differences for a contig that has the same length in both genomes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequenceMismatch {
+    /// The contig name
+    pub contig: String,
+    /// The 0-based positions where the bases differ, in increasing order
+    pub positions: Vec<usize>
+}
+
+impl SequenceMismatch {
+    /// The number of differing bases in the contig
+    pub fn mismatch_count(&self) -> usize {
+        self.positions.len()
+    }
+}
+
+/// Result of comparing two reference genomes with `ReferenceGenome::compare(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GenomeComparison {
+    /// Contigs only found in the genome `compare(...)` was called on, in load order
+    pub only_in_self: Vec<String>,
+    /// Contigs only found in the other genome, in load order
+    pub only_in_other: Vec<String>,
+    /// Shared contigs with different lengths
+    pub length_mismatches: Vec<LengthMismatch>,
+    /// Shared, same-length contigs with at least one differing base
+    pub sequence_mismatches: Vec<SequenceMismatch>
+}
+
+impl GenomeComparison {
+    /// Returns true if the two genomes contain the same contigs with identical sequences.
+    /// Contig order is not considered.
+    pub fn is_identical(&self) -> bool {
+        self.only_in_self.is_empty() &&
+            self.only_in_other.is_empty() &&
+            self.length_mismatches.is_empty() &&
+            self.sequence_mismatches.is_empty()
+    }
+
+    /// Total number of differing bases across all same-length contigs
+    pub fn total_mismatches(&self) -> usize {
+        self.sequence_mismatches.iter().map(|m| m.mismatch_count()).sum()
+    }
+}
+
+impl ReferenceGenome {
+    /// Compares this genome against another one, reporting contigs that are only in one of the genomes,
+    /// shared contigs with different lengths, and the base differences of shared contigs with the same length.
+    /// Bases are compared ignoring case, so soft-masking differences are not reported.
+    /// Lazily loaded contigs of either genome are read once and are not kept in memory.
+    /// # Arguments
+    /// * `other` - the genome to compare against
+    /// # Errors
+    /// * if a contig of either genome was unloaded or fails to load
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn compare(&self, other: &ReferenceGenome) -> Result<GenomeComparison, SimpleError> {
+        let mut comparison = GenomeComparison::default();
+
+        for contig in self.contig_keys().iter() {
+            if other.resolve_contig_name(contig).is_none() {
+                comparison.only_in_self.push(contig.clone());
+                continue;
+            }
+            let self_sequence = self.try_sequence_unkept(contig)?;
+            let other_sequence = other.try_sequence_unkept(contig)?;
+
+            if self_sequence.len() != other_sequence.len() {
+                comparison.length_mismatches.push(LengthMismatch {
+                    contig: contig.clone(),
+                    self_length: self_sequence.len(),
+                    other_length: other_sequence.len()
+                });
+                continue;
+            }
+
+            let positions: Vec<usize> = self_sequence.iter()
+                .zip(other_sequence.iter())
+                .enumerate()
+                .filter_map(|(i, (a, b))| if !a.eq_ignore_ascii_case(b) { Some(i) } else { None })
+                .collect();
+            if !positions.is_empty() {
+                comparison.sequence_mismatches.push(SequenceMismatch {
+                    contig: contig.clone(),
+                    positions
+                });
+            }
+        }
+
+        comparison.only_in_other = other.contig_keys().iter()
+            .filter(|c| self.resolve_contig_name(c).is_none())
+            .cloned()
+            .collect();
+
+        Ok(comparison)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ReferenceGenomeBuilder;
+    use std::path::PathBuf;
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn test_compare_identical() {
+        let plain = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        let gzipped = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa.gz")).unwrap();
+        let comparison = plain.compare(&gzipped).unwrap();
+        assert!(comparison.is_identical());
+        assert_eq!(comparison.total_mismatches(), 0);
+    }
+
+    #[test]
+    fn test_compare_differences() {
+        let mut first = ReferenceGenome::empty_reference();
+        first.add_contig("shared".to_string(), "ACGTACGT").unwrap();
+        first.add_contig("short".to_string(), "ACGT").unwrap();
+        first.add_contig("first_only".to_string(), "A").unwrap();
+
+        let mut second = ReferenceGenome::empty_reference();
+        second.add_contig("second_only".to_string(), "C").unwrap();
+        second.add_contig("short".to_string(), "ACG").unwrap();
+        second.add_contig("shared".to_string(), "ACCTACGA").unwrap();
+
+        let comparison = first.compare(&second).unwrap();
+        assert!(!comparison.is_identical());
+        assert_eq!(comparison.only_in_self, vec!["first_only".to_string()]);
+        assert_eq!(comparison.only_in_other, vec!["second_only".to_string()]);
+        assert_eq!(comparison.length_mismatches, vec![LengthMismatch {
+            contig: "short".to_string(),
+            self_length: 4,
+            other_length: 3
+        }]);
+        assert_eq!(comparison.sequence_mismatches, vec![SequenceMismatch {
+            contig: "shared".to_string(),
+            positions: vec![2, 7]
+        }]);
+        assert_eq!(comparison.total_mismatches(), 2);
+    }
+
+    #[test]
+    fn test_compare_ignores_case() {
+        let masked = ReferenceGenomeBuilder::from_bytes(&b">chr1\nACgtAC\n>chr2\nACGT\n"[..]).uppercase(false).build().unwrap();
+        let mut unmasked = ReferenceGenome::empty_reference();
+        unmasked.add_contig("chr1".to_string(), "ACGTAC").unwrap();
+        unmasked.add_contig("chr2".to_string(), "ACGA").unwrap();
+        let comparison = masked.compare(&unmasked).unwrap();
+        assert_eq!(comparison.sequence_mismatches, vec![SequenceMismatch {
+            contig: "chr2".to_string(),
+            positions: vec![3]
+        }]);
+
+        let mut unloaded = unmasked.clone();
+        unloaded.unload_contig("chr2").unwrap();
+        assert!(masked.compare(&unloaded).is_err());
+    }
+}
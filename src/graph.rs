@@ -0,0 +1,189 @@
+use crate::flanking::VariantSite;
+use crate::reference_genome::ReferenceGenome;
+use bytes::Bytes;
+use rustc_hash::FxHashMap as HashMap;
+use simple_error::{bail, SimpleError};
+use std::io::Write;
+
+/// A variation graph with a reference backbone and one bubble per variant, from `VariationGraphBuilder::build(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VariationGraph {
+    /// The segment sequences; the segment ID in GFA is the index plus 1
+    pub segments: Vec<Bytes>,
+    /// Links between segments as (from index, to index), all on the forward strand
+    pub links: Vec<(usize, usize)>,
+    /// The reference path of each contig as (contig, segment indices)
+    pub paths: Vec<(String, Vec<usize>)>,
+    /// Variants that were not added: symbolic-only alleles, reference alleles that do not match the genome,
+    /// or an overlap with an earlier variant
+    pub skipped: Vec<VariantSite>
+}
+
+impl VariationGraph {
+    /// Adds a segment linked from every segment of the current frontier, returning its index
+    fn add_segment(&mut self, sequence: Bytes, frontier: &[usize]) -> usize {
+        let index = self.segments.len();
+        self.segments.push(sequence);
+        self.links.extend(frontier.iter().map(|&from| (from, index)));
+        index
+    }
+
+    /// Writes the graph in GFA 1.0 format: a header, then segment (S), link (L), and path (P) lines
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// # Errors
+    /// * any errors from the underlying writer
+    pub fn write_gfa<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "H\tVN:Z:1.0")?;
+        for (index, sequence) in self.segments.iter().enumerate() {
+            write!(writer, "S\t{}\t", index + 1)?;
+            writer.write_all(sequence)?;
+            writeln!(writer)?;
+        }
+        for (from, to) in self.links.iter() {
+            writeln!(writer, "L\t{}\t+\t{}\t+\t0M", from + 1, to + 1)?;
+        }
+        for (contig, path) in self.paths.iter() {
+            let steps: Vec<String> = path.iter().map(|index| format!("{}+", index + 1)).collect();
+            writeln!(writer, "P\t{}\t{}\t*", contig, steps.join(","))?;
+        }
+        writer.flush()
+    }
+}
+
+/// Builder that combines a reference genome with variants into a simple variation graph, for prototyping graph-based methods
+/// # Examples
+/// ```
+/// use rust_lib_reference_genome::flanking::read_vcf_sites;
+/// use rust_lib_reference_genome::graph::VariationGraphBuilder;
+/// use rust_lib_reference_genome::reference_genome::ReferenceGenome;
+///
+/// let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGTACGT\n").unwrap();
+/// let sites = read_vcf_sites(&b"chr1\t4\t.\tT\tC\n"[..]).unwrap();
+/// let graph = VariationGraphBuilder::new()
+///     .variants(sites)
+///     .build(&reference_genome)
+///     .unwrap();
+/// assert_eq!(graph.segments.len(), 4);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct VariationGraphBuilder {
+    /// The variants to add as bubbles
+    sites: Vec<VariantSite>
+}
+
+impl VariationGraphBuilder {
+    /// Creates a builder without any variants, which produces a graph with one segment per contig
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds variants, e.g. from `read_vcf_file(...)`; they do not need to be sorted
+    pub fn variants(mut self, sites: impl IntoIterator<Item = VariantSite>) -> Self {
+        self.sites.extend(sites);
+        self
+    }
+
+    /// Builds the graph. Each contig becomes a chain of reference segments, and each variant becomes a bubble with one segment for
+    /// the reference allele and one for each distinct literal alternate allele, linked to the segments on either side.
+    /// Variants that overlap an earlier variant (by position) on the same contig are skipped, so every bubble is a simple one.
+    /// Lazily loaded contigs are read once and are not kept in memory.
+    /// # Arguments
+    /// * `reference_genome` - the reference genome for the backbone
+    /// # Errors
+    /// * if a variant is on a contig that is not in the reference genome
+    /// * if a contig was unloaded or fails to load
+    pub fn build(&self, reference_genome: &ReferenceGenome) -> Result<VariationGraph, SimpleError> {
+        let mut sites_by_contig: HashMap<&str, Vec<&VariantSite>> = Default::default();
+        for site in self.sites.iter() {
+            if reference_genome.contig_id(&site.contig).is_none() {
+                bail!("{}", reference_genome.missing_contig_message(&site.contig));
+            }
+            sites_by_contig.entry(site.contig.as_str()).or_default().push(site);
+        }
+
+        let mut graph = VariationGraph::default();
+        for contig in reference_genome.contig_keys().iter() {
+            let sequence = reference_genome.try_sequence_unkept(contig)?;
+            let mut sites = sites_by_contig.remove(contig.as_str()).unwrap_or_default();
+            sites.sort_by_key(|site| site.position);
+
+            let mut frontier: Vec<usize> = vec![];
+            let mut path: Vec<usize> = vec![];
+            let mut cursor = 0;
+            for site in sites {
+                let end = site.position + site.reference.len();
+                let alternates: Vec<&Vec<u8>> = site.alternates.iter()
+                    .filter(|a| !a.is_empty() && a.iter().all(|c| c.is_ascii_alphabetic()) && !a.eq_ignore_ascii_case(&site.reference))
+                    .collect();
+                if site.position < cursor || site.reference.is_empty() || end > sequence.len()
+                    || !site.reference.eq_ignore_ascii_case(&sequence[site.position..end]) || alternates.is_empty() {
+                    graph.skipped.push(site.clone());
+                    continue;
+                }
+
+                if site.position > cursor {
+                    let backbone = graph.add_segment(sequence.slice(cursor..site.position), &frontier);
+                    path.push(backbone);
+                    frontier = vec![backbone];
+                }
+                let reference_allele = graph.add_segment(sequence.slice(site.position..end), &frontier);
+                path.push(reference_allele);
+                let mut alleles: Vec<usize> = vec![reference_allele];
+                let mut seen: Vec<Vec<u8>> = vec![];
+                for alternate in alternates {
+                    let alternate = alternate.to_ascii_uppercase();
+                    if !seen.contains(&alternate) {
+                        alleles.push(graph.add_segment(Bytes::from(alternate.clone()), &frontier));
+                        seen.push(alternate);
+                    }
+                }
+                frontier = alleles;
+                cursor = end;
+            }
+            if cursor < sequence.len() {
+                let backbone = graph.add_segment(sequence.slice(cursor..), &frontier);
+                path.push(backbone);
+            }
+            if !path.is_empty() {
+                graph.paths.push((contig.clone(), path));
+            }
+        }
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flanking::read_vcf_sites;
+
+    #[test]
+    fn test_variation_graph() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGTACGT\n>chr2\nGGCC\n").unwrap();
+        let vcf = b"chr1\t5\t.\tAC\tA\nchr1\t2\t.\tC\tT,G,<DEL>\nchr1\t6\t.\tC\tG\nchr1\t8\t.\tA\tC\nchr2\t4\t.\tC\tCA\n";
+        let graph = VariationGraphBuilder::new()
+            .variants(read_vcf_sites(&vcf[..]).unwrap())
+            .build(&reference_genome)
+            .unwrap();
+
+        // the variant at 6 overlaps the deletion, and the variant at 8 does not match the reference
+        let skipped: Vec<usize> = graph.skipped.iter().map(|s| s.position + 1).collect();
+        assert_eq!(skipped, vec![6, 8]);
+
+        let mut gfa: Vec<u8> = vec![];
+        graph.write_gfa(&mut gfa).unwrap();
+        assert_eq!(String::from_utf8(gfa).unwrap(), "H\tVN:Z:1.0\n\
+            S\t1\tA\nS\t2\tC\nS\t3\tT\nS\t4\tG\nS\t5\tGT\nS\t6\tAC\nS\t7\tA\nS\t8\tGT\n\
+            S\t9\tGGC\nS\t10\tC\nS\t11\tCA\n\
+            L\t1\t+\t2\t+\t0M\nL\t1\t+\t3\t+\t0M\nL\t1\t+\t4\t+\t0M\n\
+            L\t2\t+\t5\t+\t0M\nL\t3\t+\t5\t+\t0M\nL\t4\t+\t5\t+\t0M\n\
+            L\t5\t+\t6\t+\t0M\nL\t5\t+\t7\t+\t0M\nL\t6\t+\t8\t+\t0M\nL\t7\t+\t8\t+\t0M\n\
+            L\t9\t+\t10\t+\t0M\nL\t9\t+\t11\t+\t0M\n\
+            P\tchr1\t1+,2+,5+,6+,8+\t*\n\
+            P\tchr2\t9+,10+\t*\n");
+
+        let unknown = read_vcf_sites(&b"chr3\t1\t.\tA\tC\n"[..]).unwrap();
+        assert!(VariationGraphBuilder::new().variants(unknown).build(&reference_genome).is_err());
+    }
+}
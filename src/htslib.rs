@@ -0,0 +1,69 @@
+use crate::lazy::{ContigLoader, LazyContig};
+use rust_htslib::faidx;
+use std::error::Error;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Lazy backend that reads full contigs through htslib's faidx, which supports plain and bgzip-compressed FASTA
+struct FaidxLoader {
+    /// Contig names in index order
+    names: Vec<String>,
+    /// Contig lengths in index order
+    lengths: Vec<usize>,
+    /// The htslib reader, which is `Send` but not `Sync`
+    reader: Mutex<faidx::Reader>
+}
+
+impl ContigLoader for FaidxLoader {
+    fn contig_name(&self, index: usize) -> &str {
+        &self.names[index]
+    }
+
+    fn contig_length(&self, index: usize) -> usize {
+        self.lengths[index]
+    }
+
+    fn load_contig(&self, index: usize) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let name = &self.names[index];
+        let length = self.lengths[index];
+        let reader = self.reader.lock().map_err(|e| e.to_string())?;
+        if length == 0 {
+            return Ok(vec![]);
+        }
+        // htslib uses an inclusive end coordinate
+        let sequence = reader.fetch_seq(name, 0, length - 1)?;
+        if sequence.len() != length {
+            return Err(format!("Expected {length} bases from faidx, but received {}", sequence.len()).into());
+        }
+        Ok(sequence)
+    }
+}
+
+/// Opens a FASTA file with htslib's faidx and lists its contigs without reading any sequence.
+/// The `.fai` index (and `.gzi` for bgzip-compressed files) is built by htslib if it does not exist.
+/// # Arguments
+/// * `fasta_fn` - the FASTA filename, bgzip compression is allowed
+/// * `uppercase` - if true, sequences are upper-cased when loaded
+/// * `contig_filter` - if provided, only contigs where this returns true are kept
+/// # Errors
+/// * if the index cannot be built or loaded
+pub(crate) fn index_faidx_fasta(
+    fasta_fn: &Path, uppercase: bool, contig_filter: Option<&dyn Fn(&str) -> bool>
+) -> Result<Vec<(String, LazyContig)>, Box<dyn Error>> {
+    let reader = faidx::Reader::from_path(fasta_fn)?;
+    let names = reader.seq_names()?;
+    let lengths: Vec<usize> = names.iter()
+        .map(|n| reader.fetch_seq_len(n) as usize)
+        .collect();
+    let loader: Arc<dyn ContigLoader> = Arc::new(FaidxLoader {
+        names: names.clone(),
+        lengths,
+        reader: Mutex::new(reader)
+    });
+
+    Ok(names.into_iter()
+        .enumerate()
+        .filter(|(_, seq_id)| contig_filter.map(|f| f(seq_id)).unwrap_or(true))
+        .map(|(index, seq_id)| (seq_id, LazyContig::new(loader.clone(), index, uppercase)))
+        .collect())
+}
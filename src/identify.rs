@@ -0,0 +1,294 @@
+use crate::assembly::KnownAssembly;
+use crate::reference_genome::ReferenceGenome;
+use rustc_hash::FxHashSet as HashSet;
+use std::io::Write;
+
+/// The chromosome lengths of a known build, used as a fingerprint
+struct BuildFingerprint {
+    /// The build name
+    build: &'static str,
+    /// The matching assembly preset, if there is one
+    assembly: Option<KnownAssembly>,
+    /// The chromosome name prefix of the build's usual naming convention
+    prefix: &'static str,
+    /// The mitochondrial chromosome name, without the prefix
+    mito: &'static str,
+    /// The lengths of the autosomes, X, Y, and the mitochondrial genome, in that order
+    lengths: &'static [usize]
+}
+
+/// Human hg19, identical to GRCh37 except for the older Yoruba mitochondrial sequence
+const HG19_LENGTHS: [usize; 25] = [
+    249250621, 243199373, 198022430, 191154276, 180915260, 171115067, 159138663, 146364022,
+    141213431, 135534747, 135006516, 133851895, 115169878, 107349540, 102531392, 90354753,
+    81195210, 78077248, 59128983, 63025520, 48129895, 51304566, 155270560, 59373566, 16571
+];
+
+/// Human GRCh37, with the rCRS mitochondrial sequence
+const GRCH37_LENGTHS: [usize; 25] = [
+    249250621, 243199373, 198022430, 191154276, 180915260, 171115067, 159138663, 146364022,
+    141213431, 135534747, 135006516, 133851895, 115169878, 107349540, 102531392, 90354753,
+    81195210, 78077248, 59128983, 63025520, 48129895, 51304566, 155270560, 59373566, 16569
+];
+
+/// Human GRCh38
+const GRCH38_LENGTHS: [usize; 25] = [
+    248956422, 242193529, 198295559, 190214555, 181538259, 170805979, 159345973, 145138636,
+    138394717, 133797422, 135086622, 133275309, 114364328, 107043718, 101991189, 90338345,
+    83257441, 80373285, 58617616, 64444167, 46709983, 50818468, 156040895, 57227415, 16569
+];
+
+/// Human T2T-CHM13 v2.0, with the HG002 Y chromosome
+const T2T_CHM13_LENGTHS: [usize; 25] = [
+    248387328, 242696752, 201105948, 193574945, 182045439, 172126628, 160567428, 146259331,
+    150617247, 134758134, 135127769, 133324548, 113566686, 101161492, 99753195, 96330374,
+    84276897, 80542538, 61707364, 66210255, 45090682, 51324926, 154259566, 62460029, 16569
+];
+
+/// Mouse mm10 (GRCm38)
+const MM10_LENGTHS: [usize; 22] = [
+    195471971, 182113224, 160039680, 156508116, 151834684, 149736546, 145441459, 129401213,
+    124595110, 130694993, 122082543, 120129022, 120421639, 124902244, 104043685, 98207768,
+    94987271, 90702639, 61431566, 171031299, 91744698, 16299
+];
+
+/// Mouse GRCm39 (mm39)
+const GRCM39_LENGTHS: [usize; 22] = [
+    195154279, 181755017, 159745316, 156860686, 151758149, 149588044, 144995196, 130127694,
+    124359700, 130530862, 121973369, 120092757, 120883175, 125139656, 104073951, 98008968,
+    95294699, 90720763, 61420004, 169476592, 91455967, 16299
+];
+
+/// The bundled fingerprints; ties are broken by naming convention and then by this order
+const BUILD_FINGERPRINTS: [BuildFingerprint; 6] = [
+    BuildFingerprint { build: "GRCh38", assembly: Some(KnownAssembly::GRCh38), prefix: "chr", mito: "M", lengths: &GRCH38_LENGTHS },
+    BuildFingerprint { build: "hg19", assembly: Some(KnownAssembly::Hg19), prefix: "chr", mito: "M", lengths: &HG19_LENGTHS },
+    BuildFingerprint { build: "GRCh37", assembly: Some(KnownAssembly::GRCh37), prefix: "", mito: "MT", lengths: &GRCH37_LENGTHS },
+    BuildFingerprint { build: "T2T-CHM13", assembly: Some(KnownAssembly::T2tChm13), prefix: "chr", mito: "M", lengths: &T2T_CHM13_LENGTHS },
+    BuildFingerprint { build: "GRCm39", assembly: Some(KnownAssembly::GRCm39), prefix: "chr", mito: "M", lengths: &GRCM39_LENGTHS },
+    BuildFingerprint { build: "mm10", assembly: None, prefix: "chr", mito: "M", lengths: &MM10_LENGTHS }
+];
+
+impl BuildFingerprint {
+    /// The chromosome names and lengths in the build's usual naming convention
+    fn chromosomes(&self) -> impl Iterator<Item = (String, usize)> + '_ {
+        let autosomes = self.lengths.len() - 3;
+        (1..=autosomes).map(|i| i.to_string())
+            .chain(["X", "Y", self.mito].into_iter().map(|s| s.to_string()))
+            .map(|c| format!("{}{c}", self.prefix))
+            .zip(self.lengths.iter().copied())
+    }
+}
+
+/// How well a set of contigs matches a known build, from `identify_build(...)` or `ReferenceGenome::build_matches()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildMatch {
+    /// The build name, e.g. "GRCh38", "hg19", "T2T-CHM13", or "mm10"
+    pub build: &'static str,
+    /// The assembly preset of the build, if there is one
+    pub assembly: Option<KnownAssembly>,
+    /// The number of the build's chromosomes whose length is found among the contigs
+    pub matched_chromosomes: usize,
+    /// The number of the matched chromosomes whose contig also has the build's usual name
+    pub matched_names: usize,
+    /// The number of chromosomes in the build
+    pub build_chromosomes: usize
+}
+
+impl BuildMatch {
+    /// The fraction of the build's chromosomes whose length is found among the contigs
+    pub fn fraction(&self) -> f64 {
+        self.matched_chromosomes as f64 / self.build_chromosomes as f64
+    }
+}
+
+/// Scores a set of contigs against every bundled build by chromosome length, independent of contig naming.
+/// The bundled builds are GRCh38, hg19, GRCh37, T2T-CHM13, GRCm39, and mm10; hg19 and GRCh37 only differ in the mitochondrial
+/// length and naming convention.
+/// # Arguments
+/// * `contigs` - the contig names and lengths, e.g. from a FASTA index or a SAM header
+/// # Returns
+/// * one match per build that shares at least one chromosome length, best first
+pub fn build_matches<'a>(contigs: impl IntoIterator<Item = (&'a str, usize)>) -> Vec<BuildMatch> {
+    let contigs: HashSet<(&str, usize)> = contigs.into_iter().collect();
+    let lengths: HashSet<usize> = contigs.iter().map(|(_, length)| *length).collect();
+    let mut matches: Vec<BuildMatch> = BUILD_FINGERPRINTS.iter()
+        .map(|fingerprint| {
+            let mut matched_chromosomes = 0;
+            let mut matched_names = 0;
+            for (name, length) in fingerprint.chromosomes() {
+                if lengths.contains(&length) {
+                    matched_chromosomes += 1;
+                    if contigs.contains(&(name.as_str(), length)) {
+                        matched_names += 1;
+                    }
+                }
+            }
+            BuildMatch {
+                build: fingerprint.build,
+                assembly: fingerprint.assembly,
+                matched_chromosomes,
+                matched_names,
+                build_chromosomes: fingerprint.lengths.len()
+            }
+        })
+        .filter(|m| m.matched_chromosomes > 0)
+        .collect();
+    // the sort is stable, so full ties keep the bundled order
+    matches.sort_by_key(|m| std::cmp::Reverse((m.matched_chromosomes, m.matched_names)));
+    matches
+}
+
+/// Identifies the most likely build of a set of contigs, see `build_matches(...)`
+/// # Arguments
+/// * `contigs` - the contig names and lengths
+/// # Returns
+/// * the best match, or `None` if no chromosome length matches any bundled build; check `BuildMatch::fraction()`
+///   before relying on a match from only a few chromosomes, such as a lone mitochondrial genome
+pub fn identify_build<'a>(contigs: impl IntoIterator<Item = (&'a str, usize)>) -> Option<BuildMatch> {
+    build_matches(contigs).into_iter().next()
+}
+
+/// A contig whose length matches a chromosome of at least one bundled build
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContigBuilds {
+    /// The contig name
+    pub contig: String,
+    /// The contig length
+    pub length: usize,
+    /// The builds with a chromosome of this length, in bundled order
+    pub builds: Vec<&'static str>
+}
+
+/// Whether the recognized contigs of a genome all come from one build, from `check_build_consistency(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BuildConsistencyReport {
+    /// The contigs whose length matches a bundled build, in input order; other contigs are not considered
+    pub contigs: Vec<ContigBuilds>,
+    /// The builds that every recognized contig matches, in bundled order
+    pub consistent_builds: Vec<&'static str>
+}
+
+impl BuildConsistencyReport {
+    /// Returns true if the recognized contigs do not all match a single build, e.g. hg19 chr1 concatenated with GRCh38 chr2
+    pub fn is_mixed(&self) -> bool {
+        !self.contigs.is_empty() && self.consistent_builds.is_empty()
+    }
+
+    /// Writes the report as a tab-separated table with a header line and one row per recognized contig:
+    /// `contig`, `length`, `builds` (comma-separated), and `consistent` (`yes` if the contig matches one of the consistent builds)
+    /// # Errors
+    /// * any errors from the underlying writer
+    pub fn write_table<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "contig\tlength\tbuilds\tconsistent")?;
+        for contig in self.contigs.iter() {
+            let consistent = contig.builds.iter().any(|b| self.consistent_builds.contains(b));
+            writeln!(writer, "{}\t{}\t{}\t{}", contig.contig, contig.length, contig.builds.join(","), if consistent { "yes" } else { "no" })?;
+        }
+        writer.flush()
+    }
+}
+
+/// Checks that the contigs of a genome come from a single build, a common mistake when references are concatenated.
+/// Each contig is matched to the bundled builds by length, independent of naming; contigs that match no build, such as
+/// alts and decoys, are ignored. Chromosome lengths are distinct across builds except for shared mitochondrial genomes and
+/// the nuclear chromosomes of hg19 and GRCh37, so a mix is flagged when no build matches every recognized contig.
+/// # Arguments
+/// * `contigs` - the contig names and lengths
+pub fn check_build_consistency<'a>(contigs: impl IntoIterator<Item = (&'a str, usize)>) -> BuildConsistencyReport {
+    let mut report = BuildConsistencyReport {
+        contigs: vec![],
+        consistent_builds: BUILD_FINGERPRINTS.iter().map(|f| f.build).collect()
+    };
+    for (contig, length) in contigs {
+        let builds: Vec<&'static str> = BUILD_FINGERPRINTS.iter()
+            .filter(|f| f.lengths.contains(&length))
+            .map(|f| f.build)
+            .collect();
+        if !builds.is_empty() {
+            report.consistent_builds.retain(|b| builds.contains(b));
+            report.contigs.push(ContigBuilds { contig: contig.to_string(), length, builds });
+        }
+    }
+    if report.contigs.is_empty() {
+        report.consistent_builds.clear();
+    }
+    report
+}
+
+impl ReferenceGenome {
+    /// Scores the genome against every bundled build by chromosome length, see `build_matches(...)`
+    pub fn build_matches(&self) -> Vec<BuildMatch> {
+        build_matches(self.contig_keys().iter().filter_map(|c| self.contig_length(c).map(|length| (c.as_str(), length))))
+    }
+
+    /// Identifies the most likely build of the genome from its contig lengths, e.g. for sanity checks and logging, see `identify_build(...)`.
+    /// Only contig lengths are used, so lazily loaded contigs are not read.
+    pub fn identify_build(&self) -> Option<BuildMatch> {
+        self.build_matches().into_iter().next()
+    }
+
+    /// Checks that the contigs of the genome come from a single build, see `check_build_consistency(...)`.
+    /// Only contig lengths are used, so lazily loaded contigs are not read.
+    pub fn check_build_consistency(&self) -> BuildConsistencyReport {
+        check_build_consistency(self.contig_keys().iter().filter_map(|c| self.contig_length(c).map(|length| (c.as_str(), length))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identify_build() {
+        let grch38: Vec<(String, usize)> = BUILD_FINGERPRINTS[0].chromosomes().collect();
+        let best = identify_build(grch38.iter().map(|(n, l)| (n.as_str(), *l))).unwrap();
+        assert_eq!((best.build, best.matched_chromosomes, best.matched_names), ("GRCh38", 25, 25));
+        assert_eq!(best.fraction(), 1.0);
+
+        // hg19 and GRCh37 share nuclear lengths, so the naming convention decides
+        let ensembl = [("1", 249250621), ("2", 243199373), ("X", 155270560)];
+        let best = identify_build(ensembl).unwrap();
+        assert_eq!((best.build, best.assembly, best.matched_names), ("GRCh37", Some(KnownAssembly::GRCh37), 3));
+        let ucsc = [("chr1", 249250621), ("chr2", 243199373), ("chrM", 16571)];
+        assert_eq!(identify_build(ucsc).unwrap().build, "hg19");
+        let renamed = [("NC_000001.10", 249250621), ("NC_012920.1", 16569), ("chrUn", 1000)];
+        assert_eq!(identify_build(renamed).unwrap().build, "GRCh37");
+
+        let mouse = [("chr19", 61431566), ("chrX", 171031299)];
+        let matches = build_matches(mouse);
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].build, matches[0].assembly), ("mm10", None));
+    }
+
+    #[test]
+    fn test_identify_genome_build() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGT\n").unwrap();
+        assert!(reference_genome.identify_build().is_none());
+        assert!(reference_genome.build_matches().is_empty());
+    }
+
+    #[test]
+    fn test_check_build_consistency() {
+        // hg19 chr1 and chrM, plus an unrecognized contig
+        let report = check_build_consistency([("chr1", 249250621), ("chrM", 16571), ("chrUn_gl000220", 161802)]);
+        assert!(!report.is_mixed());
+        assert_eq!(report.consistent_builds, vec!["hg19"]);
+        assert_eq!(report.contigs.len(), 2);
+
+        // hg19 chr1 concatenated with GRCh38 chr2
+        let report = check_build_consistency([("chr1", 249250621), ("chr2", 242193529), ("chrM", 16569)]);
+        assert!(report.is_mixed());
+        assert_eq!(report.contigs[2].builds, vec!["GRCh38", "GRCh37", "T2T-CHM13"]);
+        let mut table: Vec<u8> = vec![];
+        report.write_table(&mut table).unwrap();
+        assert_eq!(String::from_utf8(table).unwrap(), "contig\tlength\tbuilds\tconsistent\n\
+            chr1\t249250621\thg19,GRCh37\tno\n\
+            chr2\t242193529\tGRCh38\tno\n\
+            chrM\t16569\tGRCh38,GRCh37,T2T-CHM13\tno\n");
+
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGT\n").unwrap();
+        let report = reference_genome.check_build_consistency();
+        assert!(report.contigs.is_empty() && report.consistent_builds.is_empty() && !report.is_mixed());
+    }
+}
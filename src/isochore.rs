@@ -0,0 +1,287 @@
+use crate::reference_genome::ReferenceGenome;
+use crate::tracks::{ContigTrack, TrackInterval};
+use simple_error::{bail, SimpleError};
+use std::io::Write;
+
+/// Options for `ReferenceGenome::smoothed_gc_profile(...)` and `ReferenceGenome::gc_domains(...)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct IsochoreOptions {
+    /// The bases per window, which is also the resolution of domain boundaries; default is 10,000
+    pub window_size: usize,
+    /// The windows on each side that the triangular smoothing kernel spans; default is 5, and 0 disables smoothing
+    pub smoothing_radius: usize,
+    /// The minimum domain length in bases, default is 300,000 (the classic lower bound for isochores)
+    pub min_domain_length: usize,
+    /// The minimum difference in mean GC fraction between the two sides of a boundary, default is 0.02
+    pub min_gc_difference: f64
+}
+
+impl Default for IsochoreOptions {
+    fn default() -> Self {
+        Self {
+            window_size: 10_000,
+            smoothing_radius: 5,
+            min_domain_length: 300_000,
+            min_gc_difference: 0.02
+        }
+    }
+}
+
+/// The isochore families of vertebrate genomes, by mean GC fraction (Bernardi's classes)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IsochoreFamily {
+    /// Below 37% GC
+    L1,
+    /// 37% to 41% GC
+    L2,
+    /// 41% to 46% GC
+    H1,
+    /// 46% to 53% GC
+    H2,
+    /// 53% GC or more
+    H3
+}
+
+impl IsochoreFamily {
+    /// Classifies a GC fraction
+    /// # Arguments
+    /// * `gc_fraction` - the mean GC fraction, from 0 to 1
+    pub fn from_gc_fraction(gc_fraction: f64) -> Self {
+        match gc_fraction {
+            f if f < 0.37 => IsochoreFamily::L1,
+            f if f < 0.41 => IsochoreFamily::L2,
+            f if f < 0.46 => IsochoreFamily::H1,
+            f if f < 0.53 => IsochoreFamily::H2,
+            _ => IsochoreFamily::H3
+        }
+    }
+
+    /// The family name, e.g. "H1"
+    pub fn name(&self) -> &'static str {
+        match self {
+            IsochoreFamily::L1 => "L1",
+            IsochoreFamily::L2 => "L2",
+            IsochoreFamily::H1 => "H1",
+            IsochoreFamily::H2 => "H2",
+            IsochoreFamily::H3 => "H3"
+        }
+    }
+}
+
+/// A compositionally homogeneous domain of a contig, from `ReferenceGenome::gc_domains(...)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct GcDomain {
+    /// The contig name
+    pub contig: String,
+    /// 0-based start of the domain
+    pub start: usize,
+    /// 0-based, exclusive end of the domain
+    pub end: usize,
+    /// The fraction of G/C among the non-N bases, or `None` if every base is N
+    pub gc_fraction: Option<f64>
+}
+
+impl GcDomain {
+    /// The isochore family of the domain, or `None` if every base is N
+    pub fn family(&self) -> Option<IsochoreFamily> {
+        self.gc_fraction.map(IsochoreFamily::from_gc_fraction)
+    }
+}
+
+/// Writes domains as BED, one line per domain: contig, start, end, the isochore family (or `NA` if every base is N) as the name,
+/// and the mean GC fraction scaled to 0-1000 as the score
+/// # Arguments
+/// * `writer` - the output to write to
+/// * `domains` - the domains to write, in output order
+/// # Errors
+/// * any errors from the underlying writer
+pub fn write_gc_domains_bed<W: Write>(mut writer: W, domains: &[GcDomain]) -> std::io::Result<()> {
+    for domain in domains.iter() {
+        let name = domain.family().map(|f| f.name()).unwrap_or("NA");
+        let score = domain.gc_fraction.map(|gc| (gc * 1000.0).round() as usize).unwrap_or(0);
+        writeln!(writer, "{}\t{}\t{}\t{}\t{}", domain.contig, domain.start, domain.end, name, score)?;
+    }
+    writer.flush()
+}
+
+/// The G/C and non-N base counts of consecutive windows
+fn window_gc_counts(sequence: &[u8], window_size: usize) -> Vec<(usize, usize)> {
+    sequence.chunks(window_size)
+        .map(|window| window.iter().fold((0, 0), |(gc, called), symbol| match symbol.to_ascii_uppercase() {
+            b'G' | b'C' => (gc + 1, called + 1),
+            b'N' => (gc, called),
+            _ => (gc, called + 1)
+        }))
+        .collect()
+}
+
+/// Splits windows into domains by recursive binary segmentation: each domain is split where the weighted between-sides variance
+/// of GC is highest, as long as both sides are long enough and their mean GC differs enough
+/// # Returns
+/// * the domains as window index ranges, in order
+fn segment(counts: &[(usize, usize)], window_size: usize, contig_length: usize, options: &IsochoreOptions) -> Vec<(usize, usize)> {
+    // prefix sums of G/C and non-N bases
+    let mut prefix: Vec<(usize, usize)> = Vec::with_capacity(counts.len() + 1);
+    prefix.push((0, 0));
+    for &(gc, called) in counts.iter() {
+        let &(total_gc, total_called) = prefix.last().unwrap();
+        prefix.push((total_gc + gc, total_called + called));
+    }
+    let sums = |a: usize, b: usize| (prefix[b].0 - prefix[a].0, prefix[b].1 - prefix[a].1);
+    let bases = |a: usize, b: usize| (b * window_size).min(contig_length) - a * window_size;
+
+    let mut domains: Vec<(usize, usize)> = vec![];
+    let mut pending: Vec<(usize, usize)> = vec![(0, counts.len())];
+    while let Some((a, b)) = pending.pop() {
+        let mut best: Option<(usize, f64)> = None;
+        for split in a + 1..b {
+            if bases(a, split) < options.min_domain_length || bases(split, b) < options.min_domain_length {
+                continue;
+            }
+            let ((left_gc, left_called), (right_gc, right_called)) = (sums(a, split), sums(split, b));
+            if left_called == 0 || right_called == 0 {
+                continue;
+            }
+            let difference = left_gc as f64 / left_called as f64 - right_gc as f64 / right_called as f64;
+            if difference.abs() < options.min_gc_difference {
+                continue;
+            }
+            let (n1, n2) = (left_called as f64, right_called as f64);
+            let score = n1 * n2 / (n1 + n2) * difference * difference;
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((split, score));
+            }
+        }
+        match best {
+            Some((split, _)) => {
+                pending.push((split, b));
+                pending.push((a, split));
+            },
+            None => domains.push((a, b))
+        }
+    }
+    domains.sort_unstable();
+    domains
+}
+
+impl ReferenceGenome {
+    /// Computes a smoothed GC profile along a contig: the GC fraction of each window, averaged with its neighbors under a triangular kernel
+    /// weighted by their non-N bases. Windows where every base is N are omitted, but neighbors still smooth across them.
+    /// # Arguments
+    /// * `contig` - the contig to scan
+    /// * `options` - the window size and smoothing radius, see `IsochoreOptions::default()`
+    /// # Errors
+    /// * if the contig is not in the reference genome, was unloaded, or fails to load
+    /// * if the window size is 0
+    pub fn smoothed_gc_profile(&self, contig: &str, options: &IsochoreOptions) -> Result<ContigTrack, SimpleError> {
+        if options.window_size == 0 {
+            bail!("The window size must be at least 1");
+        }
+        let sequence = self.try_sequence_unkept(contig)?;
+        let counts = window_gc_counts(&sequence, options.window_size);
+        let radius = options.smoothing_radius;
+        let intervals = counts.iter()
+            .enumerate()
+            .filter(|(_, window)| window.1 > 0)
+            .map(|(index, _)| {
+                let (mut gc_sum, mut called_sum) = (0.0, 0.0);
+                let first = index.saturating_sub(radius);
+                for (neighbor, &(gc, called)) in counts.iter().enumerate().skip(first).take(index + radius + 1 - first) {
+                    let weight = (radius + 1 - index.abs_diff(neighbor)) as f64;
+                    gc_sum += weight * gc as f64;
+                    called_sum += weight * called as f64;
+                }
+                TrackInterval {
+                    start: index * options.window_size,
+                    end: ((index + 1) * options.window_size).min(sequence.len()),
+                    value: gc_sum / called_sum
+                }
+            })
+            .collect();
+        Ok(ContigTrack {
+            contig: contig.to_string(),
+            intervals
+        })
+    }
+
+    /// Segments every contig into isochore-like domains of homogeneous GC content, with boundaries at window edges.
+    /// Domains are found by recursive binary segmentation of the per-window GC counts, so each boundary is placed where the GC content
+    /// changes most, and is only kept if both sides are at least `min_domain_length` long and differ by `min_gc_difference` in mean GC.
+    /// Contigs shorter than twice the minimum length form a single domain. Lazily loaded contigs are read once and are not kept in memory.
+    /// See `write_gc_domains_bed(...)` for BED output.
+    /// # Arguments
+    /// * `options` - the window size and segmentation thresholds, see `IsochoreOptions::default()`
+    /// # Errors
+    /// * if the window size is 0
+    /// * if a contig was unloaded or fails to load
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn gc_domains(&self, options: &IsochoreOptions) -> Result<Vec<GcDomain>, SimpleError> {
+        if options.window_size == 0 {
+            bail!("The window size must be at least 1");
+        }
+        let mut domains: Vec<GcDomain> = vec![];
+        for contig in self.contig_keys().iter() {
+            let sequence = self.try_sequence_unkept(contig)?;
+            let counts = window_gc_counts(&sequence, options.window_size);
+            for (a, b) in segment(&counts, options.window_size, sequence.len(), options) {
+                let (gc, called) = counts[a..b].iter().fold((0, 0), |(gc, called), c| (gc + c.0, called + c.1));
+                domains.push(GcDomain {
+                    contig: contig.clone(),
+                    start: a * options.window_size,
+                    end: (b * options.window_size).min(sequence.len()),
+                    gc_fraction: (called > 0).then(|| gc as f64 / called as f64)
+                });
+            }
+        }
+        Ok(domains)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> IsochoreOptions {
+        IsochoreOptions {
+            window_size: 10,
+            smoothing_radius: 1,
+            min_domain_length: 30,
+            min_gc_difference: 0.1
+        }
+    }
+
+    #[test]
+    fn test_smoothed_gc_profile() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), &format!("{}{}{}", "A".repeat(10), "N".repeat(10), "GGGGGCCCCC")).unwrap();
+        let track = reference_genome.smoothed_gc_profile("chr1", &options()).unwrap();
+        // the all-N window is omitted, and the other two only see each other at distance 2 (weight 0)
+        assert_eq!(track.intervals, vec![
+            TrackInterval { start: 0, end: 10, value: 0.0 },
+            TrackInterval { start: 20, end: 30, value: 1.0 }
+        ]);
+
+        let unsmoothed = IsochoreOptions { smoothing_radius: 0, ..options() };
+        assert_eq!(reference_genome.smoothed_gc_profile("chr1", &unsmoothed).unwrap().intervals.len(), 2);
+        assert!(reference_genome.smoothed_gc_profile("chr2", &options()).is_err());
+        assert!(reference_genome.smoothed_gc_profile("chr1", &IsochoreOptions { window_size: 0, ..options() }).is_err());
+    }
+
+    #[test]
+    fn test_gc_domains() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        // an AT-rich block, a GC-rich block, and a short tail that is too short to be its own domain
+        let sequence = format!("{}{}{}", "AATAATAAGC".repeat(5), "GCGCGCAT".repeat(5), "ATAT");
+        reference_genome.add_contig("chr1".to_string(), &sequence).unwrap();
+        reference_genome.add_contig("chr2".to_string(), "NNNN").unwrap();
+        let domains = reference_genome.gc_domains(&options()).unwrap();
+        assert_eq!(domains.len(), 3);
+        assert_eq!((domains[0].start, domains[0].end, domains[0].family()), (0, 50, Some(IsochoreFamily::L1)));
+        assert_eq!((domains[1].start, domains[1].end, domains[1].family()), (50, 94, Some(IsochoreFamily::H3)));
+        assert_eq!(domains[2].gc_fraction, None);
+
+        let mut bed: Vec<u8> = vec![];
+        write_gc_domains_bed(&mut bed, &domains).unwrap();
+        assert_eq!(String::from_utf8(bed).unwrap(), "chr1\t0\t50\tL1\t200\nchr1\t50\t94\tH3\t682\nchr2\t0\t4\tNA\t0\n");
+    }
+}
@@ -0,0 +1,102 @@
+/// A k-mer of a sequence in 2-bit encoding (A=0, C=1, G=2, T=3), from `Kmers`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Kmer {
+    /// 0-based start of the k-mer in the sequence
+    pub(crate) start: usize,
+    /// The encoding of the forward strand
+    pub(crate) forward: u64,
+    /// The encoding of the reverse complement
+    pub(crate) reverse: u64
+}
+
+impl Kmer {
+    /// The smaller of the two strand encodings, so a k-mer and its reverse complement are the same canonical k-mer
+    pub(crate) fn canonical(&self) -> u64 {
+        self.forward.min(self.reverse)
+    }
+}
+
+/// Iterates over the k-mers of a sequence in order, skipping k-mers that contain a base other than A, C, G, or T (in either case)
+pub(crate) struct Kmers<'a> {
+    /// The remaining bases with their positions
+    bases: std::iter::Enumerate<std::slice::Iter<'a, u8>>,
+    /// The k-mer length
+    k: usize,
+    /// The bits of a forward encoding
+    mask: u64,
+    /// The forward encoding of the last `k` bases
+    forward: u64,
+    /// The reverse complement encoding of the last `k` bases
+    reverse: u64,
+    /// The number of A/C/G/T bases since the last other symbol
+    valid: usize
+}
+
+impl<'a> Kmers<'a> {
+    /// Creates an iterator over the k-mers of a sequence
+    /// # Arguments
+    /// * `sequence` - the ASCII sequence
+    /// * `k` - the k-mer length, from 1 to 32; callers check the range
+    pub(crate) fn new(sequence: &'a [u8], k: usize) -> Self {
+        debug_assert!((1..=32).contains(&k));
+        Self {
+            bases: sequence.iter().enumerate(),
+            k,
+            mask: if k == 32 { u64::MAX } else { (1 << (2 * k)) - 1 },
+            forward: 0,
+            reverse: 0,
+            valid: 0
+        }
+    }
+}
+
+impl Iterator for Kmers<'_> {
+    type Item = Kmer;
+
+    fn next(&mut self) -> Option<Kmer> {
+        for (position, symbol) in self.bases.by_ref() {
+            let code = match symbol.to_ascii_uppercase() {
+                b'A' => 0,
+                b'C' => 1,
+                b'G' => 2,
+                b'T' => 3,
+                _ => {
+                    self.valid = 0;
+                    continue;
+                }
+            };
+            self.forward = ((self.forward << 2) | code) & self.mask;
+            self.reverse = (self.reverse >> 2) | ((3 - code) << (2 * (self.k - 1)));
+            self.valid += 1;
+            if self.valid >= self.k {
+                return Some(Kmer { start: position + 1 - self.k, forward: self.forward, reverse: self.reverse });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kmers() {
+        let kmers: Vec<Kmer> = Kmers::new(b"ACGNacgtT", 3).collect();
+        // CGT is the reverse complement of ACG, and the N resets the k-mer
+        assert_eq!(kmers.iter().map(|k| k.start).collect::<Vec<usize>>(), vec![0, 4, 5, 6]);
+        assert_eq!(kmers[0].forward, 0b00_01_10);
+        assert_eq!(kmers[0].reverse, kmers[2].forward);
+        assert_eq!(kmers[0].canonical(), kmers[2].canonical());
+
+        let forward: Vec<u64> = Kmers::new(b"AACCGGTTAC", 4).map(|k| k.canonical()).collect();
+        let mut reverse: Vec<u64> = Kmers::new(b"GTAACCGGTT", 4).map(|k| k.canonical()).collect();
+        reverse.reverse();
+        assert_eq!(forward, reverse);
+
+        assert_eq!(Kmers::new(b"ACG", 4).count(), 0);
+        let long: Vec<Kmer> = Kmers::new(&[b'T'; 33], 32).collect();
+        assert_eq!(long.len(), 2);
+        assert_eq!((long[0].forward, long[0].reverse), (u64::MAX, 0));
+    }
+}
@@ -0,0 +1,317 @@
+use crate::advice::AccessPattern;
+use crate::cache::{CacheKey, ContigCache};
+use bytes::Bytes;
+use std::error::Error;
+use std::sync::{Arc, OnceLock};
+
+/// A source of full contig sequences for lazily loaded genomes, where contigs are identified by their index in the source
+pub(crate) trait ContigLoader: Send + Sync {
+    /// The name of a contig in the source, used for error messages
+    fn contig_name(&self, index: usize) -> &str;
+
+    /// The length of a contig in the source, without reading its sequence
+    fn contig_length(&self, index: usize) -> usize;
+
+    /// Reads the full sequence of a contig from the source
+    fn load_contig(&self, index: usize) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+
+    /// The heap bytes the source holds for a contig, such as a packed copy; sources that read from a file hold none
+    fn stored_bytes(&self, _index: usize) -> usize {
+        0
+    }
+
+    /// Passes an access pattern hint for the whole source to the OS; returns false if the source does not take hints,
+    /// which is the case unless it is memory-mapped
+    fn advise(&self, _pattern: AccessPattern) -> std::io::Result<bool> {
+        Ok(false)
+    }
+
+    /// Asks the OS to read ahead the pages holding a contig; returns false if the source does not take hints
+    fn will_need(&self, _index: usize) -> std::io::Result<bool> {
+        Ok(false)
+    }
+}
+
+/// A contig whose sequence is read from a `ContigLoader` on first access.
+/// Borrowed access keeps the sequence in memory permanently, while shared access goes through the optional LRU cache.
+#[derive(Clone)]
+pub(crate) struct LazyContig {
+    /// The shared source of the sequence
+    loader: Arc<dyn ContigLoader>,
+    /// The index of this contig in the loader
+    index: usize,
+    /// If true, the sequence is upper-cased when loaded
+    uppercase: bool,
+    /// The loaded sequence, populated on first borrowed access
+    loaded: OnceLock<Bytes>,
+    /// Optional cache for shared access
+    cache: Option<Arc<ContigCache>>
+}
+
+impl LazyContig {
+    /// Creates a contig that has not been loaded yet
+    pub(crate) fn new(loader: Arc<dyn ContigLoader>, index: usize, uppercase: bool) -> Self {
+        Self {
+            loader,
+            index,
+            uppercase,
+            loaded: OnceLock::new(),
+            cache: None
+        }
+    }
+
+    /// Routes shared access through a cache instead of keeping the sequence permanently
+    pub(crate) fn with_cache(mut self, cache: Arc<ContigCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// The cache key for this contig, unique across loaders
+    fn cache_key(&self) -> CacheKey {
+        (Arc::as_ptr(&self.loader) as *const () as usize, self.index)
+    }
+
+    /// Retrieves the sequence, loading it if this is the first access; nothing is kept if the load fails
+    /// # Errors
+    /// * if the loader fails
+    pub(crate) fn try_sequence(&self) -> Result<&[u8], Box<dyn Error + Send + Sync>> {
+        Ok(self.try_loaded()?)
+    }
+
+    /// Retrieves a shared handle to the sequence.
+    /// If there is a cache and the sequence was not already kept by a borrowed access, the cache is used (and populated).
+    /// # Panics
+    /// * if the loader fails
+    pub(crate) fn sequence_bytes(&self) -> Bytes {
+        match (self.loaded.get(), self.cache.as_ref()) {
+            (Some(sequence), _) => sequence.clone(),
+            (None, Some(cache)) => {
+                let key = self.cache_key();
+                cache.get(key).unwrap_or_else(|| {
+                    let sequence = self.load();
+                    cache.insert(key, sequence.clone());
+                    sequence
+                })
+            },
+            (None, None) => self.loaded().clone()
+        }
+    }
+
+    /// Retrieves a shared handle to the sequence without keeping it in this contig, for one-off sequential access.
+    /// Any kept or cached copy is reused, and a cache is populated if present.
+    /// # Errors
+    /// * if the loader fails
+    pub(crate) fn try_sequence_bytes_unkept(&self) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
+        match (self.loaded.get(), self.cache.as_ref()) {
+            (Some(sequence), _) => Ok(sequence.clone()),
+            (None, Some(cache)) => {
+                let key = self.cache_key();
+                match cache.get(key) {
+                    Some(sequence) => Ok(sequence),
+                    None => {
+                        let sequence = self.try_load()?;
+                        cache.insert(key, sequence.clone());
+                        Ok(sequence)
+                    }
+                }
+            },
+            (None, None) => self.try_load()
+        }
+    }
+
+    /// Returns true if a borrowed access would keep the sequence outside of the cache, which cannot evict it
+    pub(crate) fn bypasses_cache(&self) -> bool {
+        self.cache.is_some() && self.loaded.get().is_none()
+    }
+
+    /// The sequence length, without loading the sequence if it has not been loaded yet
+    pub(crate) fn len(&self) -> usize {
+        match self.loaded.get() {
+            Some(sequence) => sequence.len(),
+            None => self.loader.contig_length(self.index)
+        }
+    }
+
+    /// Identifies the source of this contig, which is shared by every contig read from the same file
+    pub(crate) fn loader_id(&self) -> usize {
+        self.cache_key().0
+    }
+
+    /// Passes an access pattern hint for the whole source of this contig to the OS, see `ContigLoader::advise(...)`
+    pub(crate) fn advise(&self, pattern: AccessPattern) -> std::io::Result<bool> {
+        self.loader.advise(pattern)
+    }
+
+    /// Asks the OS to read ahead the pages holding this contig, see `ContigLoader::will_need(...)`
+    pub(crate) fn will_need(&self) -> std::io::Result<bool> {
+        self.loader.will_need(self.index)
+    }
+
+    /// Releases any kept sequence; it will be read from the loader again on the next access
+    pub(crate) fn unload(&mut self) {
+        self.loaded = OnceLock::new();
+    }
+
+    /// The heap bytes currently held for this contig, either kept permanently or in the cache, plus any held by the source
+    pub(crate) fn heap_bytes(&self) -> usize {
+        let loaded_bytes = match (self.loaded.get(), self.cache.as_ref()) {
+            (Some(sequence), _) => sequence.len(),
+            (None, Some(cache)) => cache.cached_bytes(self.cache_key()),
+            (None, None) => 0
+        };
+        loaded_bytes + self.loader.stored_bytes(self.index)
+    }
+
+    /// Loads the sequence on first access and keeps it, reusing any cached copy
+    /// # Panics
+    /// * if the loader fails
+    fn loaded(&self) -> &Bytes {
+        self.try_loaded().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Loads the sequence on first access and keeps it, reusing any cached copy
+    /// # Errors
+    /// * if the loader fails
+    fn try_loaded(&self) -> Result<&Bytes, Box<dyn Error + Send + Sync>> {
+        if let Some(sequence) = self.loaded.get() {
+            return Ok(sequence);
+        }
+        let sequence = match self.cache.as_ref().and_then(|cache| cache.get(self.cache_key())) {
+            Some(sequence) => sequence,
+            None => self.try_load()?
+        };
+        // a concurrent first access may have won the race, in which case its copy is kept
+        Ok(self.loaded.get_or_init(|| sequence))
+    }
+
+    /// Reads the sequence from the loader
+    /// # Panics
+    /// * if the loader fails
+    fn load(&self) -> Bytes {
+        self.try_load().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Reads the sequence from the loader
+    /// # Errors
+    /// * if the loader fails
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "load_contig", level = "debug", skip_all, err, fields(contig = self.loader.contig_name(self.index))))]
+    fn try_load(&self) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
+        let mut sequence = self.loader.load_contig(self.index)
+            .map_err(|e| format!("Failed to load contig {:?}: {e}", self.loader.contig_name(self.index)))?;
+        if self.uppercase {
+            sequence.make_ascii_uppercase();
+        }
+        sequence.shrink_to_fit();
+        Ok(Bytes::from(sequence))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Reads a single contig from a file, counting the reads
+    struct FileLoader {
+        /// The file holding the sequence
+        path: PathBuf,
+        /// The expected sequence length
+        length: usize,
+        /// The number of calls to `load_contig(...)`
+        loads: AtomicUsize
+    }
+
+    impl ContigLoader for FileLoader {
+        fn contig_name(&self, _index: usize) -> &str {
+            "chr1"
+        }
+
+        fn contig_length(&self, _index: usize) -> usize {
+            self.length
+        }
+
+        fn load_contig(&self, _index: usize) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+            self.loads.fetch_add(1, Ordering::Relaxed);
+            let sequence = std::fs::read(&self.path)?;
+            if sequence.len() != self.length {
+                return Err(format!("expected {} bases, found {}", self.length, sequence.len()).into());
+            }
+            Ok(sequence)
+        }
+    }
+
+    /// Writes a sequence to a temporary file and returns a loader for it
+    fn file_loader(name: &str, sequence: &[u8]) -> Arc<FileLoader> {
+        let path = std::env::temp_dir().join(format!("refgenome_lazy_{}_{name}", std::process::id()));
+        std::fs::write(&path, sequence).unwrap();
+        Arc::new(FileLoader { path, length: sequence.len(), loads: AtomicUsize::new(0) })
+    }
+
+    #[test]
+    fn test_first_access_load() {
+        let loader = file_loader("first", b"ACgtN");
+        let contig = LazyContig::new(loader.clone(), 0, true);
+        // the length comes from the loader without reading the sequence
+        assert_eq!(contig.len(), 5);
+        assert_eq!(contig.heap_bytes(), 0);
+        assert_eq!(loader.loads.load(Ordering::Relaxed), 0);
+
+        assert_eq!(contig.try_sequence().unwrap(), b"ACGTN");
+        assert_eq!(contig.sequence_bytes(), Bytes::from_static(b"ACGTN"));
+        assert_eq!(loader.loads.load(Ordering::Relaxed), 1);
+        assert_eq!(contig.heap_bytes(), 5);
+
+        let mut unloaded = contig.clone();
+        unloaded.unload();
+        assert_eq!(unloaded.heap_bytes(), 0);
+        assert_eq!(unloaded.try_sequence().unwrap(), b"ACGTN");
+        assert_eq!(loader.loads.load(Ordering::Relaxed), 2);
+
+        let preserved = LazyContig::new(loader.clone(), 0, false);
+        assert_eq!(preserved.try_sequence().unwrap(), b"ACgtN");
+        std::fs::remove_file(&loader.path).unwrap();
+    }
+
+    #[test]
+    fn test_unkept_sequence() {
+        let loader = file_loader("unkept", b"ACGT");
+        let contig = LazyContig::new(loader.clone(), 0, true);
+        assert_eq!(contig.try_sequence_bytes_unkept().unwrap(), Bytes::from_static(b"ACGT"));
+        assert_eq!(contig.heap_bytes(), 0);
+        // nothing was kept, so the next access reads the file again
+        contig.try_sequence_bytes_unkept().unwrap();
+        assert_eq!(loader.loads.load(Ordering::Relaxed), 2);
+
+        // a kept sequence is reused
+        contig.try_sequence().unwrap();
+        contig.try_sequence_bytes_unkept().unwrap();
+        assert_eq!(loader.loads.load(Ordering::Relaxed), 3);
+
+        // with a cache, the sequence is cached instead of kept
+        let cached = LazyContig::new(loader.clone(), 0, true).with_cache(Arc::new(ContigCache::new(1, 1024)));
+        cached.try_sequence_bytes_unkept().unwrap();
+        cached.try_sequence_bytes_unkept().unwrap();
+        assert_eq!(loader.loads.load(Ordering::Relaxed), 4);
+        assert_eq!(cached.heap_bytes(), 4);
+        std::fs::remove_file(&loader.path).unwrap();
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let loader = file_loader("truncated", b"ACGTACGT");
+        let contig = LazyContig::new(loader.clone(), 0, true);
+        std::fs::write(&loader.path, b"ACGT").unwrap();
+        let error = contig.try_sequence_bytes_unkept().unwrap_err().to_string();
+        assert!(error.contains("chr1") && error.contains("expected 8 bases"), "{error}");
+
+        std::fs::remove_file(&loader.path).unwrap();
+        let error = contig.try_sequence_bytes_unkept().unwrap_err().to_string();
+        assert!(error.starts_with("Failed to load contig \"chr1\""), "{error}");
+        // nothing is kept after a failure
+        assert_eq!(contig.heap_bytes(), 0);
+        assert!(contig.try_sequence().is_err());
+        assert_eq!(contig.heap_bytes(), 0);
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| contig.sequence_bytes().len())).is_err());
+    }
+}
@@ -1,3 +1,162 @@
-
 /// Loads a fasta[.gz] reference genome into memory
 pub mod reference_genome;
+/// Compares two reference genomes and reports their differences
+pub mod genome_comparison;
+/// Sequence digests and digest-based duplicate detection
+pub mod digest;
+/// Classifies contigs by their role in the assembly (autosome, alt, decoy, etc.)
+pub mod contig_class;
+/// Presets and naming tables for well-known reference assemblies
+pub mod assembly;
+/// Assembly metadata, such as species and taxonomy, and the sequence dictionary and VCF headers that carry it
+pub mod metadata;
+/// Sequence dictionary (`.dict`) parsing and validation against a loaded genome
+pub mod dict;
+/// Parsing of samtools `.fai` indexes and verification against their FASTA
+pub mod fai;
+/// Streaming FASTA fingerprints (digests, lengths, and gap statistics) computed without holding any sequence
+pub mod fingerprint;
+/// Contig catalog export as an SQL dump, with lengths, digests, classes, and gap summaries
+pub mod catalog;
+/// Identification of the build of a genome from its contig lengths, and detection of mixed builds
+pub mod identify;
+/// Typed 0-based positions, intervals, and strands with explicit conversions from 1-based coordinates
+pub mod coordinates;
+/// Contig name lookup helpers, such as suggestions for unknown contigs and SAM name sanitization
+pub mod lookup;
+/// Bulk contig renaming from two-column mapping files, such as the published UCSC/Ensembl name tables
+pub mod rename;
+/// Sequence alphabets used for validation
+pub mod alphabet;
+/// Heap memory usage reporting
+pub mod memory;
+/// Load performance metrics, such as wall time and bytes read
+pub mod metrics;
+/// Structured warnings raised while loading or reading a genome, such as truncated slices and renamed records
+pub mod warnings;
+/// Builder for loading reference genomes with non-default options
+pub mod builder;
+/// The `ContigStore` trait for in-memory contig encodings, such as 4-bit packed and compressed storage
+pub mod contig_store;
+/// FASTA parser backends
+pub mod parser;
+/// FASTA line-length uniformity checks for `samtools faidx` compatibility
+pub mod line_lengths;
+/// The `SequenceSource` trait for code that is generic over reference backends, and a mock for unit tests
+pub mod source;
+/// Process-wide registry that deduplicates reference genome loads
+pub mod registry;
+/// Coordinate conversion between builds with UCSC chain files
+pub mod liftover;
+/// Several genomes keyed by build name, with shared loads, liftover, and comparison between builds
+pub mod collection;
+/// Resumable HTTP(S) downloads of remote references
+#[cfg(feature = "download")]
+pub mod download;
+/// Resolution of logical genome names to locations from a config file or environment variables
+pub mod resolver;
+/// Asset registry resolving (genome, asset) pairs from a shared local cache or a remote registry
+pub mod assets;
+/// Content-addressed on-disk sequence store with per-assembly name aliases
+pub mod store;
+/// Population of htslib-style `REF_CACHE` directories for CRAM tools
+pub mod ref_cache;
+/// Access pattern hints (`madvise`) for memory-mapped genomes
+pub mod advice;
+/// Background prefetching for sequential sweeps over lazily loaded genomes
+pub mod prefetch;
+/// Thread-safe shared handle for genomes that are extended while in use
+pub mod shared;
+/// Polling file watcher that hot-reloads a shared genome when its files change on disk
+pub mod watch;
+/// Export of decoded genomes to named shared memory segments, and read-only attach from other processes
+pub mod shm;
+/// Draft assembly cleanup, such as dropping short contigs and trimming terminal Ns
+pub mod cleanup;
+/// Concatenation of contigs into pseudo-molecules with N spacers, and AGP output
+pub mod pseudomolecule;
+/// Simulated fragmentation of a genome into a draft-like assembly with truth coordinates, for benchmarking
+pub mod fragment;
+/// Flanking sequence extraction around variant sites from a VCF or a list of positions
+pub mod flanking;
+/// Sequence context queries at single positions, such as homopolymer runs and mutational signature contexts
+pub mod context;
+/// Methylation context (CpG/CHG/CHH) classification of cytosines, and BED output
+pub mod methylation;
+/// Poly-A and poly-T tract detection, and BED output
+pub mod polya;
+/// Splice-site dinucleotides, flanking context, and motif classification of splice junctions
+pub mod splice;
+/// Strand-aware promoter windows around transcription start sites from GFF3/GTF annotations, with FASTA and BED output
+pub mod promoter;
+/// Reference codon, frame, and amino acid lookup at genomic positions from GFF3/GTF CDS models
+pub mod codon;
+/// Variation graphs built from the reference and a VCF, and GFA output
+pub mod graph;
+/// BED interval reading
+pub mod bed;
+/// Target panel extraction into a multi-FASTA with templated headers and a remapped BED
+pub mod panel;
+/// Sequence edits with coordinate maps between original and edited contigs
+pub mod edit;
+/// FASTA output, including per-contig splitting
+pub mod writer;
+/// Per-window and per-interval sequence tracks and statistics, such as GC skew, and bedGraph output
+pub mod tracks;
+/// Prefix-sum base composition indexes for constant-time GC queries over arbitrary intervals
+pub mod composition;
+/// Smoothed GC profiles and segmentation of contigs into isochore-like domains, with BED output
+pub mod isochore;
+/// Soft-masked and N fraction queries over regions, answered from precomputed interval indexes
+pub mod fractions;
+/// Telomere-to-telomere completeness assessment from gaps and telomere repeats
+pub mod completeness;
+/// Genome complexity estimates from the fraction of distinct k-mers, counted with HyperLogLog
+pub mod complexity;
+/// Contamination screening by k-mers shared with a contaminant FASTA, such as UniVec or common bacteria
+pub mod contamination;
+/// Conversions to/from rust-bio FASTA records and sequence fetches by rust-bio interval types
+#[cfg(feature = "bio")]
+pub mod rust_bio;
+/// Conversions to/from noodles-fasta types and a noodles indexed reader backend
+#[cfg(feature = "noodles")]
+pub mod noodles;
+/// bigWig output for per-window tracks
+#[cfg(feature = "bigwig")]
+pub mod bigwig;
+/// Arrow record batches and Parquet output of per-window statistics
+#[cfg(feature = "arrow")]
+pub mod columnar;
+/// C-compatible API, see `include/refgenome.h`
+#[cfg(feature = "ffi")]
+pub mod ffi;
+/// Python bindings
+#[cfg(feature = "python")]
+mod python;
+/// htslib faidx storage backend
+#[cfg(feature = "htslib")]
+mod htslib;
+/// Interned contig names and the name-to-id index
+mod contig_index;
+/// LRU cache for lazily loaded contigs
+mod cache;
+/// Contigs that are loaded on first access
+mod lazy;
+/// Memory-mapped storage backend
+mod mapped;
+/// File reads that bypass the page cache
+mod direct;
+/// File reads through io_uring that run ahead of parsing
+#[cfg(feature = "io-uring")]
+mod uring;
+/// 4-bit packed storage backend
+mod packed;
+/// Expands directories and wildcard patterns into FASTA file lists
+mod multi_file;
+/// 2-bit k-mer iteration shared by the k-mer based analyses
+mod kmer;
+/// Per-user cache, data, and config directories from the XDG environment variables
+mod user_dirs;
+/// Deterministic sequences shared by unit tests
+#[cfg(test)]
+mod test_sequences;
@@ -0,0 +1,218 @@
+use crate::builder::{decompress, is_gzip};
+use crate::coordinates::Strand;
+use rustc_hash::FxHashMap as HashMap;
+use simple_error::bail;
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// An ungapped block of a chain, aligned between the target and query builds
+#[derive(Clone, Copy, Debug)]
+struct ChainBlock {
+    /// 0-based start on the target contig
+    target_start: usize,
+    /// 0-based start on the query contig, on the query strand
+    query_start: usize,
+    /// The number of aligned bases
+    length: usize
+}
+
+/// A single chain from a target contig to a query contig
+#[derive(Clone, Debug)]
+struct Chain {
+    /// The chain score, higher chains take precedence where chains overlap
+    score: f64,
+    /// 0-based start on the target contig
+    target_start: usize,
+    /// 0-based end on the target contig
+    target_end: usize,
+    /// The query contig name
+    query_contig: String,
+    /// The query contig length
+    query_size: usize,
+    /// The query strand
+    query_strand: Strand,
+    /// Aligned blocks, sorted by target start
+    blocks: Vec<ChainBlock>
+}
+
+impl Chain {
+    /// Lifts a target position within this chain, or `None` if it falls in a gap
+    fn lift_position(&self, position: usize) -> Option<LiftedPosition> {
+        if position < self.target_start || position >= self.target_end {
+            return None;
+        }
+        let index = self.blocks.partition_point(|b| b.target_start + b.length <= position);
+        let block = self.blocks.get(index).filter(|b| b.target_start <= position)?;
+        let query_position = block.query_start + position - block.target_start;
+        let position = match self.query_strand {
+            Strand::Forward => query_position,
+            Strand::Reverse => self.query_size - 1 - query_position
+        };
+        Some(LiftedPosition {
+            contig: self.query_contig.clone(),
+            position,
+            strand: self.query_strand
+        })
+    }
+}
+
+/// A position converted to another build
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LiftedPosition {
+    /// The contig in the other build
+    pub contig: String,
+    /// The 0-based position on the forward strand of `contig`
+    pub position: usize,
+    /// The strand of the other build that the original forward strand maps to
+    pub strand: Strand
+}
+
+/// Coordinate conversion from one build to another with a UCSC chain file, such as `hg19ToHg38.over.chain.gz`.
+/// The chain target is the build being converted from, and the query is the build being converted to.
+#[derive(Clone, Debug, Default)]
+pub struct Liftover {
+    /// Chains by target contig, sorted by descending score
+    chains: HashMap<String, Vec<Chain>>
+}
+
+impl Liftover {
+    /// Reads the chains of a UCSC chain file
+    /// # Arguments
+    /// * `reader` - the uncompressed chain content
+    /// # Errors
+    /// * any reading errors
+    /// * if a header or block line is malformed, a block line is outside a chain, or a chain's blocks do not add up to its range
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut chains: HashMap<String, Vec<Chain>> = Default::default();
+        // the open chain, with its target contig and the next target and query offsets
+        let mut current: Option<(String, Chain, usize, usize)> = None;
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields[0] == "chain" {
+                if current.is_some() {
+                    bail!("Chain header on line {} before the previous chain ended", line_index + 1);
+                }
+                if fields.len() < 12 || fields[4] != "+" {
+                    bail!("Invalid chain header on line {}: {:?}", line_index + 1, line);
+                }
+                let number = |i: usize| fields[i].parse::<usize>()
+                    .map_err(|_| format!("Invalid number {:?} on line {} of the chain file", fields[i], line_index + 1));
+                let Some(query_strand) = fields[9].chars().next().and_then(Strand::from_symbol) else {
+                    bail!("Invalid query strand {:?} on line {} of the chain file", fields[9], line_index + 1);
+                };
+                let chain = Chain {
+                    score: fields[1].parse()
+                        .map_err(|_| format!("Invalid score {:?} on line {} of the chain file", fields[1], line_index + 1))?,
+                    target_start: number(5)?,
+                    target_end: number(6)?,
+                    query_contig: fields[7].to_string(),
+                    query_size: number(8)?,
+                    query_strand,
+                    blocks: vec![]
+                };
+                let (target_offset, query_offset) = (chain.target_start, number(10)?);
+                current = Some((fields[2].to_string(), chain, target_offset, query_offset));
+                continue;
+            }
+
+            let Some((_, chain, target_offset, query_offset)) = current.as_mut() else {
+                bail!("Alignment data on line {} outside of a chain", line_index + 1);
+            };
+            let numbers: Vec<usize> = match fields.iter().map(|f| f.parse::<usize>()).collect() {
+                Ok(numbers) => numbers,
+                Err(_) => bail!("Invalid alignment data on line {} of the chain file: {:?}", line_index + 1, line)
+            };
+            match numbers[..] {
+                [length, target_gap, query_gap] => {
+                    chain.blocks.push(ChainBlock { target_start: *target_offset, query_start: *query_offset, length });
+                    *target_offset += length + target_gap;
+                    *query_offset += length + query_gap;
+                },
+                [length] => {
+                    chain.blocks.push(ChainBlock { target_start: *target_offset, query_start: *query_offset, length });
+                    let (target_contig, chain, target_offset, _) = current.take().unwrap();
+                    if target_offset + length != chain.target_end || chain.query_size < chain.blocks.last().unwrap().query_start + length {
+                        bail!("Chain ending on line {} does not match the range in its header", line_index + 1);
+                    }
+                    chains.entry(target_contig).or_default().push(chain);
+                },
+                _ => bail!("Expected 1 or 3 columns on line {} of the chain file: {:?}", line_index + 1, line)
+            }
+        }
+        if current.is_some() {
+            bail!("The chain file ended in the middle of a chain");
+        }
+        for contig_chains in chains.values_mut() {
+            contig_chains.sort_by(|a, b| b.score.total_cmp(&a.score));
+        }
+        Ok(Self { chains })
+    }
+
+    /// Reads the chains of a UCSC chain file, see `from_reader(...)`
+    /// # Arguments
+    /// * `chain_fn` - the chain filename; gzip compression is detected from a `.gz` extension
+    /// # Errors
+    /// * any file reading errors
+    /// * see `from_reader(...)`
+    pub fn from_file(chain_fn: &Path) -> Result<Self, Box<dyn Error>> {
+        let chain_file = std::fs::File::open(chain_fn)?;
+        let reader = decompress(Box::new(BufReader::new(chain_file)), is_gzip(chain_fn), &Arc::default())?;
+        Self::from_reader(reader)
+    }
+
+    /// The number of chains
+    pub fn chain_count(&self) -> usize {
+        self.chains.values().map(|c| c.len()).sum()
+    }
+
+    /// Converts a position to the other build using the highest scoring chain that aligns it
+    /// # Arguments
+    /// * `contig` - the contig in the original build
+    /// * `position` - the 0-based position on `contig`
+    /// # Returns
+    /// * the converted position, or `None` if no chain aligns the position
+    pub fn lift_position(&self, contig: &str, position: usize) -> Option<LiftedPosition> {
+        self.chains.get(contig)?
+            .iter()
+            .find_map(|chain| chain.lift_position(position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// chrA (length 20) maps to chrB with a 2-base deletion, and chrC maps to the reverse strand of chrD
+    const CHAIN: &[u8] = b"chain 100 chrA 20 + 0 20 chrB 18 + 0 18 1\n5\t2\t0\n13\n\nchain 50 chrC 10 + 2 8 chrD 10 - 1 7 2\n6\n";
+
+    #[test]
+    fn test_liftover() {
+        let liftover = Liftover::from_reader(CHAIN).unwrap();
+        assert_eq!(liftover.chain_count(), 2);
+        assert_eq!(liftover.lift_position("chrA", 3), Some(LiftedPosition { contig: "chrB".to_string(), position: 3, strand: Strand::Forward }));
+        assert_eq!(liftover.lift_position("chrA", 5), None);
+        assert_eq!(liftover.lift_position("chrA", 7).unwrap().position, 5);
+        assert_eq!(liftover.lift_position("chrA", 20), None);
+
+        // query position 1 on the reverse strand is 8 on the forward strand
+        assert_eq!(liftover.lift_position("chrC", 2), Some(LiftedPosition { contig: "chrD".to_string(), position: 8, strand: Strand::Reverse }));
+        assert_eq!(liftover.lift_position("chrC", 1), None);
+        assert_eq!(liftover.lift_position("chrX", 1), None);
+    }
+
+    #[test]
+    fn test_liftover_errors() {
+        assert!(Liftover::from_reader(&b"5\t2\t0\n"[..]).is_err());
+        assert!(Liftover::from_reader(&b"chain 100 chrA 20 + 0 20 chrB 18 + 0 18 1\n5\t2\t0\n"[..]).is_err());
+        assert!(Liftover::from_reader(&b"chain 100 chrA 20 + 0 20 chrB 18 + 0 18 1\n19\n"[..]).is_err());
+        assert!(Liftover::from_reader(&b"chain 100 chrA 20 + 0 20 chrB 18 ? 0 18 1\n20\n"[..]).is_err());
+        assert!(Liftover::from_reader(&b"chain 100 chrA 20 + 0 20 chrB\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::builder::{decompress, is_gzip};
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// The first line that breaks the fixed line width of a record
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IrregularRecord {
+    /// The record ID
+    pub contig: String,
+    /// The 1-based line number in the file
+    pub line_number: usize,
+    /// The line width of the record, from its first sequence line, in bytes including the line ending
+    pub line_width: usize,
+    /// The length of the offending line, in bytes including the line ending
+    pub line_length: usize
+}
+
+/// Result of `check_line_lengths(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineLengthReport {
+    /// The number of records checked
+    pub records: usize,
+    /// Records with irregular wrapping, in file order, each reported once
+    pub irregular: Vec<IrregularRecord>
+}
+
+impl LineLengthReport {
+    /// Returns true if every record has a fixed line width, so `samtools faidx` can index the file
+    pub fn is_uniform(&self) -> bool {
+        self.irregular.is_empty()
+    }
+}
+
+/// Checks that every record wraps at a fixed line width, which `samtools faidx` requires to compute offsets:
+/// every sequence line of a record but the last must have the same length (line ending included), and the last may not be longer.
+/// Blank lines count as short lines, so they are only allowed at the end of a record.
+/// # Arguments
+/// * `reader` - the uncompressed FASTA content
+/// # Errors
+/// * any reading errors
+/// * if sequence appears before the first header
+pub fn check_line_lengths<R: BufRead>(mut reader: R) -> Result<LineLengthReport, Box<dyn Error>> {
+    let mut report = LineLengthReport::default();
+    let mut line: Vec<u8> = vec![];
+    let mut line_number = 0;
+    // the current record ID, its line width, whether a short line was seen, and whether the record was already reported
+    let mut current: Option<String> = None;
+    let mut line_width = 0;
+    let mut short_line = false;
+    let mut reported = false;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+        if let Some(header) = line.strip_prefix(b">") {
+            let id_bytes = header.split(|c| c.is_ascii_whitespace()).next().unwrap_or_default();
+            current = Some(String::from_utf8_lossy(id_bytes).to_string());
+            report.records += 1;
+            line_width = 0;
+            short_line = false;
+            reported = false;
+            continue;
+        }
+        let Some(contig) = current.as_ref() else {
+            if line.iter().all(|c| c.is_ascii_whitespace()) {
+                continue;
+            }
+            return Err(format!("Expected a FASTA header before line {line_number}").into());
+        };
+        if reported {
+            continue;
+        }
+
+        let is_blank = line.iter().all(|&c| c == b'\n' || c == b'\r');
+        if line_width == 0 && !is_blank {
+            line_width = line.len();
+        } else if !is_blank && (short_line || line.len() > line_width) {
+            report.irregular.push(IrregularRecord {
+                contig: contig.clone(),
+                line_number,
+                line_width,
+                line_length: line.len()
+            });
+            reported = true;
+        } else if line.len() < line_width || (is_blank && line_width > 0) {
+            short_line = true;
+        }
+    }
+    Ok(report)
+}
+
+/// Checks the line lengths of a FASTA file, see `check_line_lengths(...)`
+/// # Arguments
+/// * `fasta_fn` - the FASTA filename; gzip-compressed files are decompressed first, although `samtools faidx` also requires bgzip
+/// # Errors
+/// * any file reading errors
+/// * see `check_line_lengths(...)`
+pub fn check_fasta_line_lengths(fasta_fn: &Path) -> Result<LineLengthReport, Box<dyn Error>> {
+    let fasta_file = std::fs::File::open(fasta_fn)?;
+    let reader = decompress(Box::new(BufReader::new(fasta_file)), is_gzip(fasta_fn), &Arc::default())?;
+    check_line_lengths(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_check_line_lengths() {
+        let report = check_fasta_line_lengths(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        assert_eq!(report, LineLengthReport { records: 2, irregular: vec![] });
+        assert!(report.is_uniform());
+
+        // trailing blank lines and a short last line are fine, a short line in the middle is not
+        let fasta = b">uniform\nACGT\nAC\n\n>long_last\nACG\nACGT\n>short_middle desc\nACGT\nAC\nACGT\n>blank_middle\nACGT\n\nACGT\n>mixed_endings\nACGT\nACGT\r\nAC\n";
+        let report = check_line_lengths(&fasta[..]).unwrap();
+        assert_eq!(report.records, 5);
+        let irregular: Vec<(&str, usize, usize, usize)> = report.irregular.iter()
+            .map(|r| (r.contig.as_str(), r.line_number, r.line_width, r.line_length))
+            .collect();
+        assert_eq!(irregular, vec![
+            ("long_last", 7, 4, 5),
+            ("short_middle", 11, 5, 5),
+            ("blank_middle", 15, 5, 5),
+            ("mixed_endings", 18, 5, 6)
+        ]);
+
+        assert!(check_line_lengths(&b"ACGT\n"[..]).is_err());
+    }
+}
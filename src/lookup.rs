@@ -0,0 +1,253 @@
+use crate::reference_genome::ReferenceGenome;
+use rustc_hash::FxHashSet as HashSet;
+use simple_error::SimpleError;
+
+/// Maximum number of suggestions to report for an unknown contig
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Computes the Levenshtein edit distance between two byte strings
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+    for (i, &ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Normalizes a contig name for loose comparisons: trims whitespace, lower-cases, removes any "chr" prefix, and treats "MT" as "M"
+pub(crate) fn loose_name(name: &str) -> String {
+    let lower = name.trim().to_ascii_lowercase();
+    let stripped = match lower.strip_prefix("chr") {
+        Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+        _ => lower
+    };
+    if stripped == "mt" { "m".to_string() } else { stripped }
+}
+
+/// Returns true if a character may appear in a SAM reference name, see `is_valid_sam_name(...)`
+/// # Arguments
+/// * `c` - the character
+/// * `first` - whether this is the first character of the name, which may not be `*` or `=`
+fn is_sam_name_char(c: char, first: bool) -> bool {
+    match c {
+        '*' | '=' => !first,
+        '\\' | ',' | '"' | '\'' | '(' | ')' | '[' | ']' | '{' | '}' | '<' | '>' => false,
+        _ => c.is_ascii_graphic()
+    }
+}
+
+/// Returns true if a name is a valid reference sequence name per the SAM specification (v1.6, section 1.2.1):
+/// printable ASCII without whitespace or any of `\ , " ' ( ) [ ] { } < >`, and not starting with `*` or `=`
+/// # Arguments
+/// * `name` - the contig name
+pub fn is_valid_sam_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().enumerate().all(|(i, c)| is_sam_name_char(c, i == 0))
+}
+
+/// Makes a name valid per the SAM specification by replacing every invalid character with `_`, see `is_valid_sam_name(...)`
+/// # Arguments
+/// * `name` - the contig name
+pub fn sanitize_sam_name(name: &str) -> String {
+    if name.is_empty() {
+        return "_".to_string();
+    }
+    name.chars().enumerate()
+        .map(|(i, c)| if is_sam_name_char(c, i == 0) { c } else { '_' })
+        .collect()
+}
+
+impl ReferenceGenome {
+    /// Renames every contig whose name is invalid per the SAM specification (see `is_valid_sam_name(...)`), which is necessary
+    /// before writing SAM headers or sequence dictionaries. Invalid characters are replaced with `_`, and a name that would collide
+    /// with another contig gets the first free suffix of `_2`, `_3`, etc.
+    /// # Returns
+    /// * the pairs of (old name, new name) that were applied, in load order; empty if every name was already valid
+    /// # Errors
+    /// * see `rename_contigs(...)`, although sanitized names are chosen to never fail
+    pub fn sanitize_names(&mut self) -> Result<Vec<(String, String)>, SimpleError> {
+        let mut used: HashSet<String> = self.contig_keys().iter().cloned().collect();
+        let mut renames: Vec<(String, String)> = vec![];
+        for contig in self.contig_keys().iter() {
+            if is_valid_sam_name(contig) {
+                continue;
+            }
+            let sanitized = sanitize_sam_name(contig);
+            let mut new_name = sanitized.clone();
+            let mut suffix = 2;
+            while used.contains(&new_name) {
+                new_name = format!("{sanitized}_{suffix}");
+                suffix += 1;
+            }
+            used.insert(new_name.clone());
+            renames.push((contig.clone(), new_name));
+        }
+        if !renames.is_empty() {
+            self.rename_contigs(&renames)?;
+        }
+        Ok(renames)
+    }
+
+    /// Finds the single contig whose name loosely matches a given name, or `None` if there are zero or multiple matches.
+    /// The loose names are indexed on the first call, so each lookup is a hash lookup rather than a scan of every contig.
+    /// # Arguments
+    /// * `name` - the contig name to match
+    pub(crate) fn resolve_loose_name(&self, name: &str) -> Option<&str> {
+        self.loose_contig_id(name).and_then(|id| self.contig_name(id))
+    }
+
+    /// Suggests contigs with names similar to a given name, best match first.
+    /// If any names only differ by whitespace, case, or a "chr" prefix, only those are returned.
+    /// Otherwise, names within a small edit distance are returned.
+    /// # Arguments
+    /// * `name` - the (likely misspelled) contig name
+    pub fn suggest_contig_names(&self, name: &str) -> Vec<&str> {
+        let loose_query = loose_name(name);
+        let max_distance = (name.len() / 4).max(1);
+        let mut scored: Vec<(usize, &str)> = self.contig_keys().iter()
+            .filter(|c| c.as_str() != name)
+            .filter_map(|c| {
+                if loose_name(c) == loose_query {
+                    Some((0, c.as_str()))
+                } else {
+                    let distance = edit_distance(name.as_bytes(), c.as_bytes());
+                    if distance <= max_distance { Some((distance, c.as_str())) } else { None }
+                }
+            })
+            .collect();
+        // stable sort keeps ties in load order
+        scored.sort_by_key(|&(distance, _)| distance);
+        if scored.first().map(|&(distance, _)| distance) == Some(0) {
+            // a loose match is almost certainly the intended contig, so do not dilute it
+            scored.retain(|&(distance, _)| distance == 0);
+        }
+        scored.into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, c)| c)
+            .collect()
+    }
+
+    /// Builds the error message for a contig that is not in the reference genome, including any suggestions,
+    /// so tools built on the library report unknown contigs the same way it does
+    /// # Arguments
+    /// * `name` - the contig name that was not found
+    pub fn missing_contig_message(&self, name: &str) -> String {
+        let suggestions = self.suggest_contig_names(name);
+        if suggestions.is_empty() {
+            format!("Contig key {name:?} is not in the reference genome")
+        } else {
+            let quoted: Vec<String> = suggestions.iter().map(|s| format!("{s:?}")).collect();
+            format!("Contig key {name:?} is not in the reference genome; did you mean {}?", quoted.join(" or "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance(b"", b""), 0);
+        assert_eq!(edit_distance(b"chr1", b"chr1"), 0);
+        assert_eq!(edit_distance(b"chr1", b"chr11"), 1);
+        assert_eq!(edit_distance(b"kitten", b"sitting"), 3);
+        assert_eq!(edit_distance(b"", b"abc"), 3);
+    }
+
+    #[test]
+    fn test_suggest_contig_names() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        for contig in ["chr1", "chr2", "chr11", "chrX", "scaffold_1234"] {
+            reference_genome.add_contig(contig.to_string(), "A").unwrap();
+        }
+
+        assert_eq!(reference_genome.suggest_contig_names("chr1 "), vec!["chr1"]);
+        assert_eq!(reference_genome.suggest_contig_names("X")[0], "chrX");
+        assert_eq!(reference_genome.suggest_contig_names("Chr2")[0], "chr2");
+        assert_eq!(reference_genome.suggest_contig_names("chr12"), vec!["chr1", "chr2", "chr11"]);
+        assert_eq!(reference_genome.suggest_contig_names("scaffold_1243"), vec!["scaffold_1234"]);
+        assert!(reference_genome.suggest_contig_names("unrelated").is_empty());
+
+        assert_eq!(
+            reference_genome.missing_contig_message("chrx"),
+            "Contig key \"chrx\" is not in the reference genome; did you mean \"chrX\"?"
+        );
+        assert_eq!(
+            reference_genome.missing_contig_message("unrelated"),
+            "Contig key \"unrelated\" is not in the reference genome"
+        );
+    }
+
+    #[test]
+    fn test_normalized_lookup() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        for contig in ["chr1", "chrM", "2", "chr2", "scaffold_1"] {
+            reference_genome.add_contig(contig.to_string(), "A").unwrap();
+        }
+
+        // disabled by default
+        assert!(!reference_genome.normalized_lookup());
+        assert_eq!(reference_genome.resolve_contig_name("chr1"), Some("chr1"));
+        assert_eq!(reference_genome.resolve_contig_name("1"), None);
+        assert_eq!(reference_genome.get("Chr1"), None);
+
+        reference_genome.set_normalized_lookup(true);
+        for name in ["chr1", "Chr1", "1", "CHR1", " chr1"] {
+            assert_eq!(reference_genome.resolve_contig_name(name), Some("chr1"), "{name}");
+        }
+        assert_eq!(reference_genome.resolve_contig_name("MT"), Some("chrM"));
+        assert_eq!(reference_genome.resolve_contig_name("Scaffold_1"), Some("scaffold_1"));
+        assert_eq!(reference_genome.get_full_chromosome("1"), b"A");
+        assert_eq!(&reference_genome["chrm"], b"A");
+        // full-contig sweeps resolve names the same way as slices
+        assert_eq!(reference_genome.try_sequence_unkept("1").unwrap(), &b"A"[..]);
+        assert_eq!(reference_genome.poly_a_tracts("Chr1", 1).unwrap(), reference_genome.poly_a_tracts("chr1", 1).unwrap());
+        assert!(reference_genome.try_sequence_unkept("chr3").is_err());
+
+        // exact matches always win, but "Chr2" is ambiguous
+        assert_eq!(reference_genome.resolve_contig_name("2"), Some("2"));
+        assert_eq!(reference_genome.resolve_contig_name("Chr2"), None);
+        assert_eq!(reference_genome.get("Chr2"), None);
+    }
+
+    #[test]
+    fn test_sanitize_names() {
+        assert!(is_valid_sam_name("chr1"));
+        assert!(is_valid_sam_name("HLA-A*01:01:01:01"));
+        assert!(is_valid_sam_name("chr1=alt"));
+        assert!(!is_valid_sam_name(""));
+        assert!(!is_valid_sam_name("*chr1"));
+        assert!(!is_valid_sam_name("=chr1"));
+        assert!(!is_valid_sam_name("chr 1"));
+        assert!(!is_valid_sam_name("chr1,2"));
+        assert!(!is_valid_sam_name("contig\u{e9}"));
+        assert_eq!(sanitize_sam_name("*chr1 (alt)"), "_chr1__alt_");
+        assert_eq!(sanitize_sam_name("=x*"), "_x*");
+
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        for contig in ["chr1", "chr 2", "chr_2", "<chr3>", "chrX=Y"] {
+            reference_genome.add_contig(contig.to_string(), "A").unwrap();
+        }
+        let renames = reference_genome.sanitize_names().unwrap();
+        assert_eq!(renames, vec![
+            ("chr 2".to_string(), "chr_2_2".to_string()),
+            ("<chr3>".to_string(), "_chr3_".to_string())
+        ]);
+        assert_eq!(reference_genome.contig_keys(), &["chr1", "chr_2_2", "chr_2", "_chr3_", "chrX=Y"]);
+        assert!(reference_genome.sanitize_names().unwrap().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "did you mean \"chr1\"")]
+    fn test_missing_contig_panic() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), "A").unwrap();
+        reference_genome.get_slice("chr1 ", 0, 1);
+    }
+}
@@ -0,0 +1,129 @@
+#[cfg(unix)]
+use crate::advice::AccessPattern;
+use crate::alphabet::Alphabet;
+use crate::lazy::{ContigLoader, LazyContig};
+use memchr::{memchr, memchr_iter, memmem};
+#[cfg(unix)]
+use memmap2::Advice;
+use memmap2::Mmap;
+use simple_error::bail;
+use std::error::Error;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A memory-mapped FASTA file along with the location of each record's sequence
+struct MappedFasta {
+    /// The mapped FASTA file
+    mmap: Mmap,
+    /// The name of each record
+    names: Vec<String>,
+    /// The byte range of the sequence lines of each record, including line endings
+    ranges: Vec<Range<usize>>
+}
+
+impl MappedFasta {
+    /// Iterates over the sequence characters of a record, skipping line endings
+    fn raw_symbols(&self, index: usize) -> impl Iterator<Item = u8> + '_ {
+        self.mmap[self.ranges[index].clone()].iter()
+            .copied()
+            .filter(|&c| c != b'\n' && c != b'\r')
+    }
+}
+
+impl ContigLoader for MappedFasta {
+    fn contig_name(&self, index: usize) -> &str {
+        &self.names[index]
+    }
+
+    fn contig_length(&self, index: usize) -> usize {
+        let raw_bytes = &self.mmap[self.ranges[index].clone()];
+        raw_bytes.len() - memchr_iter(b'\n', raw_bytes).count() - memchr_iter(b'\r', raw_bytes).count()
+    }
+
+    fn load_contig(&self, index: usize) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut sequence: Vec<u8> = Vec::with_capacity(self.ranges[index].len());
+        sequence.extend(self.raw_symbols(index));
+        Ok(sequence)
+    }
+
+    #[cfg(unix)]
+    fn advise(&self, pattern: AccessPattern) -> std::io::Result<bool> {
+        let advice = match pattern {
+            AccessPattern::Normal => Advice::Normal,
+            AccessPattern::Sequential => Advice::Sequential,
+            AccessPattern::Random => Advice::Random
+        };
+        self.mmap.advise(advice)?;
+        Ok(true)
+    }
+
+    #[cfg(unix)]
+    fn will_need(&self, index: usize) -> std::io::Result<bool> {
+        let range = &self.ranges[index];
+        if !range.is_empty() {
+            self.mmap.advise_range(Advice::WillNeed, range.start, range.len())?;
+        }
+        Ok(true)
+    }
+}
+
+/// Memory-maps a plain-text FASTA file and indexes the byte range of each record without decoding any sequence
+/// # Arguments
+/// * `fasta_fn` - the FASTA filename, must not be compressed
+/// * `uppercase` - if true, sequences are upper-cased when decoded
+/// * `contig_filter` - if provided, only contigs where this returns true are kept
+/// * `alphabet` - sequences are checked against this alphabet while indexing
+/// # Errors
+/// * if the file cannot be opened or mapped
+/// * if the file is not a FASTA file or a sequence fails validation
+pub(crate) fn index_mapped_fasta(
+    fasta_fn: &Path, uppercase: bool, contig_filter: Option<&dyn Fn(&str) -> bool>, alphabet: Alphabet
+) -> Result<Vec<(String, LazyContig)>, Box<dyn std::error::Error>> {
+    let fasta_file = std::fs::File::open(fasta_fn)?;
+    // SAFETY: the mapping is read-only; modifying the file while it is mapped is documented as unsupported on `Backend::Mmap`
+    let mmap = unsafe { Mmap::map(&fasta_file)? };
+    let data: &[u8] = &mmap;
+
+    let mut position = data.iter().position(|&c| c != b'\n' && c != b'\r').unwrap_or(data.len());
+    if position < data.len() && data[position] != b'>' {
+        bail!("Expected a FASTA header at byte {position} of {fasta_fn:?}");
+    }
+
+    let mut names: Vec<String> = vec![];
+    let mut ranges: Vec<Range<usize>> = vec![];
+    while position < data.len() {
+        let header_end = memchr(b'\n', &data[position..]).map(|i| position + i).unwrap_or(data.len());
+        let header = std::str::from_utf8(&data[position + 1..header_end])?;
+        let seq_id = header.split_whitespace().next().unwrap_or_default().to_string();
+
+        let start = (header_end + 1).min(data.len());
+        let end = if data.get(start) == Some(&b'>') {
+            // empty record, the next header immediately follows
+            start
+        } else {
+            memmem::find(&data[start..], b"\n>").map(|i| start + i + 1).unwrap_or(data.len())
+        };
+        position = end;
+
+        if contig_filter.map(|f| f(&seq_id)).unwrap_or(true) {
+            names.push(seq_id);
+            ranges.push(start..end);
+        }
+    }
+
+    let mapped_fasta = Arc::new(MappedFasta {
+        mmap,
+        names,
+        ranges
+    });
+    for (index, seq_id) in mapped_fasta.names.iter().enumerate() {
+        alphabet.validate_symbols(seq_id, mapped_fasta.raw_symbols(index))?;
+    }
+
+    let loader: Arc<dyn ContigLoader> = mapped_fasta.clone();
+    Ok(mapped_fasta.names.iter()
+        .enumerate()
+        .map(|(index, seq_id)| (seq_id.clone(), LazyContig::new(loader.clone(), index, uppercase)))
+        .collect())
+}
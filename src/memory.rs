@@ -0,0 +1,83 @@
+use crate::reference_genome::ReferenceGenome;
+
+/// Heap memory used by a single contig
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContigMemoryUsage {
+    /// The contig name
+    pub contig: String,
+    /// Heap bytes used by the sequence; 0 for lazily loaded contigs that have not been accessed yet
+    pub sequence_bytes: usize
+}
+
+/// Heap memory used by a reference genome, from `ReferenceGenome::memory_usage()`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Per-contig usage, in load order
+    pub contigs: Vec<ContigMemoryUsage>,
+    /// Heap bytes used by contig names and lookup structures, including spare capacity
+    pub index_bytes: usize
+}
+
+impl MemoryUsage {
+    /// Total heap bytes used by all sequences
+    pub fn sequence_bytes(&self) -> usize {
+        self.contigs.iter().map(|c| c.sequence_bytes).sum()
+    }
+
+    /// Total heap bytes used by the genome
+    pub fn total_bytes(&self) -> usize {
+        self.sequence_bytes() + self.index_bytes
+    }
+}
+
+impl ReferenceGenome {
+    /// Reports the heap memory used by the genome, per-contig and in total.
+    /// Memory-mapped pages are not heap memory and are not included.
+    /// Sequences shared with other genomes (e.g. from `subset(...)`) are counted in each genome that references them.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let contigs = self.contig_keys().iter()
+            .map(|c| ContigMemoryUsage {
+                contig: c.clone(),
+                sequence_bytes: self.contig_sequence(c).map(|s| s.heap_bytes()).unwrap_or_default()
+            })
+            .collect();
+        MemoryUsage {
+            contigs,
+            index_bytes: self.index_heap_bytes()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{Backend, ReferenceGenomeBuilder};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_memory_usage() {
+        let reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        let usage = reference_genome.memory_usage();
+        assert_eq!(usage.contigs, vec![
+            ContigMemoryUsage { contig: "chr1".to_string(), sequence_bytes: 8 },
+            ContigMemoryUsage { contig: "chr2".to_string(), sequence_bytes: 8 }
+        ]);
+        assert_eq!(usage.sequence_bytes(), 16);
+        assert!(usage.index_bytes > 0);
+        assert_eq!(usage.total_bytes(), 16 + usage.index_bytes);
+    }
+
+    #[test]
+    fn test_memory_usage_lazy() {
+        let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .backend(Backend::Mmap)
+            .build()
+            .unwrap();
+        assert_eq!(reference_genome.memory_usage().sequence_bytes(), 0);
+
+        reference_genome.get_full_chromosome("chr2");
+        let usage = reference_genome.memory_usage();
+        assert_eq!(usage.contigs[0].sequence_bytes, 0);
+        assert_eq!(usage.contigs[1].sequence_bytes, 8);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::assembly::KnownAssembly;
+use crate::builder::{decompress, is_gzip};
+use crate::reference_genome::ReferenceGenome;
+use simple_error::bail;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Descriptive metadata of an assembly, written into generated sequence dictionaries and VCF headers
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AssemblyMetadata {
+    /// The species, e.g. "Homo sapiens"
+    pub species: Option<String>,
+    /// The assembly name, e.g. "GRCh38.p14"
+    pub assembly_name: Option<String>,
+    /// The organization or database that released the assembly, e.g. "Genome Reference Consortium"
+    pub source: Option<String>,
+    /// The release date as given by the source, e.g. "2013-12-17"
+    pub release_date: Option<String>,
+    /// The NCBI taxonomy ID, e.g. 9606
+    pub taxonomy_id: Option<u32>
+}
+
+impl AssemblyMetadata {
+    /// Returns true if no field is set
+    pub fn is_empty(&self) -> bool {
+        *self == AssemblyMetadata::default()
+    }
+
+    /// Reads the header of an NCBI assembly report (`*_assembly_report.txt`), using the assembly name, organism name
+    /// (without the common name in parentheses), submitter, date, and taxid; other lines are ignored
+    /// # Arguments
+    /// * `reader` - the uncompressed report
+    /// # Errors
+    /// * any reading errors
+    /// * if the taxid is not a number, or the report has none of the fields
+    pub fn from_assembly_report_reader<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut metadata = AssemblyMetadata::default();
+        for line in reader.lines() {
+            let line = line?;
+            let Some(header) = line.strip_prefix('#') else {
+                continue;
+            };
+            let Some((key, value)) = header.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match key.trim() {
+                "Assembly name" => metadata.assembly_name = Some(value.to_string()),
+                "Organism name" => {
+                    let species = value.split_once(" (").map(|(species, _)| species).unwrap_or(value);
+                    metadata.species = Some(species.to_string());
+                },
+                "Submitter" => metadata.source = Some(value.to_string()),
+                "Date" => metadata.release_date = Some(value.to_string()),
+                "Taxid" => match value.parse() {
+                    Ok(taxonomy_id) => metadata.taxonomy_id = Some(taxonomy_id),
+                    Err(_) => bail!("Invalid taxid in the assembly report: {:?}", value)
+                },
+                _ => {}
+            }
+        }
+        if metadata.is_empty() {
+            bail!("No assembly metadata found in the assembly report header");
+        }
+        Ok(metadata)
+    }
+
+    /// Reads the header of an NCBI assembly report, see `from_assembly_report_reader(...)`
+    /// # Arguments
+    /// * `report_fn` - the report filename; gzip compression is detected from a `.gz` extension
+    /// # Errors
+    /// * any file reading errors
+    /// * see `from_assembly_report_reader(...)`
+    pub fn from_assembly_report(report_fn: &Path) -> Result<Self, Box<dyn Error>> {
+        let report_file = std::fs::File::open(report_fn)?;
+        let reader = decompress(Box::new(BufReader::new(report_file)), is_gzip(report_fn), &Arc::default())?;
+        Self::from_assembly_report_reader(reader)
+    }
+}
+
+impl From<KnownAssembly> for AssemblyMetadata {
+    fn from(assembly: KnownAssembly) -> Self {
+        let (species, assembly_name, source, release_date, taxonomy_id) = match assembly {
+            KnownAssembly::GRCh38 => ("Homo sapiens", "GRCh38", "Genome Reference Consortium", "2013-12-17", 9606),
+            KnownAssembly::GRCh37 => ("Homo sapiens", "GRCh37", "Genome Reference Consortium", "2009-02-27", 9606),
+            KnownAssembly::Hg19 => ("Homo sapiens", "hg19", "UCSC", "2009-02-27", 9606),
+            KnownAssembly::T2tChm13 => ("Homo sapiens", "T2T-CHM13v2.0", "T2T Consortium", "2022-01-24", 9606),
+            KnownAssembly::GRCm39 => ("Mus musculus", "GRCm39", "Genome Reference Consortium", "2020-06-24", 10090)
+        };
+        Self {
+            species: Some(species.to_string()),
+            assembly_name: Some(assembly_name.to_string()),
+            source: Some(source.to_string()),
+            release_date: Some(release_date.to_string()),
+            taxonomy_id: Some(taxonomy_id)
+        }
+    }
+}
+
+impl ReferenceGenome {
+    /// The file URI written to the `UR` tag of a dictionary and the `##reference` line of a VCF header, or `None` for genomes without a file
+    fn reference_uri(&self) -> Option<String> {
+        if self.filename().as_os_str().is_empty() {
+            return None;
+        }
+        let path = std::fs::canonicalize(self.filename()).unwrap_or_else(|_| self.filename().to_path_buf());
+        Some(format!("file://{}", path.display()))
+    }
+
+    /// Writes a Picard-style sequence dictionary (`.dict`): an `@HD` line, then one `@SQ` line per contig in load order with the
+    /// `SN`, `LN`, `M5`, and `UR` tags, plus `AS` and `SP` from the assembly metadata when set.
+    /// Digests come from the cache of `contig_digests(...)`, which reads lazily loaded contigs without keeping them in memory.
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// # Errors
+    /// * if a contig was unloaded or fails to load
+    /// * any errors from the underlying writer
+    pub fn write_dict<W: Write>(&self, mut writer: W) -> Result<(), Box<dyn Error>> {
+        let metadata = self.assembly_metadata();
+        let uri = self.reference_uri();
+        writeln!(writer, "@HD\tVN:1.6")?;
+        for contig in self.contig_keys().iter() {
+            let length = self.contig_length(contig).unwrap_or_default();
+            write!(writer, "@SQ\tSN:{}\tLN:{}\tM5:{}", contig, length, self.try_contig_digests(contig)?.md5)?;
+            if let Some(uri) = uri.as_ref() {
+                write!(writer, "\tUR:{uri}")?;
+            }
+            if let Some(assembly_name) = metadata.assembly_name.as_ref() {
+                write!(writer, "\tAS:{assembly_name}")?;
+            }
+            if let Some(species) = metadata.species.as_ref() {
+                write!(writer, "\tSP:{species}")?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the reference lines of a VCF header: a `##reference` line if the genome was loaded from a file, then one `##contig`
+    /// line per contig in load order with its length and the `assembly`, `species`, and `taxonomy` fields from the assembly metadata when set.
+    /// The `##fileformat` line and the column header are left to the caller.
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// * `with_md5` - if true, adds the `md5` field, which reads every contig
+    /// # Errors
+    /// * if `with_md5` is set and a contig was unloaded or fails to load
+    /// * any errors from the underlying writer
+    pub fn write_vcf_header<W: Write>(&self, mut writer: W, with_md5: bool) -> Result<(), Box<dyn Error>> {
+        let metadata = self.assembly_metadata();
+        if let Some(uri) = self.reference_uri() {
+            writeln!(writer, "##reference={uri}")?;
+        }
+        for contig in self.contig_keys().iter() {
+            write!(writer, "##contig=<ID={},length={}", contig, self.contig_length(contig).unwrap_or_default())?;
+            if let Some(assembly_name) = metadata.assembly_name.as_ref() {
+                write!(writer, ",assembly={assembly_name}")?;
+            }
+            if with_md5 {
+                write!(writer, ",md5={}", self.try_contig_digests(contig)?.md5)?;
+            }
+            if let Some(species) = metadata.species.as_ref() {
+                write!(writer, ",species=\"{species}\"")?;
+            }
+            if let Some(taxonomy_id) = metadata.taxonomy_id {
+                write!(writer, ",taxonomy={taxonomy_id}")?;
+            }
+            writeln!(writer, ">")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_assembly_report() {
+        let report = b"# Assembly name:  GRCh38.p14\n# Organism name:  Homo sapiens (human)\n# Taxid:          9606\n\
+            # Submitter:      Genome Reference Consortium\n# Date:           2022-02-03\n#\n\
+            # Sequence-Name\tSequence-Role\n1\tassembled-molecule\n";
+        let metadata = AssemblyMetadata::from_assembly_report_reader(&report[..]).unwrap();
+        assert_eq!(metadata, AssemblyMetadata {
+            species: Some("Homo sapiens".to_string()),
+            assembly_name: Some("GRCh38.p14".to_string()),
+            source: Some("Genome Reference Consortium".to_string()),
+            release_date: Some("2022-02-03".to_string()),
+            taxonomy_id: Some(9606)
+        });
+        assert!(AssemblyMetadata::from_assembly_report_reader(&b"# Taxid: human\n"[..]).is_err());
+        assert!(AssemblyMetadata::from_assembly_report_reader(&b"1\tassembled-molecule\n"[..]).is_err());
+        assert_eq!(AssemblyMetadata::from(KnownAssembly::GRCm39).taxonomy_id, Some(10090));
+    }
+
+    #[test]
+    fn test_write_headers() {
+        let mut reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        reference_genome.set_assembly_metadata(AssemblyMetadata {
+            species: Some("Homo sapiens".to_string()),
+            assembly_name: Some("test".to_string()),
+            taxonomy_id: Some(9606),
+            ..Default::default()
+        });
+
+        let mut dict: Vec<u8> = vec![];
+        reference_genome.write_dict(&mut dict).unwrap();
+        let dict = String::from_utf8(dict).unwrap();
+        let lines: Vec<&str> = dict.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("@SQ\tSN:chr1\tLN:8\tM5:"));
+        assert!(lines[1].contains("\tUR:file:///") && lines[1].ends_with("\tAS:test\tSP:Homo sapiens"));
+        // the digests match the checked-in dictionary
+        let expected = std::fs::read_to_string("./test_data/test_reference.dict").unwrap();
+        let m5 = |line: &str| line.split('\t').find(|f| f.starts_with("M5:")).map(|f| f.to_string());
+        assert_eq!(m5(lines[2]), expected.lines().filter_map(m5).nth(1));
+
+        let mut vcf: Vec<u8> = vec![];
+        reference_genome.subset(|c| c == "chr2").write_vcf_header(&mut vcf, false).unwrap();
+        let vcf = String::from_utf8(vcf).unwrap();
+        assert!(vcf.starts_with("##reference=file:///"));
+        assert!(vcf.ends_with("##contig=<ID=chr2,length=8,assembly=test,species=\"Homo sapiens\",taxonomy=9606>\n"));
+
+        let empty = ReferenceGenome::from_fasta_bytes(b">chr1\nACGT\n").unwrap();
+        let mut vcf: Vec<u8> = vec![];
+        empty.write_vcf_header(&mut vcf, true).unwrap();
+        assert_eq!(vcf, b"##contig=<ID=chr1,length=4,md5=f1f8f4bf413b16ad135722aa4591043e>\n");
+    }
+}
@@ -0,0 +1,146 @@
+use crate::alphabet::complement;
+use crate::reference_genome::ReferenceGenome;
+use simple_error::SimpleError;
+use std::io::Write;
+
+/// The sequence context of a cytosine, which determines the methyltransferases that act on it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MethylationContext {
+    /// C followed by G
+    CpG,
+    /// C, then a non-G base, then G
+    Chg,
+    /// C followed by two non-G bases
+    Chh
+}
+
+impl MethylationContext {
+    /// The conventional name of the context: "CpG", "CHG", or "CHH"
+    pub fn name(&self) -> &'static str {
+        match self {
+            MethylationContext::CpG => "CpG",
+            MethylationContext::Chg => "CHG",
+            MethylationContext::Chh => "CHH"
+        }
+    }
+}
+
+/// A cytosine on either strand, from `scan_methylation_sites(...)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MethylationSite {
+    /// The 0-based position of the C, or of the G on the forward strand for a reverse strand C
+    pub position: usize,
+    /// True if the cytosine is on the reverse strand
+    pub reverse: bool,
+    /// The context of the cytosine
+    pub context: MethylationContext
+}
+
+/// Classifies a cytosine from the next two bases on its own strand, or `None` if either base is undetermined (e.g. N)
+fn classify(next: u8, after_next: Option<u8>) -> Option<MethylationContext> {
+    let is_h = |c: u8| matches!(c, b'A' | b'C' | b'T');
+    match (next.to_ascii_uppercase(), after_next.map(|c| c.to_ascii_uppercase())) {
+        (b'G', _) => Some(MethylationContext::CpG),
+        (n, Some(b'G')) if is_h(n) => Some(MethylationContext::Chg),
+        (n, Some(a)) if is_h(n) && is_h(a) => Some(MethylationContext::Chh),
+        _ => None
+    }
+}
+
+/// Finds every cytosine on both strands of a sequence and classifies its methylation context, ignoring case.
+/// Cytosines whose context is cut off by the sequence end or contains an ambiguous base are skipped.
+/// # Arguments
+/// * `sequence` - the forward strand sequence
+/// # Returns
+/// * the sites in position order; a CpG is reported once per strand
+pub fn scan_methylation_sites(sequence: &[u8]) -> Vec<MethylationSite> {
+    let mut sites: Vec<MethylationSite> = vec![];
+    for (position, symbol) in sequence.iter().enumerate() {
+        let (reverse, context) = match symbol.to_ascii_uppercase() {
+            b'C' => match sequence.get(position + 1) {
+                Some(&next) => (false, classify(next, sequence.get(position + 2).copied())),
+                None => continue
+            },
+            b'G' if position > 0 => {
+                let after_next = position.checked_sub(2).map(|i| complement(sequence[i]));
+                (true, classify(complement(sequence[position - 1]), after_next))
+            },
+            _ => continue
+        };
+        if let Some(context) = context {
+            sites.push(MethylationSite { position, reverse, context });
+        }
+    }
+    sites
+}
+
+impl ReferenceGenome {
+    /// Classifies every cytosine of a contig by methylation context, see `scan_methylation_sites(...)`
+    /// # Arguments
+    /// * `chromosome` - the contig name
+    /// # Errors
+    /// * if the contig is not in the reference genome, was unloaded, or fails to load
+    pub fn methylation_sites(&self, chromosome: &str) -> Result<Vec<MethylationSite>, SimpleError> {
+        Ok(scan_methylation_sites(&self.try_sequence_unkept(chromosome)?))
+    }
+
+    /// Writes the cytosines of one methylation context across all contigs as BED6: contig, start, end, context name, score 0, and strand.
+    /// Lazily loaded contigs are read once and are not kept in memory.
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// * `context` - the context to write; call once per context for one file each
+    /// # Returns
+    /// * the number of sites written
+    /// # Errors
+    /// * if a contig was unloaded or fails to load
+    /// * any errors from the underlying writer
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(context = context.name())))]
+    pub fn write_methylation_bed<W: Write>(&self, mut writer: W, context: MethylationContext) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut written = 0;
+        for contig in self.contig_keys().iter() {
+            for site in self.methylation_sites(contig)?.iter().filter(|s| s.context == context) {
+                let strand = if site.reverse { '-' } else { '+' };
+                writeln!(writer, "{}\t{}\t{}\t{}\t0\t{}", contig, site.position, site.position + 1, context.name(), strand)?;
+                written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_methylation_sites() {
+        let sites: Vec<(usize, bool, &str)> = scan_methylation_sites(b"ACGTCAGCTTncGc")
+            .iter()
+            .map(|s| (s.position, s.reverse, s.context.name()))
+            .collect();
+        assert_eq!(sites, vec![
+            (1, false, "CpG"),
+            (2, true, "CpG"),
+            // C at 4 is followed by A then G
+            (4, false, "CHG"),
+            // G at 6 is C at 6 on the reverse strand, followed by T (complement of A) then G (complement of C)
+            (6, true, "CHG"),
+            (7, false, "CHH"),
+            (11, false, "CpG"),
+            (12, true, "CpG")
+        ]);
+    }
+
+    #[test]
+    fn test_write_methylation_bed() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGTCAGCTT\n>chr2\nCCG\n").unwrap();
+        let mut bed: Vec<u8> = vec![];
+        assert_eq!(reference_genome.write_methylation_bed(&mut bed, MethylationContext::CpG).unwrap(), 4);
+        assert_eq!(String::from_utf8(bed).unwrap(), "chr1\t1\t2\tCpG\t0\t+\nchr1\t2\t3\tCpG\t0\t-\nchr2\t1\t2\tCpG\t0\t+\nchr2\t2\t3\tCpG\t0\t-\n");
+
+        let mut bed: Vec<u8> = vec![];
+        assert_eq!(reference_genome.write_methylation_bed(&mut bed, MethylationContext::Chh).unwrap(), 1);
+        assert!(reference_genome.methylation_sites("chr3").is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::reference_genome::ReferenceGenome;
+use std::io::{BufRead, Read};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Performance of the load that produced a genome, from `ReferenceGenome::load_metrics()`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoadMetrics {
+    /// Wall-clock time of the whole load, including index reads and digest verification
+    pub wall_time: Duration,
+    /// Bytes read from the input before decompression; lazy backends only count what indexing scans (the whole file for `Backend::Mmap`)
+    pub bytes_read: u64,
+    /// Time spent in the gzip decoder, including reads of the compressed input; zero for uncompressed input
+    pub decompression_time: Duration,
+    /// The number of contigs loaded
+    pub contig_count: usize,
+    /// Estimated peak heap bytes during the load: the retained sequences plus the largest record buffer being parsed,
+    /// or the final heap usage if that is larger. Allocations inside parser backends are not included.
+    pub peak_memory_estimate: usize
+}
+
+/// Counters shared by the readers and record handlers of a single load
+#[derive(Debug, Default)]
+pub(crate) struct LoadCounters {
+    /// Bytes read from the raw input
+    bytes_read: AtomicU64,
+    /// Nanoseconds spent in decompression
+    decompression_nanos: AtomicU64,
+    /// Heap bytes of the sequences kept so far
+    retained_bytes: AtomicUsize,
+    /// The highest estimated heap bytes so far
+    peak_bytes: AtomicUsize
+}
+
+impl LoadCounters {
+    /// Records bytes read from the input outside of a `CountingReader`, such as a memory-mapped file
+    pub(crate) fn add_bytes_read(&self, bytes: u64) {
+        self.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Records a record buffer that is about to be stored, updating the peak estimate
+    /// # Arguments
+    /// * `buffer_bytes` - the capacity of the parsed record buffer
+    /// * `stored_bytes` - the heap bytes the record keeps once it is stored
+    pub(crate) fn add_record(&self, buffer_bytes: usize, stored_bytes: usize) {
+        let retained = self.retained_bytes.load(Ordering::Relaxed);
+        self.peak_bytes.fetch_max(retained + buffer_bytes.max(stored_bytes), Ordering::Relaxed);
+        self.retained_bytes.fetch_add(stored_bytes, Ordering::Relaxed);
+    }
+
+    /// Assembles the final metrics for a loaded genome
+    /// # Arguments
+    /// * `start` - when the load started
+    /// * `reference_genome` - the loaded genome
+    pub(crate) fn finish(&self, start: Instant, reference_genome: &ReferenceGenome) -> LoadMetrics {
+        let metrics = LoadMetrics {
+            wall_time: start.elapsed(),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            decompression_time: Duration::from_nanos(self.decompression_nanos.load(Ordering::Relaxed)),
+            contig_count: reference_genome.contig_keys().len(),
+            peak_memory_estimate: self.peak_bytes.load(Ordering::Relaxed).max(reference_genome.memory_usage().total_bytes())
+        };
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            contig_count = metrics.contig_count,
+            bytes_read = metrics.bytes_read,
+            wall_time_ms = metrics.wall_time.as_millis() as u64,
+            decompression_time_ms = metrics.decompression_time.as_millis() as u64,
+            peak_memory_estimate = metrics.peak_memory_estimate,
+            "Finished loading reference genome"
+        );
+        metrics
+    }
+}
+
+/// Counts the bytes read through a buffered reader
+pub(crate) struct CountingReader<R> {
+    /// The wrapped reader
+    inner: R,
+    /// Receives the byte count
+    counters: Arc<LoadCounters>
+}
+
+impl<R> CountingReader<R> {
+    /// Wraps a reader
+    pub(crate) fn new(inner: R, counters: Arc<LoadCounters>) -> Self {
+        Self { inner, counters }
+    }
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.counters.add_bytes_read(count as u64);
+        Ok(count)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.counters.add_bytes_read(amount as u64);
+        self.inner.consume(amount);
+    }
+}
+
+/// Accumulates the time spent reading from a decoder
+#[cfg(feature = "gzip")]
+pub(crate) struct TimedReader<R> {
+    /// The wrapped decoder
+    inner: R,
+    /// Receives the elapsed time
+    counters: Arc<LoadCounters>
+}
+
+#[cfg(feature = "gzip")]
+impl<R> TimedReader<R> {
+    /// Wraps a decoder
+    pub(crate) fn new(inner: R, counters: Arc<LoadCounters>) -> Self {
+        Self { inner, counters }
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl<R: Read> Read for TimedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let start = Instant::now();
+        let result = self.inner.read(buf);
+        self.counters.decompression_nanos.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{Backend, ReferenceGenomeBuilder};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_load_metrics() {
+        let fasta_fn = PathBuf::from("./test_data/test_reference.fa");
+        let file_size = std::fs::metadata(&fasta_fn).unwrap().len();
+        let reference_genome = ReferenceGenome::from_fasta(&fasta_fn).unwrap();
+        let metrics = reference_genome.load_metrics().unwrap();
+        assert_eq!(metrics.contig_count, 2);
+        assert_eq!(metrics.bytes_read, file_size);
+        assert_eq!(metrics.decompression_time, Duration::ZERO);
+        assert!(metrics.peak_memory_estimate >= 16);
+
+        let mapped = ReferenceGenomeBuilder::new(&fasta_fn)
+            .backend(Backend::Mmap)
+            .build()
+            .unwrap();
+        assert_eq!(mapped.load_metrics().unwrap().bytes_read, file_size);
+
+        // clones keep the metrics, genomes built by hand have none
+        assert_eq!(reference_genome.clone().load_metrics(), Some(metrics));
+        assert!(ReferenceGenome::empty_reference().load_metrics().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn test_load_metrics_gzip() {
+        let fasta_fn = PathBuf::from("./test_data/test_reference.fa.gz");
+        let reference_genome = ReferenceGenome::from_fasta(&fasta_fn).unwrap();
+        let metrics = reference_genome.load_metrics().unwrap();
+        assert_eq!(metrics.bytes_read, std::fs::metadata(&fasta_fn).unwrap().len());
+        assert!(metrics.decompression_time > Duration::ZERO);
+
+        let in_memory = ReferenceGenomeBuilder::from_bytes(std::fs::read(&fasta_fn).unwrap()).build().unwrap();
+        assert_eq!(in_memory.load_metrics().unwrap().bytes_read, metrics.bytes_read);
+    }
+}
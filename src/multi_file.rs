@@ -0,0 +1,79 @@
+use crate::reference_genome::natural_cmp;
+use glob::Pattern;
+use simple_error::bail;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// File name suffixes that are recognized as FASTA files when loading a directory
+const FASTA_SUFFIXES: [&str; 8] = [".fa", ".fasta", ".fna", ".fas", ".fa.gz", ".fasta.gz", ".fna.gz", ".fas.gz"];
+
+/// Expands a FASTA path into the list of files to load.
+/// A directory expands to every FASTA file it contains (by extension), and a path whose final component contains
+/// `*`, `?`, or `[...]` wildcards expands to every matching file, see `glob::Pattern`. Files are sorted naturally by name (e.g. "chr2.fa" before "chr10.fa"),
+/// so the contig order is deterministic. Any other path is returned as-is.
+/// # Arguments
+/// * `fasta_fn` - a FASTA file, a directory of FASTA files, or a wildcard pattern
+/// # Errors
+/// * if the directory cannot be read
+/// * if the wildcard pattern is invalid
+/// * if a directory or pattern matches no files
+pub(crate) fn expand_fasta_paths(fasta_fn: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let (directory, pattern): (PathBuf, Option<Pattern>) = if fasta_fn.is_dir() {
+        (fasta_fn.to_path_buf(), None)
+    } else {
+        let file_name = fasta_fn.file_name().unwrap_or_default().to_string_lossy();
+        if !file_name.contains(['*', '?', '[']) {
+            return Ok(vec![fasta_fn.to_path_buf()]);
+        }
+        let directory = match fasta_fn.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from(".")
+        };
+        (directory, Some(Pattern::new(&file_name)?))
+    };
+
+    let mut paths: Vec<(String, PathBuf)> = vec![];
+    for entry in std::fs::read_dir(&directory)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() && !entry.path().is_file() {
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let matched = match pattern.as_ref() {
+            Some(p) => p.matches(&file_name),
+            None => FASTA_SUFFIXES.iter().any(|s| file_name.ends_with(s))
+        };
+        if matched {
+            paths.push((file_name, entry.path()));
+        }
+    }
+    if paths.is_empty() {
+        bail!("No FASTA files found for {:?}", fasta_fn);
+    }
+    paths.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+    Ok(paths.into_iter().map(|(_, p)| p).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_fasta_paths() {
+        let paths = expand_fasta_paths(&PathBuf::from("./test_data/split")).unwrap();
+        let names: Vec<String> = paths.iter().map(|p| p.file_name().unwrap().to_string_lossy().to_string()).collect();
+        assert_eq!(names, vec!["chr2.fa", "chr10.fa.gz", "chrM.fa"]);
+
+        let paths = expand_fasta_paths(&PathBuf::from("./test_data/split/chr*.fa")).unwrap();
+        assert_eq!(paths.len(), 2);
+        let paths = expand_fasta_paths(&PathBuf::from("./test_data/split/chr?.fa*")).unwrap();
+        assert_eq!(paths.len(), 2);
+        let paths = expand_fasta_paths(&PathBuf::from("./test_data/split/chr[0-9]*")).unwrap();
+        assert_eq!(paths.len(), 2);
+        assert!(expand_fasta_paths(&PathBuf::from("./test_data/split/chr[.fa")).is_err());
+
+        let single = PathBuf::from("./test_data/test_reference.fa");
+        assert_eq!(expand_fasta_paths(&single).unwrap(), vec![single]);
+        assert!(expand_fasta_paths(&PathBuf::from("./test_data/split/*.bed")).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::lazy::{ContigLoader, LazyContig};
+use crate::reference_genome::{ContigSequence, ReferenceGenome};
+use bytes::Bytes;
+use noodles_core::Region;
+use noodles_fasta::io::IndexedReader;
+use noodles_fasta::record::{Definition, Sequence};
+use noodles_fasta::Record;
+use simple_error::SimpleError;
+use std::error::Error;
+use std::io::{BufRead, Seek};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Lazy backend that queries full contigs from a noodles indexed reader
+struct NoodlesLoader<R> {
+    /// Contig names in index order
+    names: Vec<String>,
+    /// Contig lengths in index order
+    lengths: Vec<usize>,
+    /// The reader, which needs exclusive access for each query
+    reader: Mutex<IndexedReader<R>>
+}
+
+impl<R> ContigLoader for NoodlesLoader<R> where R: BufRead + Seek + Send {
+    fn contig_name(&self, index: usize) -> &str {
+        &self.names[index]
+    }
+
+    fn contig_length(&self, index: usize) -> usize {
+        self.lengths[index]
+    }
+
+    fn load_contig(&self, index: usize) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let region = Region::new(self.names[index].as_str(), ..);
+        let mut reader = self.reader.lock().map_err(|e| e.to_string())?;
+        let record = reader.query(&region)?;
+        Ok(record.sequence().as_ref().to_vec())
+    }
+}
+
+impl ReferenceGenome {
+    /// Creates a reference genome backed by a noodles indexed reader.
+    /// Contigs are listed from the index immediately, but each sequence is only read (and upper-cased) the first time it is accessed.
+    /// # Arguments
+    /// * `reader` - the indexed reader, e.g. from `noodles_fasta::io::indexed_reader::Builder`
+    /// # Errors
+    /// * if the index contains a contig name more than once or a name that is not valid UTF-8
+    /// # Panics
+    /// * sequence accessors will panic if the reader fails when a contig is first accessed
+    pub fn from_noodles_indexed_reader<R>(reader: IndexedReader<R>) -> Result<ReferenceGenome, SimpleError>
+        where R: BufRead + Seek + Send + 'static {
+        let names: Vec<String> = reader.index().as_ref().iter()
+            .map(|r| String::from_utf8(r.name().to_vec()))
+            .collect::<Result<_, _>>()
+            .map_err(SimpleError::from)?;
+        let lengths: Vec<usize> = reader.index().as_ref().iter()
+            .map(|r| r.length() as usize)
+            .collect();
+        let loader: Arc<dyn ContigLoader> = Arc::new(NoodlesLoader {
+            names: names.clone(),
+            lengths,
+            reader: Mutex::new(reader)
+        });
+        let contigs = names.into_iter()
+            .enumerate()
+            .map(|(index, name)| (name, ContigSequence::Lazy(LazyContig::new(loader.clone(), index, true))))
+            .collect();
+        ReferenceGenome::from_contigs(PathBuf::from(""), contigs)
+    }
+
+    /// Creates a reference genome from noodles FASTA records, preserving their order.
+    /// Sequences are upper-cased, matching `add_contig(...)`.
+    /// # Arguments
+    /// * `records` - the records to add
+    /// # Errors
+    /// * if a record name is present more than once or is not valid UTF-8
+    pub fn from_noodles_records<I>(records: I) -> Result<ReferenceGenome, SimpleError> where I: IntoIterator<Item = Record> {
+        let mut contigs: Vec<(String, ContigSequence)> = vec![];
+        for record in records.into_iter() {
+            let name = String::from_utf8(record.name().to_vec()).map_err(SimpleError::from)?;
+            let sequence = record.sequence().as_ref().to_ascii_uppercase();
+            contigs.push((name, ContigSequence::Loaded(Bytes::from(sequence))));
+        }
+        ReferenceGenome::from_contigs(PathBuf::from(""), contigs)
+    }
+
+    /// Converts a contig into a noodles FASTA record, or `None` if the contig is not in the reference genome
+    /// # Arguments
+    /// * `chromosome` - the contig to convert
+    pub fn to_noodles_record(&self, chromosome: &str) -> Option<Record> {
+        let name = self.resolve_contig_name(chromosome)?;
+        let sequence = self.get_full_chromosome(name);
+        Some(Record::new(Definition::new(name, None), Sequence::from(sequence.to_vec())))
+    }
+
+    /// Converts every contig into a noodles FASTA record, in load order
+    pub fn to_noodles_records(&self) -> Vec<Record> {
+        self.contig_keys().iter()
+            .filter_map(|c| self.to_noodles_record(c))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noodles_records() {
+        let records = vec![
+            Record::new(Definition::new("chr1", None), Sequence::from(b"acgtACGT".to_vec())),
+            Record::new(Definition::new("chr2", None), Sequence::from(b"AccATGTA".to_vec()))
+        ];
+        let reference_genome = ReferenceGenome::from_noodles_records(records.clone()).unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["chr1".to_string(), "chr2".to_string()]);
+        assert_eq!(reference_genome.get_full_chromosome("chr2"), b"ACCATGTA");
+
+        let round_trip = reference_genome.to_noodles_records();
+        assert_eq!(round_trip[1].name(), b"chr2");
+        assert_eq!(round_trip[1].sequence().as_ref(), b"ACCATGTA");
+        assert!(reference_genome.to_noodles_record("chr3").is_none());
+
+        assert!(ReferenceGenome::from_noodles_records([records[0].clone(), records[0].clone()]).is_err());
+    }
+
+    #[test]
+    fn test_noodles_indexed_reader() {
+        let reader = noodles_fasta::io::indexed_reader::Builder::default()
+            .build_from_path("./test_data/test_reference.fa")
+            .unwrap();
+        let reference_genome = ReferenceGenome::from_noodles_indexed_reader(reader).unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["chr1".to_string(), "chr2".to_string()]);
+        assert_eq!(reference_genome.get_slice("chr1", 2, 6), b"GTAC");
+        assert_eq!(reference_genome.get_full_chromosome("chr2"), b"ACCATGTA");
+    }
+}
@@ -0,0 +1,108 @@
+use crate::contig_store::ContigStore;
+use simple_error::{bail, SimpleError};
+use std::error::Error;
+use std::ops::Range;
+
+/// The symbol for each 4-bit code, matching the nibble encoding of BAM sequences
+const NIBBLE_SYMBOLS: &[u8; 16] = b"=ACMGRSVTWYHKDBN";
+
+/// The 4-bit code for each upper-case ASCII symbol, or 0xff if it cannot be packed
+const SYMBOL_NIBBLES: [u8; 256] = {
+    let mut table = [0xff; 256];
+    let mut code = 0;
+    while code < NIBBLE_SYMBOLS.len() {
+        table[NIBBLE_SYMBOLS[code] as usize] = code as u8;
+        code += 1;
+    }
+    table
+};
+
+/// A single contig stored with two symbols per byte, along with the runs of lower-case symbols so the content is exact
+pub(crate) struct PackedSequence {
+    /// The number of symbols
+    length: usize,
+    /// Packed symbols, high nibble first
+    packed: Vec<u8>,
+    /// Ranges of lower-case symbols, in order
+    lowercase: Vec<Range<usize>>
+}
+
+impl ContigStore for PackedSequence {
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn decode(&self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut sequence: Vec<u8> = Vec::with_capacity(self.packed.len() * 2);
+        for &pair in self.packed.iter() {
+            sequence.push(NIBBLE_SYMBOLS[(pair >> 4) as usize]);
+            sequence.push(NIBBLE_SYMBOLS[(pair & 0x0f) as usize]);
+        }
+        sequence.truncate(self.length);
+        for range in self.lowercase.iter() {
+            sequence[range.clone()].make_ascii_lowercase();
+        }
+        Ok(sequence)
+    }
+
+    fn stored_bytes(&self) -> usize {
+        self.packed.capacity() + self.lowercase.capacity() * std::mem::size_of::<Range<usize>>()
+    }
+}
+
+/// Packs an ASCII sequence into a contig that is unpacked on access, see `encode_packed(...)`.
+/// Any of the 16 symbols `=ACMGRSVTWYHKDBN` can be packed in either case.
+/// # Arguments
+/// * `seq_id` - the contig name
+/// * `sequence` - the ASCII sequence
+/// # Errors
+/// * if the sequence contains a symbol that cannot be packed, reporting the first 0-based position
+pub(crate) fn pack_sequence(seq_id: &str, sequence: &[u8]) -> Result<PackedSequence, SimpleError> {
+    let mut packed: Vec<u8> = Vec::with_capacity(sequence.len().div_ceil(2));
+    let mut lowercase: Vec<Range<usize>> = vec![];
+    for (pair_index, pair) in sequence.chunks(2).enumerate() {
+        let mut byte = 0;
+        for (offset, &symbol) in pair.iter().enumerate() {
+            let position = 2 * pair_index + offset;
+            let code = SYMBOL_NIBBLES[symbol.to_ascii_uppercase() as usize];
+            if code == 0xff {
+                bail!("Contig {seq_id:?} contains character {:?} at position {position}, which cannot be packed into 4 bits", symbol as char);
+            }
+            byte |= code << (4 * (1 - offset));
+            if symbol.is_ascii_lowercase() {
+                match lowercase.last_mut() {
+                    Some(range) if range.end == position => range.end += 1,
+                    _ => lowercase.push(position..position + 1)
+                };
+            }
+        }
+        packed.push(byte);
+    }
+    lowercase.shrink_to_fit();
+
+    Ok(PackedSequence {
+        length: sequence.len(),
+        packed,
+        lowercase
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_sequence() {
+        let sequences: [&[u8]; 4] = [b"", b"A", b"acgtNNNNacgtRYKMSWBDHV=n", b"ACGTacgtA"];
+        for sequence in sequences {
+            let contig = pack_sequence("chr1", sequence).unwrap();
+            assert_eq!(contig.len(), sequence.len());
+            assert_eq!(contig.decode().unwrap(), sequence);
+        }
+
+        // two symbols per byte plus the single lower-case run
+        let contig = pack_sequence("chr1", b"ACGTACGTacgt").unwrap();
+        assert_eq!(contig.stored_bytes(), 6 + std::mem::size_of::<Range<usize>>());
+        assert!(pack_sequence("chr1", b"ACGU").err().unwrap().to_string().contains("'U' at position 3"));
+    }
+}
@@ -0,0 +1,205 @@
+use crate::alphabet::reverse_complement;
+use crate::bed::BedRecord;
+use crate::reference_genome::ReferenceGenome;
+use crate::writer::{write_fasta_record, DEFAULT_LINE_WIDTH};
+use bytes::Bytes;
+use rustc_hash::FxHashSet as HashSet;
+use simple_error::{bail, SimpleError};
+use std::error::Error;
+use std::io::Write;
+
+/// The default FASTA header template of `PanelExtractor`
+pub const DEFAULT_HEADER_TEMPLATE: &str = "{name}|{chrom}:{start}-{end}|{strand}";
+
+/// Extracts capture targets into a small panel reference: a multi-FASTA with one record per target, with headers from a template,
+/// and a BED of the targets in the coordinates of the extracted records
+/// # Examples
+/// ```
+/// use rust_lib_reference_genome::bed::read_bed_records;
+/// use rust_lib_reference_genome::panel::PanelExtractor;
+/// use rust_lib_reference_genome::reference_genome::ReferenceGenome;
+///
+/// let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nAACCGGTTAA\n").unwrap();
+/// let targets = read_bed_records(&b"chr1\t4\t6\texon1\t0\t+\n"[..]).unwrap();
+/// let (mut fasta, mut bed) = (vec![], vec![]);
+/// PanelExtractor::new().padding(2).extract(&reference_genome, &targets, &mut fasta, &mut bed).unwrap();
+/// assert_eq!(fasta, b">exon1|chr1:3-8|+\nCCGGTT\n");
+/// assert_eq!(bed, b"exon1|chr1:3-8|+\t2\t4\texon1\t0\t+\n");
+/// ```
+#[derive(Clone, Debug)]
+pub struct PanelExtractor {
+    /// The FASTA header template
+    header_template: String,
+    /// The number of flanking bases added on each side of a target
+    padding: usize,
+    /// If true, targets on the minus strand are reverse complemented
+    reverse_complement_minus: bool,
+    /// The FASTA line width
+    line_width: usize
+}
+
+impl Default for PanelExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PanelExtractor {
+    /// Creates an extractor with `DEFAULT_HEADER_TEMPLATE`, no padding, and all targets on the forward strand
+    pub fn new() -> Self {
+        Self {
+            header_template: DEFAULT_HEADER_TEMPLATE.to_string(),
+            padding: 0,
+            reverse_complement_minus: false,
+            line_width: DEFAULT_LINE_WIDTH
+        }
+    }
+
+    /// Sets the FASTA header template, default is `DEFAULT_HEADER_TEMPLATE`. The placeholders are `{name}` (the BED name,
+    /// or `chrom:start-end` if there is none), `{chrom}`, `{start}` and `{end}` (the extracted region, 1-based inclusive),
+    /// `{strand}` (`+`, `-`, or `.`), and `{index}` (the 1-based target number). The record ID, up to the first whitespace, must be unique.
+    pub fn header_template(mut self, header_template: &str) -> Self {
+        self.header_template = header_template.to_string();
+        self
+    }
+
+    /// Sets the number of flanking bases added on each side of a target, clipped at the contig ends, default is 0
+    pub fn padding(mut self, padding: usize) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets whether targets on the minus strand are reverse complemented, so every record reads 5' to 3' on the target strand, default is false
+    pub fn reverse_complement_minus(mut self, reverse_complement_minus: bool) -> Self {
+        self.reverse_complement_minus = reverse_complement_minus;
+        self
+    }
+
+    /// Sets the FASTA line width, default is `DEFAULT_LINE_WIDTH`
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Fills in the header template for a target
+    /// # Arguments
+    /// * `target` - the capture target
+    /// * `index` - the 0-based target number
+    /// * `start` - the 0-based start of the extracted region
+    /// * `end` - the 0-based exclusive end of the extracted region
+    /// # Errors
+    /// * if the template has an unknown or unterminated placeholder
+    fn render_header(&self, target: &BedRecord, index: usize, start: usize, end: usize) -> Result<String, SimpleError> {
+        let mut header = String::new();
+        let mut rest = self.header_template.as_str();
+        while let Some(open) = rest.find('{') {
+            header.push_str(&rest[..open]);
+            let Some(close) = rest[open..].find('}') else {
+                bail!("Unterminated placeholder in header template {:?}", self.header_template);
+            };
+            match &rest[open + 1..open + close] {
+                "name" => match target.name() {
+                    Some(name) => header.push_str(name),
+                    None => header.push_str(&format!("{}:{}-{}", target.contig, target.start + 1, target.end))
+                },
+                "chrom" => header.push_str(&target.contig),
+                "start" => header.push_str(&(start + 1).to_string()),
+                "end" => header.push_str(&end.to_string()),
+                "strand" => header.push(target.strand().unwrap_or('.')),
+                "index" => header.push_str(&(index + 1).to_string()),
+                placeholder => bail!("Unknown placeholder {{{}}} in header template {:?}", placeholder, self.header_template)
+            }
+            rest = &rest[open + close + 1..];
+        }
+        header.push_str(rest);
+        Ok(header)
+    }
+
+    /// Writes the panel FASTA and the remapped target BED. Each BED line has the record ID, the target interval within the record,
+    /// the target name (or `.`), a score of 0, and the strand of the target relative to the record.
+    /// Consecutive targets on the same contig share a single read of a lazily loaded contig.
+    /// # Arguments
+    /// * `reference_genome` - the genome to extract from
+    /// * `targets` - the capture targets, e.g. from `read_bed_file(...)`, written in the given order
+    /// * `fasta_writer` - where the panel FASTA is written
+    /// * `bed_writer` - where the remapped BED is written
+    /// # Returns
+    /// * the number of records written
+    /// # Errors
+    /// * if a target is empty, extends past the end of its contig, or is on a contig that is not in the genome, was unloaded, or fails to load
+    /// * if the header template is invalid or two records have the same ID
+    /// * any errors from the underlying writers
+    pub fn extract<F: Write, B: Write>(&self, reference_genome: &ReferenceGenome, targets: &[BedRecord], mut fasta_writer: F, mut bed_writer: B) -> Result<usize, Box<dyn Error>> {
+        let mut record_ids: HashSet<String> = Default::default();
+        let mut current: Option<(&str, Bytes)> = None;
+        for (index, target) in targets.iter().enumerate() {
+            let sequence = match current.as_ref() {
+                Some((contig, sequence)) if *contig == target.contig => sequence.clone(),
+                _ => {
+                    let sequence = reference_genome.try_sequence_unkept(&target.contig)?;
+                    current = Some((&target.contig, sequence.clone()));
+                    sequence
+                }
+            };
+            if target.is_empty() || target.end > sequence.len() {
+                bail!("Target {}:{}-{} is empty or extends past the end of the contig ({} bp)", target.contig, target.start, target.end, sequence.len());
+            }
+
+            let start = target.start.saturating_sub(self.padding);
+            let end = (target.end + self.padding).min(sequence.len());
+            let header = self.render_header(target, index, start, end)?;
+            let record_id = header.split_whitespace().next().unwrap_or_default().to_string();
+            if record_id.is_empty() || !record_ids.insert(record_id.clone()) {
+                bail!("Target {} has an empty or repeated record ID {:?}", index + 1, record_id);
+            }
+
+            let reverse = self.reverse_complement_minus && target.strand() == Some('-');
+            let (offset, strand) = if reverse {
+                (end - target.end, '+')
+            } else {
+                (target.start - start, target.strand().unwrap_or('.'))
+            };
+            if reverse {
+                write_fasta_record(&mut fasta_writer, &header, &reverse_complement(&sequence[start..end]), self.line_width)?;
+            } else {
+                write_fasta_record(&mut fasta_writer, &header, &sequence[start..end], self.line_width)?;
+            }
+            writeln!(bed_writer, "{}\t{}\t{}\t{}\t0\t{}", record_id, offset, offset + target.len(), target.name().unwrap_or("."), strand)?;
+        }
+        fasta_writer.flush()?;
+        bed_writer.flush()?;
+        Ok(targets.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bed::read_bed_records;
+
+    #[test]
+    fn test_extract_panel() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nAACCGGTTAA\n>chr2\nACGTAC\n").unwrap();
+        let targets = read_bed_records(&b"chr1\t1\t4\texon1\t0\t-\nchr2\t4\t6\nchr1\t7\t9\texon2\t0\t+\n"[..]).unwrap();
+        let (mut fasta, mut bed) = (vec![], vec![]);
+        let extractor = PanelExtractor::new()
+            .header_template("{name}_{index} {chrom}:{start}-{end}({strand})")
+            .padding(2)
+            .reverse_complement_minus(true);
+        assert_eq!(extractor.extract(&reference_genome, &targets, &mut fasta, &mut bed).unwrap(), 3);
+        assert_eq!(String::from_utf8(fasta).unwrap(), ">exon1_1 chr1:1-6(-)\nCCGGTT\n\
+            >chr2:5-6_2 chr2:3-6(.)\nGTAC\n\
+            >exon2_3 chr1:6-10(+)\nGTTAA\n");
+        // exon1 is at 2-5 in the reverse complement of 0-6
+        assert_eq!(String::from_utf8(bed).unwrap(), "exon1_1\t2\t5\texon1\t0\t+\n\
+            chr2:5-6_2\t2\t4\t.\t0\t.\n\
+            exon2_3\t2\t4\texon2\t0\t+\n");
+
+        let sink = || Vec::<u8>::new();
+        assert!(PanelExtractor::new().header_template("{gene}").extract(&reference_genome, &targets, sink(), sink()).is_err());
+        assert!(PanelExtractor::new().header_template("{chrom").extract(&reference_genome, &targets, sink(), sink()).is_err());
+        assert!(PanelExtractor::new().header_template("{chrom}").extract(&reference_genome, &targets, sink(), sink()).is_err());
+        let outside = read_bed_records(&b"chr2\t4\t7\n"[..]).unwrap();
+        assert!(PanelExtractor::new().extract(&reference_genome, &outside, sink(), sink()).is_err());
+    }
+}
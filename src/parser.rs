@@ -0,0 +1,249 @@
+use crate::writer::RecordFormat;
+use std::error::Error;
+use std::io::BufRead;
+
+/// The reader type handed to FASTA parsers, already decompressed if needed
+pub(crate) type FastaReader = Box<dyn BufRead + Send>;
+
+/// FASTA parsing backends for the in-memory loader.
+/// All parsers produce identical genomes; they only differ in dependencies and performance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Parser {
+    /// The built-in parser, which has no additional dependencies
+    #[default]
+    Native,
+    /// The rust-bio parser, requires the `bio` feature
+    #[cfg(feature = "bio")]
+    RustBio,
+    /// The needletail parser, requires the `needletail` feature
+    #[cfg(feature = "needletail")]
+    Needletail,
+    /// The noodles-fasta parser, requires the `noodles` feature
+    #[cfg(feature = "noodles")]
+    Noodles
+}
+
+impl Parser {
+    /// All parsers that are enabled in this build
+    pub fn available() -> Vec<Parser> {
+        vec![
+            Parser::Native,
+            #[cfg(feature = "bio")]
+            Parser::RustBio,
+            #[cfg(feature = "needletail")]
+            Parser::Needletail,
+            #[cfg(feature = "noodles")]
+            Parser::Noodles
+        ]
+    }
+
+    /// Parses every record from a FASTA reader, passing the record ID and raw sequence to a callback in file order
+    /// # Arguments
+    /// * `reader` - the FASTA content
+    /// * `on_record` - called with (record ID, sequence) for each record; any error stops parsing
+    /// # Errors
+    /// * any reading or parsing error, or an error from `on_record`
+    pub(crate) fn read_records<F>(&self, reader: FastaReader, on_record: F) -> Result<(), Box<dyn Error>>
+        where F: FnMut(String, Vec<u8>) -> Result<(), Box<dyn Error>> {
+        match self {
+            Parser::Native => read_records_native(reader, on_record),
+            #[cfg(feature = "bio")]
+            Parser::RustBio => read_records_bio(reader, on_record),
+            #[cfg(feature = "needletail")]
+            Parser::Needletail => read_records_needletail(reader, on_record),
+            #[cfg(feature = "noodles")]
+            Parser::Noodles => read_records_noodles(reader, on_record)
+        }
+    }
+}
+
+/// Extracts the record ID from a FASTA header line (without the `>`), which is everything before the first whitespace
+pub(crate) fn header_id(header: &[u8]) -> Result<String, Box<dyn Error>> {
+    let id_bytes = header.split(|c| c.is_ascii_whitespace()).next().unwrap_or_default();
+    Ok(String::from_utf8(id_bytes.to_vec())?)
+}
+
+/// Line-based FASTA parser with no external dependencies
+fn read_records_native<F>(mut reader: FastaReader, mut on_record: F) -> Result<(), Box<dyn Error>>
+    where F: FnMut(String, Vec<u8>) -> Result<(), Box<dyn Error>> {
+    let mut line: Vec<u8> = vec![];
+    let mut current: Option<(String, Vec<u8>)> = None;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        while matches!(line.last(), Some(b'\n') | Some(b'\r')) {
+            line.pop();
+        }
+
+        if let Some(header) = line.strip_prefix(b">") {
+            if let Some((seq_id, sequence)) = current.take() {
+                on_record(seq_id, sequence)?;
+            }
+            current = Some((header_id(header)?, vec![]));
+        } else if let Some((_, sequence)) = current.as_mut() {
+            sequence.extend_from_slice(&line);
+        } else if !line.is_empty() {
+            return Err("Expected > at record start.".into());
+        }
+    }
+    if let Some((seq_id, sequence)) = current.take() {
+        on_record(seq_id, sequence)?;
+    }
+    Ok(())
+}
+
+/// Line-based FASTA parser that also captures the header description and line width of each record, see `RecordFormat`.
+/// Only layouts that can be written back identically are accepted.
+/// # Arguments
+/// * `reader` - the FASTA content
+/// * `on_record` - called with (record ID, format, sequence) for each record; any error stops parsing
+/// # Errors
+/// * any reading or parsing error, or an error from `on_record`
+/// * if a line is not newline-terminated, has a Windows line ending, or is blank
+/// * if a record has irregular line lengths, i.e. a line other than the last that is not the full line width
+pub(crate) fn read_records_preserving<F>(mut reader: FastaReader, mut on_record: F) -> Result<(), Box<dyn Error>>
+    where F: FnMut(String, RecordFormat, Vec<u8>) -> Result<(), Box<dyn Error>> {
+    let mut line: Vec<u8> = vec![];
+    let mut current: Option<(String, RecordFormat, Vec<u8>)> = None;
+    // length of the previous sequence line in the current record
+    let mut previous_length = 0;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        if line.pop() != Some(b'\n') {
+            return Err("The last line does not end with a newline, so the format cannot be preserved.".into());
+        }
+        if line.last() == Some(&b'\r') {
+            return Err("Windows line endings are not supported when preserving the format.".into());
+        }
+
+        if let Some(header) = line.strip_prefix(b">") {
+            if let Some((seq_id, format, sequence)) = current.take() {
+                on_record(seq_id, format, sequence)?;
+            }
+            let seq_id = header_id(header)?;
+            let format = RecordFormat {
+                description: String::from_utf8(header[seq_id.len()..].to_vec())?,
+                line_width: 0
+            };
+            current = Some((seq_id, format, vec![]));
+            previous_length = 0;
+        } else if let Some((seq_id, format, sequence)) = current.as_mut() {
+            if line.is_empty() {
+                return Err(format!("Record \"{seq_id}\" contains a blank line, so the format cannot be preserved.").into());
+            }
+            if format.line_width == 0 {
+                format.line_width = line.len();
+            } else if previous_length != format.line_width || line.len() > format.line_width {
+                return Err(format!("Record \"{seq_id}\" has irregular line lengths, so the format cannot be preserved.").into());
+            }
+            previous_length = line.len();
+            sequence.extend_from_slice(&line);
+        } else {
+            return Err("Expected > at record start.".into());
+        }
+    }
+    if let Some((seq_id, format, sequence)) = current.take() {
+        on_record(seq_id, format, sequence)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "bio")]
+fn read_records_bio<F>(reader: FastaReader, mut on_record: F) -> Result<(), Box<dyn Error>>
+    where F: FnMut(String, Vec<u8>) -> Result<(), Box<dyn Error>> {
+    use bio::io::fasta;
+    let fasta_reader = fasta::Reader::from_bufread(reader);
+    for entry in fasta_reader.records() {
+        let record: fasta::Record = entry?;
+        on_record(record.id().to_string(), record.seq().to_vec())?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "needletail")]
+fn read_records_needletail<F>(mut reader: FastaReader, mut on_record: F) -> Result<(), Box<dyn Error>>
+    where F: FnMut(String, Vec<u8>) -> Result<(), Box<dyn Error>> {
+    // needletail rejects empty input, but an empty FASTA is just an empty genome
+    if reader.fill_buf()?.is_empty() {
+        return Ok(());
+    }
+    let mut fastx_reader = needletail::parse_fastx_reader(reader)?;
+    while let Some(entry) = fastx_reader.next() {
+        let record = entry?;
+        on_record(header_id(record.id())?, record.seq().into_owned())?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "noodles")]
+fn read_records_noodles<F>(reader: FastaReader, mut on_record: F) -> Result<(), Box<dyn Error>>
+    where F: FnMut(String, Vec<u8>) -> Result<(), Box<dyn Error>> {
+    let mut fasta_reader = noodles_fasta::io::Reader::new(reader);
+    for entry in fasta_reader.records() {
+        let record = entry?;
+        on_record(String::from_utf8(record.name().to_vec())?, record.sequence().as_ref().to_vec())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Record = (String, Vec<u8>);
+
+    fn parse_all(parser: Parser, content: &'static [u8]) -> Result<Vec<Record>, Box<dyn Error>> {
+        let mut records = vec![];
+        parser.read_records(Box::new(content), |seq_id, sequence| {
+            records.push((seq_id, sequence));
+            Ok(())
+        })?;
+        Ok(records)
+    }
+
+    #[test]
+    fn test_parsers_agree() {
+        let content = b">chr1 description\nacgt\r\nACGT\n\n>chr2\n>chr3\nAccATGTA";
+        let expected = vec![
+            ("chr1".to_string(), b"acgtACGT".to_vec()),
+            ("chr2".to_string(), vec![]),
+            ("chr3".to_string(), b"AccATGTA".to_vec())
+        ];
+        for parser in Parser::available() {
+            assert_eq!(parse_all(parser, content).unwrap(), expected, "{parser:?}");
+            assert!(parse_all(parser, b"").unwrap().is_empty(), "{parser:?}");
+        }
+    }
+
+    #[test]
+    fn test_read_records_preserving() {
+        let mut records = vec![];
+        read_records_preserving(Box::new(&b">chr1 first  record\nacg\nTAC\nG\n>chr2\n>chr3\tx\nAC\n"[..]), |seq_id, format, sequence| {
+            records.push((seq_id, format, sequence));
+            Ok(())
+        }).unwrap();
+        assert_eq!(records, vec![
+            ("chr1".to_string(), RecordFormat { description: " first  record".to_string(), line_width: 3 }, b"acgTACG".to_vec()),
+            ("chr2".to_string(), RecordFormat { description: "".to_string(), line_width: 0 }, vec![]),
+            ("chr3".to_string(), RecordFormat { description: "\tx".to_string(), line_width: 2 }, b"AC".to_vec())
+        ]);
+
+        let irreproducible: [&'static [u8]; 5] = [b">a\nAC\nACG\n", b">a\nAC\nA\nA\n", b">a\nAC\n\n", b">a\r\nAC\r\n", b">a\nAC"];
+        for content in irreproducible {
+            assert!(read_records_preserving(Box::new(content), |_, _, _| Ok(())).is_err(), "{content:?}");
+        }
+    }
+
+    #[test]
+    fn test_native_errors() {
+        assert!(parse_all(Parser::Native, b"ACGT\n>chr1\nACGT\n").is_err());
+        // errors from the callback stop the parse
+        let result = Parser::Native.read_records(Box::new(&b">chr1\nA\n>chr2\nC\n"[..]), |_, _| Err("stop".into()));
+        assert_eq!(result.unwrap_err().to_string(), "stop");
+    }
+}
@@ -0,0 +1,116 @@
+use crate::reference_genome::ReferenceGenome;
+use simple_error::{bail, SimpleError};
+use std::io::Write;
+
+/// A run of A (or T) of at least a minimum length, from `scan_poly_a_tracts(...)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PolyTract {
+    /// The 0-based start of the run (included)
+    pub start: usize,
+    /// The 0-based end of the run (excluded)
+    pub end: usize,
+    /// True for a poly-T run, which is a poly-A tract on the reverse strand
+    pub reverse: bool
+}
+
+impl PolyTract {
+    /// The number of bases in the run
+    pub fn length(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// The BED name of the run, "polyA" or "polyT"
+    pub fn name(&self) -> &'static str {
+        if self.reverse { "polyT" } else { "polyA" }
+    }
+}
+
+/// Finds every run of A or T of at least `min_length` bases in a sequence, ignoring case so soft-masking does not split a run
+/// # Arguments
+/// * `sequence` - the forward strand sequence
+/// * `min_length` - the shortest run to report
+/// # Returns
+/// * the runs in position order
+pub fn scan_poly_a_tracts(sequence: &[u8], min_length: usize) -> Vec<PolyTract> {
+    let mut tracts: Vec<PolyTract> = vec![];
+    let mut start = 0;
+    while start < sequence.len() {
+        let base = sequence[start].to_ascii_uppercase();
+        let end = start + sequence[start..].iter().take_while(|c| c.to_ascii_uppercase() == base).count();
+        if matches!(base, b'A' | b'T') && end - start >= min_length {
+            tracts.push(PolyTract { start, end, reverse: base == b'T' });
+        }
+        start = end;
+    }
+    tracts
+}
+
+impl ReferenceGenome {
+    /// Finds the poly-A and poly-T runs of a contig, see `scan_poly_a_tracts(...)`
+    /// # Arguments
+    /// * `chromosome` - the contig name
+    /// * `min_length` - the shortest run to report
+    /// # Errors
+    /// * if `min_length` is 0
+    /// * if the contig is not in the reference genome, was unloaded, or fails to load
+    pub fn poly_a_tracts(&self, chromosome: &str, min_length: usize) -> Result<Vec<PolyTract>, SimpleError> {
+        if min_length == 0 {
+            bail!("The minimum tract length must be at least 1");
+        }
+        Ok(scan_poly_a_tracts(&self.try_sequence_unkept(chromosome)?, min_length))
+    }
+
+    /// Writes the poly-A and poly-T runs of all contigs as BED6, such as for filtering internal-priming artifacts of 3' RNA-seq:
+    /// contig, start, end, "polyA" or "polyT", the run length capped at 1000 as the score, and the strand of the poly-A tract
+    /// (`-` for poly-T). Lazily loaded contigs are read once and are not kept in memory.
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// * `min_length` - the shortest run to write
+    /// # Returns
+    /// * the number of runs written
+    /// # Errors
+    /// * if `min_length` is 0
+    /// * if a contig was unloaded or fails to load
+    /// * any errors from the underlying writer
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, writer)))]
+    pub fn write_poly_a_bed<W: Write>(&self, mut writer: W, min_length: usize) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut written = 0;
+        for contig in self.contig_keys().iter() {
+            for tract in self.poly_a_tracts(contig, min_length)?.iter() {
+                let strand = if tract.reverse { '-' } else { '+' };
+                writeln!(writer, "{}\t{}\t{}\t{}\t{}\t{}", contig, tract.start, tract.end, tract.name(), tract.length().min(1000), strand)?;
+                written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_poly_a_tracts() {
+        let tracts = scan_poly_a_tracts(b"AAAAcGTTTttTNNNNAAAA", 4);
+        assert_eq!(tracts, vec![
+            PolyTract { start: 0, end: 4, reverse: false },
+            PolyTract { start: 6, end: 12, reverse: true },
+            PolyTract { start: 16, end: 20, reverse: false }
+        ]);
+        assert_eq!(tracts[1].length(), 6);
+        assert!(scan_poly_a_tracts(b"AAAAcGTTTttTNNNNAAAA", 7).is_empty());
+        assert_eq!(scan_poly_a_tracts(b"CGA", 1).len(), 1);
+    }
+
+    #[test]
+    fn test_write_poly_a_bed() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nCAAAAAG\n>chr2\nTTTTTTAC\n").unwrap();
+        let mut bed: Vec<u8> = vec![];
+        assert_eq!(reference_genome.write_poly_a_bed(&mut bed, 5).unwrap(), 2);
+        assert_eq!(String::from_utf8(bed).unwrap(), "chr1\t1\t6\tpolyA\t5\t+\nchr2\t0\t6\tpolyT\t6\t-\n");
+        assert!(reference_genome.write_poly_a_bed(std::io::sink(), 0).is_err());
+        assert!(reference_genome.poly_a_tracts("chr3", 5).is_err());
+    }
+}
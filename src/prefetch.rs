@@ -0,0 +1,101 @@
+use crate::reference_genome::{ContigSequence, ReferenceGenome};
+use bytes::Bytes;
+use std::error::Error;
+use std::sync::mpsc::{sync_channel, Receiver};
+
+/// The result of reading one contig on the prefetch thread
+type PrefetchResult = Result<Bytes, Box<dyn Error + Send + Sync>>;
+
+/// Iterator over every contig in order, where upcoming contigs are read on a background thread, from `ReferenceGenome::prefetch_contigs(...)`.
+/// The iterator does not borrow the genome, so it can be moved to another thread. Dropping it stops the background thread
+/// after the contig it is currently reading.
+pub struct PrefetchedContigs {
+    /// Contig names in iteration order
+    names: std::vec::IntoIter<String>,
+    /// Sequences from the background thread, in the same order as `names`
+    receiver: Receiver<PrefetchResult>
+}
+
+impl Iterator for PrefetchedContigs {
+    type Item = (String, Bytes);
+
+    /// # Panics
+    /// * if a contig was unloaded or a lazy backend fails to read it
+    fn next(&mut self) -> Option<Self::Item> {
+        let name = self.names.next()?;
+        match self.receiver.recv() {
+            Ok(Ok(sequence)) => Some((name, sequence)),
+            Ok(Err(e)) => panic!("{e}"),
+            Err(_) => panic!("Prefetch thread stopped before contig {name:?}")
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.names.size_hint()
+    }
+}
+
+impl ReferenceGenome {
+    /// Iterates over every contig in `contig_keys()` order as (name, sequence), reading upcoming contigs on a background thread
+    /// so that a lazy backend's I/O and decoding overlap with the caller's processing of the current contig.
+    /// Sequences are not kept in the genome after they are yielded (an LRU cache from the builder is still populated),
+    /// so a whole-genome sweep only holds about `depth + 1` contigs in memory at a time.
+    /// In-memory genomes are supported, but gain nothing from prefetching. Requires thread support, so it is not available on `wasm32-unknown-unknown`.
+    /// # Arguments
+    /// * `depth` - the maximum number of contigs read ahead of the caller, at least 1
+    pub fn prefetch_contigs(&self, depth: usize) -> PrefetchedContigs {
+        let names: Vec<String> = self.contig_keys().to_vec();
+        let sequences: Vec<(String, ContigSequence)> = names.iter()
+            .map(|n| (n.clone(), self.contig_sequence(n).unwrap().clone()))
+            .collect();
+        // the worker holds one finished contig while blocked on a full channel, so the channel holds depth - 1
+        let (sender, receiver) = sync_channel(depth.max(1) - 1);
+        std::thread::spawn(move || {
+            for (name, sequence) in sequences.iter() {
+                if sender.send(sequence.try_as_bytes_unkept(name)).is_err() {
+                    // the iterator was dropped
+                    break;
+                }
+            }
+        });
+        PrefetchedContigs {
+            names: names.into_iter(),
+            receiver
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{Backend, ReferenceGenomeBuilder};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_prefetch_contigs() {
+        let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .backend(Backend::Mmap)
+            .build()
+            .unwrap();
+        let contigs: Vec<(String, Bytes)> = reference_genome.prefetch_contigs(1).collect();
+        assert_eq!(contigs, vec![
+            ("chr1".to_string(), Bytes::from_static(b"ACGTACGT")),
+            ("chr2".to_string(), Bytes::from_static(b"ACCATGTA"))
+        ]);
+        // nothing is kept in the genome
+        assert_eq!(reference_genome.memory_usage().sequence_bytes(), 0);
+
+        // dropping early is fine
+        let mut partial = reference_genome.prefetch_contigs(4);
+        assert_eq!(partial.next().unwrap().0, "chr1");
+    }
+
+    #[test]
+    #[should_panic(expected = "has been unloaded")]
+    fn test_prefetch_unloaded() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("a".to_string(), "ACGT").unwrap();
+        reference_genome.unload_contig("a").unwrap();
+        reference_genome.prefetch_contigs(1).for_each(drop);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::alphabet::reverse_complement;
+use crate::builder::{decompress, is_gzip};
+use crate::coordinates::Strand;
+use crate::reference_genome::ReferenceGenome;
+use crate::writer::write_fasta_record;
+use bytes::Bytes;
+use simple_error::{bail, SimpleError};
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A stranded feature from a GFF3 or GTF annotation, such as a gene or transcript
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneRecord {
+    /// The contig name
+    pub contig: String,
+    /// The 0-based start (included)
+    pub start: usize,
+    /// The 0-based end (excluded)
+    pub end: usize,
+    /// The strand of the feature
+    pub strand: Strand,
+    /// The first of the `gene_name`, `Name`, `gene_id`, `transcript_id`, and `ID` attributes, or `contig:start-end` (1-based) if none is set
+    pub name: String
+}
+
+impl GeneRecord {
+    /// The 0-based position of the transcription start site: the first base on the forward strand, or the last base on the reverse strand
+    pub fn tss(&self) -> usize {
+        match self.strand {
+            Strand::Forward => self.start,
+            Strand::Reverse => self.end - 1
+        }
+    }
+}
+
+/// Looks up an attribute in a GFF3 (`key=value;`) or GTF (`key "value";`) attribute column
+pub(crate) fn attribute<'a>(attributes: &'a str, key: &str) -> Option<&'a str> {
+    attributes.split(';')
+        .filter_map(|a| {
+            let a = a.trim();
+            a.split_once('=').or_else(|| a.split_once(' '))
+        })
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, v)| v.trim().trim_matches('"'))
+}
+
+/// Reads the features of one type from a GFF3 or GTF annotation, e.g. "gene" or "transcript"; the format of the attribute column
+/// is detected per line. Features on an unknown strand (`.` or `?`) are skipped, and a GFF3 `##FASTA` section ends the annotation.
+/// # Arguments
+/// * `reader` - the uncompressed annotation
+/// * `feature_type` - the value of the third column to keep
+/// # Errors
+/// * any reading errors
+/// * if a kept line has fewer than 9 columns or invalid coordinates
+pub fn read_gene_records<R: BufRead>(reader: R, feature_type: &str) -> Result<Vec<GeneRecord>, Box<dyn Error>> {
+    let mut records: Vec<GeneRecord> = vec![];
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.starts_with("##FASTA") {
+            break;
+        }
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let columns: Vec<&str> = line.trim_end_matches('\r').split('\t').collect();
+        if columns.get(2) != Some(&feature_type) {
+            continue;
+        }
+        if columns.len() < 9 {
+            bail!("Expected 9 columns on line {} of the annotation, found {}", line_index + 1, columns.len());
+        }
+        let (start, end) = match (columns[3].parse::<usize>(), columns[4].parse::<usize>()) {
+            (Ok(start), Ok(end)) if start > 0 && start <= end => (start - 1, end),
+            _ => bail!("Invalid feature {}-{} on line {} of the annotation", columns[3], columns[4], line_index + 1)
+        };
+        let Some(strand) = columns[6].chars().next().filter(|_| columns[6].len() == 1).and_then(Strand::from_symbol) else {
+            continue;
+        };
+        let name = ["gene_name", "Name", "gene_id", "transcript_id", "ID"].iter()
+            .find_map(|key| attribute(columns[8], key).filter(|v| !v.is_empty()))
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| format!("{}:{}-{}", columns[0], start + 1, end));
+        records.push(GeneRecord { contig: columns[0].to_string(), start, end, strand, name });
+    }
+    Ok(records)
+}
+
+/// Reads the features of one type from a GFF3 or GTF file, see `read_gene_records(...)`
+/// # Arguments
+/// * `annotation_fn` - the annotation filename; gzip compression is detected from a `.gz` extension
+/// * `feature_type` - the value of the third column to keep, e.g. "gene"
+/// # Errors
+/// * any file reading errors
+/// * see `read_gene_records(...)`
+pub fn read_gene_file(annotation_fn: &Path, feature_type: &str) -> Result<Vec<GeneRecord>, Box<dyn Error>> {
+    let annotation_file = std::fs::File::open(annotation_fn)?;
+    let reader = decompress(Box::new(BufReader::new(annotation_file)), is_gzip(annotation_fn), &Arc::default())?;
+    read_gene_records(reader, feature_type)
+}
+
+/// A window around a transcription start site, from `ReferenceGenome::promoter_regions(...)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PromoterRegion {
+    /// The name of the feature
+    pub name: String,
+    /// The contig name
+    pub contig: String,
+    /// The 0-based start on the forward strand (included)
+    pub start: usize,
+    /// The 0-based end on the forward strand (excluded)
+    pub end: usize,
+    /// The strand of the feature
+    pub strand: Strand,
+    /// True if the window was cut short by a contig end
+    pub clipped: bool
+}
+
+impl ReferenceGenome {
+    /// Computes strand-aware windows around the transcription start site of each feature, e.g. `upstream = 2000` and `downstream = 200`
+    /// for the common -2000..+200 promoter window. Downstream bases include the TSS itself; windows are clipped to the contig bounds.
+    /// # Arguments
+    /// * `genes` - the features, e.g. from `read_gene_file(...)`
+    /// * `upstream` - the bases before the TSS, on the feature strand
+    /// * `downstream` - the bases from the TSS onward, on the feature strand
+    /// # Errors
+    /// * if a feature is on a contig that is not in the genome, or starts past the end of its contig
+    pub fn promoter_regions(&self, genes: &[GeneRecord], upstream: usize, downstream: usize) -> Result<Vec<PromoterRegion>, SimpleError> {
+        let mut regions: Vec<PromoterRegion> = Vec::with_capacity(genes.len());
+        for gene in genes.iter() {
+            let Some(contig_length) = self.contig_length(&gene.contig) else {
+                bail!("{}", self.missing_contig_message(&gene.contig));
+            };
+            let tss = gene.tss();
+            if tss >= contig_length {
+                bail!("Feature {} at {}:{} starts past the end of the contig ({} bp)", gene.name, gene.contig, tss + 1, contig_length);
+            }
+            // the unclipped window, as signed forward strand coordinates
+            let (start, end) = match gene.strand {
+                Strand::Forward => (tss as i64 - upstream as i64, (tss + downstream) as i64),
+                Strand::Reverse => ((tss + 1) as i64 - downstream as i64, (tss + 1 + upstream) as i64)
+            };
+            let (clipped_start, clipped_end) = (start.max(0) as usize, (end as usize).min(contig_length));
+            regions.push(PromoterRegion {
+                name: gene.name.clone(),
+                contig: gene.contig.clone(),
+                start: clipped_start,
+                end: clipped_end,
+                strand: gene.strand,
+                clipped: start < 0 || end as usize > contig_length
+            });
+        }
+        Ok(regions)
+    }
+
+    /// Writes promoter windows as a FASTA of the feature-strand sequences, reverse complemented for `-` features, and as BED6
+    /// (contig, start, end, name, score 0, strand). FASTA headers follow `bedtools getfasta -name -s`: `name::contig:start-end(strand)`
+    /// with BED coordinates. Consecutive regions on the same contig share a single read of a lazily loaded contig.
+    /// # Arguments
+    /// * `regions` - the windows, e.g. from `promoter_regions(...)`, written in the given order
+    /// * `fasta_writer` - where the sequences are written
+    /// * `bed_writer` - where the windows are written
+    /// * `line_width` - the FASTA line width
+    /// # Returns
+    /// * the number of regions written
+    /// # Errors
+    /// * if a region extends past the end of its contig, or is on a contig that is not in the genome, was unloaded, or fails to load
+    /// * any errors from the underlying writers
+    pub fn write_promoters<F: Write, B: Write>(&self, regions: &[PromoterRegion], mut fasta_writer: F, mut bed_writer: B, line_width: usize) -> Result<usize, Box<dyn Error>> {
+        let mut current: Option<(&str, Bytes)> = None;
+        for region in regions.iter() {
+            let sequence = match current.as_ref() {
+                Some((contig, sequence)) if *contig == region.contig => sequence.clone(),
+                _ => {
+                    let sequence = self.try_sequence_unkept(&region.contig)?;
+                    current = Some((&region.contig, sequence.clone()));
+                    sequence
+                }
+            };
+            if region.start > region.end || region.end > sequence.len() {
+                bail!("Region {}:{}-{} extends past the end of the contig ({} bp)", region.contig, region.start, region.end, sequence.len());
+            }
+            let strand = region.strand.symbol();
+            let header = format!("{}::{}:{}-{}({})", region.name, region.contig, region.start, region.end, strand);
+            match region.strand {
+                Strand::Forward => write_fasta_record(&mut fasta_writer, &header, &sequence[region.start..region.end], line_width)?,
+                Strand::Reverse => write_fasta_record(&mut fasta_writer, &header, &reverse_complement(&sequence[region.start..region.end]), line_width)?
+            }
+            writeln!(bed_writer, "{}\t{}\t{}\t{}\t0\t{}", region.contig, region.start, region.end, region.name, strand)?;
+        }
+        fasta_writer.flush()?;
+        bed_writer.flush()?;
+        Ok(regions.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_gene_records() {
+        let gff = b"##gff-version 3\nchr1\tsrc\tgene\t3\t6\t.\t+\t.\tID=gene:G1;Name=ALPHA\n\
+            chr1\tsrc\tmRNA\t3\t6\t.\t+\t.\tID=tx1\nchr1\tsrc\tgene\t8\t9\t.\t.\t.\tID=G2\n##FASTA\n>chr1\n";
+        let records = read_gene_records(&gff[..], "gene").unwrap();
+        assert_eq!(records, vec![GeneRecord { contig: "chr1".to_string(), start: 2, end: 6, strand: Strand::Forward, name: "ALPHA".to_string() }]);
+
+        let gtf = b"chr2\tsrc\tgene\t5\t8\t.\t-\t.\tgene_id \"ENSG1\"; gene_version \"2\";\nchr2\tsrc\tgene\t1\t2\t.\t+\t.\t\n";
+        let records = read_gene_records(&gtf[..], "gene").unwrap();
+        assert_eq!((records[0].name.as_str(), records[0].tss()), ("ENSG1", 7));
+        assert_eq!(records[1].name, "chr2:1-2");
+
+        assert!(read_gene_records(&b"chr1\tsrc\tgene\t3\t6\n"[..], "gene").is_err());
+        assert!(read_gene_records(&b"chr1\tsrc\tgene\t0\t6\t.\t+\t.\t\n"[..], "gene").is_err());
+    }
+
+    #[test]
+    fn test_promoters() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nAACCGGTTAC\n").unwrap();
+        let gene = |start: usize, end: usize, strand: Strand, name: &str| GeneRecord { contig: "chr1".to_string(), start, end, strand, name: name.to_string() };
+        let genes = vec![gene(4, 8, Strand::Forward, "plus"), gene(2, 6, Strand::Reverse, "minus"), gene(1, 3, Strand::Forward, "edge")];
+        let regions = reference_genome.promoter_regions(&genes, 3, 2).unwrap();
+        assert_eq!((regions[0].start, regions[0].end, regions[0].clipped), (1, 6, false));
+        // the TSS of the minus gene is position 5, so the window is 4..9 on the forward strand
+        assert_eq!((regions[1].start, regions[1].end, regions[1].clipped), (4, 9, false));
+        assert_eq!((regions[2].start, regions[2].end, regions[2].clipped), (0, 3, true));
+
+        let (mut fasta, mut bed) = (vec![], vec![]);
+        assert_eq!(reference_genome.write_promoters(&regions[..2], &mut fasta, &mut bed, 60).unwrap(), 2);
+        assert_eq!(String::from_utf8(fasta).unwrap(), ">plus::chr1:1-6(+)\nACCGG\n>minus::chr1:4-9(-)\nTAACC\n");
+        assert_eq!(String::from_utf8(bed).unwrap(), "chr1\t1\t6\tplus\t0\t+\nchr1\t4\t9\tminus\t0\t-\n");
+
+        assert!(reference_genome.promoter_regions(&[GeneRecord { contig: "chr2".to_string(), ..gene(1, 3, Strand::Forward, "x") }], 3, 2).is_err());
+        assert!(reference_genome.promoter_regions(&[gene(10, 12, Strand::Forward, "past")], 3, 2).is_err());
+    }
+}
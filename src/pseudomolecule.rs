@@ -0,0 +1,253 @@
+use crate::reference_genome::{ContigSequence, ReferenceGenome};
+use bytes::Bytes;
+use rustc_hash::FxHashSet as HashSet;
+use simple_error::{bail, SimpleError};
+use std::io::Write;
+
+/// The default N spacer length, which is the conventional size of AGP gaps of unknown length
+pub const DEFAULT_SPACER_LENGTH: usize = 100;
+
+/// The location of a source contig within a pseudo-molecule
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Placement {
+    /// The pseudo-molecule name
+    pub molecule: String,
+    /// The source contig name
+    pub contig: String,
+    /// The 0-based offset of the contig in the pseudo-molecule; add it to contig coordinates to get molecule coordinates
+    pub start: usize,
+    /// The contig length
+    pub length: usize
+}
+
+/// Result of `PseudomoleculeBuilder::build(...)`
+#[derive(Clone)]
+pub struct Pseudomolecules {
+    /// The pseudo-molecules, followed by any unplaced contigs that were kept
+    pub genome: ReferenceGenome,
+    /// Where each source contig was placed, in output order; kept unplaced contigs are placed at offset 0 of themselves
+    pub placements: Vec<Placement>,
+    /// The N spacer length between adjacent contigs
+    pub spacer_length: usize
+}
+
+impl Pseudomolecules {
+    /// Finds the placement of a source contig, or `None` if it was not placed
+    /// # Arguments
+    /// * `contig` - the source contig name
+    pub fn placement(&self, contig: &str) -> Option<&Placement> {
+        self.placements.iter().find(|p| p.contig == contig)
+    }
+
+    /// Writes an AGP v2.1 file describing how the output was assembled from the source contigs.
+    /// Spacers are written as unspanned gaps between contigs (gap type `contig`, linkage `no`), using component type `U`
+    /// for the conventional 100 bp spacer of unknown length and `N` for any other length.
+    /// # Arguments
+    /// * `writer` - where the AGP is written
+    /// # Errors
+    /// * any writing errors
+    pub fn write_agp<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "##agp-version\t2.1")?;
+        let gap_type = if self.spacer_length == DEFAULT_SPACER_LENGTH { "U" } else { "N" };
+        let mut previous: Option<&str> = None;
+        let mut part_number = 0;
+        for placement in self.placements.iter() {
+            if previous == Some(placement.molecule.as_str()) {
+                if self.spacer_length > 0 {
+                    part_number += 1;
+                    writeln!(
+                        writer, "{}\t{}\t{}\t{}\t{}\t{}\tcontig\tno\tna",
+                        placement.molecule, placement.start - self.spacer_length + 1, placement.start,
+                        part_number, gap_type, self.spacer_length
+                    )?;
+                }
+            } else {
+                part_number = 0;
+            }
+            part_number += 1;
+            writeln!(
+                writer, "{}\t{}\t{}\t{}\tW\t{}\t1\t{}\t+",
+                placement.molecule, placement.start + 1, placement.start + placement.length,
+                part_number, placement.contig, placement.length
+            )?;
+            previous = Some(placement.molecule.as_str());
+        }
+        Ok(())
+    }
+}
+
+/// Builder that concatenates contigs into pseudo-molecules (e.g. pseudo-chromosomes of a draft assembly), separated by N spacers
+/// # Examples
+/// ```
+/// use rust_lib_reference_genome::pseudomolecule::PseudomoleculeBuilder;
+/// use rust_lib_reference_genome::reference_genome::ReferenceGenome;
+///
+/// let reference_genome = ReferenceGenome::from_fasta_bytes(b">ctg1\nACGT\n>ctg2\nGG\n").unwrap();
+/// let pseudomolecules = PseudomoleculeBuilder::new()
+///     .spacer_length(3)
+///     .molecule("chr1", &["ctg1", "ctg2"])
+///     .build(&reference_genome)
+///     .unwrap();
+/// assert_eq!(pseudomolecules.genome.get_full_chromosome("chr1"), b"ACGTNNNGG");
+/// assert_eq!(pseudomolecules.placement("ctg2").unwrap().start, 7);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PseudomoleculeBuilder {
+    /// The N spacer length between adjacent contigs
+    spacer_length: usize,
+    /// The pseudo-molecules as (name, source contigs in order)
+    molecules: Vec<(String, Vec<String>)>,
+    /// If true, contigs that are not in any pseudo-molecule are kept as they are
+    keep_unplaced: bool
+}
+
+impl Default for PseudomoleculeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PseudomoleculeBuilder {
+    /// Creates a builder without any pseudo-molecules
+    pub fn new() -> Self {
+        Self {
+            spacer_length: DEFAULT_SPACER_LENGTH,
+            molecules: vec![],
+            keep_unplaced: false
+        }
+    }
+
+    /// Sets the number of N bases between adjacent contigs, default is `DEFAULT_SPACER_LENGTH`
+    pub fn spacer_length(mut self, spacer_length: usize) -> Self {
+        self.spacer_length = spacer_length;
+        self
+    }
+
+    /// Adds a pseudo-molecule; pseudo-molecules are output in the order they are added
+    /// # Arguments
+    /// * `name` - the pseudo-molecule name
+    /// * `contigs` - the source contigs, concatenated in the given order on the forward strand
+    pub fn molecule(mut self, name: &str, contigs: &[&str]) -> Self {
+        self.molecules.push((name.to_string(), contigs.iter().map(|c| c.to_string()).collect()));
+        self
+    }
+
+    /// Sets whether contigs that are not in any pseudo-molecule are kept after the pseudo-molecules, in load order, default is false
+    pub fn keep_unplaced(mut self, keep_unplaced: bool) -> Self {
+        self.keep_unplaced = keep_unplaced;
+        self
+    }
+
+    /// Builds the pseudo-molecules from the contigs of a reference genome. Lazily loaded source contigs are read once and not kept in memory;
+    /// kept unplaced contigs share their sequence with the source genome.
+    /// # Arguments
+    /// * `reference_genome` - the genome containing the source contigs
+    /// # Errors
+    /// * if a pseudo-molecule is empty, or a source contig is missing, empty, unloaded, fails to load, or is placed more than once
+    /// * if an output name is used more than once, including by a kept unplaced contig
+    pub fn build(&self, reference_genome: &ReferenceGenome) -> Result<Pseudomolecules, SimpleError> {
+        let mut placed: HashSet<&str> = Default::default();
+        let mut placements: Vec<Placement> = vec![];
+        let mut contigs: Vec<(String, ContigSequence)> = vec![];
+        for (name, sources) in self.molecules.iter() {
+            if sources.is_empty() {
+                bail!("Pseudo-molecule {:?} has no contigs", name);
+            }
+            let mut sequence: Vec<u8> = vec![];
+            for source in sources.iter() {
+                if !placed.insert(source) {
+                    bail!("Contig {:?} is placed more than once", source);
+                }
+                let source_sequence = reference_genome.try_sequence_unkept(source)?;
+                if source_sequence.is_empty() {
+                    bail!("Contig {:?} is empty and cannot be placed", source);
+                }
+                if !sequence.is_empty() {
+                    sequence.resize(sequence.len() + self.spacer_length, b'N');
+                }
+                placements.push(Placement {
+                    molecule: name.clone(),
+                    contig: source.clone(),
+                    start: sequence.len(),
+                    length: source_sequence.len()
+                });
+                sequence.extend_from_slice(&source_sequence);
+            }
+            contigs.push((name.clone(), ContigSequence::Loaded(Bytes::from(sequence))));
+        }
+
+        if self.keep_unplaced {
+            for contig in reference_genome.contig_keys().iter() {
+                if placed.contains(contig.as_str()) {
+                    continue;
+                }
+                placements.push(Placement {
+                    molecule: contig.clone(),
+                    contig: contig.clone(),
+                    start: 0,
+                    length: reference_genome.contig_length(contig).unwrap()
+                });
+                contigs.push((contig.clone(), reference_genome.contig_sequence(contig).unwrap().clone()));
+            }
+        }
+
+        let genome = ReferenceGenome::from_contigs(reference_genome.filename().to_path_buf(), contigs)?;
+        Ok(Pseudomolecules {
+            genome,
+            placements,
+            spacer_length: self.spacer_length
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pseudomolecules() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">ctg1\nACGT\n>ctg2\nGG\n>ctg3\nT\n>ctg4\nCA\n").unwrap();
+        let pseudomolecules = PseudomoleculeBuilder::new()
+            .spacer_length(3)
+            .molecule("chr1", &["ctg1", "ctg2", "ctg3"])
+            .keep_unplaced(true)
+            .build(&reference_genome)
+            .unwrap();
+        assert_eq!(pseudomolecules.genome.contig_keys(), &["chr1", "ctg4"]);
+        assert_eq!(pseudomolecules.genome.get_full_chromosome("chr1"), b"ACGTNNNGGNNNT");
+        assert_eq!(pseudomolecules.genome.get_full_chromosome("ctg4"), b"CA");
+        let offsets: Vec<(&str, &str, usize, usize)> = pseudomolecules.placements.iter()
+            .map(|p| (p.molecule.as_str(), p.contig.as_str(), p.start, p.length))
+            .collect();
+        assert_eq!(offsets, vec![
+            ("chr1", "ctg1", 0, 4),
+            ("chr1", "ctg2", 7, 2),
+            ("chr1", "ctg3", 12, 1),
+            ("ctg4", "ctg4", 0, 2)
+        ]);
+
+        let mut agp: Vec<u8> = vec![];
+        pseudomolecules.write_agp(&mut agp).unwrap();
+        assert_eq!(String::from_utf8(agp).unwrap(), "##agp-version\t2.1\n\
+            chr1\t1\t4\t1\tW\tctg1\t1\t4\t+\n\
+            chr1\t5\t7\t2\tN\t3\tcontig\tno\tna\n\
+            chr1\t8\t9\t3\tW\tctg2\t1\t2\t+\n\
+            chr1\t10\t12\t4\tN\t3\tcontig\tno\tna\n\
+            chr1\t13\t13\t5\tW\tctg3\t1\t1\t+\n\
+            ctg4\t1\t2\t1\tW\tctg4\t1\t2\t+\n");
+
+        // unplaced contigs are dropped by default
+        let pseudomolecules = PseudomoleculeBuilder::new()
+            .molecule("chr1", &["ctg4", "ctg1"])
+            .build(&reference_genome)
+            .unwrap();
+        assert_eq!(pseudomolecules.genome.contig_keys(), &["chr1"]);
+        assert_eq!(pseudomolecules.genome.contig_length("chr1"), Some(106));
+        assert_eq!(pseudomolecules.placement("ctg1").unwrap().start, 102);
+
+        assert!(PseudomoleculeBuilder::new().molecule("chr1", &["ctg1", "ctg1"]).build(&reference_genome).is_err());
+        assert!(PseudomoleculeBuilder::new().molecule("chr1", &["missing"]).build(&reference_genome).is_err());
+        assert!(PseudomoleculeBuilder::new().molecule("chr1", &[]).build(&reference_genome).is_err());
+        assert!(PseudomoleculeBuilder::new().molecule("ctg4", &["ctg1"]).keep_unplaced(true).build(&reference_genome).is_err());
+    }
+}
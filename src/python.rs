@@ -0,0 +1,108 @@
+use crate::alphabet;
+use crate::builder::{Backend, ReferenceGenomeBuilder};
+use crate::digest;
+use crate::reference_genome::ReferenceGenome;
+use pyo3::exceptions::{PyIOError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::path::PathBuf;
+
+/// Python wrapper for `ReferenceGenome`, exposed as `ReferenceGenome`
+#[pyclass(name = "ReferenceGenome", module = "rust_lib_reference_genome", frozen)]
+struct PyReferenceGenome {
+    /// The wrapped genome
+    inner: ReferenceGenome
+}
+
+impl PyReferenceGenome {
+    /// Converts a missing contig into a Python `KeyError` with suggestions
+    fn check_contig(&self, contig: &str) -> PyResult<()> {
+        match self.inner.resolve_contig_name(contig) {
+            Some(_) => Ok(()),
+            None => Err(PyKeyError::new_err(self.inner.missing_contig_message(contig)))
+        }
+    }
+}
+
+#[pymethods]
+impl PyReferenceGenome {
+    /// Loads a reference genome from a FASTA file, directory, or wildcard pattern.
+    /// If `mmap` is true, the FASTA is memory-mapped and contigs are decoded on first access.
+    #[new]
+    #[pyo3(signature = (path, uppercase=true, mmap=false))]
+    fn new(path: PathBuf, uppercase: bool, mmap: bool) -> PyResult<Self> {
+        let backend = if mmap { Backend::Mmap } else { Backend::InMemory };
+        let inner = ReferenceGenomeBuilder::new(&path)
+            .uppercase(uppercase)
+            .backend(backend)
+            .build()
+            .map_err(|e| PyIOError::new_err(e.to_string()))?;
+        Ok(Self { inner })
+    }
+
+    /// The contig names in load order
+    fn contig_keys(&self) -> Vec<String> {
+        self.inner.contig_keys().to_vec()
+    }
+
+    /// The length of a contig, or `None` if it is not in the reference genome
+    fn contig_length(&self, contig: &str) -> Option<usize> {
+        self.inner.contig_length(contig)
+    }
+
+    /// Fetches a 0-based, half-open region as `bytes`; the full contig is returned if `start` and `end` are omitted.
+    /// Coordinates past the end of the contig are truncated. Raises `KeyError` for unknown contigs.
+    #[pyo3(signature = (contig, start=0, end=None, reverse_complement=false))]
+    fn fetch<'py>(&self, py: Python<'py>, contig: &str, start: usize, end: Option<usize>, reverse_complement: bool) -> PyResult<Bound<'py, PyBytes>> {
+        self.check_contig(contig)?;
+        let end = end.unwrap_or(usize::MAX);
+        if start > end {
+            return Err(PyValueError::new_err(format!("start > end: {start} > {end}")));
+        }
+        let length = self.inner.contig_length(contig).unwrap_or_default();
+        let sequence = self.inner.get_slice_shared(contig, start.min(length), end.min(length));
+        if reverse_complement {
+            Ok(PyBytes::new(py, &alphabet::reverse_complement(&sequence)))
+        } else {
+            Ok(PyBytes::new(py, &sequence))
+        }
+    }
+
+    /// The lower-case hexadecimal MD5 digest of a contig's upper-cased sequence, matching the `M5` tag of a SAM header
+    fn contig_md5(&self, contig: &str) -> PyResult<String> {
+        self.check_contig(contig)?;
+        let name = self.inner.resolve_contig_name(contig).unwrap();
+        self.inner.try_contig_digests(name)
+            .map(|d| d.md5.clone())
+            .map_err(|e| PyIOError::new_err(e.to_string()))
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.contig_keys().len()
+    }
+
+    fn __contains__(&self, contig: &str) -> bool {
+        self.inner.resolve_contig_name(contig).is_some()
+    }
+}
+
+/// Computes the reverse complement of a sequence, preserving case and IUPAC codes
+#[pyfunction]
+fn reverse_complement<'py>(py: Python<'py>, sequence: &[u8]) -> Bound<'py, PyBytes> {
+    PyBytes::new(py, &alphabet::reverse_complement(sequence))
+}
+
+/// Computes the lower-case hexadecimal MD5 digest of a sequence
+#[pyfunction]
+fn md5_hex(sequence: &[u8]) -> String {
+    digest::md5_hex(sequence)
+}
+
+/// The `rust_lib_reference_genome` Python module
+#[pymodule]
+fn rust_lib_reference_genome(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyReferenceGenome>()?;
+    m.add_function(wrap_pyfunction!(reverse_complement, m)?)?;
+    m.add_function(wrap_pyfunction!(md5_hex, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use crate::digest::md5_hex_uppercase;
+use crate::reference_genome::ReferenceGenome;
+use crate::store::write_file_atomically;
+use crate::user_dirs::{default_cache_dir, non_empty_env};
+use rustc_hash::FxHashSet as HashSet;
+use simple_error::{bail, SimpleError};
+use std::path::PathBuf;
+
+/// The cache layout htslib uses when `REF_CACHE` is not set, relative to the user cache directory
+const DEFAULT_CACHE_LAYOUT: &str = "hts-ref/%2s/%2s/%s";
+
+/// Returns the htslib `REF_CACHE` path template in effect: the `REF_CACHE` environment variable if set,
+/// otherwise `hts-ref/%2s/%2s/%s` under `$XDG_CACHE_HOME` or `$HOME/.cache`
+/// # Returns
+/// * the template, or `None` if none of the environment variables are set
+pub fn default_ref_cache_template() -> Option<String> {
+    if let Some(template) = non_empty_env("REF_CACHE") {
+        return Some(template);
+    }
+    default_cache_dir(DEFAULT_CACHE_LAYOUT).map(|template| template.to_string_lossy().to_string())
+}
+
+/// Expands an htslib `REF_CACHE` path template for a digest, following htslib: `%Ns` takes the next N digest characters,
+/// `%s` takes the rest, and any characters left over are appended as a final path component.
+/// For example, `/cache/%2s/%2s/%s` places `cc0af3a4...` at `/cache/cc/0a/f3a4...`.
+/// # Arguments
+/// * `template` - the path template
+/// * `md5` - the lower-case hexadecimal MD5 digest
+/// # Errors
+/// * if the template is empty or `md5` is not 32 hexadecimal characters
+pub fn ref_cache_path(template: &str, md5: &str) -> Result<PathBuf, SimpleError> {
+    if template.is_empty() {
+        bail!("The REF_CACHE template must not be empty");
+    }
+    if md5.len() != 32 || !md5.bytes().all(|c| c.is_ascii_hexdigit()) {
+        bail!("Invalid MD5 digest: {:?}", md5);
+    }
+    let mut path = String::new();
+    let mut remaining = md5;
+    let mut rest = template;
+    while let Some(percent) = rest.find('%') {
+        path.push_str(&rest[..percent]);
+        rest = &rest[percent + 1..];
+        let digits = rest.bytes().take_while(|c| c.is_ascii_digit()).count();
+        if rest[digits..].starts_with('s') {
+            let take = if digits == 0 {
+                remaining.len()
+            } else {
+                rest[..digits].parse::<usize>().unwrap_or(usize::MAX).min(remaining.len())
+            };
+            path.push_str(&remaining[..take]);
+            remaining = &remaining[take..];
+            rest = &rest[digits + 1..];
+        } else {
+            // not a substitution, kept as-is
+            path.push('%');
+        }
+    }
+    path.push_str(rest);
+    if !remaining.is_empty() {
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+        path.push_str(remaining);
+    }
+    Ok(PathBuf::from(path))
+}
+
+impl ReferenceGenome {
+    /// Populates an htslib-style `REF_CACHE` with one file per contig, named by its MD5 digest and holding the upper-cased sequence
+    /// without line breaks, so CRAM tools on the same host find these references without downloading them.
+    /// Contigs already in the cache are skipped, and files are renamed into place so concurrent readers never see partial sequences.
+    /// Lazily loaded contigs are read once and are not kept in memory.
+    /// # Arguments
+    /// * `template` - the cache path template, see `ref_cache_path(...)` and `default_ref_cache_template()`
+    /// # Returns
+    /// * the number of files written
+    /// # Errors
+    /// * if the template is empty
+    /// * if a contig was unloaded or fails to load
+    /// * any directory creation and/or file writing errors
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn populate_ref_cache(&self, template: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut seen: HashSet<String> = Default::default();
+        let mut written = 0;
+        for contig in self.contig_keys().iter() {
+            let sequence = self.try_sequence_unkept(contig)?;
+            let md5 = md5_hex_uppercase(&sequence);
+            let path = ref_cache_path(template, &md5)?;
+            if !seen.insert(md5) || path.is_file() {
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            write_file_atomically(&path, &sequence.to_ascii_uppercase())?;
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ReferenceGenomeBuilder;
+
+    #[test]
+    fn test_ref_cache_path() {
+        let md5 = "cc0af3a4fedb18378b4b57b98068e69f";
+        assert_eq!(ref_cache_path("/cache/%2s/%2s/%s", md5).unwrap(), PathBuf::from("/cache/cc/0a/f3a4fedb18378b4b57b98068e69f"));
+        assert_eq!(ref_cache_path("/cache", md5).unwrap(), PathBuf::from(format!("/cache/{md5}")));
+        assert_eq!(ref_cache_path("/cache/%3s/", md5).unwrap(), PathBuf::from("/cache/cc0/af3a4fedb18378b4b57b98068e69f"));
+        assert_eq!(ref_cache_path("/cache%d/%s", md5).unwrap(), PathBuf::from(format!("/cache%d/{md5}")));
+        assert!(ref_cache_path("", md5).is_err());
+        assert!(ref_cache_path("/cache/%s", "chr1").is_err());
+    }
+
+    #[test]
+    fn test_populate_ref_cache() {
+        let reference_genome = ReferenceGenomeBuilder::from_bytes(&b">chr1\nacgt\n>chr2\nACGT\n>chr3\nGG\n"[..])
+            .uppercase(false)
+            .build()
+            .unwrap();
+        let directory = std::env::temp_dir().join(format!("refgenome_ref_cache_{}", std::process::id()));
+        let template = format!("{}/%2s/%2s/%s", directory.to_string_lossy());
+        // chr1 and chr2 share a digest
+        assert_eq!(reference_genome.populate_ref_cache(&template).unwrap(), 2);
+        assert_eq!(reference_genome.populate_ref_cache(&template).unwrap(), 0);
+
+        let md5 = md5_hex_uppercase(b"ACGT");
+        let path = ref_cache_path(&template, &md5).unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), b"ACGT");
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1,20 +1,141 @@
 
-use bio::io::fasta;
-use flate2::bufread::MultiGzDecoder;
-use log::{debug, warn};
-use rustc_hash::FxHashMap as HashMap;
+use crate::builder::ReferenceGenomeBuilder;
+use crate::contig_index::ContigIndex;
+use crate::digest::{DigestCache, Md5Manifest};
+use crate::fractions::{RunIndex, RunIndexCache};
+use crate::lazy::LazyContig;
+use crate::metadata::AssemblyMetadata;
+use crate::metrics::LoadMetrics;
+use crate::warnings::{Warning, WarningChannel};
+use crate::writer::RecordFormat;
+use bytes::Bytes;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
 use simple_error::{bail, SimpleError};
-use std::io::{BufRead, BufReader};
+use std::cmp::Ordering;
+use std::ops::{Index, Range};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 
-/// Wrapper structure for a reference genome
+/// Identifier for a contig, which is its 0-based position in `ReferenceGenome::contig_keys()`
+pub type ContigId = u32;
+
+/// How the slice accessors handle requests that extend past the end of a contig, see `ReferenceGenome::set_out_of_bounds_policy(...)`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OutOfBoundsPolicy {
+    /// The request fails: `get_slice(...)` and `get_slice_shared(...)` panic, and `get_slice_checked(...)` returns an error
+    Error,
+    /// The request is truncated to the contig length, with a warning
+    #[default]
+    Clamp,
+    /// The result has the requested length, with `N` for every position past the contig end.
+    /// A borrowed slice cannot hold padding, so this policy is only accepted per call by `get_slice_checked(...)`,
+    /// and `set_out_of_bounds_policy(...)` rejects it.
+    PadWithN
+}
+
+/// Storage for the sequence of a single contig
+#[derive(Clone)]
+pub(crate) enum ContigSequence {
+    /// ASCII sequence held in memory, reference counted so slices can be shared
+    Loaded(Bytes),
+    /// ASCII sequence read from a lazy backend on first access
+    Lazy(LazyContig),
+    /// ASCII sequence in a read-only shared memory segment, which other processes may map too
+    Mapped(Bytes),
+    /// The sequence was released by `unload_contig(...)`, only the length is kept
+    Unloaded(usize)
+}
+
+impl ContigSequence {
+    /// Retrieves the ASCII sequence, loading and keeping a lazily loaded sequence
+    /// # Errors
+    /// * if the sequence was unloaded or a lazy backend fails to read it
+    pub(crate) fn try_as_slice(&self, contig: &str) -> Result<&[u8], Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            ContigSequence::Loaded(sequence) | ContigSequence::Mapped(sequence) => Ok(sequence),
+            ContigSequence::Lazy(lazy_contig) => lazy_contig.try_sequence(),
+            ContigSequence::Unloaded(_) => Err(format!("Contig key \"{contig}\" has been unloaded").into())
+        }
+    }
+
+    /// Retrieves a shared handle to the ASCII sequence without copying it, or `None` if it was unloaded
+    pub(crate) fn try_as_bytes(&self) -> Option<Bytes> {
+        match self {
+            ContigSequence::Loaded(sequence) | ContigSequence::Mapped(sequence) => Some(sequence.clone()),
+            ContigSequence::Lazy(contig) => Some(contig.sequence_bytes()),
+            ContigSequence::Unloaded(_) => None
+        }
+    }
+
+    /// Retrieves a shared handle to the ASCII sequence without keeping a lazily loaded sequence in memory afterwards
+    /// # Errors
+    /// * if the sequence was unloaded or a lazy backend fails to read it
+    pub(crate) fn try_as_bytes_unkept(&self, contig: &str) -> Result<Bytes, Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            ContigSequence::Loaded(sequence) | ContigSequence::Mapped(sequence) => Ok(sequence.clone()),
+            ContigSequence::Lazy(lazy_contig) => lazy_contig.try_sequence_bytes_unkept(),
+            ContigSequence::Unloaded(_) => Err(format!("Contig key \"{contig}\" has been unloaded").into())
+        }
+    }
+
+    /// Returns true if a borrowed access would keep a lazily loaded sequence outside of the LRU cache
+    pub(crate) fn bypasses_cache(&self) -> bool {
+        match self {
+            ContigSequence::Lazy(contig) => contig.bypasses_cache(),
+            _ => false
+        }
+    }
+
+    /// The sequence length, which never requires loading the sequence
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            ContigSequence::Loaded(sequence) | ContigSequence::Mapped(sequence) => sequence.len(),
+            ContigSequence::Lazy(contig) => contig.len(),
+            ContigSequence::Unloaded(length) => *length
+        }
+    }
+
+    /// The heap bytes used by the sequence, or 0 if it is not currently loaded or is in shared memory.
+    /// Sequences are stored with no spare capacity, so this is the sequence length once loaded.
+    pub(crate) fn heap_bytes(&self) -> usize {
+        match self {
+            ContigSequence::Loaded(sequence) => sequence.len(),
+            ContigSequence::Lazy(contig) => contig.heap_bytes(),
+            ContigSequence::Mapped(_) | ContigSequence::Unloaded(_) => 0
+        }
+    }
+
+}
+
+/// Wrapper structure for a reference genome.
+/// Cloning is cheap: the clone shares contig names and sequences with the original, and a contig is only copied
+/// when one of the genomes modifies it (e.g. `modify_contig(...)`), so many lightly modified variants can share one base genome.
+#[derive(Clone)]
 pub struct ReferenceGenome {
     /// The filename we loaded 
     filename: PathBuf,
-    /// Contains the keys in order of the reference load
-    contig_keys: Vec<String>,
-    /// Map where keys are contig names and value is ASCII formatted sequence
-    contig_map: HashMap<String, Vec<u8>>
+    /// Interned contig names in order of the reference load, shared between clones until modified
+    contigs: Arc<ContigIndex>,
+    /// Sequences indexed by `ContigId`, shared between clones until modified
+    sequences: Arc<Vec<ContigSequence>>,
+    /// If true, sequence lookups fall back to case-insensitive and chr-prefix tolerant matching
+    normalized_lookup: bool,
+    /// Original record formats by contig name, only populated by `ReferenceGenomeBuilder::preserve_format(...)`
+    record_formats: Arc<HashMap<String, RecordFormat>>,
+    /// Metrics of the load that produced this genome, only populated by `ReferenceGenomeBuilder`
+    load_metrics: Option<LoadMetrics>,
+    /// Records renamed at load by `DuplicatePolicy::Rename`, as (original ID, new name)
+    duplicate_renames: Arc<Vec<(String, String)>>,
+    /// How slice requests past the end of a contig are handled
+    out_of_bounds: OutOfBoundsPolicy,
+    /// Warnings raised by the load and by later reads, shared between clones
+    warnings: WarningChannel,
+    /// Descriptive metadata of the assembly, such as species and assembly name
+    assembly_metadata: Arc<AssemblyMetadata>,
+    /// Per-contig digests and the MD5 index, computed on first use and reset when sequences change
+    digests: Arc<OnceLock<DigestCache>>,
+    /// Per-contig run indexes for region fraction queries, computed on first use and reset when sequences change
+    run_indexes: Arc<RunIndexCache>
 }
 
 impl ReferenceGenome {
@@ -22,49 +143,99 @@ impl ReferenceGenome {
     pub fn empty_reference() -> Self {
         Self {
             filename: PathBuf::from(""),
-            contig_keys: vec![],
-            contig_map: Default::default()
+            contigs: Default::default(),
+            sequences: Default::default(),
+            normalized_lookup: false,
+            record_formats: Default::default(),
+            load_metrics: None,
+            duplicate_renames: Default::default(),
+            out_of_bounds: Default::default(),
+            warnings: Default::default(),
+            assembly_metadata: Default::default(),
+            digests: Default::default(),
+            run_indexes: Default::default()
         }
     }
 
-    /// Loads a reference genome from a given FASTA file
+    /// Loads a reference genome from a given FASTA file.
+    /// See `ReferenceGenomeBuilder` for additional load options, including how multi-file references are ordered.
     /// # Arguments
-    /// * `fasta_fn` - the FASTA filename, gzip is allowed
+    /// * `fasta_fn` - the FASTA filename, gzip is allowed; a directory or a `*`/`?`/`[...]` wildcard pattern loads multiple files
     /// # Errors
     /// This will pass through any error detected from loading the provided FASTA file.
-    /// This includes file reading and/or record reading errors.
+    /// This includes file reading and/or record reading errors, as well as duplicate contig names.
     pub fn from_fasta(fasta_fn: &Path) -> Result<ReferenceGenome, Box<dyn std::error::Error>> {
-        debug!("Loading {:?}...", fasta_fn);
-        let mut contig_keys: Vec<String> = Default::default();
-        let mut contig_map: HashMap<String, Vec<u8>> = Default::default();
-        
-        // needletail can technically read FASTA and FASTQ, not sure we can check for that easy though
-        let fasta_file: std::fs::File = std::fs::File::open(fasta_fn)?;
-        let file_reader = BufReader::new(fasta_file);
-        let fasta_reader: fasta::Reader<Box<dyn BufRead>> = if fasta_fn.extension().unwrap_or_default() == "gz" {
-            debug!("Detected gzip extension, loading reference with MultiGzDecoder...");
-            let gz_decoder = MultiGzDecoder::new(file_reader);
-            let bufreader = BufReader::new(gz_decoder);
-            fasta::Reader::from_bufread(Box::new(bufreader))
-        } else {
-            debug!("Loading reference as plain-text file...");
-            fasta::Reader::from_bufread(Box::new(file_reader))
-        };
+        ReferenceGenomeBuilder::new(fasta_fn).build()
+    }
+
+    /// Loads a reference genome and verifies it against expected MD5 digests, see `ReferenceGenomeBuilder::verify_md5(...)`
+    /// # Arguments
+    /// * `fasta_fn` - the FASTA filename, directory, or wildcard pattern
+    /// * `manifest` - the expected digests, e.g. from `Md5Manifest::from_dict(...)`
+    /// # Errors
+    /// * any error from `from_fasta(...)`
+    /// * if a contig's digest differs from the manifest, or the contig names do not match the manifest
+    pub fn from_fasta_verified(fasta_fn: &Path, manifest: &Md5Manifest) -> Result<ReferenceGenome, Box<dyn std::error::Error>> {
+        ReferenceGenomeBuilder::new(fasta_fn).verify_md5(manifest.clone()).build()
+    }
+
+    /// Loads a reference genome from FASTA content in memory, which may be gzip-compressed.
+    /// This does not touch the filesystem, so it can be used on targets such as `wasm32-unknown-unknown`.
+    /// See `ReferenceGenomeBuilder::from_bytes(...)` for additional load options.
+    /// # Arguments
+    /// * `data` - the FASTA content
+    /// # Errors
+    /// This will pass through any error detected from decompressing or parsing the content, as well as duplicate contig names.
+    pub fn from_fasta_bytes(data: &[u8]) -> Result<ReferenceGenome, Box<dyn std::error::Error>> {
+        ReferenceGenomeBuilder::from_bytes(data.to_vec()).build()
+    }
 
-        for entry in fasta_reader.records() {
-            let record: fasta::Record = entry?;
-            let seq_id: String = record.id().to_string();
-            let sequence: Vec<u8> = record.seq().to_ascii_uppercase();
+    /// Assembles a reference genome from in-memory sequences, such as the results of a database query, preserving their order.
+    /// Sequences are upper-cased, matching `add_contig(...)`, without any intermediate string conversion.
+    /// See also `from_noodles_records(...)` and `from_bio_records(...)` for FASTA records of those crates.
+    /// # Arguments
+    /// * `contigs` - pairs of (contig name, ASCII sequence)
+    /// # Errors
+    /// * if a contig name is present more than once
+    pub fn from_sequences<I, N, S>(contigs: I) -> Result<ReferenceGenome, SimpleError>
+        where I: IntoIterator<Item = (N, S)>, N: Into<String>, S: Into<Vec<u8>> {
+        let contigs: Vec<(String, ContigSequence)> = contigs.into_iter()
+            .map(|(name, sequence)| {
+                let mut sequence: Vec<u8> = sequence.into();
+                sequence.make_ascii_uppercase();
+                (name.into(), ContigSequence::Loaded(Bytes::from(sequence)))
+            })
+            .collect();
+        ReferenceGenome::from_contigs(PathBuf::from(""), contigs)
+    }
 
-            contig_keys.push(seq_id.clone());
-            contig_map.insert(seq_id, sequence);
+    /// Assembles a reference genome from loaded contigs, preserving their order
+    /// # Arguments
+    /// * `filename` - the filename the contigs were loaded from
+    /// * `contigs` - pairs of (contig name, sequence)
+    /// # Errors
+    /// * if a contig name is present more than once
+    pub(crate) fn from_contigs(filename: PathBuf, contigs: Vec<(String, ContigSequence)>) -> Result<ReferenceGenome, SimpleError> {
+        let mut index = ContigIndex::with_capacity(contigs.len());
+        let mut sequences: Vec<ContigSequence> = Vec::with_capacity(contigs.len());
+        for (seq_id, sequence) in contigs.into_iter() {
+            index.insert(seq_id)?;
+            sequences.push(sequence);
         }
-        debug!("Finished loading {} contigs.", contig_map.len());
 
         Ok(ReferenceGenome {
-            filename: fasta_fn.to_path_buf(),
-            contig_keys,
-            contig_map
+            filename,
+            contigs: Arc::new(index),
+            sequences: Arc::new(sequences),
+            normalized_lookup: false,
+            record_formats: Default::default(),
+            load_metrics: None,
+            duplicate_renames: Default::default(),
+            out_of_bounds: Default::default(),
+            warnings: Default::default(),
+            assembly_metadata: Default::default(),
+            digests: Default::default(),
+            run_indexes: Default::default()
         })
     }
 
@@ -73,48 +244,437 @@ impl ReferenceGenome {
     /// * `contig_key` - the name of the contig
     /// * `contig_sequence` - the sequence to add; all sequence is automatically upper-cased
     pub fn add_contig(&mut self, contig_key: String, contig_sequence: &str) -> Result<(), SimpleError> {
-        if self.contig_map.contains_key(&contig_key) {
-            bail!("Contig key \"{contig_key}\" is already in the reference genome");
-        }
+        Arc::make_mut(&mut self.contigs).insert(contig_key)?;
 
-        // create the uppercase byte form
+        // create the uppercase byte form and save it
         let byte_form = contig_sequence.to_ascii_uppercase().into_bytes();
-        
-        // save everything
-        self.contig_keys.push(contig_key.clone());
-        self.contig_map.insert(contig_key, byte_form);
+        Arc::make_mut(&mut self.sequences).push(ContigSequence::Loaded(Bytes::from(byte_form)));
+        self.digests = Default::default();
+        self.run_indexes = Default::default();
         Ok(())
     }
 
+    /// Renames contigs in place, preserving load order. All renames are applied simultaneously, so names can be swapped.
+    /// # Arguments
+    /// * `renames` - pairs of (old name, new name); contigs that are not listed keep their name
+    /// # Errors
+    /// * if an old name is not in the reference genome or is listed more than once
+    /// * if the renamed genome would contain duplicate contig names
+    pub fn rename_contigs(&mut self, renames: &[(String, String)]) -> Result<(), SimpleError> {
+        let mut rename_map: HashMap<&str, &str> = Default::default();
+        for (old_name, new_name) in renames.iter() {
+            if self.contigs.get(old_name).is_none() {
+                bail!("{}", self.missing_contig_message(old_name));
+            }
+            if rename_map.insert(old_name, new_name).is_some() {
+                bail!("Contig key \"{old_name}\" is renamed more than once");
+            }
+        }
+
+        let new_keys: Vec<String> = self.contigs.names().iter()
+            .map(|k| rename_map.get(k.as_str()).map(|n| n.to_string()).unwrap_or_else(|| k.clone()))
+            .collect();
+        let mut observed: HashSet<&str> = Default::default();
+        for (old_key, new_key) in self.contigs.names().iter().zip(new_keys.iter()) {
+            if !observed.insert(new_key) {
+                bail!("Renaming \"{old_key}\" to \"{new_key}\" would create a duplicate contig key");
+            }
+        }
+
+        // everything is valid, and sequences keep their ids
+        if !self.record_formats.is_empty() {
+            self.record_formats = Arc::new(self.record_formats.iter()
+                .map(|(k, f)| (rename_map.get(k.as_str()).map(|n| n.to_string()).unwrap_or_else(|| k.clone()), f.clone()))
+                .collect());
+        }
+        self.contigs = Arc::new(ContigIndex::from_names(new_keys)?);
+        Ok(())
+    }
+
+    /// Sorts the contig order using a comparator on contig names
+    /// # Arguments
+    /// * `compare` - the comparison function for two contig names
+    pub fn sort_contigs_by<F>(&mut self, mut compare: F) where F: FnMut(&str, &str) -> Ordering {
+        let names = self.contigs.names();
+        let mut order: Vec<usize> = (0..names.len()).collect();
+        order.sort_by(|&a, &b| compare(&names[a], &names[b]));
+        self.reorder(order);
+    }
+
+    /// Sorts the contig order naturally, such that numeric runs are compared by value (e.g. "chr2" before "chr10")
+    pub fn sort_contigs_natural(&mut self) {
+        self.sort_contigs_by(natural_cmp);
+    }
+
+    /// Sorts the contig order to match an explicit list, such as the header of an external file.
+    /// Any contigs not in the list are placed at the end in their current relative order.
+    /// # Arguments
+    /// * `order` - the contig names in the desired order
+    /// # Errors
+    /// * if `order` contains a contig that is not in the reference genome, or contains a contig more than once
+    pub fn sort_contigs_by_order(&mut self, order: &[String]) -> Result<(), SimpleError> {
+        let mut rank: HashMap<&str, usize> = Default::default();
+        for (i, contig) in order.iter().enumerate() {
+            if self.contigs.get(contig).is_none() {
+                bail!("{}", self.missing_contig_message(contig));
+            }
+            if rank.insert(contig, i).is_some() {
+                bail!("Contig key \"{contig}\" is in the order more than once");
+            }
+        }
+        // stable sort keeps the unlisted contigs in their current order
+        let names = self.contigs.names();
+        let mut new_order: Vec<usize> = (0..names.len()).collect();
+        new_order.sort_by_key(|&i| rank.get(names[i].as_str()).copied().unwrap_or(order.len()));
+        self.reorder(new_order);
+        Ok(())
+    }
+
+    /// Rearranges the contigs such that the contig at position `order[i]` moves to position `i`, reassigning ids
+    fn reorder(&mut self, order: Vec<usize>) {
+        let names = self.contigs.names();
+        let new_names: Vec<String> = order.iter().map(|&i| names[i].clone()).collect();
+        self.sequences = Arc::new(order.iter().map(|&i| self.sequences[i].clone()).collect());
+        self.digests = Default::default();
+        self.run_indexes = Default::default();
+        // a permutation of unique names is still unique
+        self.contigs = Arc::new(ContigIndex::from_names(new_names).unwrap());
+    }
+
     pub fn filename(&self) -> &Path {
         &self.filename
     }
 
     pub fn contig_keys(&self) -> &[String] {
-        &self.contig_keys
+        self.contigs.names()
+    }
+
+    /// Retrieves the id of a contig, which is its 0-based position in `contig_keys()`.
+    /// Ids are reassigned when contigs are sorted or renamed. No lookup normalization is applied.
+    /// # Arguments
+    /// * `chromosome` - the contig name
+    pub fn contig_id(&self, chromosome: &str) -> Option<ContigId> {
+        self.contigs.get(chromosome)
+    }
+
+    /// Retrieves the name of a contig from its id, or `None` if the id is out of range
+    /// # Arguments
+    /// * `id` - the contig id, see `contig_id(...)`
+    pub fn contig_name(&self, id: ContigId) -> Option<&str> {
+        self.contigs.names().get(id as usize).map(|n| n.as_str())
+    }
+
+    /// Retrieves the id of the single contig whose name loosely matches a given name, see `resolve_loose_name(...)`
+    /// # Arguments
+    /// * `name` - the contig name to match
+    pub(crate) fn loose_contig_id(&self, name: &str) -> Option<ContigId> {
+        self.contigs.get_loose(name)
+    }
+
+    /// Retrieves a contig for a full sweep without keeping a lazily loaded sequence in memory afterwards
+    /// # Arguments
+    /// * `chromosome` - the contig name, resolved like `get(...)` when normalized lookup is enabled
+    /// # Errors
+    /// * if the contig is not in the reference genome, was unloaded, or fails to load
+    pub(crate) fn try_sequence_unkept(&self, chromosome: &str) -> Result<Bytes, SimpleError> {
+        match self.resolve_contig_name(chromosome) {
+            Some(name) => self.contig_sequence(name).unwrap().try_as_bytes_unkept(name).map_err(|e| SimpleError::new(e.to_string())),
+            None => bail!("{}", self.missing_contig_message(chromosome))
+        }
+    }
+
+    /// Retrieves the original header description and line width of a contig, which are only captured
+    /// when loading with `ReferenceGenomeBuilder::preserve_format(true)`
+    /// # Arguments
+    /// * `chromosome` - the contig name; no lookup normalization is applied
+    pub fn record_format(&self, chromosome: &str) -> Option<&RecordFormat> {
+        self.record_formats.get(chromosome)
+    }
+
+    /// Replaces the captured record formats, see `record_format(...)`
+    pub(crate) fn set_record_formats(&mut self, record_formats: HashMap<String, RecordFormat>) {
+        self.record_formats = Arc::new(record_formats);
+    }
+
+    /// The metrics of the load that produced this genome, such as wall time and bytes read, or `None` if it was not loaded
+    /// through `ReferenceGenomeBuilder` (e.g. `empty_reference()`). Clones and subsets keep the metrics of the original load.
+    pub fn load_metrics(&self) -> Option<LoadMetrics> {
+        self.load_metrics
+    }
+
+    /// Sets the metrics of the load that produced this genome
+    pub(crate) fn set_load_metrics(&mut self, load_metrics: LoadMetrics) {
+        self.load_metrics = Some(load_metrics);
+    }
+
+    /// The records renamed at load because their ID was repeated, as (original ID, new name) in load order.
+    /// Only `ReferenceGenomeBuilder::duplicate_policy(DuplicatePolicy::Rename)` renames records, and later renames are not tracked.
+    pub fn duplicate_renames(&self) -> &[(String, String)] {
+        &self.duplicate_renames
+    }
+
+    /// Sets the records renamed at load
+    pub(crate) fn set_duplicate_renames(&mut self, duplicate_renames: Vec<(String, String)>) {
+        self.duplicate_renames = Arc::new(duplicate_renames);
+    }
+
+    /// The descriptive metadata of the assembly, which is empty unless set with `set_assembly_metadata(...)` or loaded with a preset
+    /// (`from_fasta_preset(...)`). It is written into `write_dict(...)` and `write_vcf_header(...)`; clones and subsets keep it.
+    pub fn assembly_metadata(&self) -> &AssemblyMetadata {
+        &self.assembly_metadata
+    }
+
+    /// Sets the descriptive metadata of the assembly, e.g. from `AssemblyMetadata::from_assembly_report(...)`
+    /// # Arguments
+    /// * `assembly_metadata` - the metadata, replacing any previous metadata
+    pub fn set_assembly_metadata(&mut self, assembly_metadata: AssemblyMetadata) {
+        self.assembly_metadata = Arc::new(assembly_metadata);
+    }
+
+    /// The warnings raised so far, such as truncated slices and renamed records, in the order they were raised.
+    /// Only the first `MAX_COLLECTED_WARNINGS` are kept; clones and subsets share the warnings with the original genome.
+    /// Every warning is also logged with `log::warn!`.
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.warnings.collected()
+    }
+
+    /// Removes and returns the warnings raised so far, e.g. to report them per request
+    pub fn take_warnings(&self) -> Vec<Warning> {
+        self.warnings.take()
+    }
+
+    /// The total number of warnings raised, including any past the collection limit or removed by `take_warnings()`
+    pub fn warning_count(&self) -> usize {
+        self.warnings.count()
+    }
+
+    /// Sets a callback that receives each warning raised from now on, e.g. to surface them to users or abort on them.
+    /// Load warnings are only seen through `ReferenceGenomeBuilder::on_warning(...)`.
+    /// # Arguments
+    /// * `handler` - the callback, which replaces any previous one for this genome and its later clones
+    pub fn set_warning_handler<F>(&mut self, handler: F) where F: Fn(&Warning) + Send + Sync + 'static {
+        self.warnings.set_handler(Arc::new(handler));
+    }
+
+    /// Replaces the warning channel, used to hand over the load warnings from the builder
+    pub(crate) fn set_warning_channel(&mut self, warnings: WarningChannel) {
+        self.warnings = warnings;
+    }
+
+    pub fn normalized_lookup(&self) -> bool {
+        self.normalized_lookup
+    }
+
+    /// Enables or disables normalized lookups for sequence retrieval (`get_slice`, `get_full_chromosome`, `get`, and indexing).
+    /// When enabled, a name that is not an exact match will resolve to the single contig that matches it ignoring case,
+    /// surrounding whitespace, a "chr" prefix, and M/MT differences (e.g. `Chr1`, `chr1`, and `1`).
+    /// Ambiguous names, such as `1` in a genome containing both `1` and `chr1`, only resolve by exact match.
+    /// # Arguments
+    /// * `enabled` - whether normalized lookups are enabled
+    pub fn set_normalized_lookup(&mut self, enabled: bool) {
+        self.normalized_lookup = enabled;
+    }
+
+    /// How slice requests past the end of a contig are handled, default is `OutOfBoundsPolicy::Clamp`
+    pub fn out_of_bounds_policy(&self) -> OutOfBoundsPolicy {
+        self.out_of_bounds
+    }
+
+    /// Sets how `get_slice(...)`, `get_slice_shared(...)`, and `SharedReferenceGenome::get_slice(...)` handle requests past the end of a contig,
+    /// so strict pipelines can catch coordinate bugs instead of silently receiving shorter slices.
+    /// See `get_slice_checked(...)` to choose the policy per call, which is the only way to pad with `N`.
+    /// # Arguments
+    /// * `policy` - the policy for this genome; clones and subsets keep it
+    /// # Errors
+    /// * if `policy` is `OutOfBoundsPolicy::PadWithN`, since `get_slice(...)` returns a borrowed slice that cannot hold padding
+    pub fn set_out_of_bounds_policy(&mut self, policy: OutOfBoundsPolicy) -> Result<(), SimpleError> {
+        if policy == OutOfBoundsPolicy::PadWithN {
+            bail!("OutOfBoundsPolicy::PadWithN cannot be set for the whole genome; pass it to get_slice_checked(...) instead");
+        }
+        self.out_of_bounds = policy;
+        Ok(())
+    }
+
+    /// The digests of every contig and the MD5 index, see `contig_digests(...)`.
+    /// The cache is computed on first use, shared with clones made afterwards, and rebuilt after any change to the sequences.
+    pub(crate) fn digest_cache(&self) -> &DigestCache {
+        self.digests.get_or_init(|| DigestCache::compute(self.contigs.names(), &self.sequences))
+    }
+
+    /// The soft-masked runs of every contig by ID, see `masked_fraction(...)`.
+    /// The index is computed on first use, shared with clones made afterwards, and rebuilt after any change to the sequences.
+    pub(crate) fn masked_runs(&self) -> &[Option<RunIndex>] {
+        self.run_indexes.masked(self.contigs.names(), &self.sequences)
+    }
+
+    /// The `N`/`n` runs of every contig by ID, see `n_fraction(...)`; cached like `masked_runs()`
+    pub(crate) fn gap_runs(&self) -> &[Option<RunIndex>] {
+        self.run_indexes.gaps(self.contigs.names(), &self.sequences)
+    }
+
+    /// Contig IDs by the MD5 digest of their sequence, see `contig_by_md5(...)`.
+    /// Contigs that are unloaded or fail to load are left out; for identical sequences, the first contig in load order is kept.
+    pub(crate) fn md5_index(&self) -> &HashMap<String, ContigId> {
+        &self.digest_cache().md5_index
+    }
+
+    /// Resolves a contig name to the name stored in the genome.
+    /// Exact matches are always resolved; if normalized lookup is enabled (see `set_normalized_lookup(...)`),
+    /// names that loosely match exactly one contig are also resolved.
+    /// # Arguments
+    /// * `chromosome` - the contig name to resolve
+    pub fn resolve_contig_name(&self, chromosome: &str) -> Option<&str> {
+        match self.contigs.get(chromosome) {
+            Some(id) => Some(&self.contigs.names()[id as usize]),
+            None if self.normalized_lookup => self.resolve_loose_name(chromosome),
+            None => None
+        }
+    }
+
+    /// Retrieves the stored sequence for a contig without loading it, or `None` if the contig is not in the reference genome.
+    /// No lookup normalization is applied.
+    pub(crate) fn contig_sequence(&self, chromosome: &str) -> Option<&ContigSequence> {
+        self.contigs.get(chromosome).map(|id| &self.sequences[id as usize])
+    }
+
+    /// Estimates the heap bytes used by the contig names and lookup structures, excluding sequences
+    pub(crate) fn index_heap_bytes(&self) -> usize {
+        self.contigs.heap_bytes() + self.sequences.capacity() * std::mem::size_of::<ContigSequence>()
+    }
+
+    /// Retrieves the stored sequence for a contig name, applying lookup normalization if enabled
+    fn lookup_contig(&self, chromosome: &str) -> Option<&ContigSequence> {
+        match self.contig_sequence(chromosome) {
+            Some(contig) => Some(contig),
+            None if self.normalized_lookup => self.resolve_loose_name(chromosome)
+                .and_then(|name| self.contig_sequence(name)),
+            None => None
+        }
     }
 
     /// Retrieves a reference slice from a given 0-based coordinates.
-    /// If `start` or `end` goes past the full contig length, it will be truncated to the full contig length.
+    /// By default, if `start` or `end` goes past the full contig length, it will be truncated to the full contig length;
+    /// see `set_out_of_bounds_policy(...)` for alternatives.
     /// # Arguments
     /// * `chromosome` - the chromosome to slice from
     /// * `start` - the 0-based start index (included)
     /// * `end` - the 0-based end index (excluded)
     /// # Panics
     /// * if `chromosome` was not in the FASTA file
+    /// * if `chromosome` was unloaded from an in-memory genome, see `unload_contig(...)`
     /// * if `start` > `end`
+    /// * if `end` is past the contig end and the policy is `OutOfBoundsPolicy::Error`
     pub fn get_slice(&self, chromosome: &str, start: usize, end: usize) -> &[u8] {
-        let full_contig = self.contig_map.get(chromosome).expect("a chromosome from the reference file");
-        assert!(start <= end, "start > end: {start} > {end}");
-        let truncated_start = if start <= full_contig.len() { start } else {
-            warn!("Received get_slice({:?}, {}, {}), truncated start to {}", chromosome, start, end, full_contig.len());
-            full_contig.len()
-        };
-        let truncated_end = if end <= full_contig.len() { end } else {
-            warn!("Received get_slice({:?}, {}, {}), truncated end to {}", chromosome, start, end, full_contig.len());
-            full_contig.len()
+        let full_contig = self.get_full_chromosome(chromosome);
+        // the genome-wide policy is never `PadWithN`, so there is no padding
+        let (range, _) = self.bounded_range(chromosome, start, end, full_contig.len(), self.out_of_bounds)
+            .unwrap_or_else(|e| panic!("{e}"));
+        &full_contig[range]
+    }
+
+    /// Retrieves a full chromosome by name as a shared, owned handle without copying the sequence.
+    /// For lazy backends with an LRU cache, this goes through the cache rather than keeping the contig in memory permanently.
+    /// # Arguments
+    /// * `chromosome` - the chromosome to retrieve
+    /// # Panics
+    /// * if `chromosome` was not in the FASTA file
+    /// * if `chromosome` was unloaded from an in-memory genome, see `unload_contig(...)`
+    pub fn get_full_chromosome_shared(&self, chromosome: &str) -> Bytes {
+        self.lookup_contig(chromosome)
+            .unwrap_or_else(|| panic!("{}", self.missing_contig_message(chromosome)))
+            .try_as_bytes()
+            .unwrap_or_else(|| panic!("Contig key \"{chromosome}\" has been unloaded"))
+    }
+
+    /// Same as `get_full_chromosome_shared(...)`, but returns `None` if the contig is not in the reference genome or was unloaded
+    pub(crate) fn try_get_full_chromosome_shared(&self, chromosome: &str) -> Option<Bytes> {
+        self.lookup_contig(chromosome).and_then(|c| c.try_as_bytes())
+    }
+
+    /// Retrieves a reference slice from a given 0-based coordinates as a shared, owned handle without copying the sequence.
+    /// The handle is independent of the genome's lifetime, so it can be sent to other threads or tasks.
+    /// For lazy backends with an LRU cache, this goes through the cache like `get_full_chromosome_shared(...)`.
+    /// Requests past the contig end follow `out_of_bounds_policy()`; see `get_slice_checked(...)` to pad with `N`.
+    /// # Arguments
+    /// * `chromosome` - the chromosome to slice from
+    /// * `start` - the 0-based start index (included)
+    /// * `end` - the 0-based end index (excluded)
+    /// # Panics
+    /// * if `chromosome` was not in the FASTA file
+    /// * if `chromosome` was unloaded from an in-memory genome, see `unload_contig(...)`
+    /// * if `start` > `end`
+    /// * if `end` is past the contig end and the policy is `OutOfBoundsPolicy::Error`
+    pub fn get_slice_shared(&self, chromosome: &str, start: usize, end: usize) -> Bytes {
+        let full_contig = self.get_full_chromosome_shared(chromosome);
+        self.slice_with_policy(chromosome, full_contig, start, end, self.out_of_bounds)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Retrieves a reference slice like `get_slice_shared(...)`, with the out-of-bounds policy chosen for this call and errors instead of panics.
+    /// With `OutOfBoundsPolicy::PadWithN`, padded slices are copied.
+    /// # Arguments
+    /// * `chromosome` - the chromosome to slice from
+    /// * `start` - the 0-based start index (included)
+    /// * `end` - the 0-based end index (excluded)
+    /// * `policy` - how a request past the contig end is handled
+    /// # Errors
+    /// * if `chromosome` is not in the reference genome, was unloaded, or fails to load
+    /// * if `start` > `end`
+    /// * if `end` is past the contig end and the policy is `OutOfBoundsPolicy::Error`
+    pub fn get_slice_checked(&self, chromosome: &str, start: usize, end: usize, policy: OutOfBoundsPolicy) -> Result<Bytes, SimpleError> {
+        let Some(contig) = self.lookup_contig(chromosome) else {
+            bail!("{}", self.missing_contig_message(chromosome));
         };
-        &full_contig[truncated_start..truncated_end]
+        let full_contig = contig.try_as_bytes_unkept(chromosome).map_err(|e| SimpleError::new(e.to_string()))?;
+        self.slice_with_policy(chromosome, full_contig, start, end, policy)
+    }
+
+    /// Slices a contig sequence according to an out-of-bounds policy, padding with `N` if needed
+    /// # Errors
+    /// * see `bounded_range(...)`
+    pub(crate) fn slice_with_policy(&self, chromosome: &str, full_contig: Bytes, start: usize, end: usize, policy: OutOfBoundsPolicy) -> Result<Bytes, SimpleError> {
+        let (range, padding) = self.bounded_range(chromosome, start, end, full_contig.len(), policy)?;
+        if padding == 0 {
+            Ok(full_contig.slice(range))
+        } else {
+            let mut padded = full_contig[range].to_vec();
+            padded.resize(padded.len() + padding, b'N');
+            Ok(Bytes::from(padded))
+        }
+    }
+
+    /// Applies an out-of-bounds policy to a requested slice
+    /// # Returns
+    /// * the range within the contig, and the number of `N` to append after it
+    /// # Errors
+    /// * if `start` > `end`
+    /// * if `end` is past the contig end and the policy is `OutOfBoundsPolicy::Error`
+    fn bounded_range(&self, chromosome: &str, start: usize, end: usize, contig_len: usize, policy: OutOfBoundsPolicy) -> Result<(Range<usize>, usize), SimpleError> {
+        if start > end {
+            bail!("start > end: {} > {}", start, end);
+        }
+        match policy {
+            OutOfBoundsPolicy::Error if end > contig_len => {
+                bail!("Received get_slice({:?}, {}, {}), which extends past the contig length {}", chromosome, start, end, contig_len)
+            },
+            OutOfBoundsPolicy::PadWithN => {
+                let range = start.min(contig_len)..end.min(contig_len);
+                let padding = (end - start) - range.len();
+                Ok((range, padding))
+            },
+            _ => Ok((self.truncated_range(chromosome, start, end, contig_len), 0))
+        }
+    }
+
+    /// Truncates a requested slice to the contig length, raising a `Warning::SliceTruncated` if any truncation happened
+    /// # Panics
+    /// * if `start` > `end`
+    fn truncated_range(&self, chromosome: &str, start: usize, end: usize, contig_len: usize) -> Range<usize> {
+        assert!(start <= end, "start > end: {start} > {end}");
+        if end > contig_len {
+            self.warnings.emit(Warning::SliceTruncated { contig: chromosome.to_string(), start, end, contig_len });
+        }
+        start.min(contig_len)..end.min(contig_len)
     }
 
     /// Retrieves a full chromosome by name
@@ -122,9 +682,204 @@ impl ReferenceGenome {
     /// * `chromosome` - the chromosome to slice from
     /// # Panics
     /// * if `chromosome` was not in the FASTA file
+    /// * if `chromosome` was unloaded from an in-memory genome, see `unload_contig(...)`
+    /// * if a lazy backend fails to read the sequence
     pub fn get_full_chromosome(&self, chromosome: &str) -> &[u8] {
-        let full_contig = self.contig_map.get(chromosome).expect("a chromosome from the reference file");
-        full_contig
+        self.try_full_chromosome(chromosome).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Retrieves a full chromosome by name, loading and keeping a lazily loaded sequence.
+    /// This is the path of every borrowing accessor, so it emits `Warning::CacheBypassed` when it keeps a contig
+    /// that the LRU cache would otherwise have held.
+    /// # Errors
+    /// * if `chromosome` is not in the reference genome or was unloaded
+    /// * if a lazy backend fails to read the sequence
+    pub(crate) fn try_full_chromosome(&self, chromosome: &str) -> Result<&[u8], Box<dyn std::error::Error + Send + Sync>> {
+        let contig = self.lookup_contig(chromosome).ok_or_else(|| self.missing_contig_message(chromosome))?;
+        let bypasses_cache = contig.bypasses_cache();
+        let sequence = contig.try_as_slice(chromosome)?;
+        if bypasses_cache {
+            self.warnings.emit(Warning::CacheBypassed { contig: chromosome.to_string(), bytes: sequence.len() });
+        }
+        Ok(sequence)
+    }
+
+    /// Retrieves a full chromosome by name, or `None` if it is not in the reference genome, its sequence was unloaded,
+    /// or a lazy backend fails to read it
+    /// # Arguments
+    /// * `chromosome` - the chromosome to retrieve
+    pub fn get(&self, chromosome: &str) -> Option<&[u8]> {
+        self.try_full_chromosome(chromosome).ok()
+    }
+
+    /// Retrieves the length of a contig without loading its sequence, or `None` if it is not in the reference genome.
+    /// Lengths remain available after a contig is unloaded.
+    /// # Arguments
+    /// * `chromosome` - the contig to measure
+    pub fn contig_length(&self, chromosome: &str) -> Option<usize> {
+        self.lookup_contig(chromosome).map(|c| c.len())
+    }
+
+    /// Frees the sequence memory of a contig while keeping its name, order, and length.
+    /// Contigs from a lazy backend (`Backend::Mmap`, `Backend::Packed`, `Backend::Compressed`, a custom `ContigStore`, noodles, or faidx) are read from their source again on the next access.
+    /// Contigs held in memory have no source to reload from, so their sequence becomes unavailable:
+    /// `get(...)` returns `None` and the other sequence accessors panic.
+    /// Any sequence handles already returned by the `*_shared` accessors remain valid.
+    /// # Arguments
+    /// * `chromosome` - the contig to unload; no lookup normalization is applied
+    /// # Errors
+    /// * if `chromosome` is not in the reference genome
+    pub fn unload_contig(&mut self, chromosome: &str) -> Result<(), SimpleError> {
+        let contig = match self.contigs.get(chromosome) {
+            Some(id) => &mut Arc::make_mut(&mut self.sequences)[id as usize],
+            None => bail!("{}", self.missing_contig_message(chromosome))
+        };
+        match contig {
+            ContigSequence::Loaded(sequence) | ContigSequence::Mapped(sequence) => *contig = ContigSequence::Unloaded(sequence.len()),
+            ContigSequence::Lazy(lazy_contig) => lazy_contig.unload(),
+            ContigSequence::Unloaded(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Modifies the sequence of a contig in place. If the sequence is shared with a clone of this genome (or a `subset(...)`),
+    /// it is copied first so the other genomes are unaffected; otherwise the existing allocation is reused.
+    /// Lazily loaded contigs are read and then held in memory. The modified sequence is stored as-is, without upper-casing.
+    /// # Arguments
+    /// * `chromosome` - the contig to modify; no lookup normalization is applied
+    /// * `modify` - function that edits the ASCII sequence
+    /// # Errors
+    /// * if `chromosome` is not in the reference genome, was unloaded, or fails to load
+    pub fn modify_contig<F>(&mut self, chromosome: &str, modify: F) -> Result<(), SimpleError> where F: FnOnce(&mut Vec<u8>) {
+        let Some(id) = self.contigs.get(chromosome) else {
+            bail!("{}", self.missing_contig_message(chromosome));
+        };
+        let slot = &mut Arc::make_mut(&mut self.sequences)[id as usize];
+        let length = slot.len();
+        let sequence = std::mem::replace(slot, ContigSequence::Unloaded(length));
+        let bytes = match sequence.try_as_bytes_unkept(chromosome) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                *slot = sequence;
+                bail!("{}", e);
+            }
+        };
+        drop(sequence);
+        // this only copies if another genome still references the sequence
+        let mut editable: Vec<u8> = Vec::from(bytes);
+        modify(&mut editable);
+        *slot = ContigSequence::Loaded(Bytes::from(editable));
+        self.digests = Default::default();
+        self.run_indexes = Default::default();
+        Ok(())
+    }
+
+    /// Unloads the sequence of every contig that does not match a predicate, see `unload_contig(...)`.
+    /// Unlike `subset(...)`, all contig names and lengths are kept.
+    /// # Arguments
+    /// * `predicate` - returns true for each contig name whose sequence should be kept
+    pub fn retain_contigs<F>(&mut self, predicate: F) where F: Fn(&str) -> bool {
+        let unloaded: Vec<String> = self.contigs.names().iter()
+            .filter(|k| !predicate(k))
+            .cloned()
+            .collect();
+        for contig in unloaded.iter() {
+            self.unload_contig(contig).unwrap();
+        }
+    }
+
+    /// Creates a new reference genome containing each contig that matches a predicate.
+    /// Sequences are shared with this genome rather than copied, and the load order of the retained contigs is preserved.
+    /// # Arguments
+    /// * `predicate` - returns true for each contig name that should be kept
+    pub fn subset<F>(&self, predicate: F) -> ReferenceGenome where F: Fn(&str) -> bool {
+        let mut contigs = ContigIndex::default();
+        let mut sequences: Vec<ContigSequence> = vec![];
+        for (name, sequence) in self.contigs.names().iter().zip(self.sequences.iter()) {
+            if predicate(name) {
+                // names are unique in this genome, so they are unique in the subset
+                contigs.insert(name.clone()).unwrap();
+                sequences.push(sequence.clone());
+            }
+        }
+        ReferenceGenome {
+            filename: self.filename.clone(),
+            contigs: Arc::new(contigs),
+            sequences: Arc::new(sequences),
+            normalized_lookup: self.normalized_lookup,
+            record_formats: Arc::new(self.record_formats.iter()
+                .filter(|(k, _)| predicate(k))
+                .map(|(k, f)| (k.clone(), f.clone()))
+                .collect()),
+            load_metrics: self.load_metrics,
+            duplicate_renames: self.duplicate_renames.clone(),
+            out_of_bounds: self.out_of_bounds,
+            warnings: self.warnings.clone(),
+            assembly_metadata: self.assembly_metadata.clone(),
+            digests: Default::default(),
+            run_indexes: Default::default()
+        }
+    }
+}
+
+/// Compares two strings such that runs of digits are ordered by numeric value and everything else is ordered lexicographically
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_bytes = a.as_bytes();
+    let mut b_bytes = b.as_bytes();
+    while !a_bytes.is_empty() && !b_bytes.is_empty() {
+        let a_digit = a_bytes[0].is_ascii_digit();
+        let b_digit = b_bytes[0].is_ascii_digit();
+        let a_len = a_bytes.iter().position(|c| c.is_ascii_digit() != a_digit).unwrap_or(a_bytes.len());
+        let b_len = b_bytes.iter().position(|c| c.is_ascii_digit() != b_digit).unwrap_or(b_bytes.len());
+        let (a_run, b_run) = (&a_bytes[..a_len], &b_bytes[..b_len]);
+
+        let ordering = if a_digit && b_digit {
+            // compare by value without parsing, which avoids overflow on long runs
+            let a_trimmed = &a_run[a_run.iter().position(|&c| c != b'0').unwrap_or(a_run.len())..];
+            let b_trimmed = &b_run[b_run.iter().position(|&c| c != b'0').unwrap_or(b_run.len())..];
+            a_trimmed.len().cmp(&b_trimmed.len())
+                .then_with(|| a_trimmed.cmp(b_trimmed))
+                .then_with(|| a_run.len().cmp(&b_run.len()))
+        } else {
+            a_run.cmp(b_run)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        a_bytes = &a_bytes[a_len..];
+        b_bytes = &b_bytes[b_len..];
+    }
+    a_bytes.len().cmp(&b_bytes.len())
+}
+
+impl TryFrom<Vec<(String, Vec<u8>)>> for ReferenceGenome {
+    type Error = SimpleError;
+
+    /// Assembles a reference genome from (contig name, sequence) pairs, see `from_sequences(...)`
+    /// # Errors
+    /// * if a contig name is present more than once
+    fn try_from(contigs: Vec<(String, Vec<u8>)>) -> Result<Self, SimpleError> {
+        ReferenceGenome::from_sequences(contigs)
+    }
+}
+
+impl<N: Into<String>, S: Into<Vec<u8>>> FromIterator<(N, S)> for ReferenceGenome {
+    /// Collects (contig name, sequence) pairs into a reference genome, see `from_sequences(...)`
+    /// # Panics
+    /// * if a contig name is present more than once; use `from_sequences(...)` to handle this as an error
+    fn from_iter<I: IntoIterator<Item = (N, S)>>(contigs: I) -> Self {
+        ReferenceGenome::from_sequences(contigs).unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+impl Index<&str> for ReferenceGenome {
+    type Output = [u8];
+
+    /// Retrieves a full chromosome by name, equivalent to `get_full_chromosome(...)`
+    /// # Panics
+    /// * if `chromosome` was not in the FASTA file
+    fn index(&self, chromosome: &str) -> &[u8] {
+        self.get_full_chromosome(chromosome)
     }
 }
 
@@ -133,9 +888,11 @@ mod tests {
     use super::*;
     use std::path::PathBuf;
     #[test]
+    #[allow(clippy::useless_vec, clippy::needless_borrow)]
     fn test_simple_reference() {
         let references = vec![
             "./test_data/test_reference.fa",
+            #[cfg(feature = "gzip")]
             "./test_data/test_reference.fa.gz"
         ];
         for &reference_fn in references.iter() {
@@ -169,4 +926,241 @@ mod tests {
         assert_eq!(reference_genome.get_full_chromosome("test"), b"ACGT");
         assert_eq!(reference_genome.get_full_chromosome("test2"), b"TGNA");
     }
+
+    #[test]
+    fn test_from_sequences() {
+        let reference_genome = ReferenceGenome::try_from(vec![
+            ("chr1".to_string(), b"acgt".to_vec()),
+            ("chr2".to_string(), b"GGNN".to_vec())
+        ]).unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["chr1".to_string(), "chr2".to_string()]);
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"ACGT");
+
+        let collected: ReferenceGenome = [("chr3", &b"TTA"[..]), ("chr1", &b"C"[..])].into_iter().collect();
+        assert_eq!(collected.contig_keys(), &["chr3".to_string(), "chr1".to_string()]);
+        assert_eq!(collected.get_full_chromosome("chr3"), b"TTA");
+        assert!(ReferenceGenome::from_sequences([("chr1", "A"), ("chr1", "C")]).is_err());
+        assert!(ReferenceGenome::try_from(vec![("chr1".to_string(), b"A".to_vec()), ("chr1".to_string(), b"C".to_vec())]).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_iterator_duplicate_panics() {
+        let _reference_genome: ReferenceGenome = [("chr1", "A"), ("chr1", "C")].into_iter().collect();
+    }
+
+    #[test]
+    fn test_get_slice_shared() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("test".to_string(), "ACGTACGT").unwrap();
+
+        let shared = reference_genome.get_slice_shared("test", 2, 6);
+        assert_eq!(&shared[..], b"GTAC");
+        assert_eq!(&reference_genome.get_slice_shared("test", 6, 100)[..], b"GT");
+
+        // the handle outlives the genome and can move across threads
+        drop(reference_genome);
+        let handle = std::thread::spawn(move || shared.to_vec());
+        assert_eq!(handle.join().unwrap(), b"GTAC");
+    }
+
+    #[test]
+    fn test_out_of_bounds_policy() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("test".to_string(), "ACGT").unwrap();
+        assert_eq!(reference_genome.out_of_bounds_policy(), OutOfBoundsPolicy::Clamp);
+
+        assert_eq!(reference_genome.get_slice_checked("test", 2, 6, OutOfBoundsPolicy::Clamp).unwrap(), &b"GT"[..]);
+        assert_eq!(reference_genome.get_slice_checked("test", 2, 6, OutOfBoundsPolicy::PadWithN).unwrap(), &b"GTNN"[..]);
+        assert_eq!(reference_genome.get_slice_checked("test", 5, 7, OutOfBoundsPolicy::PadWithN).unwrap(), &b"NN"[..]);
+        assert!(reference_genome.get_slice_checked("test", 2, 6, OutOfBoundsPolicy::Error).is_err());
+        assert_eq!(reference_genome.get_slice_checked("test", 0, 4, OutOfBoundsPolicy::Error).unwrap(), &b"ACGT"[..]);
+        assert!(reference_genome.get_slice_checked("test", 3, 2, OutOfBoundsPolicy::Clamp).is_err());
+        assert!(reference_genome.get_slice_checked("missing", 0, 1, OutOfBoundsPolicy::Clamp).is_err());
+
+        // padding is only available per call, so get_slice(...) never has to pad
+        assert!(reference_genome.set_out_of_bounds_policy(OutOfBoundsPolicy::PadWithN).is_err());
+        assert_eq!(reference_genome.out_of_bounds_policy(), OutOfBoundsPolicy::Clamp);
+        assert_eq!(reference_genome.get_slice("test", 3, 5), b"T");
+
+        reference_genome.set_out_of_bounds_policy(OutOfBoundsPolicy::Error).unwrap();
+        let shared = crate::shared::SharedReferenceGenome::new(reference_genome.subset(|_| true));
+        assert_eq!(shared.get_slice("test", 1, 3).unwrap(), &b"CG"[..]);
+        assert!(std::panic::catch_unwind(|| shared.get_slice("test", 3, 5)).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "extends past the contig length")]
+    fn test_out_of_bounds_error_panics() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("test".to_string(), "ACGT").unwrap();
+        reference_genome.set_out_of_bounds_policy(OutOfBoundsPolicy::Error).unwrap();
+        reference_genome.get_slice("test", 2, 5);
+    }
+
+    #[test]
+    fn test_subset() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("a".to_string(), "A").unwrap();
+        reference_genome.add_contig("b".to_string(), "C").unwrap();
+        reference_genome.add_contig("c".to_string(), "G").unwrap();
+
+        let subset = reference_genome.subset(|c| c != "b");
+        assert_eq!(subset.contig_keys(), &["a".to_string(), "c".to_string()]);
+        assert_eq!(subset.get_full_chromosome("c"), b"G");
+        assert_eq!(subset.get("b"), None);
+    }
+
+    #[test]
+    fn test_unload_contig() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("a".to_string(), "ACGT").unwrap();
+        reference_genome.add_contig("b".to_string(), "CC").unwrap();
+        reference_genome.add_contig("c".to_string(), "G").unwrap();
+        let shared = reference_genome.get_full_chromosome_shared("a");
+
+        reference_genome.unload_contig("a").unwrap();
+        assert!(reference_genome.unload_contig("missing").is_err());
+        assert_eq!(reference_genome.contig_keys(), &["a".to_string(), "b".to_string(), "c".to_string()]);
+        assert_eq!(reference_genome.contig_length("a"), Some(4));
+        assert_eq!(reference_genome.get("a"), None);
+        assert_eq!(reference_genome.memory_usage().contigs[0].sequence_bytes, 0);
+        assert_eq!(&shared[..], b"ACGT");
+
+        reference_genome.retain_contigs(|c| c == "c");
+        assert_eq!(reference_genome.contig_length("b"), Some(2));
+        assert_eq!(reference_genome.get("b"), None);
+        assert_eq!(reference_genome.get_full_chromosome("c"), b"G");
+    }
+
+    #[test]
+    #[should_panic(expected = "has been unloaded")]
+    fn test_unloaded_panics() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("a".to_string(), "ACGT").unwrap();
+        reference_genome.unload_contig("a").unwrap();
+        reference_genome.get_full_chromosome("a");
+    }
+
+    #[test]
+    fn test_clone_on_write() {
+        let mut base = ReferenceGenome::empty_reference();
+        base.add_contig("a".to_string(), "ACGT").unwrap();
+        base.add_contig("b".to_string(), "GGGG").unwrap();
+
+        let mut variant = base.clone();
+        variant.modify_contig("a", |s| s[1] = b'T').unwrap();
+        assert_eq!(variant.get_full_chromosome("a"), b"ATGT");
+        assert_eq!(base.get_full_chromosome("a"), b"ACGT");
+        // the unmodified contig is still shared
+        assert_eq!(variant.get_full_chromosome("b").as_ptr(), base.get_full_chromosome("b").as_ptr());
+
+        // a sequence that is not shared is modified in place
+        let before = variant.get_full_chromosome("a").as_ptr();
+        variant.modify_contig("a", |s| s.make_ascii_lowercase()).unwrap();
+        assert_eq!(variant.get_full_chromosome("a"), b"atgt");
+        assert_eq!(variant.get_full_chromosome("a").as_ptr(), before);
+
+        variant.add_contig("c".to_string(), "A").unwrap();
+        assert_eq!(base.contig_keys().len(), 2);
+        assert!(variant.modify_contig("missing", |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_rename_contigs() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("a".to_string(), "A").unwrap();
+        reference_genome.add_contig("b".to_string(), "C").unwrap();
+        reference_genome.add_contig("c".to_string(), "G").unwrap();
+
+        // swap a and b
+        reference_genome.rename_contigs(&[
+            ("a".to_string(), "b".to_string()),
+            ("b".to_string(), "a".to_string())
+        ]).unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["b".to_string(), "a".to_string(), "c".to_string()]);
+        assert_eq!(reference_genome.get_full_chromosome("a"), b"C");
+
+        // failures leave the genome untouched
+        assert!(reference_genome.rename_contigs(&[("missing".to_string(), "d".to_string())]).is_err());
+        assert!(reference_genome.rename_contigs(&[("a".to_string(), "c".to_string())]).is_err());
+        assert_eq!(reference_genome.contig_keys(), &["b".to_string(), "a".to_string(), "c".to_string()]);
+        assert_eq!(reference_genome.get_full_chromosome("c"), b"G");
+    }
+
+    #[test]
+    fn test_sort_contigs() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        for contig in ["chr10", "chrX", "chr2", "chrM", "chr1", "chr1_KI270706v1_random", "chr02"] {
+            reference_genome.add_contig(contig.to_string(), "A").unwrap();
+        }
+
+        reference_genome.sort_contigs_natural();
+        assert_eq!(reference_genome.contig_keys(), &["chr1", "chr1_KI270706v1_random", "chr2", "chr02", "chr10", "chrM", "chrX"]);
+
+        reference_genome.sort_contigs_by(|a, b| b.cmp(a));
+        assert_eq!(reference_genome.contig_keys()[0], "chrX");
+
+        reference_genome.sort_contigs_by_order(&["chrM".to_string(), "chr2".to_string()]).unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["chrM", "chr2", "chrX", "chr1_KI270706v1_random", "chr10", "chr1", "chr02"]);
+
+        assert!(reference_genome.sort_contigs_by_order(&["chr3".to_string()]).is_err());
+        assert!(reference_genome.sort_contigs_by_order(&["chr1".to_string(), "chr1".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_contig_ids() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr2".to_string(), "CC").unwrap();
+        reference_genome.add_contig("chr1".to_string(), "A").unwrap();
+        assert_eq!(reference_genome.contig_id("chr1"), Some(1));
+        assert_eq!(reference_genome.contig_id("chr3"), None);
+
+        // sorting reassigns ids, and sequences follow their names
+        reference_genome.sort_contigs_natural();
+        assert_eq!(reference_genome.contig_id("chr1"), Some(0));
+        assert_eq!(reference_genome.contig_name(1), Some("chr2"));
+        assert_eq!(reference_genome.contig_name(2), None);
+        assert_eq!(reference_genome.get_full_chromosome("chr1"), b"A");
+        assert_eq!(reference_genome.get_full_chromosome("chr2"), b"CC");
+    }
+
+    #[test]
+    fn test_index_and_get() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("test".to_string(), "Acgt").unwrap();
+
+        assert_eq!(&reference_genome["test"], b"ACGT");
+        assert_eq!(reference_genome.get("test"), Some(&b"ACGT"[..]));
+        assert_eq!(reference_genome.get("missing"), None);
+
+        // a lazy backend that fails to decode gives `None` instead of panicking
+        struct Corrupt;
+        impl crate::contig_store::ContigStore for Corrupt {
+            fn len(&self) -> usize {
+                8
+            }
+
+            fn decode(&self) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+                Err("corrupt".into())
+            }
+
+            fn stored_bytes(&self) -> usize {
+                0
+            }
+        }
+        let corrupt_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa"))
+            .contig_store(|_, _| Ok(Box::new(Corrupt)))
+            .build()
+            .unwrap();
+        assert_eq!(corrupt_genome.get("chr1"), None);
+        assert_eq!(corrupt_genome.contig_length("chr1"), Some(8));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_missing() {
+        let reference_genome = ReferenceGenome::empty_reference();
+        let _ = &reference_genome["missing"];
+    }
 }
\ No newline at end of file
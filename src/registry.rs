@@ -0,0 +1,109 @@
+use crate::reference_genome::ReferenceGenome;
+use rustc_hash::FxHashMap as HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, Weak};
+use std::time::SystemTime;
+
+/// Identifies a loaded file by its canonical path, modification time, and size, so a file that changes on disk is loaded again
+type RegistryKey = (PathBuf, Option<SystemTime>, u64);
+
+/// A slot for one key; loads of the same key wait on the slot, while loads of other keys proceed in parallel
+type RegistrySlot = Arc<Mutex<Weak<ReferenceGenome>>>;
+
+/// Deduplicates reference genome loads, such that components that load the same file share one in-memory copy.
+/// Genomes are held weakly: once every `Arc` from the registry is dropped, the memory is released and the next request loads the file again.
+/// Most code should use the process-wide registry through `ReferenceGenome::from_fasta_shared(...)`.
+#[derive(Default)]
+pub struct GenomeRegistry {
+    /// Slots keyed by file identity
+    slots: Mutex<HashMap<RegistryKey, RegistrySlot>>
+}
+
+impl GenomeRegistry {
+    /// Creates an empty registry, independent of the process-wide one
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The process-wide registry
+    pub fn global() -> &'static GenomeRegistry {
+        static GLOBAL: OnceLock<GenomeRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(GenomeRegistry::new)
+    }
+
+    /// Returns the genome for a FASTA file or directory, loading it with `ReferenceGenome::from_fasta(...)` if it is not already in use.
+    /// Concurrent requests for the same file wait for a single load.
+    /// # Arguments
+    /// * `fasta_fn` - the FASTA filename or directory; wildcard patterns are not supported
+    /// # Errors
+    /// * if the path does not exist
+    /// * any error from `ReferenceGenome::from_fasta(...)`
+    pub fn get_or_load(&self, fasta_fn: &Path) -> Result<Arc<ReferenceGenome>, Box<dyn std::error::Error>> {
+        let canonical_fn = std::fs::canonicalize(fasta_fn)?;
+        let metadata = std::fs::metadata(&canonical_fn)?;
+        let key: RegistryKey = (canonical_fn, metadata.modified().ok(), metadata.len());
+
+        let slot = {
+            let mut slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+            // drop entries for genomes that are no longer in use, such as older versions of a file;
+            // slots held by another request are kept since they may be loading
+            slots.retain(|_, s| Arc::strong_count(s) > 1 || s.lock().unwrap_or_else(PoisonError::into_inner).strong_count() > 0);
+            slots.entry(key).or_default().clone()
+        };
+
+        let mut weak_genome = slot.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(genome) = weak_genome.upgrade() {
+            return Ok(genome);
+        }
+        let genome = Arc::new(ReferenceGenome::from_fasta(fasta_fn)?);
+        *weak_genome = Arc::downgrade(&genome);
+        Ok(genome)
+    }
+
+    /// The number of genomes in the registry that are still in use
+    pub fn loaded_count(&self) -> usize {
+        let slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+        slots.values()
+            .filter(|s| s.lock().unwrap_or_else(PoisonError::into_inner).strong_count() > 0)
+            .count()
+    }
+}
+
+impl ReferenceGenome {
+    /// Loads a reference genome through the process-wide `GenomeRegistry`, sharing one copy with any other component
+    /// that has loaded the same unchanged file and still holds it.
+    /// # Arguments
+    /// * `fasta_fn` - the FASTA filename or directory
+    /// # Errors
+    /// * see `GenomeRegistry::get_or_load(...)`
+    pub fn from_fasta_shared(fasta_fn: &Path) -> Result<Arc<ReferenceGenome>, Box<dyn std::error::Error>> {
+        GenomeRegistry::global().get_or_load(fasta_fn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let registry = GenomeRegistry::new();
+        let first = registry.get_or_load(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        // a different spelling of the same path shares the load
+        let second = registry.get_or_load(&PathBuf::from("./test_data/../test_data/test_reference.fa")).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(registry.loaded_count(), 1);
+
+        drop(first);
+        drop(second);
+        assert_eq!(registry.loaded_count(), 0);
+        assert!(registry.get_or_load(&PathBuf::from("./test_data/missing.fa")).is_err());
+    }
+
+    #[test]
+    fn test_from_fasta_shared() {
+        let first = ReferenceGenome::from_fasta_shared(&PathBuf::from("./test_data/test_iupac.fa")).unwrap();
+        let second = ReferenceGenome::from_fasta_shared(&PathBuf::from("./test_data/test_iupac.fa")).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::builder::{decompress, is_gzip};
+use crate::reference_genome::ReferenceGenome;
+use rustc_hash::FxHashMap as HashMap;
+use simple_error::bail;
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// The outcome of `ReferenceGenome::rename_contigs_from_tsv(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenameReport {
+    /// Contigs that were renamed, as (old name, new name) in load order
+    pub renamed: Vec<(String, String)>,
+    /// Contigs without a mapping row, or mapped to their current name, in load order
+    pub unchanged: Vec<String>,
+    /// Contigs whose mapping row has no new name, which keep their current name, in load order
+    pub missing_target: Vec<String>,
+    /// Names in the mapping that are not in the genome, in file order; mapping files usually cover a whole assembly
+    pub not_in_genome: Vec<String>
+}
+
+impl ReferenceGenome {
+    /// Renames contigs from a two-column mapping of old to new names, such as the published UCSC/Ensembl/GenBank
+    /// mapping files, see `rename_contigs_from_tsv(...)`
+    /// # Arguments
+    /// * `reader` - the uncompressed mapping, with tab-separated old and new names; lines starting with `#` and blank lines are ignored,
+    ///   and columns after the second are ignored
+    /// # Errors
+    /// * any reading errors
+    /// * if an old name is listed more than once, or two contigs in the genome map to the same new name
+    /// * if the renamed genome would contain duplicate contig names, see `rename_contigs(...)`
+    pub fn rename_contigs_from_reader<R: BufRead>(&mut self, reader: R) -> Result<RenameReport, Box<dyn Error>> {
+        let mut mapping: HashMap<String, Option<String>> = Default::default();
+        let mut report = RenameReport::default();
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split('\t');
+            let old_name = fields.next().unwrap_or_default().trim();
+            let new_name = fields.next().map(|n| n.trim()).filter(|n| !n.is_empty());
+            if old_name.is_empty() {
+                bail!("Missing the old contig name on line {} of the mapping", line_index + 1);
+            }
+            if mapping.insert(old_name.to_string(), new_name.map(|n| n.to_string())).is_some() {
+                bail!("Contig {:?} is listed more than once in the mapping", old_name);
+            }
+            if self.contig_id(old_name).is_none() {
+                report.not_in_genome.push(old_name.to_string());
+            }
+        }
+
+        let mut targets: HashMap<&str, &str> = Default::default();
+        for contig in self.contig_keys().iter() {
+            match mapping.get(contig) {
+                Some(Some(new_name)) if new_name != contig => {
+                    if let Some(previous) = targets.insert(new_name, contig) {
+                        bail!("Contigs {:?} and {:?} are both mapped to {:?}", previous, contig, new_name);
+                    }
+                    report.renamed.push((contig.clone(), new_name.clone()));
+                },
+                Some(None) => report.missing_target.push(contig.clone()),
+                _ => report.unchanged.push(contig.clone())
+            }
+        }
+        self.rename_contigs(&report.renamed)?;
+        Ok(report)
+    }
+
+    /// Renames contigs from a two-column mapping file of old to new names, such as the published UCSC/Ensembl/GenBank mapping files
+    /// (e.g. `GRCh38_UCSC2ensembl.txt`). Contigs are renamed all at once, so either every rename is applied or none is.
+    /// Mapping rows for contigs that are not in the genome are reported rather than rejected, as are genome contigs without a row.
+    /// # Arguments
+    /// * `mapping_fn` - the mapping filename; gzip compression is detected from a `.gz` extension
+    /// # Errors
+    /// * any file reading errors
+    /// * see `rename_contigs_from_reader(...)`
+    pub fn rename_contigs_from_tsv(&mut self, mapping_fn: &Path) -> Result<RenameReport, Box<dyn Error>> {
+        let mapping_file = std::fs::File::open(mapping_fn)?;
+        let reader = decompress(Box::new(BufReader::new(mapping_file)), is_gzip(mapping_fn), &Arc::default())?;
+        self.rename_contigs_from_reader(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn test_genome() -> ReferenceGenome {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        for contig in ["chr1", "chr2", "chrM", "chrUn_KI270302v1"] {
+            reference_genome.add_contig(contig.to_string(), "ACGT").unwrap();
+        }
+        reference_genome
+    }
+
+    #[test]
+    fn test_rename_contigs_from_reader() {
+        let mut reference_genome = test_genome();
+        let mapping = b"# UCSC\tEnsembl\nchr1\t1\nchr2\t2\r\nchrM\tMT\textra\nchrUn_KI270302v1\nchr3\t3\n\n";
+        let report = reference_genome.rename_contigs_from_reader(&mapping[..]).unwrap();
+        assert_eq!(report, RenameReport {
+            renamed: vec![
+                ("chr1".to_string(), "1".to_string()),
+                ("chr2".to_string(), "2".to_string()),
+                ("chrM".to_string(), "MT".to_string())
+            ],
+            unchanged: vec![],
+            missing_target: vec!["chrUn_KI270302v1".to_string()],
+            not_in_genome: vec!["chr3".to_string()]
+        });
+        assert_eq!(reference_genome.contig_keys(), &["1", "2", "MT", "chrUn_KI270302v1"].map(|c| c.to_string()));
+    }
+
+    #[test]
+    fn test_rename_contigs_from_reader_errors() {
+        let mut reference_genome = test_genome();
+        assert!(reference_genome.rename_contigs_from_reader(&b"chr1\t1\nchr1\tone\n"[..]).is_err());
+        assert!(reference_genome.rename_contigs_from_reader(&b"chr1\t1\nchr2\t1\n"[..]).unwrap_err().to_string().contains("are both mapped to \"1\""));
+        // a new name that is already taken by an unmapped contig
+        assert!(reference_genome.rename_contigs_from_reader(&b"chr1\tchr2\n"[..]).is_err());
+        assert!(reference_genome.rename_contigs_from_tsv(&PathBuf::from("./test_data/missing.tsv")).is_err());
+        // failed renames leave the genome untouched
+        assert_eq!(reference_genome.contig_keys(), test_genome().contig_keys());
+    }
+}
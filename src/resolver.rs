@@ -0,0 +1,208 @@
+use crate::reference_genome::ReferenceGenome;
+use crate::user_dirs::{default_config_dir, non_empty_env};
+use rustc_hash::FxHashMap as HashMap;
+use simple_error::{bail, SimpleError};
+use std::path::{Path, PathBuf};
+
+/// The environment variable naming the resolver config file
+pub const CONFIG_ENV_VAR: &str = "REFGENOME_CONFIG";
+/// The prefix of environment variables that set the location of a single genome, e.g. `REFGENOME_PATH_GRCH38`
+pub const PATH_ENV_PREFIX: &str = "REFGENOME_PATH_";
+
+/// Where a logical genome name points
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReferenceLocation {
+    /// A local FASTA file, directory, or wildcard pattern
+    Path(PathBuf),
+    /// A remote location, which the application must fetch itself
+    Url(String)
+}
+
+/// Maps logical genome names (e.g. "GRCh38", "mm39") to locations, from a config file and environment variables,
+/// so applications can load genomes by name instead of threading file paths through every layer.
+/// Names are matched ignoring case and punctuation, such that "T2T-CHM13", "t2t_chm13", and the environment variable
+/// `REFGENOME_PATH_T2T_CHM13` all refer to the same genome.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GenomeResolver {
+    /// Locations by normalized name
+    locations: HashMap<String, ReferenceLocation>
+}
+
+/// Normalizes a genome name for matching: upper-case, with every character other than a letter or digit replaced by `_`
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
+
+/// Parses a location, treating anything with a URL scheme as remote
+fn parse_location(location: &str) -> ReferenceLocation {
+    if location.contains("://") {
+        ReferenceLocation::Url(location.to_string())
+    } else {
+        ReferenceLocation::Path(PathBuf::from(location))
+    }
+}
+
+impl GenomeResolver {
+    /// Creates an empty resolver
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds or replaces the location of a genome
+    /// # Arguments
+    /// * `name` - the logical genome name
+    /// * `location` - a local path, or a URL such as `https://...` or `s3://...`
+    /// # Errors
+    /// * if the name or location is empty
+    pub fn insert(&mut self, name: &str, location: &str) -> Result<(), SimpleError> {
+        if name.trim().is_empty() || location.trim().is_empty() {
+            bail!("Genome names and locations must not be empty: {:?} = {:?}", name, location);
+        }
+        self.locations.insert(normalize_name(name.trim()), parse_location(location.trim()));
+        Ok(())
+    }
+
+    /// Reads a config file with one `name = location` entry per line; blank lines and lines starting with `#` are ignored.
+    /// Relative paths are resolved against the directory of the config file.
+    /// # Arguments
+    /// * `config_fn` - the config filename
+    /// # Errors
+    /// * if the file cannot be read
+    /// * if a line is not a `name = location` entry
+    pub fn from_config(config_fn: &Path) -> Result<GenomeResolver, Box<dyn std::error::Error>> {
+        let mut resolver = GenomeResolver::new();
+        resolver.read_config(config_fn)?;
+        Ok(resolver)
+    }
+
+    /// Adds the entries of a config file, see `from_config(...)`
+    fn read_config(&mut self, config_fn: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let config_directory = config_fn.parent().unwrap_or(Path::new(""));
+        for (line_index, line) in std::fs::read_to_string(config_fn)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, location)) = line.split_once('=') else {
+                bail!("Expected \"name = location\" on line {} of {:?}", line_index + 1, config_fn);
+            };
+            self.insert(name, location)?;
+            if let Some(ReferenceLocation::Path(path)) = self.locations.get_mut(&normalize_name(name.trim())) {
+                if path.is_relative() {
+                    *path = config_directory.join(&*path);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds a resolver from the environment: the config file named by `REFGENOME_CONFIG`, or if unset,
+    /// `refgenome/genomes.conf` under `$XDG_CONFIG_HOME` or `$HOME/.config` when that file exists;
+    /// then `REFGENOME_PATH_<NAME>` variables, which take precedence over the config file.
+    /// # Errors
+    /// * if `REFGENOME_CONFIG` is set but the file cannot be read
+    /// * if the config file is malformed
+    pub fn from_env() -> Result<GenomeResolver, Box<dyn std::error::Error>> {
+        let config_fn = match non_empty_env(CONFIG_ENV_VAR) {
+            Some(config_fn) => Some(PathBuf::from(config_fn)),
+            None => default_config_dir("refgenome/genomes.conf").filter(|config_fn| config_fn.is_file())
+        };
+        let mut resolver = GenomeResolver::new();
+        if let Some(config_fn) = config_fn {
+            resolver.read_config(&config_fn)?;
+        }
+        resolver.insert_env_vars(std::env::vars())?;
+        Ok(resolver)
+    }
+
+    /// Adds the `REFGENOME_PATH_<NAME>` entries from a set of environment variables, ignoring all others
+    fn insert_env_vars<I>(&mut self, vars: I) -> Result<(), SimpleError> where I: IntoIterator<Item = (String, String)> {
+        for (key, value) in vars.into_iter() {
+            if let Some(name) = key.strip_prefix(PATH_ENV_PREFIX) {
+                if !value.is_empty() {
+                    self.insert(name, &value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The location of a genome, or `None` if the name is not known
+    /// # Arguments
+    /// * `name` - the logical genome name
+    pub fn locate(&self, name: &str) -> Option<&ReferenceLocation> {
+        self.locations.get(&normalize_name(name))
+    }
+
+    /// The number of known genomes
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    /// Returns true if no genomes are known
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    /// Loads a genome by name with `ReferenceGenome::from_fasta(...)`
+    /// # Arguments
+    /// * `name` - the logical genome name
+    /// # Errors
+    /// * if the name is not known
+    /// * if the name points to a URL, which must be downloaded first
+    /// * any error from `ReferenceGenome::from_fasta(...)`
+    pub fn load(&self, name: &str) -> Result<ReferenceGenome, Box<dyn std::error::Error>> {
+        match self.locate(name) {
+            Some(ReferenceLocation::Path(path)) => ReferenceGenome::from_fasta(path),
+            Some(ReferenceLocation::Url(url)) => bail!("Genome {:?} points to {}, which must be downloaded before loading", name, url),
+            None => bail!("Unknown genome {:?}; add it to the {} config file or set {}{}", name, CONFIG_ENV_VAR, PATH_ENV_PREFIX, normalize_name(name))
+        }
+    }
+}
+
+impl ReferenceGenome {
+    /// Loads a genome by logical name (e.g. "GRCh38"), using the locations from `GenomeResolver::from_env()`
+    /// # Arguments
+    /// * `name` - the logical genome name
+    /// # Errors
+    /// * see `GenomeResolver::from_env()` and `GenomeResolver::load(...)`
+    pub fn resolve(name: &str) -> Result<ReferenceGenome, Box<dyn std::error::Error>> {
+        GenomeResolver::from_env()?.load(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config() {
+        let directory = std::env::temp_dir().join(format!("refgenome_resolver_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let config_fn = directory.join("genomes.conf");
+        std::fs::write(&config_fn, "# local genomes\nGRCh38 = /data/GRCh38.fa\n\nT2T-CHM13 = chm13.fa\nmm39 = https://example.org/mm39.fa.gz\n").unwrap();
+
+        let mut resolver = GenomeResolver::from_config(&config_fn).unwrap();
+        assert_eq!(resolver.len(), 3);
+        assert_eq!(resolver.locate("grch38"), Some(&ReferenceLocation::Path(PathBuf::from("/data/GRCh38.fa"))));
+        assert_eq!(resolver.locate("t2t_chm13"), Some(&ReferenceLocation::Path(directory.join("chm13.fa"))));
+        assert_eq!(resolver.locate("mm39"), Some(&ReferenceLocation::Url("https://example.org/mm39.fa.gz".to_string())));
+        assert!(resolver.load("mm39").is_err());
+        assert!(resolver.load("hg19").err().unwrap().to_string().contains("REFGENOME_PATH_HG19"));
+
+        // environment variables override the config file
+        let vars = vec![
+            ("REFGENOME_PATH_GRCH38".to_string(), "./test_data/test_reference.fa".to_string()),
+            ("PATH".to_string(), "/usr/bin".to_string())
+        ];
+        resolver.insert_env_vars(vars).unwrap();
+        assert_eq!(resolver.len(), 3);
+        assert_eq!(resolver.load("GRCh38").unwrap().contig_keys().len(), 2);
+
+        std::fs::write(&config_fn, "GRCh38 /data/GRCh38.fa\n").unwrap();
+        assert!(GenomeResolver::from_config(&config_fn).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
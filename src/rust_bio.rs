@@ -0,0 +1,107 @@
+use crate::reference_genome::{ContigSequence, ReferenceGenome};
+use bio::bio_types::genome::AbstractInterval;
+use bio::io::fasta::Record;
+use bio::utils::Interval;
+use bytes::Bytes;
+use simple_error::SimpleError;
+use std::path::PathBuf;
+
+impl ReferenceGenome {
+    /// Creates a reference genome from rust-bio FASTA records, preserving their order.
+    /// Sequences are upper-cased, matching `add_contig(...)`.
+    /// # Arguments
+    /// * `records` - the records to add, e.g. from `bio::io::fasta::Reader::records()`
+    /// # Errors
+    /// * if a record name is present more than once
+    pub fn from_bio_records<I>(records: I) -> Result<ReferenceGenome, SimpleError> where I: IntoIterator<Item = Record> {
+        let contigs: Vec<(String, ContigSequence)> = records.into_iter()
+            .map(|record| (record.id().to_string(), ContigSequence::Loaded(Bytes::from(record.seq().to_ascii_uppercase()))))
+            .collect();
+        ReferenceGenome::from_contigs(PathBuf::from(""), contigs)
+    }
+
+    /// Converts a contig into a rust-bio FASTA record without a description, or `None` if the contig is not in the reference genome
+    /// # Arguments
+    /// * `chromosome` - the contig to convert
+    pub fn to_bio_record(&self, chromosome: &str) -> Option<Record> {
+        let name = self.resolve_contig_name(chromosome)?;
+        Some(Record::with_attrs(name, None, self.get_full_chromosome(name)))
+    }
+
+    /// Converts every contig into a rust-bio FASTA record, in load order, e.g. for `bio::io::fasta::Writer::write_record(...)`
+    pub fn to_bio_records(&self) -> Vec<Record> {
+        self.contig_keys().iter()
+            .filter_map(|c| self.to_bio_record(c))
+            .collect()
+    }
+
+    /// Retrieves the sequence of a rust-bio genomic interval, such as `bio_types::genome::Interval`, as a `TextSlice`.
+    /// Truncation and panics are identical to `get_slice(...)`.
+    /// # Arguments
+    /// * `interval` - the contig and 0-based half-open range to retrieve
+    /// # Panics
+    /// * if the contig was not in the FASTA file or was unloaded
+    /// * if the interval start is after its end
+    pub fn get_genome_interval<I: AbstractInterval>(&self, interval: &I) -> &[u8] {
+        let range = interval.range();
+        self.get_slice(interval.contig(), range.start as usize, range.end as usize)
+    }
+
+    /// Same as `get_genome_interval(...)`, but returns a shared, owned handle like `get_slice_shared(...)`
+    pub fn get_genome_interval_shared<I: AbstractInterval>(&self, interval: &I) -> Bytes {
+        let range = interval.range();
+        self.get_slice_shared(interval.contig(), range.start as usize, range.end as usize)
+    }
+
+    /// Retrieves the sequence of a rust-bio interval on a contig, e.g. an entry of a `bio::data_structures::interval_tree::IntervalTree`.
+    /// Truncation and panics are identical to `get_slice(...)`.
+    /// # Arguments
+    /// * `chromosome` - the chromosome to slice from
+    /// * `interval` - the 0-based half-open range to retrieve
+    /// # Panics
+    /// * if `chromosome` was not in the FASTA file or was unloaded
+    pub fn get_interval(&self, chromosome: &str, interval: &Interval<usize>) -> &[u8] {
+        self.get_slice(chromosome, interval.start, interval.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bio::bio_types::genome;
+    use bio::data_structures::interval_tree::IntervalTree;
+
+    #[test]
+    fn test_bio_records() {
+        let records = vec![
+            Record::with_attrs("chr1", Some("first"), b"acgtACGT"),
+            Record::with_attrs("chr2", None, b"AccATGTA")
+        ];
+        let reference_genome = ReferenceGenome::from_bio_records(records.clone()).unwrap();
+        assert_eq!(reference_genome.contig_keys(), &["chr1".to_string(), "chr2".to_string()]);
+        assert_eq!(reference_genome.get_full_chromosome("chr2"), b"ACCATGTA");
+
+        let round_trip = reference_genome.to_bio_records();
+        assert_eq!(round_trip[1], Record::with_attrs("chr2", None, b"ACCATGTA"));
+        assert!(reference_genome.to_bio_record("chr3").is_none());
+
+        assert!(ReferenceGenome::from_bio_records([records[0].clone(), records[0].clone()]).is_err());
+    }
+
+    #[test]
+    fn test_bio_intervals() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGTACGT\n").unwrap();
+        let interval = genome::Interval::new("chr1".to_string(), 2..6);
+        assert_eq!(reference_genome.get_genome_interval(&interval), b"GTAC");
+        assert_eq!(reference_genome.get_genome_interval_shared(&interval), Bytes::from_static(b"GTAC"));
+
+        let mut tree: IntervalTree<usize, &str> = IntervalTree::new();
+        tree.insert(1..3, "a");
+        tree.insert(5..8, "b");
+        let sequences: Vec<&[u8]> = tree.find(0..8)
+            .map(|entry| reference_genome.get_interval("chr1", entry.interval()))
+            .collect();
+        assert_eq!(sequences.len(), 2);
+        assert!(sequences.contains(&&b"CG"[..]) && sequences.contains(&&b"CGT"[..]));
+    }
+}
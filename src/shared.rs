@@ -0,0 +1,125 @@
+use crate::reference_genome::ReferenceGenome;
+use bytes::Bytes;
+use simple_error::SimpleError;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// A reference genome that can be shared across threads and extended while in use, e.g. appending decoys to a running server.
+/// Clones are cheap and refer to the same genome. Reads take a shared lock and return owned `Bytes` handles, so sequences stay valid
+/// after the lock is released and never block writers for longer than a lookup; `add_contig(...)` takes an exclusive lock.
+/// This type is `Send + Sync`. A panic while a lock is held does not poison later access, since no operation leaves the genome partially modified.
+#[derive(Clone)]
+pub struct SharedReferenceGenome {
+    /// The wrapped genome
+    inner: Arc<RwLock<ReferenceGenome>>
+}
+
+impl From<ReferenceGenome> for SharedReferenceGenome {
+    fn from(reference_genome: ReferenceGenome) -> Self {
+        Self::new(reference_genome)
+    }
+}
+
+impl SharedReferenceGenome {
+    /// Wraps a reference genome for shared access
+    /// # Arguments
+    /// * `reference_genome` - the genome to share
+    pub fn new(reference_genome: ReferenceGenome) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(reference_genome))
+        }
+    }
+
+    /// Acquires a shared lock for access to the full `ReferenceGenome` API.
+    /// Writers are blocked while the guard is held, so avoid holding it across long-running work.
+    pub fn read(&self) -> RwLockReadGuard<'_, ReferenceGenome> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Acquires an exclusive lock for modifications beyond `add_contig(...)`, such as renaming or sorting contigs
+    pub fn write(&self) -> RwLockWriteGuard<'_, ReferenceGenome> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns a snapshot of the contig names in load order
+    pub fn contig_keys(&self) -> Vec<String> {
+        self.read().contig_keys().to_vec()
+    }
+
+    /// Retrieves the length of a contig, or `None` if it is not in the reference genome
+    /// # Arguments
+    /// * `chromosome` - the contig to measure
+    pub fn contig_length(&self, chromosome: &str) -> Option<usize> {
+        self.read().contig_length(chromosome)
+    }
+
+    /// Retrieves a full chromosome, or `None` if it is not in the reference genome or was unloaded
+    /// # Arguments
+    /// * `chromosome` - the chromosome to retrieve
+    pub fn get_full_chromosome(&self, chromosome: &str) -> Option<Bytes> {
+        self.read().try_get_full_chromosome_shared(chromosome)
+    }
+
+    /// Retrieves a reference slice from a given 0-based coordinates, or `None` if the contig is not in the reference genome or was unloaded.
+    /// Coordinates past the end of the contig follow `ReferenceGenome::out_of_bounds_policy()`, like `ReferenceGenome::get_slice_shared(...)`.
+    /// # Arguments
+    /// * `chromosome` - the chromosome to slice from
+    /// * `start` - the 0-based start index (included)
+    /// * `end` - the 0-based end index (excluded)
+    /// # Panics
+    /// * if `start` > `end`
+    /// * if `end` is past the contig end and the policy is `OutOfBoundsPolicy::Error`
+    pub fn get_slice(&self, chromosome: &str, start: usize, end: usize) -> Option<Bytes> {
+        assert!(start <= end, "start > end: {start} > {end}");
+        let reference_genome = self.read();
+        let full_contig = reference_genome.try_get_full_chromosome_shared(chromosome)?;
+        let policy = reference_genome.out_of_bounds_policy();
+        Some(reference_genome.slice_with_policy(chromosome, full_contig, start, end, policy).unwrap_or_else(|e| panic!("{e}")))
+    }
+
+    /// Adds a new contig, which is visible to all clones once this returns
+    /// # Arguments
+    /// * `contig_key` - the name of the contig
+    /// * `contig_sequence` - the sequence to add; all sequence is automatically upper-cased
+    /// # Errors
+    /// * if the contig is already in the reference genome
+    pub fn add_contig(&self, contig_key: String, contig_sequence: &str) -> Result<(), SimpleError> {
+        self.write().add_contig(contig_key, contig_sequence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ReferenceGenome>();
+        assert_send_sync::<SharedReferenceGenome>();
+    }
+
+    #[test]
+    fn test_shared_reference_genome() {
+        let reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        let shared = SharedReferenceGenome::from(reference_genome);
+        assert_eq!(shared.get_slice("chr1", 4, 100).unwrap(), &b"ACGT"[..]);
+        assert_eq!(shared.get_full_chromosome("decoy"), None);
+
+        // readers on other threads see contigs added after they started
+        let handles: Vec<_> = (0..4).map(|i| {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                shared.add_contig(format!("decoy{i}"), "acgt").unwrap();
+                shared.get_full_chromosome("chr2").unwrap()
+            })
+        }).collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), &b"ACCATGTA"[..]);
+        }
+        assert_eq!(shared.contig_keys().len(), 6);
+        assert_eq!(shared.get_full_chromosome("decoy3").unwrap(), &b"ACGT"[..]);
+        assert!(shared.add_contig("decoy0".to_string(), "A").is_err());
+        assert_eq!(shared.read().contig_length("decoy0"), Some(4));
+    }
+}
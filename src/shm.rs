@@ -0,0 +1,194 @@
+use crate::reference_genome::{ContigSequence, ReferenceGenome};
+use bytes::Bytes;
+use memmap2::Mmap;
+use simple_error::bail;
+use std::error::Error;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The first bytes of a shared genome segment, including the format version
+const SEGMENT_MAGIC: &[u8; 8] = b"RGSHM\x00\x00\x01";
+
+/// Resolves a segment name to its backing file: `/dev/shm/<name>` where it exists (Linux), otherwise the temporary directory
+/// # Errors
+/// * if the name is empty or contains a path separator
+fn segment_path(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    if name.is_empty() || name.contains('/') || name.contains('\\') || name == "." || name == ".." {
+        bail!("Invalid shared memory segment name {:?}", name);
+    }
+    let shm_directory = Path::new("/dev/shm");
+    let directory = if shm_directory.is_dir() { shm_directory.to_path_buf() } else { std::env::temp_dir() };
+    Ok(directory.join(name))
+}
+
+/// A named shared memory segment holding a decoded genome, from `ReferenceGenome::export_shared_memory(...)`.
+/// The segment outlives the process that created it, so it must be removed with `remove()` (or by deleting its file) once
+/// no new process needs to attach; processes that are already attached keep their mapping until they drop the genome.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SharedMemorySegment {
+    /// The segment name
+    name: String,
+    /// The backing file
+    path: PathBuf
+}
+
+impl SharedMemorySegment {
+    /// The segment name, to pass to `ReferenceGenome::attach_shared_memory(...)` in other processes
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The backing file, e.g. `/dev/shm/<name>` on Linux
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Removes the segment name; attached processes keep working, and the memory is freed when the last of them detaches
+    /// # Errors
+    /// * if the backing file cannot be removed
+    pub fn remove(self) -> std::io::Result<()> {
+        std::fs::remove_file(&self.path)
+    }
+}
+
+/// Reads a little-endian integer field of a segment header
+/// # Errors
+/// * if the field is past the end of the segment
+fn read_u64(data: &[u8], position: &mut usize) -> Result<u64, Box<dyn Error>> {
+    let Some(field) = data.get(*position..*position + 8) else {
+        bail!("The shared memory segment header is truncated");
+    };
+    *position += 8;
+    Ok(u64::from_le_bytes(field.try_into().unwrap()))
+}
+
+impl ReferenceGenome {
+    /// Places the decoded genome into a named shared memory segment, so worker processes can attach to one read-only copy
+    /// with `attach_shared_memory(...)` instead of each loading their own. The segment is written under a temporary name and then
+    /// renamed, so processes never attach to a partially written segment; an existing segment with the same name is replaced.
+    /// Lazily loaded contigs are read once and are not kept in memory.
+    /// # Arguments
+    /// * `name` - the segment name, e.g. "GRCh38"; a file name without path separators
+    /// # Errors
+    /// * if the name is invalid
+    /// * if a contig was unloaded or fails to load
+    /// * if the segment cannot be written, e.g. because `/dev/shm` is too small
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub fn export_shared_memory(&self, name: &str) -> Result<SharedMemorySegment, Box<dyn Error>> {
+        let path = segment_path(name)?;
+        let partial_path = path.with_file_name(format!(".{}.{}.partial", name, std::process::id()));
+
+        // the header is the magic, the contig count, then (name length, name, offset, length) per contig
+        let header_length = 16 + self.contig_keys().iter().map(|c| 24 + c.len()).sum::<usize>();
+        let result = (|| -> Result<(), Box<dyn Error>> {
+            let mut writer = BufWriter::new(std::fs::File::create(&partial_path)?);
+            writer.write_all(SEGMENT_MAGIC)?;
+            writer.write_all(&(self.contig_keys().len() as u64).to_le_bytes())?;
+            let mut offset = header_length;
+            for contig in self.contig_keys().iter() {
+                let length = self.contig_length(contig).unwrap_or_default();
+                writer.write_all(&(contig.len() as u64).to_le_bytes())?;
+                writer.write_all(contig.as_bytes())?;
+                writer.write_all(&(offset as u64).to_le_bytes())?;
+                writer.write_all(&(length as u64).to_le_bytes())?;
+                offset += length;
+            }
+            for contig in self.contig_keys().iter() {
+                writer.write_all(&self.try_sequence_unkept(contig)?)?;
+            }
+            writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            std::fs::rename(&partial_path, &path)?;
+            Ok(())
+        })();
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&partial_path);
+            return Err(e);
+        }
+        Ok(SharedMemorySegment { name: name.to_string(), path })
+    }
+
+    /// Attaches to a genome in a shared memory segment created by `export_shared_memory(...)`, typically in another process.
+    /// Sequences are read-only views of the shared mapping, so attaching costs no heap memory for sequences
+    /// and `memory_usage()` reports them as 0 bytes; modifying a contig copies it into this process.
+    /// # Arguments
+    /// * `name` - the segment name
+    /// # Errors
+    /// * if the name is invalid or no segment has that name
+    /// * if the segment is not a shared genome segment or is corrupted
+    pub fn attach_shared_memory(name: &str) -> Result<ReferenceGenome, Box<dyn Error>> {
+        let path = segment_path(name)?;
+        let segment_file = std::fs::File::open(&path)?;
+        // SAFETY: the mapping is read-only, and segments are only ever replaced by a rename, never modified in place
+        let mmap = unsafe { Mmap::map(&segment_file)? };
+        let data = Bytes::from_owner(mmap);
+        if data.get(..8) != Some(&SEGMENT_MAGIC[..]) {
+            bail!("{:?} is not a shared genome segment", path);
+        }
+
+        let mut position = 8;
+        let contig_count = read_u64(&data, &mut position)? as usize;
+        let mut contigs: Vec<(String, ContigSequence)> = Vec::with_capacity(contig_count.min(data.len() / 24));
+        for _ in 0..contig_count {
+            let name_length = read_u64(&data, &mut position)? as usize;
+            let Some(contig_name) = data.get(position..position.saturating_add(name_length)) else {
+                bail!("The shared memory segment header is truncated");
+            };
+            let contig_name = std::str::from_utf8(contig_name)?.to_string();
+            position += name_length;
+            let offset = read_u64(&data, &mut position)? as usize;
+            let length = read_u64(&data, &mut position)? as usize;
+            if offset.checked_add(length).is_none_or(|end| end > data.len()) {
+                bail!("Contig {:?} extends past the end of the shared memory segment", contig_name);
+            }
+            contigs.push((contig_name, ContigSequence::Mapped(data.slice(offset..offset + length))));
+        }
+        Ok(ReferenceGenome::from_contigs(path, contigs)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_memory() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nACGTACGT\n>chr2\nACCATG\n").unwrap();
+        let name = format!("refgenome_shm_{}", std::process::id());
+        let segment = reference_genome.export_shared_memory(&name).unwrap();
+        assert_eq!(segment.name(), name);
+
+        let mut attached = ReferenceGenome::attach_shared_memory(&name).unwrap();
+        assert_eq!(attached.contig_keys(), reference_genome.contig_keys());
+        for contig in reference_genome.contig_keys().iter() {
+            assert_eq!(attached.get_full_chromosome(contig), reference_genome.get_full_chromosome(contig));
+        }
+        assert_eq!(attached.memory_usage().sequence_bytes(), 0);
+
+        // modifying a contig copies it out of the segment
+        attached.modify_contig("chr1", |s| s[0] = b'N').unwrap();
+        assert_eq!(attached.get_slice("chr1", 0, 2), b"NC");
+        assert_eq!(ReferenceGenome::attach_shared_memory(&name).unwrap().get_slice("chr1", 0, 2), b"AC");
+
+        // attached genomes stay valid after the name is removed
+        segment.remove().unwrap();
+        assert_eq!(attached.get_slice("chr2", 0, 3), b"ACC");
+        assert!(ReferenceGenome::attach_shared_memory(&name).is_err());
+    }
+
+    #[test]
+    fn test_shared_memory_errors() {
+        let reference_genome = ReferenceGenome::empty_reference();
+        assert!(reference_genome.export_shared_memory("a/b").is_err());
+        assert!(reference_genome.export_shared_memory("").is_err());
+
+        let name = format!("refgenome_shm_corrupt_{}", std::process::id());
+        let path = segment_path(&name).unwrap();
+        std::fs::write(&path, b"not a segment").unwrap();
+        assert!(ReferenceGenome::attach_shared_memory(&name).is_err());
+        let mut truncated = SEGMENT_MAGIC.to_vec();
+        truncated.extend_from_slice(&5u64.to_le_bytes());
+        std::fs::write(&path, &truncated).unwrap();
+        assert!(ReferenceGenome::attach_shared_memory(&name).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
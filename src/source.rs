@@ -0,0 +1,303 @@
+use crate::reference_genome::ReferenceGenome;
+use crate::shared::SharedReferenceGenome;
+use bytes::Bytes;
+use simple_error::{bail, SimpleError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A minimal read-only view of a reference: contig listing, lengths, and ranged fetches.
+/// Code written against this trait works with a loaded `ReferenceGenome` (whatever its backend, e.g. in-memory, mmap, or faidx),
+/// a `SharedReferenceGenome`, a `MockSequenceSource` in unit tests, or a downstream implementation such as a remote service.
+pub trait SequenceSource {
+    /// The contig names in source order
+    fn contig_names(&self) -> Vec<String>;
+
+    /// The length of a contig, or `None` if it is not in the source
+    /// # Arguments
+    /// * `contig` - the contig to measure
+    fn contig_length(&self, contig: &str) -> Option<usize>;
+
+    /// Fetches a 0-based, half-open range of a contig
+    /// # Arguments
+    /// * `contig` - the contig to fetch from
+    /// * `start` - the 0-based start index (included)
+    /// * `end` - the 0-based end index (excluded)
+    /// # Errors
+    /// * if the contig is not in the source or cannot be read
+    /// * if `start` > `end` or `end` is past the end of the contig; unlike `ReferenceGenome::get_slice(...)`, nothing is truncated
+    fn fetch(&self, contig: &str, start: usize, end: usize) -> Result<Bytes, SimpleError>;
+
+    /// Fetches the full sequence of a contig
+    /// # Errors
+    /// * if the contig is not in the source or cannot be read
+    fn fetch_contig(&self, contig: &str) -> Result<Bytes, SimpleError> {
+        match self.contig_length(contig) {
+            Some(length) => self.fetch(contig, 0, length),
+            None => bail!("Contig {:?} is not in the sequence source", contig)
+        }
+    }
+
+    /// Fetches a batch of 0-based, half-open ranges with one underlying `fetch(...)` per merged block: ranges on the same contig
+    /// that overlap, touch, or are at most `max_gap` bases apart are read together and then sliced back out without copying.
+    /// This is a large win for dense tiling windows on a lazy or remote source, where each read has a fixed cost.
+    /// # Arguments
+    /// * `regions` - the ranges as (contig, start, end), in any order
+    /// * `max_gap` - the largest gap between ranges that is read through to merge them; 0 merges only overlapping or adjacent ranges
+    /// # Returns
+    /// * the sequences in the order of `regions`
+    /// # Errors
+    /// * if a contig is not in the source, or a range is invalid as in `fetch(...)`; every range is checked before anything is read
+    /// * if a merged block cannot be read
+    fn fetch_many(&self, regions: &[(&str, usize, usize)], max_gap: usize) -> Result<Vec<Bytes>, SimpleError> {
+        for &(contig, start, end) in regions.iter() {
+            match self.contig_length(contig) {
+                Some(length) => check_range(contig, start, end, length)?,
+                None => bail!("Contig {:?} is not in the sequence source", contig)
+            }
+        }
+
+        let mut order: Vec<usize> = (0..regions.len()).collect();
+        order.sort_by_key(|&i| (regions[i].0, regions[i].1));
+        let mut results: Vec<Bytes> = vec![Bytes::new(); regions.len()];
+        let mut block_start = 0;
+        while block_start < order.len() {
+            // extend the block while the next range starts within the gap of its end
+            let (contig, start, mut end) = regions[order[block_start]];
+            let mut block_end = block_start + 1;
+            while block_end < order.len() {
+                let (next_contig, next_start, next_end) = regions[order[block_end]];
+                if next_contig != contig || next_start > end.saturating_add(max_gap) {
+                    break;
+                }
+                end = end.max(next_end);
+                block_end += 1;
+            }
+            let block = self.fetch(contig, start, end)?;
+            if block.len() != end - start {
+                bail!("Fetching {}-{} of contig {:?} returned {} bases", start, end, contig, block.len());
+            }
+            for &i in order[block_start..block_end].iter() {
+                results[i] = block.slice(regions[i].1 - start..regions[i].2 - start);
+            }
+            block_start = block_end;
+        }
+        Ok(results)
+    }
+}
+
+/// Checks a requested range against the contig length
+fn check_range(contig: &str, start: usize, end: usize, length: usize) -> Result<(), SimpleError> {
+    if start > end || end > length {
+        bail!("Invalid range {}-{} for contig {:?} with length {}", start, end, contig, length);
+    }
+    Ok(())
+}
+
+impl SequenceSource for ReferenceGenome {
+    fn contig_names(&self) -> Vec<String> {
+        self.contig_keys().to_vec()
+    }
+
+    fn contig_length(&self, contig: &str) -> Option<usize> {
+        ReferenceGenome::contig_length(self, contig)
+    }
+
+    fn fetch(&self, contig: &str, start: usize, end: usize) -> Result<Bytes, SimpleError> {
+        if ReferenceGenome::contig_length(self, contig).is_none() {
+            bail!("{}", self.missing_contig_message(contig));
+        }
+        let Some(sequence) = self.try_get_full_chromosome_shared(contig) else {
+            bail!("Contig key {:?} has been unloaded", contig);
+        };
+        check_range(contig, start, end, sequence.len())?;
+        Ok(sequence.slice(start..end))
+    }
+}
+
+impl SequenceSource for SharedReferenceGenome {
+    fn contig_names(&self) -> Vec<String> {
+        self.contig_keys()
+    }
+
+    fn contig_length(&self, contig: &str) -> Option<usize> {
+        SharedReferenceGenome::contig_length(self, contig)
+    }
+
+    fn fetch(&self, contig: &str, start: usize, end: usize) -> Result<Bytes, SimpleError> {
+        self.read().fetch(contig, start, end)
+    }
+
+    /// Holds one shared lock for the whole batch, so every range comes from the same version of the genome
+    fn fetch_many(&self, regions: &[(&str, usize, usize)], max_gap: usize) -> Result<Vec<Bytes>, SimpleError> {
+        self.read().fetch_many(regions, max_gap)
+    }
+}
+
+impl<S: SequenceSource + ?Sized> SequenceSource for &S {
+    fn contig_names(&self) -> Vec<String> {
+        (**self).contig_names()
+    }
+
+    fn contig_length(&self, contig: &str) -> Option<usize> {
+        (**self).contig_length(contig)
+    }
+
+    fn fetch(&self, contig: &str, start: usize, end: usize) -> Result<Bytes, SimpleError> {
+        (**self).fetch(contig, start, end)
+    }
+
+    fn fetch_many(&self, regions: &[(&str, usize, usize)], max_gap: usize) -> Result<Vec<Bytes>, SimpleError> {
+        (**self).fetch_many(regions, max_gap)
+    }
+}
+
+impl<S: SequenceSource + ?Sized> SequenceSource for Arc<S> {
+    fn contig_names(&self) -> Vec<String> {
+        (**self).contig_names()
+    }
+
+    fn contig_length(&self, contig: &str) -> Option<usize> {
+        (**self).contig_length(contig)
+    }
+
+    fn fetch(&self, contig: &str, start: usize, end: usize) -> Result<Bytes, SimpleError> {
+        (**self).fetch(contig, start, end)
+    }
+
+    fn fetch_many(&self, regions: &[(&str, usize, usize)], max_gap: usize) -> Result<Vec<Bytes>, SimpleError> {
+        (**self).fetch_many(regions, max_gap)
+    }
+}
+
+/// An in-memory `SequenceSource` for unit tests, built from literal sequences without a FASTA file.
+/// Sequences are stored as given (no upper-casing), and every call to `fetch(...)` is counted, e.g. to test caching layers.
+/// # Examples
+/// ```
+/// use rust_lib_reference_genome::source::{MockSequenceSource, SequenceSource};
+///
+/// let source = MockSequenceSource::new()
+///     .with_contig("chr1", b"ACGTACGT")
+///     .with_contig("chr2", b"GGCC");
+/// assert_eq!(source.contig_names(), vec!["chr1", "chr2"]);
+/// assert_eq!(source.fetch("chr1", 2, 6).unwrap(), &b"GTAC"[..]);
+/// assert_eq!(source.fetch_count(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct MockSequenceSource {
+    /// The contigs in insertion order
+    contigs: Vec<(String, Bytes)>,
+    /// The number of calls to `fetch(...)`
+    fetches: AtomicUsize
+}
+
+impl MockSequenceSource {
+    /// Creates a source without any contigs
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a contig, replacing the sequence of an existing contig with the same name
+    /// # Arguments
+    /// * `contig` - the contig name
+    /// * `sequence` - the contig sequence
+    pub fn with_contig(mut self, contig: &str, sequence: &[u8]) -> Self {
+        let sequence = Bytes::copy_from_slice(sequence);
+        match self.contigs.iter_mut().find(|(name, _)| name == contig) {
+            Some(existing) => existing.1 = sequence,
+            None => self.contigs.push((contig.to_string(), sequence))
+        }
+        self
+    }
+
+    /// The number of calls to `fetch(...)` so far, including failed ones and those from `fetch_contig(...)`
+    pub fn fetch_count(&self) -> usize {
+        self.fetches.load(Ordering::Relaxed)
+    }
+}
+
+impl SequenceSource for MockSequenceSource {
+    fn contig_names(&self) -> Vec<String> {
+        self.contigs.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    fn contig_length(&self, contig: &str) -> Option<usize> {
+        self.contigs.iter().find(|(name, _)| name == contig).map(|(_, sequence)| sequence.len())
+    }
+
+    fn fetch(&self, contig: &str, start: usize, end: usize) -> Result<Bytes, SimpleError> {
+        self.fetches.fetch_add(1, Ordering::Relaxed);
+        let Some((_, sequence)) = self.contigs.iter().find(|(name, _)| name == contig) else {
+            bail!("Contig {:?} is not in the sequence source", contig);
+        };
+        check_range(contig, start, end, sequence.len())?;
+        Ok(sequence.slice(start..end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A generic consumer, as downstream code would write it
+    fn gc_count<S: SequenceSource>(source: S, contig: &str) -> Result<usize, SimpleError> {
+        Ok(source.fetch_contig(contig)?.iter().filter(|&&c| matches!(c, b'G' | b'C')).count())
+    }
+
+    #[test]
+    fn test_reference_genome_source() {
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nacgtAC\n>chr2\nGG\n").unwrap();
+        assert_eq!(SequenceSource::contig_names(&reference_genome), vec!["chr1", "chr2"]);
+        assert_eq!(reference_genome.fetch("chr1", 1, 4).unwrap(), &b"CGT"[..]);
+        assert!(reference_genome.fetch("chr1", 4, 7).is_err());
+        assert!(reference_genome.fetch("chr1", 4, 3).is_err());
+        assert!(reference_genome.fetch("chr3", 0, 1).is_err());
+        assert_eq!(gc_count(&reference_genome, "chr1").unwrap(), 3);
+
+        let shared = SharedReferenceGenome::new(reference_genome);
+        assert_eq!(gc_count(shared.clone(), "chr2").unwrap(), 2);
+        assert!(gc_count(Arc::new(shared), "chr3").is_err());
+    }
+
+    #[test]
+    fn test_mock_sequence_source() {
+        let source = MockSequenceSource::new()
+            .with_contig("chr1", b"ACGT")
+            .with_contig("chr1", b"GGGA");
+        assert_eq!(source.contig_names(), vec!["chr1"]);
+        assert_eq!(gc_count(&source, "chr1").unwrap(), 3);
+        assert!(source.fetch("chr1", 2, 5).is_err());
+        assert!(source.fetch_contig("chr2").is_err());
+        // fetch_contig(...) on an unknown contig fails before calling fetch(...)
+        assert_eq!(source.fetch_count(), 2);
+    }
+
+    #[test]
+    fn test_fetch_many() {
+        let source = MockSequenceSource::new()
+            .with_contig("chr1", b"ACGTACGTAACCGGTT")
+            .with_contig("chr2", b"GGGGCCCC");
+        // tiling windows, an overlapping window, an empty range, and ranges on another contig, out of order
+        let regions = [("chr1", 4, 8), ("chr2", 6, 8), ("chr1", 0, 4), ("chr1", 2, 6), ("chr2", 0, 2), ("chr1", 8, 8), ("chr1", 12, 16)];
+        let sequences = source.fetch_many(&regions, 0).unwrap();
+        assert_eq!(source.fetch_count(), 4);
+        for (&(contig, start, end), sequence) in regions.iter().zip(sequences.iter()) {
+            assert_eq!(sequence, &source.fetch(contig, start, end).unwrap());
+        }
+        assert_eq!(sequences[3], &b"GTAC"[..]);
+
+        // a large enough gap reads through, so each contig is one fetch
+        let source = MockSequenceSource::new()
+            .with_contig("chr1", b"ACGTACGTAACCGGTT")
+            .with_contig("chr2", b"GGGGCCCC");
+        let sequences = source.fetch_many(&regions, 4).unwrap();
+        assert_eq!(source.fetch_count(), 2);
+        assert_eq!(sequences[6], &b"GGTT"[..]);
+        assert_eq!(sequences[1], &b"CC"[..]);
+
+        // invalid ranges fail before anything is read
+        assert!(source.fetch_many(&[("chr1", 0, 4), ("chr1", 10, 20)], 0).is_err());
+        assert!(source.fetch_many(&[("chr1", 0, 4), ("chr3", 0, 1)], 0).is_err());
+        assert_eq!(source.fetch_count(), 2);
+        assert!(source.fetch_many(&[], 0).unwrap().is_empty());
+    }
+}
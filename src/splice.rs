@@ -0,0 +1,305 @@
+use crate::alphabet::{complement, reverse_complement};
+use crate::bed::BedRecord;
+use crate::builder::{decompress, is_gzip};
+use crate::coordinates::Strand;
+use crate::reference_genome::ReferenceGenome;
+use bytes::Bytes;
+use simple_error::{bail, SimpleError};
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// An intron between two exons, in forward strand coordinates
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpliceJunction {
+    /// The contig name
+    pub contig: String,
+    /// The 0-based position of the first intron base (included)
+    pub start: usize,
+    /// The 0-based position after the last intron base (excluded)
+    pub end: usize,
+    /// The transcript strand, or `None` if unknown; the strand is then inferred from the motif
+    pub strand: Option<Strand>
+}
+
+impl From<&BedRecord> for SpliceJunction {
+    /// Converts a BED interval covering the intron, taking the strand from the sixth column if present
+    fn from(record: &BedRecord) -> Self {
+        Self {
+            contig: record.contig.clone(),
+            start: record.start,
+            end: record.end,
+            strand: record.strand().and_then(Strand::from_symbol)
+        }
+    }
+}
+
+/// Reads the junctions of a STAR `SJ.out.tab` file, whose first four columns are the contig, the 1-based first and last intron bases,
+/// and the strand as 0 (undefined), 1 (+), or 2 (-); the remaining columns are ignored
+/// # Arguments
+/// * `reader` - the uncompressed junction table
+/// # Errors
+/// * any reading errors
+/// * if a line has fewer than 4 columns, an invalid intron, or an invalid strand
+pub fn read_star_junctions<R: BufRead>(reader: R) -> Result<Vec<SpliceJunction>, Box<dyn Error>> {
+    let mut junctions: Vec<SpliceJunction> = vec![];
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let columns: Vec<&str> = line.trim_end_matches('\r').split('\t').collect();
+        if columns.len() < 4 {
+            bail!("Expected at least 4 columns on line {} of the junctions, found {}", line_index + 1, columns.len());
+        }
+        let (start, end) = match (columns[1].parse::<usize>(), columns[2].parse::<usize>()) {
+            (Ok(first), Ok(last)) if first > 0 && first <= last => (first - 1, last),
+            _ => bail!("Invalid intron {}-{} on line {} of the junctions", columns[1], columns[2], line_index + 1)
+        };
+        let strand = match columns[3] {
+            "0" => None,
+            "1" => Some(Strand::Forward),
+            "2" => Some(Strand::Reverse),
+            other => bail!("Invalid strand {:?} on line {} of the junctions", other, line_index + 1)
+        };
+        junctions.push(SpliceJunction { contig: columns[0].to_string(), start, end, strand });
+    }
+    Ok(junctions)
+}
+
+/// Reads the junctions of a STAR `SJ.out.tab` file, see `read_star_junctions(...)`
+/// # Arguments
+/// * `junctions_fn` - the junction table filename; gzip compression is detected from a `.gz` extension
+/// # Errors
+/// * any file reading errors
+/// * see `read_star_junctions(...)`
+pub fn read_star_junctions_file(junctions_fn: &Path) -> Result<Vec<SpliceJunction>, Box<dyn Error>> {
+    let junctions_file = std::fs::File::open(junctions_fn)?;
+    let reader = decompress(Box::new(BufReader::new(junctions_file)), is_gzip(junctions_fn), &Arc::default())?;
+    read_star_junctions(reader)
+}
+
+/// The donor/acceptor dinucleotide class of an intron, on the transcript strand
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SpliceMotif {
+    /// GT donor, AG acceptor: the canonical U2 intron motif
+    GtAg,
+    /// GC donor, AG acceptor
+    GcAg,
+    /// AT donor, AC acceptor: the U12 intron motif
+    AtAc,
+    /// Any other pair
+    NonCanonical
+}
+
+impl SpliceMotif {
+    /// Classifies a pair of upper-case donor and acceptor dinucleotides
+    pub fn from_dinucleotides(donor: &[u8], acceptor: &[u8]) -> Self {
+        match (donor, acceptor) {
+            (b"GT", b"AG") => SpliceMotif::GtAg,
+            (b"GC", b"AG") => SpliceMotif::GcAg,
+            (b"AT", b"AC") => SpliceMotif::AtAc,
+            _ => SpliceMotif::NonCanonical
+        }
+    }
+
+    /// The motif name, e.g. "GT-AG"
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpliceMotif::GtAg => "GT-AG",
+            SpliceMotif::GcAg => "GC-AG",
+            SpliceMotif::AtAc => "AT-AC",
+            SpliceMotif::NonCanonical => "non-canonical"
+        }
+    }
+
+    /// Returns true for GT-AG
+    pub fn is_canonical(&self) -> bool {
+        *self == SpliceMotif::GtAg
+    }
+}
+
+/// The flanking bases of `ReferenceGenome::splice_site_contexts(...)`; the default is the 9-mer donor and 23-mer acceptor of MaxEntScan
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpliceContextOptions {
+    /// The exon bases before the donor, default is 3
+    pub donor_exon_flank: usize,
+    /// The intron bases from the donor, including the dinucleotide, default is 6
+    pub donor_intron_flank: usize,
+    /// The intron bases up to the acceptor, including the dinucleotide, default is 20
+    pub acceptor_intron_flank: usize,
+    /// The exon bases after the acceptor, default is 3
+    pub acceptor_exon_flank: usize
+}
+
+impl Default for SpliceContextOptions {
+    fn default() -> Self {
+        Self {
+            donor_exon_flank: 3,
+            donor_intron_flank: 6,
+            acceptor_intron_flank: 20,
+            acceptor_exon_flank: 3
+        }
+    }
+}
+
+/// The splice sites of a junction on its transcript strand, from `ReferenceGenome::splice_site_contexts(...)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpliceSiteContext {
+    /// The junction as given
+    pub junction: SpliceJunction,
+    /// The strand the sites are reported on: the given strand, the strand inferred from the motif, or `None` (reported on the forward
+    /// strand) if the junction is unstranded and non-canonical on both strands
+    pub strand: Option<Strand>,
+    /// The first two intron bases on the transcript strand, upper-cased
+    pub donor: [u8; 2],
+    /// The last two intron bases on the transcript strand, upper-cased
+    pub acceptor: [u8; 2],
+    /// The motif of the donor and acceptor
+    pub motif: SpliceMotif,
+    /// The exon and intron bases around the donor on the transcript strand, upper-cased and padded with N past the contig ends
+    pub donor_context: Vec<u8>,
+    /// The intron and exon bases around the acceptor on the transcript strand, upper-cased and padded with N past the contig ends
+    pub acceptor_context: Vec<u8>
+}
+
+/// The upper-cased bases from `before` bases before `position` up to `after` bases from it, padded with N past either contig end
+fn padded_window(sequence: &[u8], position: usize, before: usize, after: usize) -> Vec<u8> {
+    let start = position.saturating_sub(before);
+    let end = (position + after).min(sequence.len());
+    let mut window: Vec<u8> = vec![b'N'; before - (position - start)];
+    window.extend(sequence[start..end].iter().map(|c| c.to_ascii_uppercase()));
+    window.resize(before + after, b'N');
+    window
+}
+
+/// The donor and acceptor dinucleotides of an intron on one strand
+fn dinucleotides(sequence: &[u8], start: usize, end: usize, strand: Strand) -> ([u8; 2], [u8; 2]) {
+    let upper = |s: &[u8]| [s[0].to_ascii_uppercase(), s[1].to_ascii_uppercase()];
+    let (first, last) = (upper(&sequence[start..start + 2]), upper(&sequence[end - 2..end]));
+    match strand {
+        Strand::Forward => (first, last),
+        Strand::Reverse => (
+            [complement(last[1]), complement(last[0])],
+            [complement(first[1]), complement(first[0])]
+        )
+    }
+}
+
+impl ReferenceGenome {
+    /// Extracts the donor/acceptor dinucleotides and flanking context of each junction, and classifies its motif, such as for RNA-seq
+    /// QC against the exact reference in use. Sites are reported on the transcript strand; for unstranded junctions, the strand whose
+    /// motif is closest to canonical (GT-AG, then GC-AG, then AT-AC) is used.
+    /// Junctions are processed in the given order; consecutive junctions on the same contig share a single read of a lazily loaded contig.
+    /// # Arguments
+    /// * `junctions` - the introns, e.g. from `read_star_junctions_file(...)` or BED records
+    /// * `options` - the flank lengths, see `SpliceContextOptions::default()`
+    /// # Errors
+    /// * if a contig is not in the reference genome, was unloaded, or fails to load
+    /// * if an intron is shorter than 4 bases or extends past the end of its contig
+    pub fn splice_site_contexts(&self, junctions: &[SpliceJunction], options: &SpliceContextOptions) -> Result<Vec<SpliceSiteContext>, SimpleError> {
+        let mut contexts: Vec<SpliceSiteContext> = Vec::with_capacity(junctions.len());
+        let mut current: Option<(&str, Bytes)> = None;
+        for junction in junctions.iter() {
+            let sequence = match current.as_ref() {
+                Some((contig, sequence)) if *contig == junction.contig => sequence.clone(),
+                _ => {
+                    let sequence = self.try_sequence_unkept(&junction.contig)?;
+                    current = Some((&junction.contig, sequence.clone()));
+                    sequence
+                }
+            };
+            if junction.end > sequence.len() {
+                bail!("Junction {}:{}-{} extends past the end of the contig ({} bp)", junction.contig, junction.start + 1, junction.end, sequence.len());
+            }
+            if junction.end < junction.start + 4 {
+                bail!("Junction {}:{}-{} is shorter than 4 bases", junction.contig, junction.start + 1, junction.end);
+            }
+
+            let (start, end) = (junction.start, junction.end);
+            let (strand, (donor, acceptor)) = match junction.strand {
+                Some(strand) => (Some(strand), dinucleotides(&sequence, start, end, strand)),
+                None => {
+                    let forward = dinucleotides(&sequence, start, end, Strand::Forward);
+                    let reverse = dinucleotides(&sequence, start, end, Strand::Reverse);
+                    let rank = |(d, a): &([u8; 2], [u8; 2])| SpliceMotif::from_dinucleotides(d, a) as usize;
+                    match (rank(&forward), rank(&reverse)) {
+                        (f, r) if f == SpliceMotif::NonCanonical as usize && r == f => (None, forward),
+                        (f, r) if r < f => (Some(Strand::Reverse), reverse),
+                        _ => (Some(Strand::Forward), forward)
+                    }
+                }
+            };
+            let (donor_context, acceptor_context) = match strand {
+                Some(Strand::Reverse) => (
+                    reverse_complement(&padded_window(&sequence, end, options.donor_intron_flank, options.donor_exon_flank)),
+                    reverse_complement(&padded_window(&sequence, start, options.acceptor_exon_flank, options.acceptor_intron_flank))
+                ),
+                _ => (
+                    padded_window(&sequence, start, options.donor_exon_flank, options.donor_intron_flank),
+                    padded_window(&sequence, end, options.acceptor_intron_flank, options.acceptor_exon_flank)
+                )
+            };
+            contexts.push(SpliceSiteContext {
+                junction: junction.clone(),
+                strand,
+                donor,
+                acceptor,
+                motif: SpliceMotif::from_dinucleotides(&donor, &acceptor),
+                donor_context,
+                acceptor_context
+            });
+        }
+        Ok(contexts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn junction(start: usize, end: usize, strand: Option<Strand>) -> SpliceJunction {
+        SpliceJunction { contig: "chr1".to_string(), start, end, strand }
+    }
+
+    #[test]
+    fn test_read_star_junctions() {
+        let table = b"chr1\t4\t9\t1\t1\t1\t10\t0\t20\nchr1\t4\t9\t0\t0\t0\t1\t0\t5\r\nchr2\t20\t30\t2\t2\t0\t3\t1\t40\n";
+        let junctions = read_star_junctions(&table[..]).unwrap();
+        assert_eq!(junctions[0], junction(3, 9, Some(Strand::Forward)));
+        assert_eq!((junctions[1].strand, junctions[2].strand), (None, Some(Strand::Reverse)));
+        assert!(read_star_junctions(&b"chr1\t4\t9\n"[..]).is_err());
+        assert!(read_star_junctions(&b"chr1\t0\t9\t1\n"[..]).is_err());
+        assert!(read_star_junctions(&b"chr1\t4\t9\t+\n"[..]).is_err());
+
+        let record = BedRecord { contig: "chr1".to_string(), start: 3, end: 9, extra: vec![".".to_string(), "0".to_string(), "-".to_string()] };
+        assert_eq!(SpliceJunction::from(&record), junction(3, 9, Some(Strand::Reverse)));
+    }
+
+    #[test]
+    fn test_splice_site_contexts() {
+        // a GT-AG intron at 3..11 and, on the reverse strand, a GC-AG intron at 13..19 (CT...GC on the forward strand)
+        let reference_genome = ReferenceGenome::from_fasta_bytes(b">chr1\nCAGgtaagtAGGTCTAAGCA\n").unwrap();
+        let options = SpliceContextOptions { donor_exon_flank: 2, donor_intron_flank: 3, acceptor_intron_flank: 3, acceptor_exon_flank: 2 };
+        let junctions = vec![junction(3, 11, None), junction(3, 11, Some(Strand::Forward)), junction(13, 19, None), junction(0, 5, None)];
+        let contexts = reference_genome.splice_site_contexts(&junctions, &options).unwrap();
+
+        assert_eq!((contexts[0].strand, contexts[0].motif), (Some(Strand::Forward), SpliceMotif::GtAg));
+        assert_eq!((&contexts[0].donor, &contexts[0].acceptor), (b"GT", b"AG"));
+        assert_eq!((contexts[0].donor_context.as_slice(), contexts[0].acceptor_context.as_slice()), (&b"AGGTA"[..], &b"TAGGT"[..]));
+        assert_eq!(contexts[1].motif, SpliceMotif::GtAg);
+
+        // reverse strand sites are reverse complemented, and the donor exon flank runs past the contig end
+        assert_eq!((contexts[2].strand, contexts[2].motif), (Some(Strand::Reverse), SpliceMotif::GcAg));
+        assert_eq!((contexts[2].donor_context.as_slice(), contexts[2].acceptor_context.as_slice()), (&b"NTGCT"[..], &b"TAGAC"[..]));
+
+        // CAGgt is non-canonical on both strands
+        assert_eq!((contexts[3].strand, contexts[3].motif.name()), (None, "non-canonical"));
+        assert!(contexts[3].donor_context.starts_with(b"NN"));
+
+        assert!(reference_genome.splice_site_contexts(&[junction(3, 21, None)], &options).is_err());
+        assert!(reference_genome.splice_site_contexts(&[junction(3, 6, None)], &options).is_err());
+        assert!(reference_genome.splice_site_contexts(&[SpliceJunction { contig: "chr2".to_string(), ..junction(3, 11, None) }], &options).is_err());
+    }
+}
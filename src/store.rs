@@ -0,0 +1,314 @@
+use crate::digest::md5_hex_uppercase;
+use crate::lazy::{ContigLoader, LazyContig};
+use crate::reference_genome::{ContigSequence, ReferenceGenome};
+use simple_error::{bail, SimpleError};
+use std::error::Error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The store subdirectory holding one file per sequence
+const SEQUENCES_DIRECTORY: &str = "sequences";
+/// The store subdirectory holding one alias file per namespace
+const ALIASES_DIRECTORY: &str = "aliases";
+
+/// Writes a file under a temporary name in the same directory and renames it into place, so readers never see partial contents
+/// # Arguments
+/// * `path` - the destination file, whose directory must exist
+/// * `contents` - the file contents
+/// # Errors
+/// * any file creation and/or writing errors
+pub(crate) fn write_file_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let file_name = path.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
+    let temporary = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+    let mut file = std::fs::File::create(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    std::fs::rename(&temporary, path)
+}
+
+/// A content-addressed directory of sequences, in the style of seqrepo, where each distinct sequence is stored once no matter
+/// how many assemblies contain it.
+/// Sequences are addressed by the SAM `M5` digest (lower-case hexadecimal MD5 of the upper-cased sequence) and stored upper-cased
+/// as `sequences/<first two digest characters>/<digest>`.
+/// Names are mapped to digests within a namespace, usually one per assembly, in `aliases/<namespace>.tsv`, which keeps the contig order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequenceStore {
+    /// The store directory
+    root: PathBuf
+}
+
+impl SequenceStore {
+    /// Opens a store, creating the directory layout if it does not exist
+    /// # Arguments
+    /// * `root` - the store directory
+    /// # Errors
+    /// * if the directories cannot be created
+    pub fn open(root: &Path) -> Result<SequenceStore, Box<dyn Error>> {
+        std::fs::create_dir_all(root.join(SEQUENCES_DIRECTORY))?;
+        std::fs::create_dir_all(root.join(ALIASES_DIRECTORY))?;
+        Ok(SequenceStore {
+            root: root.to_path_buf()
+        })
+    }
+
+    /// The store directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The file holding a sequence, or an error if the digest is not a lower-case MD5 digest
+    fn sequence_path(&self, digest: &str) -> Result<PathBuf, SimpleError> {
+        if digest.len() != 32 || !digest.bytes().all(|c| c.is_ascii_digit() || (b'a'..=b'f').contains(&c)) {
+            bail!("Invalid sequence digest: {:?}", digest);
+        }
+        Ok(self.root.join(SEQUENCES_DIRECTORY).join(&digest[..2]).join(digest))
+    }
+
+    /// The alias file of a namespace, or an error if the namespace is not a plain file name
+    fn alias_path(&self, namespace: &str) -> Result<PathBuf, SimpleError> {
+        if namespace.is_empty() || namespace.starts_with('.') || namespace.contains(['/', '\\']) {
+            bail!("Invalid alias namespace: {:?}", namespace);
+        }
+        Ok(self.root.join(ALIASES_DIRECTORY).join(format!("{namespace}.tsv")))
+    }
+
+    /// Returns true if the store holds a sequence; invalid digests are never present
+    /// # Arguments
+    /// * `digest` - the lower-case MD5 digest
+    pub fn contains(&self, digest: &str) -> bool {
+        self.sequence_path(digest).map(|p| p.is_file()).unwrap_or(false)
+    }
+
+    /// Adds a sequence, upper-cased, unless an identical sequence is already present.
+    /// The file is written under a temporary name and renamed into place, so concurrent writers never expose a partial sequence.
+    /// # Arguments
+    /// * `sequence` - the ASCII sequence
+    /// # Returns
+    /// * the digest addressing the sequence
+    /// # Errors
+    /// * any file creation and/or writing errors
+    pub fn insert(&self, sequence: &[u8]) -> Result<String, Box<dyn Error>> {
+        let digest = md5_hex_uppercase(sequence);
+        let path = self.sequence_path(&digest)?;
+        if !path.is_file() {
+            std::fs::create_dir_all(path.parent().expect("sequence paths have a parent"))?;
+            write_file_atomically(&path, &sequence.to_ascii_uppercase())?;
+        }
+        Ok(digest)
+    }
+
+    /// Reads a sequence
+    /// # Arguments
+    /// * `digest` - the lower-case MD5 digest
+    /// # Errors
+    /// * if the digest is invalid or not in the store
+    /// * if the file cannot be read
+    pub fn get(&self, digest: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let path = self.sequence_path(digest)?;
+        if !path.is_file() {
+            bail!("Sequence {} is not in the store at {:?}", digest, self.root);
+        }
+        Ok(std::fs::read(path)?)
+    }
+
+    /// The namespaces with aliases, sorted by name
+    /// # Errors
+    /// * if the alias directory cannot be read
+    pub fn namespaces(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut namespaces: Vec<String> = vec![];
+        for entry in std::fs::read_dir(self.root.join(ALIASES_DIRECTORY))? {
+            let file_name = entry?.file_name().to_string_lossy().to_string();
+            if let Some(namespace) = file_name.strip_suffix(".tsv") {
+                if !namespace.starts_with('.') {
+                    namespaces.push(namespace.to_string());
+                }
+            }
+        }
+        namespaces.sort();
+        Ok(namespaces)
+    }
+
+    /// Reads the (name, digest) aliases of a namespace, in the order they were written
+    /// # Arguments
+    /// * `namespace` - the namespace, e.g. an assembly name
+    /// # Errors
+    /// * if the namespace is invalid or does not exist
+    /// * if the alias file is malformed
+    pub fn aliases(&self, namespace: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let path = self.alias_path(namespace)?;
+        if !path.is_file() {
+            bail!("Namespace {:?} is not in the store at {:?}", namespace, self.root);
+        }
+        let mut aliases: Vec<(String, String)> = vec![];
+        for (line_index, line) in std::fs::read_to_string(&path)?.lines().enumerate() {
+            match line.split_once('\t') {
+                Some((name, digest)) if !name.is_empty() && self.sequence_path(digest).is_ok() => {
+                    aliases.push((name.to_string(), digest.to_string()));
+                },
+                _ => bail!("Malformed alias on line {} of {:?}", line_index + 1, path)
+            };
+        }
+        Ok(aliases)
+    }
+
+    /// Replaces the aliases of a namespace, which is created if needed
+    /// # Arguments
+    /// * `namespace` - the namespace, e.g. an assembly name
+    /// * `aliases` - (name, digest) pairs, in contig order
+    /// # Errors
+    /// * if the namespace is invalid
+    /// * if a name is empty or contains a tab or newline, or a digest is not in the store
+    /// * any file writing errors
+    pub fn set_aliases(&self, namespace: &str, aliases: &[(String, String)]) -> Result<(), Box<dyn Error>> {
+        let path = self.alias_path(namespace)?;
+        let mut content = String::new();
+        for (name, digest) in aliases.iter() {
+            if name.is_empty() || name.contains(['\t', '\n', '\r']) {
+                bail!("Invalid alias name: {:?}", name);
+            }
+            if !self.contains(digest) {
+                bail!("Alias {:?} refers to sequence {}, which is not in the store", name, digest);
+            }
+            content.push_str(&format!("{name}\t{digest}\n"));
+        }
+        Ok(write_file_atomically(&path, content.as_bytes())?)
+    }
+
+    /// Looks up the digest of a name within a namespace
+    /// # Arguments
+    /// * `namespace` - the namespace, e.g. an assembly name
+    /// * `name` - the sequence name
+    /// # Errors
+    /// * see `aliases(...)`
+    pub fn resolve(&self, namespace: &str, name: &str) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self.aliases(namespace)?.into_iter()
+            .find(|(alias, _)| alias == name)
+            .map(|(_, digest)| digest))
+    }
+}
+
+/// Stored sequences backing a genome loaded with `ReferenceGenome::from_store(...)`
+struct StoreLoader {
+    /// The contig names
+    names: Vec<String>,
+    /// The file holding each contig
+    paths: Vec<PathBuf>,
+    /// The length of each contig
+    lengths: Vec<usize>
+}
+
+impl ContigLoader for StoreLoader {
+    fn contig_name(&self, index: usize) -> &str {
+        &self.names[index]
+    }
+
+    fn contig_length(&self, index: usize) -> usize {
+        self.lengths[index]
+    }
+
+    fn load_contig(&self, index: usize) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let sequence = std::fs::read(&self.paths[index])?;
+        if sequence.len() != self.lengths[index] {
+            return Err(format!("Stored sequence {:?} changed length since it was opened", self.paths[index]).into());
+        }
+        Ok(sequence)
+    }
+}
+
+impl ReferenceGenome {
+    /// Writes every contig into a sequence store and records the contig names, in order, as the aliases of a namespace.
+    /// Sequences already in the store are not written again, and any previous aliases of the namespace are replaced.
+    /// Lazily loaded contigs are read once and are not kept in memory.
+    /// # Arguments
+    /// * `store` - the sequence store
+    /// * `namespace` - the namespace for the contig names, e.g. an assembly name
+    /// # Errors
+    /// * if the namespace is invalid
+    /// * if a contig was unloaded or fails to load
+    /// * any file writing errors
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn write_to_store(&self, store: &SequenceStore, namespace: &str) -> Result<(), Box<dyn Error>> {
+        store.alias_path(namespace)?;
+        let mut aliases: Vec<(String, String)> = Vec::with_capacity(self.contig_keys().len());
+        for contig in self.contig_keys().iter() {
+            let sequence = self.try_sequence_unkept(contig)?;
+            aliases.push((contig.clone(), store.insert(&sequence)?));
+        }
+        store.set_aliases(namespace, &aliases)
+    }
+
+    /// Opens the contigs of a namespace in a sequence store, in alias order.
+    /// Contigs are listed immediately, but each sequence is only read the first time it is accessed.
+    /// # Arguments
+    /// * `store` - the sequence store
+    /// * `namespace` - the namespace to open, e.g. an assembly name
+    /// # Errors
+    /// * if the namespace does not exist or an alias refers to a missing sequence
+    /// * if a name is present more than once
+    pub fn from_store(store: &SequenceStore, namespace: &str) -> Result<ReferenceGenome, Box<dyn Error>> {
+        let aliases = store.aliases(namespace)?;
+        let mut names: Vec<String> = Vec::with_capacity(aliases.len());
+        let mut paths: Vec<PathBuf> = Vec::with_capacity(aliases.len());
+        let mut lengths: Vec<usize> = Vec::with_capacity(aliases.len());
+        for (name, digest) in aliases.into_iter() {
+            let path = store.sequence_path(&digest)?;
+            let metadata = std::fs::metadata(&path)
+                .map_err(|e| SimpleError::new(format!("Sequence {digest} for {name:?} is missing from the store: {e}")))?;
+            names.push(name);
+            paths.push(path);
+            lengths.push(metadata.len() as usize);
+        }
+
+        let loader: Arc<dyn ContigLoader> = Arc::new(StoreLoader {
+            names: names.clone(),
+            paths,
+            lengths
+        });
+        let contigs = names.into_iter()
+            .enumerate()
+            .map(|(index, name)| (name, ContigSequence::Lazy(LazyContig::new(loader.clone(), index, false))))
+            .collect();
+        Ok(ReferenceGenome::from_contigs(store.root().to_path_buf(), contigs)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_store() {
+        let directory = std::env::temp_dir().join(format!("refgenome_store_{}", std::process::id()));
+        let store = SequenceStore::open(&directory).unwrap();
+        let digest = store.insert(b"acgt").unwrap();
+        assert_eq!(digest, md5_hex_uppercase(b"ACGT"));
+        assert_eq!(store.insert(b"ACGT").unwrap(), digest);
+        assert!(store.contains(&digest));
+        assert_eq!(store.get(&digest).unwrap(), b"ACGT");
+        assert!(!store.contains("not a digest"));
+        assert!(store.get(&md5_hex_uppercase(b"TTTT")).is_err());
+
+        // two assemblies sharing one sequence
+        let mut first = ReferenceGenome::empty_reference();
+        first.add_contig("chr1".to_string(), "ACGT").unwrap();
+        first.add_contig("chrM".to_string(), "GGCCAA").unwrap();
+        let mut second = ReferenceGenome::empty_reference();
+        second.add_contig("MT".to_string(), "GGCCAA").unwrap();
+        first.write_to_store(&store, "first").unwrap();
+        second.write_to_store(&store, "second").unwrap();
+        assert_eq!(store.namespaces().unwrap(), vec!["first", "second"]);
+        assert_eq!(store.resolve("second", "MT").unwrap(), store.resolve("first", "chrM").unwrap());
+        assert_eq!(store.resolve("second", "chrM").unwrap(), None);
+
+        let reloaded = ReferenceGenome::from_store(&store, "first").unwrap();
+        assert_eq!(reloaded.contig_keys(), first.contig_keys());
+        assert_eq!(reloaded.contig_length("chrM"), Some(6));
+        assert!(reloaded.compare(&first).unwrap().is_identical());
+
+        assert!(ReferenceGenome::from_store(&store, "missing").is_err());
+        assert!(first.write_to_store(&store, "../escape").is_err());
+        assert!(store.set_aliases("third", &[("chr1".to_string(), md5_hex_uppercase(b"TTTT"))]).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,12 @@
+use crate::complexity::mix;
+
+/// A deterministic pseudo-random sequence of A, C, G, and T, for tests that need realistic sequence without a fixture file.
+/// Each base comes from the top bits of a splitmix64 stream, so different seeds give unrelated sequences.
+/// # Arguments
+/// * `length` - the sequence length
+/// * `seed` - the seed of the stream
+pub(crate) fn random_sequence(length: usize, seed: u64) -> String {
+    (0..length as u64)
+        .map(|i| ['A', 'C', 'G', 'T'][(mix(seed.wrapping_add(i.wrapping_mul(0x9e3779b97f4a7c15))) >> 62) as usize])
+        .collect()
+}
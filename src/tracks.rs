@@ -0,0 +1,378 @@
+use crate::bed::BedRecord;
+use crate::reference_genome::ReferenceGenome;
+use bytes::Bytes;
+use simple_error::{bail, SimpleError};
+use std::io::Write;
+
+/// A value over a 0-based, half-open interval of a contig
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackInterval {
+    /// 0-based start of the interval
+    pub start: usize,
+    /// 0-based, exclusive end of the interval
+    pub end: usize,
+    /// The value over the interval
+    pub value: f64
+}
+
+/// Per-window values along one contig, with non-overlapping intervals in increasing order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContigTrack {
+    /// The contig name
+    pub contig: String,
+    /// The intervals with a value; windows without a defined value are omitted
+    pub intervals: Vec<TrackInterval>
+}
+
+/// The extremes of a cumulative GC skew track.
+/// For a circular bacterial chromosome, the minimum marks the likely origin of replication and the maximum the terminus.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkewExtrema {
+    /// The window where the cumulative skew is lowest
+    pub minimum: TrackInterval,
+    /// The window where the cumulative skew is highest
+    pub maximum: TrackInterval
+}
+
+/// Sequence composition of one window, from `ReferenceGenome::window_stats(...)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowStats {
+    /// The contig name
+    pub contig: String,
+    /// 0-based start of the window
+    pub start: usize,
+    /// 0-based, exclusive end of the window
+    pub end: usize,
+    /// The fraction of G/C among the non-N bases, or `None` if every base is N
+    pub gc_fraction: Option<f64>,
+    /// The fraction of N bases
+    pub n_fraction: f64,
+    /// The fraction of lower-case (soft-masked) bases, which requires loading with `uppercase(false)`
+    pub masked_fraction: f64,
+    /// The Shannon entropy of the A/C/G/T composition in bits, from 0 (a single base) to 2 (all four equally),
+    /// or `None` if there are no A/C/G/T bases
+    pub complexity: Option<f64>
+}
+
+impl WindowStats {
+    /// Computes the statistics of a window
+    /// # Arguments
+    /// * `contig` - the contig name
+    /// * `start` - the window start
+    /// * `window` - the window sequence
+    fn from_window(contig: &str, start: usize, window: &[u8]) -> Self {
+        // counts of A, C, G, T, N, and other symbols
+        let mut counts = [0usize; 6];
+        let mut masked = 0;
+        for &symbol in window.iter() {
+            let index = match symbol.to_ascii_uppercase() {
+                b'A' => 0,
+                b'C' => 1,
+                b'G' => 2,
+                b'T' => 3,
+                b'N' => 4,
+                _ => 5
+            };
+            counts[index] += 1;
+            masked += usize::from(symbol.is_ascii_lowercase());
+        }
+        let length = window.len() as f64;
+        let called = window.len() - counts[4];
+        let acgt: usize = counts[..4].iter().sum();
+        let complexity = (acgt > 0).then(|| {
+            counts[..4].iter()
+                .filter(|&&c| c > 0)
+                .map(|&c| {
+                    let p = c as f64 / acgt as f64;
+                    -p * p.log2()
+                })
+                .sum::<f64>()
+                .max(0.0)
+        });
+        WindowStats {
+            contig: contig.to_string(),
+            start,
+            end: start + window.len(),
+            gc_fraction: (called > 0).then(|| (counts[1] + counts[2]) as f64 / called as f64),
+            n_fraction: counts[4] as f64 / length,
+            masked_fraction: masked as f64 / length,
+            complexity
+        }
+    }
+}
+
+/// Writes tracks in bedGraph format, one line per interval: contig, start, end, and value
+/// # Arguments
+/// * `writer` - the output to write to
+/// * `tracks` - the tracks to write, in output order
+/// # Errors
+/// * any errors from the underlying writer
+pub fn write_bedgraph<W: Write>(mut writer: W, tracks: &[ContigTrack]) -> std::io::Result<()> {
+    for track in tracks.iter() {
+        for interval in track.intervals.iter() {
+            writeln!(writer, "{}\t{}\t{}\t{}", track.contig, interval.start, interval.end, interval.value)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Counts the G and C bases of a sequence, ignoring case
+fn count_g_c(sequence: &[u8]) -> (usize, usize) {
+    sequence.iter().fold((0, 0), |(g, c), symbol| match symbol.to_ascii_uppercase() {
+        b'G' => (g + 1, c),
+        b'C' => (g, c + 1),
+        _ => (g, c)
+    })
+}
+
+/// Finds the lowest and highest values of a cumulative skew track, see `ReferenceGenome::cumulative_gc_skew(...)`.
+/// Ties resolve to the first interval.
+/// # Arguments
+/// * `track` - the cumulative track
+/// # Returns
+/// * the extremes, or `None` if the track is empty
+pub fn skew_extrema(track: &ContigTrack) -> Option<SkewExtrema> {
+    let first = *track.intervals.first()?;
+    let mut extrema = SkewExtrema { minimum: first, maximum: first };
+    for interval in track.intervals.iter() {
+        if interval.value < extrema.minimum.value {
+            extrema.minimum = *interval;
+        }
+        if interval.value > extrema.maximum.value {
+            extrema.maximum = *interval;
+        }
+    }
+    Some(extrema)
+}
+
+impl ReferenceGenome {
+    /// Computes the GC skew, (G - C) / (G + C), of consecutive non-overlapping windows along a contig.
+    /// The last window is shorter if the contig length is not a multiple of the window size, and windows without any G or C are omitted.
+    /// # Arguments
+    /// * `contig` - the contig to scan
+    /// * `window_size` - the bases per window, at least 1
+    /// # Errors
+    /// * if the contig is not in the reference genome, was unloaded, or fails to load
+    /// * if `window_size` is 0
+    pub fn gc_skew(&self, contig: &str, window_size: usize) -> Result<ContigTrack, SimpleError> {
+        if window_size == 0 {
+            bail!("The window size must be at least 1");
+        }
+        let sequence = self.try_sequence_unkept(contig)?;
+        let intervals = sequence.chunks(window_size)
+            .enumerate()
+            .filter_map(|(index, window)| {
+                let (g, c) = count_g_c(window);
+                (g + c > 0).then(|| TrackInterval {
+                    start: index * window_size,
+                    end: index * window_size + window.len(),
+                    value: (g as f64 - c as f64) / (g + c) as f64
+                })
+            })
+            .collect();
+        Ok(ContigTrack {
+            contig: contig.to_string(),
+            intervals
+        })
+    }
+
+    /// Computes composition statistics (GC, N, soft-masked fraction, and complexity) for consecutive non-overlapping windows of every contig.
+    /// The last window of a contig is shorter if its length is not a multiple of the window size, and empty contigs have no windows.
+    /// Lazily loaded contigs are read once and are not kept in memory.
+    /// # Arguments
+    /// * `window_size` - the bases per window, at least 1
+    /// # Errors
+    /// * if `window_size` is 0
+    /// * if a contig was unloaded or fails to load
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn window_stats(&self, window_size: usize) -> Result<Vec<WindowStats>, SimpleError> {
+        if window_size == 0 {
+            bail!("The window size must be at least 1");
+        }
+        let mut stats: Vec<WindowStats> = vec![];
+        for contig in self.contig_keys().iter() {
+            let sequence = self.try_sequence_unkept(contig)?;
+            stats.extend(sequence.chunks(window_size)
+                .enumerate()
+                .map(|(index, window)| WindowStats::from_window(contig, index * window_size, window)));
+        }
+        Ok(stats)
+    }
+
+    /// Computes composition statistics (GC, N, soft-masked fraction, and complexity) for arbitrary intervals, such as the targets of a capture panel.
+    /// Consecutive intervals on the same contig share a single read of a lazily loaded contig.
+    /// # Arguments
+    /// * `intervals` - the intervals, e.g. from `read_bed_file(...)`
+    /// # Returns
+    /// * one entry per interval, in the given order
+    /// # Errors
+    /// * if an interval is empty, extends past the end of its contig, or is on a contig that is not in the genome, was unloaded, or fails to load
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn interval_stats(&self, intervals: &[BedRecord]) -> Result<Vec<WindowStats>, SimpleError> {
+        let mut stats: Vec<WindowStats> = Vec::with_capacity(intervals.len());
+        let mut current: Option<(&str, Bytes)> = None;
+        for interval in intervals.iter() {
+            let sequence = match current.as_ref() {
+                Some((contig, sequence)) if *contig == interval.contig => sequence.clone(),
+                _ => {
+                    let sequence = self.try_sequence_unkept(&interval.contig)?;
+                    current = Some((&interval.contig, sequence.clone()));
+                    sequence
+                }
+            };
+            if interval.is_empty() || interval.end > sequence.len() {
+                bail!("Interval {}:{}-{} is empty or extends past the end of the contig ({} bp)", interval.contig, interval.start, interval.end, sequence.len());
+            }
+            stats.push(WindowStats::from_window(&interval.contig, interval.start, &sequence[interval.start..interval.end]));
+        }
+        Ok(stats)
+    }
+
+    /// Writes BED intervals with their composition appended, for coverage-bias modeling: every original column, then the GC fraction
+    /// (or `NA` if every base is N), the N fraction, and the soft-masked fraction, with 4 decimals. See `interval_stats(...)`.
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// * `intervals` - the intervals to annotate, written in the given order
+    /// # Errors
+    /// * see `interval_stats(...)`
+    /// * any errors from the underlying writer
+    pub fn write_annotated_bed<W: Write>(&self, mut writer: W, intervals: &[BedRecord]) -> Result<(), Box<dyn std::error::Error>> {
+        let stats = self.interval_stats(intervals)?;
+        for (interval, interval_stats) in intervals.iter().zip(stats.iter()) {
+            write!(writer, "{}\t{}\t{}", interval.contig, interval.start, interval.end)?;
+            for column in interval.extra.iter() {
+                write!(writer, "\t{column}")?;
+            }
+            match interval_stats.gc_fraction {
+                Some(gc_fraction) => write!(writer, "\t{gc_fraction:.4}")?,
+                None => write!(writer, "\tNA")?
+            }
+            writeln!(writer, "\t{:.4}\t{:.4}", interval_stats.n_fraction, interval_stats.masked_fraction)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Computes the running sum of the per-window GC skew along a contig, with one interval per window holding the sum up to and
+    /// including that window; windows without any G or C add nothing. See `skew_extrema(...)` to locate the minimum and maximum.
+    /// # Arguments
+    /// * `contig` - the contig to scan
+    /// * `window_size` - the bases per window, at least 1
+    /// # Errors
+    /// * see `gc_skew(...)`
+    pub fn cumulative_gc_skew(&self, contig: &str, window_size: usize) -> Result<ContigTrack, SimpleError> {
+        if window_size == 0 {
+            bail!("The window size must be at least 1");
+        }
+        let sequence = self.try_sequence_unkept(contig)?;
+        let mut total = 0.0;
+        let intervals = sequence.chunks(window_size)
+            .enumerate()
+            .map(|(index, window)| {
+                let (g, c) = count_g_c(window);
+                if g + c > 0 {
+                    total += (g as f64 - c as f64) / (g + c) as f64;
+                }
+                TrackInterval {
+                    start: index * window_size,
+                    end: index * window_size + window.len(),
+                    value: total
+                }
+            })
+            .collect();
+        Ok(ContigTrack {
+            contig: contig.to_string(),
+            intervals
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bed::read_bed_records;
+    use crate::builder::ReferenceGenomeBuilder;
+
+    #[test]
+    fn test_gc_skew() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), "GGGCAATTCCCGCCAAG").unwrap();
+        let track = reference_genome.gc_skew("chr1", 4).unwrap();
+        assert_eq!(track.intervals, vec![
+            TrackInterval { start: 0, end: 4, value: 0.5 },
+            TrackInterval { start: 8, end: 12, value: -0.5 },
+            TrackInterval { start: 12, end: 16, value: -1.0 },
+            TrackInterval { start: 16, end: 17, value: 1.0 }
+        ]);
+
+        let mut output: Vec<u8> = vec![];
+        write_bedgraph(&mut output, &[track]).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap().lines().next().unwrap(), "chr1\t0\t4\t0.5");
+
+        assert!(reference_genome.gc_skew("chr1", 0).is_err());
+        assert!(reference_genome.gc_skew("chr2", 10).is_err());
+    }
+
+    #[test]
+    fn test_window_stats() {
+        let reference_genome = ReferenceGenomeBuilder::from_bytes(&b">chr1\nACGTaaaaNNNNGGCC\n>chr2\n>chr3\nAT\n"[..])
+            .uppercase(false)
+            .build()
+            .unwrap();
+        let stats = reference_genome.window_stats(4).unwrap();
+        assert_eq!(stats.len(), 5);
+        assert_eq!(stats[0], WindowStats {
+            contig: "chr1".to_string(),
+            start: 0,
+            end: 4,
+            gc_fraction: Some(0.5),
+            n_fraction: 0.0,
+            masked_fraction: 0.0,
+            complexity: Some(2.0)
+        });
+        assert_eq!((stats[1].masked_fraction, stats[1].complexity, stats[1].gc_fraction), (1.0, Some(0.0), Some(0.0)));
+        assert_eq!((stats[2].n_fraction, stats[2].gc_fraction, stats[2].complexity), (1.0, None, None));
+        assert_eq!((stats[3].gc_fraction, stats[3].complexity), (Some(1.0), Some(1.0)));
+        assert_eq!((stats[4].contig.as_str(), stats[4].start, stats[4].end), ("chr3", 0, 2));
+        assert!(reference_genome.window_stats(0).is_err());
+    }
+
+    #[test]
+    fn test_cumulative_gc_skew() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        // C-rich first half and G-rich second half, so the origin is in the middle
+        reference_genome.add_contig("chr1".to_string(), "CCCCATATCCCCGGGGATATGGGG").unwrap();
+        let track = reference_genome.cumulative_gc_skew("chr1", 4).unwrap();
+        let values: Vec<f64> = track.intervals.iter().map(|i| i.value).collect();
+        assert_eq!(values, vec![-1.0, -1.0, -2.0, -1.0, -1.0, 0.0]);
+
+        let extrema = skew_extrema(&track).unwrap();
+        assert_eq!(extrema.minimum, TrackInterval { start: 8, end: 12, value: -2.0 });
+        assert_eq!(extrema.maximum.end, 24);
+        assert!(skew_extrema(&ContigTrack::default()).is_none());
+    }
+
+    #[test]
+    fn test_interval_stats() {
+        let reference_genome = ReferenceGenomeBuilder::from_bytes(&b">chr1\nACGTNNacgg\n>chr2\nNNNN\n"[..])
+            .uppercase(false)
+            .build()
+            .unwrap();
+        let intervals = read_bed_records(&b"chr1\t0\t4\tamplicon1\t0\t+\nchr1\t4\t10\tamplicon2\nchr2\t0\t2\n"[..]).unwrap();
+        let stats = reference_genome.interval_stats(&intervals).unwrap();
+        assert_eq!(stats.len(), 3);
+        assert_eq!((stats[0].start, stats[0].end, stats[0].gc_fraction), (0, 4, Some(0.5)));
+        assert_eq!((stats[1].gc_fraction, stats[1].n_fraction, stats[1].masked_fraction), (Some(0.75), 2.0 / 6.0, 4.0 / 6.0));
+
+        let mut output: Vec<u8> = vec![];
+        reference_genome.write_annotated_bed(&mut output, &intervals).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "chr1\t0\t4\tamplicon1\t0\t+\t0.5000\t0.0000\t0.0000\n\
+            chr1\t4\t10\tamplicon2\t0.7500\t0.3333\t0.6667\n\
+            chr2\t0\t2\tNA\t1.0000\t0.0000\n");
+
+        let outside = read_bed_records(&b"chr2\t2\t5\n"[..]).unwrap();
+        assert!(reference_genome.interval_stats(&outside).is_err());
+        let empty = read_bed_records(&b"chr2\t2\t2\n"[..]).unwrap();
+        assert!(reference_genome.interval_stats(&empty).is_err());
+    }
+}
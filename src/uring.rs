@@ -0,0 +1,493 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// The bytes requested per read, large enough that reads stream at device speed
+const BLOCK_SIZE: usize = 4 << 20;
+
+/// The number of blocks kept in flight ahead of the parser
+#[cfg(target_os = "linux")]
+const QUEUE_DEPTH: usize = 4;
+
+/// Opens a file for sequential reading with io_uring, which keeps several large reads in flight ahead of the consumer.
+/// If io_uring is unavailable (e.g. kernels before 5.1, or blocked by a seccomp policy) or on platforms other than Linux,
+/// the file is read with a normal buffered reader instead.
+/// # Arguments
+/// * `path` - the file to read
+/// # Errors
+/// * if the file cannot be opened
+pub(crate) fn open(path: &Path) -> std::io::Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path)?;
+    #[cfg(target_os = "linux")] {
+        match ring::Ring::new(QUEUE_DEPTH as u32) {
+            Ok(ring) => return Ok(Box::new(UringFile::new(ring, file, BLOCK_SIZE)?)),
+            Err(e) => log::debug!("io_uring is unavailable ({e}), reading {path:?} with buffered reads")
+        }
+    }
+    Ok(Box::new(BufReader::with_capacity(BLOCK_SIZE, file)))
+}
+
+/// Raw io_uring system calls and ring structures, see `io_uring_setup(2)` and `io_uring_enter(2)`
+#[cfg(target_os = "linux")]
+mod ring {
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// The mmap offset of the submission queue ring
+    const IORING_OFF_SQ_RING: libc::off_t = 0;
+    /// The mmap offset of the completion queue ring
+    const IORING_OFF_CQ_RING: libc::off_t = 0x8000000;
+    /// The mmap offset of the submission queue entries
+    const IORING_OFF_SQES: libc::off_t = 0x10000000;
+    /// Vectored read, supported by every kernel with io_uring
+    const IORING_OP_READV: u8 = 1;
+    /// Makes `io_uring_enter` wait for completions
+    const IORING_ENTER_GETEVENTS: libc::c_uint = 1;
+
+    /// `struct io_sqring_offsets`
+    #[repr(C)]
+    #[derive(Default)]
+    struct SqRingOffsets {
+        head: u32,
+        tail: u32,
+        ring_mask: u32,
+        ring_entries: u32,
+        flags: u32,
+        dropped: u32,
+        array: u32,
+        resv1: u32,
+        user_addr: u64
+    }
+
+    /// `struct io_cqring_offsets`
+    #[repr(C)]
+    #[derive(Default)]
+    struct CqRingOffsets {
+        head: u32,
+        tail: u32,
+        ring_mask: u32,
+        ring_entries: u32,
+        overflow: u32,
+        cqes: u32,
+        flags: u32,
+        resv1: u32,
+        user_addr: u64
+    }
+
+    /// `struct io_uring_params`
+    #[repr(C)]
+    #[derive(Default)]
+    struct Params {
+        sq_entries: u32,
+        cq_entries: u32,
+        flags: u32,
+        sq_thread_cpu: u32,
+        sq_thread_idle: u32,
+        features: u32,
+        wq_fd: u32,
+        resv: [u32; 3],
+        sq_off: SqRingOffsets,
+        cq_off: CqRingOffsets
+    }
+
+    /// `struct io_uring_sqe`, with the unions reduced to the fields used for reads
+    #[repr(C)]
+    struct SubmissionEntry {
+        opcode: u8,
+        flags: u8,
+        ioprio: u16,
+        fd: i32,
+        off: u64,
+        addr: u64,
+        len: u32,
+        rw_flags: u32,
+        user_data: u64,
+        buf_index: u16,
+        personality: u16,
+        splice_fd_in: i32,
+        addr3: u64,
+        pad: u64
+    }
+
+    /// `struct io_uring_cqe`
+    #[repr(C)]
+    struct CompletionEntry {
+        user_data: u64,
+        res: i32,
+        flags: u32
+    }
+
+    /// A shared memory region of the ring, unmapped on drop
+    struct Mapping {
+        /// The start of the region
+        ptr: *mut u8,
+        /// The length of the region
+        len: usize
+    }
+
+    impl Mapping {
+        /// Maps a region of the ring
+        fn new(fd: RawFd, len: usize, offset: libc::off_t) -> std::io::Result<Self> {
+            // SAFETY: a fresh shared mapping of the ring descriptor, which the kernel sized from the setup parameters
+            let ptr = unsafe {
+                libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED | libc::MAP_POPULATE, fd, offset)
+            };
+            if ptr == libc::MAP_FAILED {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(Self { ptr: ptr as *mut u8, len })
+        }
+
+        /// The ring field at a byte offset, which the kernel updates concurrently
+        /// # Safety
+        /// `offset` must be an aligned `u32` field within the region
+        unsafe fn atomic(&self, offset: u32) -> &AtomicU32 {
+            &*(self.ptr.add(offset as usize) as *const AtomicU32)
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            // SAFETY: the region was mapped in `new(...)` and nothing borrows it past the ring
+            unsafe {
+                libc::munmap(self.ptr as *mut libc::c_void, self.len);
+            }
+        }
+    }
+
+    /// An io_uring instance with its submission and completion queues
+    pub(super) struct Ring {
+        /// The submission queue ring
+        sq: Mapping,
+        /// The completion queue ring
+        cq: Mapping,
+        /// The submission queue entries
+        sqes: Mapping,
+        /// The ring layout returned by the kernel
+        params: Params,
+        /// The ring descriptor, closed after the mappings are dropped
+        fd: OwnedFd
+    }
+
+    // SAFETY: the ring is only used through `&mut self`, and the mappings are owned by it
+    unsafe impl Send for Ring {}
+
+    impl Ring {
+        /// Sets up a ring with room for `entries` reads in flight
+        /// # Errors
+        /// * if io_uring is not supported or not permitted
+        pub(super) fn new(entries: u32) -> std::io::Result<Self> {
+            let mut params = Params::default();
+            // SAFETY: `params` is a valid `struct io_uring_params` that the kernel fills in
+            let fd = unsafe { libc::syscall(libc::SYS_io_uring_setup, entries as libc::c_long, &mut params as *mut Params) };
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            // SAFETY: the descriptor was just returned by io_uring_setup and is owned by nothing else
+            let fd = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+            let sq_len = params.sq_off.array as usize + params.sq_entries as usize * std::mem::size_of::<u32>();
+            let cq_len = params.cq_off.cqes as usize + params.cq_entries as usize * std::mem::size_of::<CompletionEntry>();
+            let sqes_len = params.sq_entries as usize * std::mem::size_of::<SubmissionEntry>();
+            Ok(Self {
+                sq: Mapping::new(fd.as_raw_fd(), sq_len, IORING_OFF_SQ_RING)?,
+                cq: Mapping::new(fd.as_raw_fd(), cq_len, IORING_OFF_CQ_RING)?,
+                sqes: Mapping::new(fd.as_raw_fd(), sqes_len, IORING_OFF_SQES)?,
+                params,
+                fd
+            })
+        }
+
+        /// Submits a vectored read; the caller must keep at most as many reads in flight as the ring has entries.
+        /// # Safety
+        /// `iovec` and the buffer it points to must stay valid until the completion for `user_data` is reaped
+        pub(super) unsafe fn submit_readv(&mut self, fd: RawFd, iovec: *const libc::iovec, offset: u64, user_data: u64) -> std::io::Result<()> {
+            let tail = self.sq.atomic(self.params.sq_off.tail).load(Ordering::Acquire);
+            let index = tail & *(self.sq.ptr.add(self.params.sq_off.ring_mask as usize) as *const u32);
+            let entry = (self.sqes.ptr as *mut SubmissionEntry).add(index as usize);
+            entry.write(SubmissionEntry {
+                opcode: IORING_OP_READV,
+                flags: 0,
+                ioprio: 0,
+                fd,
+                off: offset,
+                addr: iovec as u64,
+                len: 1,
+                rw_flags: 0,
+                user_data,
+                buf_index: 0,
+                personality: 0,
+                splice_fd_in: 0,
+                addr3: 0,
+                pad: 0
+            });
+            *(self.sq.ptr.add(self.params.sq_off.array as usize) as *mut u32).add(index as usize) = index;
+            self.sq.atomic(self.params.sq_off.tail).store(tail.wrapping_add(1), Ordering::Release);
+            self.enter(1, 0, 0)
+        }
+
+        /// Waits for the next completion
+        /// # Returns
+        /// * the `user_data` of the read and its result, which is the byte count or a negated errno
+        pub(super) fn wait(&mut self) -> std::io::Result<(u64, i32)> {
+            loop {
+                // SAFETY: the offsets come from the kernel and lie within the completion ring mapping
+                unsafe {
+                    let head = self.cq.atomic(self.params.cq_off.head).load(Ordering::Relaxed);
+                    let tail = self.cq.atomic(self.params.cq_off.tail).load(Ordering::Acquire);
+                    if head != tail {
+                        let mask = *(self.cq.ptr.add(self.params.cq_off.ring_mask as usize) as *const u32);
+                        let entry = &*(self.cq.ptr.add(self.params.cq_off.cqes as usize) as *const CompletionEntry).add((head & mask) as usize);
+                        let completion = (entry.user_data, entry.res);
+                        self.cq.atomic(self.params.cq_off.head).store(head.wrapping_add(1), Ordering::Release);
+                        return Ok(completion);
+                    }
+                }
+                self.enter(0, 1, IORING_ENTER_GETEVENTS)?;
+            }
+        }
+
+        /// Calls `io_uring_enter`, retrying if it is interrupted
+        fn enter(&mut self, to_submit: u32, min_complete: u32, flags: libc::c_uint) -> std::io::Result<()> {
+            loop {
+                // SAFETY: the descriptor is a valid ring and no signal mask is passed
+                let result = unsafe {
+                    libc::syscall(libc::SYS_io_uring_enter, self.fd.as_raw_fd(), to_submit, min_complete, flags, std::ptr::null::<libc::sigset_t>(), 0 as libc::size_t)
+                };
+                if result >= 0 {
+                    return Ok(());
+                }
+                let error = std::io::Error::last_os_error();
+                if error.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(error);
+                }
+            }
+        }
+    }
+}
+
+/// A buffered reader that keeps `QUEUE_DEPTH` block reads in flight through io_uring, so the file is read ahead of parsing.
+/// Each block has its own buffer; block `i` is read into buffer `i % QUEUE_DEPTH`, which is resubmitted once it is consumed.
+#[cfg(target_os = "linux")]
+pub(crate) struct UringFile {
+    /// The ring that the reads are submitted to
+    ring: ring::Ring,
+    /// The open file
+    file: File,
+    /// The file length when it was opened; later growth is not read
+    length: u64,
+    /// One buffer per read slot
+    buffers: Vec<Vec<u8>>,
+    /// The read target of each slot, kept alive while its read is in flight
+    iovecs: Vec<libc::iovec>,
+    /// The completed result of each slot, the byte count or a negated errno
+    results: Vec<Option<i32>>,
+    /// The number of reads in flight
+    in_flight: usize,
+    /// The size of each read
+    block_size: usize,
+    /// The index of the next block to submit
+    next_block: u64,
+    /// The index of the block being consumed
+    current_block: u64,
+    /// If true, the current block has been received and is being consumed
+    loaded: bool,
+    /// The consumed position in the current block
+    position: usize,
+    /// The valid bytes in the current block
+    filled: usize
+}
+
+// SAFETY: the iovecs only point into `buffers`, which are owned by the reader and never shared
+#[cfg(target_os = "linux")]
+unsafe impl Send for UringFile {}
+
+#[cfg(target_os = "linux")]
+impl UringFile {
+    /// Starts reading a file through a ring, submitting the first blocks right away
+    fn new(ring: ring::Ring, file: File, block_size: usize) -> std::io::Result<Self> {
+        let length = file.metadata()?.len();
+        let buffers: Vec<Vec<u8>> = (0..QUEUE_DEPTH).map(|_| vec![0; block_size]).collect();
+        let mut uring_file = Self {
+            ring,
+            file,
+            length,
+            buffers,
+            iovecs: vec![libc::iovec { iov_base: std::ptr::null_mut(), iov_len: 0 }; QUEUE_DEPTH],
+            results: vec![None; QUEUE_DEPTH],
+            in_flight: 0,
+            block_size,
+            next_block: 0,
+            current_block: 0,
+            loaded: false,
+            position: 0,
+            filled: 0
+        };
+        for slot in 0..QUEUE_DEPTH {
+            uring_file.submit(slot)?;
+        }
+        Ok(uring_file)
+    }
+
+    /// The file offset and length of a block
+    fn block_range(&self, block: u64) -> (u64, usize) {
+        let offset = block * self.block_size as u64;
+        (offset, (self.length - offset).min(self.block_size as u64) as usize)
+    }
+
+    /// Submits the next block into a free slot, unless the whole file has been submitted
+    fn submit(&mut self, slot: usize) -> std::io::Result<()> {
+        if self.next_block * self.block_size as u64 >= self.length {
+            return Ok(());
+        }
+        use std::os::unix::io::AsRawFd;
+        let (offset, length) = self.block_range(self.next_block);
+        self.iovecs[slot] = libc::iovec { iov_base: self.buffers[slot].as_mut_ptr() as *mut libc::c_void, iov_len: length };
+        self.results[slot] = None;
+        // SAFETY: the iovec and buffer are owned by `self`, and `drop(...)` waits for every read in flight
+        unsafe {
+            self.ring.submit_readv(self.file.as_raw_fd(), &self.iovecs[slot], offset, slot as u64)?;
+        }
+        self.in_flight += 1;
+        self.next_block += 1;
+        Ok(())
+    }
+
+    /// Waits until the read of a slot has completed
+    fn wait_for(&mut self, slot: usize) -> std::io::Result<i32> {
+        while self.results[slot].is_none() {
+            let (user_data, result) = self.ring.wait()?;
+            self.results[user_data as usize] = Some(result);
+            self.in_flight -= 1;
+        }
+        Ok(self.results[slot].unwrap())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for UringFile {
+    fn drop(&mut self) {
+        // the kernel may still write into the buffers, so they are only freed once every read has completed
+        while self.in_flight > 0 {
+            match self.ring.wait() {
+                Ok(_) => self.in_flight -= 1,
+                Err(_) => {
+                    std::mem::forget(std::mem::take(&mut self.buffers));
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::io::Read for UringFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.fill_buf()?;
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl BufRead for UringFile {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        let slot = (self.current_block % QUEUE_DEPTH as u64) as usize;
+        if self.loaded && self.position == self.filled {
+            // the consumed buffer is free again, so it reads further ahead
+            self.loaded = false;
+            self.current_block += 1;
+            self.submit(slot)?;
+        }
+        let slot = (self.current_block % QUEUE_DEPTH as u64) as usize;
+        if !self.loaded && self.current_block < self.next_block {
+            let result = self.wait_for(slot)?;
+            if result < 0 {
+                return Err(std::io::Error::from_raw_os_error(-result));
+            }
+            let (offset, length) = self.block_range(self.current_block);
+            let received = result as usize;
+            if received < length {
+                // short reads are rare for regular files, so the rest of the block is read synchronously
+                use std::os::unix::fs::FileExt;
+                self.file.read_exact_at(&mut self.buffers[slot][received..length], offset + received as u64)?;
+            }
+            self.loaded = true;
+            self.position = 0;
+            self.filled = length;
+        }
+        if !self.loaded {
+            return Ok(&[]);
+        }
+        Ok(&self.buffers[slot][self.position..self.filled])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.filled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_uring_open() {
+        let directory = std::env::temp_dir().join(format!("refgenome_uring_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let file_fn = directory.join("data.txt");
+        let content: Vec<u8> = (0..1_000_003).map(|i| b"ACGT\n"[i % 5]).collect();
+        std::fs::write(&file_fn, &content).unwrap();
+
+        let mut data: Vec<u8> = vec![];
+        open(&file_fn).unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, content);
+        let lines = open(&file_fn).unwrap().lines().count();
+        assert_eq!(lines, content.iter().filter(|&&c| c == b'\n').count() + 1);
+
+        std::fs::write(&file_fn, b"").unwrap();
+        let mut data: Vec<u8> = vec![];
+        open(&file_fn).unwrap().read_to_end(&mut data).unwrap();
+        assert!(data.is_empty());
+
+        assert!(open(&directory.join("missing.txt")).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_uring_file() {
+        let Ok(_) = ring::Ring::new(QUEUE_DEPTH as u32) else {
+            // nothing to test where io_uring is not permitted
+            return;
+        };
+        let directory = std::env::temp_dir().join(format!("refgenome_uring_file_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let file_fn = directory.join("data.txt");
+        // more blocks than read slots, plus a partial one
+        let content: Vec<u8> = (0..3 * QUEUE_DEPTH * 4096 + 123).map(|i| b"ACGT\n"[i % 5]).collect();
+        std::fs::write(&file_fn, &content).unwrap();
+        let open_blocks = |block_size: usize| {
+            UringFile::new(ring::Ring::new(QUEUE_DEPTH as u32).unwrap(), File::open(&file_fn).unwrap(), block_size).unwrap()
+        };
+
+        let mut data: Vec<u8> = vec![];
+        open_blocks(4096).read_to_end(&mut data).unwrap();
+        assert_eq!(data, content);
+
+        // a file that ends on a block boundary
+        let mut data: Vec<u8> = vec![];
+        open_blocks(content.len()).read_to_end(&mut data).unwrap();
+        assert_eq!(data, content);
+
+        // dropping the reader waits for the reads still in flight
+        let mut reader = open_blocks(4096);
+        let mut prefix = [0; 10];
+        reader.read_exact(&mut prefix).unwrap();
+        assert_eq!(&prefix, &content[..10]);
+        drop(reader);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+
+/// Reads an environment variable, treating an empty value as unset
+/// # Arguments
+/// * `name` - the variable name
+pub(crate) fn non_empty_env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Returns an XDG base directory: `$<variable>`, or `<home_relative>` under `$HOME` if it is unset or empty
+/// # Arguments
+/// * `variable` - the XDG variable, e.g. `XDG_CACHE_HOME`
+/// * `home_relative` - the default relative to the home directory, e.g. `.cache`
+fn xdg_base_dir(variable: &str, home_relative: &str) -> Option<PathBuf> {
+    non_empty_env(variable)
+        .map(PathBuf::from)
+        .or_else(|| non_empty_env("HOME").map(|home| PathBuf::from(home).join(home_relative)))
+}
+
+/// Returns a directory under the user cache directory, `$XDG_CACHE_HOME` or `$HOME/.cache`
+/// # Arguments
+/// * `subdir` - the path relative to the cache directory
+/// # Returns
+/// * the directory, or `None` if neither environment variable is set
+pub(crate) fn default_cache_dir(subdir: &str) -> Option<PathBuf> {
+    xdg_base_dir("XDG_CACHE_HOME", ".cache").map(|directory| directory.join(subdir))
+}
+
+/// Returns a directory under the user data directory, `$XDG_DATA_HOME` or `$HOME/.local/share`
+/// # Arguments
+/// * `subdir` - the path relative to the data directory
+/// # Returns
+/// * the directory, or `None` if neither environment variable is set
+pub(crate) fn default_data_dir(subdir: &str) -> Option<PathBuf> {
+    xdg_base_dir("XDG_DATA_HOME", ".local/share").map(|directory| directory.join(subdir))
+}
+
+/// Returns a directory under the user config directory, `$XDG_CONFIG_HOME` or `$HOME/.config`
+/// # Arguments
+/// * `subdir` - the path relative to the config directory
+/// # Returns
+/// * the directory, or `None` if neither environment variable is set
+pub(crate) fn default_config_dir(subdir: &str) -> Option<PathBuf> {
+    xdg_base_dir("XDG_CONFIG_HOME", ".config").map(|directory| directory.join(subdir))
+}
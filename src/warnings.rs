@@ -0,0 +1,175 @@
+use crate::alphabet::Alphabet;
+use log::warn;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The maximum number of warnings kept by a genome for `ReferenceGenome::warnings()`; later warnings are still logged,
+/// passed to the handler, and counted by `ReferenceGenome::warning_count()`
+pub const MAX_COLLECTED_WARNINGS: usize = 10_000;
+
+/// A non-fatal issue found while loading or reading a reference genome
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Warning {
+    /// A slice request past the end of a contig was truncated under `OutOfBoundsPolicy::Clamp`
+    SliceTruncated {
+        /// The requested contig
+        contig: String,
+        /// The requested 0-based start (included)
+        start: usize,
+        /// The requested 0-based end (excluded)
+        end: usize,
+        /// The contig length that the request was truncated to
+        contig_len: usize
+    },
+    /// A record contains symbols outside the IUPAC nucleotide codes, such as digits or gaps.
+    /// Only checked for in-memory loads without `ReferenceGenomeBuilder::validate(...)`, which rejects such records instead.
+    SuspiciousSymbols {
+        /// The record ID
+        contig: String,
+        /// The first suspicious symbol
+        symbol: u8,
+        /// The 0-based position of the first suspicious symbol
+        position: usize,
+        /// The number of suspicious symbols in the record
+        count: usize
+    },
+    /// A repeated record ID was renamed under `DuplicatePolicy::Rename`
+    DuplicateRenamed {
+        /// The original record ID
+        original: String,
+        /// The new contig name
+        renamed: String
+    },
+    /// The projected size was over `ReferenceGenomeBuilder::memory_limit(...)`, so the load fell back to `Backend::Mmap`
+    MmapFallback {
+        /// The projected sequence bytes
+        projected_bytes: usize,
+        /// The memory limit
+        max_bytes: usize
+    },
+    /// A borrowing accessor (e.g. `get_slice(...)`) kept a contig in memory outside of `ReferenceGenomeBuilder::lru_cache(...)`,
+    /// which cannot evict it; use the shared accessors (e.g. `get_slice_shared(...)`) to stay within the cache bounds
+    CacheBypassed {
+        /// The contig that was kept
+        contig: String,
+        /// The sequence bytes that were kept
+        bytes: usize
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::SliceTruncated { contig, start, end, contig_len } => {
+                write!(f, "Received get_slice({contig:?}, {start}, {end}), truncated to the contig length {contig_len}")
+            },
+            Warning::SuspiciousSymbols { contig, symbol, position, count } => {
+                write!(f, "Contig {contig:?} contains {count} non-IUPAC symbols, the first is {:?} at position {position}", *symbol as char)
+            },
+            Warning::DuplicateRenamed { original, renamed } => {
+                write!(f, "Renamed repeated record ID {original:?} to {renamed:?}")
+            },
+            Warning::MmapFallback { projected_bytes, max_bytes } => {
+                write!(f, "Projected size is {projected_bytes} bytes, over the limit of {max_bytes} bytes; switched to the Mmap backend")
+            },
+            Warning::CacheBypassed { contig, bytes } => {
+                write!(f, "Borrowed access kept {bytes} bytes of contig {contig:?} outside of the LRU cache; use the shared accessors instead")
+            }
+        }
+    }
+}
+
+/// A callback that receives every warning as it is raised
+pub type WarningHandler = dyn Fn(&Warning) + Send + Sync;
+
+/// Collects warnings and forwards them to an optional handler; clones share the collected warnings
+#[derive(Clone, Default)]
+pub(crate) struct WarningChannel {
+    /// The first `MAX_COLLECTED_WARNINGS` warnings
+    collected: Arc<Mutex<Vec<Warning>>>,
+    /// The number of warnings raised, including those past the collection limit
+    count: Arc<AtomicUsize>,
+    /// Optional callback for each warning
+    handler: Option<Arc<WarningHandler>>
+}
+
+impl WarningChannel {
+    /// Sets the callback for warnings raised from now on
+    pub(crate) fn set_handler(&mut self, handler: Arc<WarningHandler>) {
+        self.handler = Some(handler);
+    }
+
+    /// Logs a warning, passes it to the handler, and collects it
+    pub(crate) fn emit(&self, warning: Warning) {
+        warn!("{warning}");
+        if let Some(handler) = self.handler.as_ref() {
+            handler(&warning);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let mut collected = self.collected.lock().unwrap();
+        if collected.len() < MAX_COLLECTED_WARNINGS {
+            collected.push(warning);
+        }
+    }
+
+    /// A copy of the collected warnings
+    pub(crate) fn collected(&self) -> Vec<Warning> {
+        self.collected.lock().unwrap().clone()
+    }
+
+    /// Removes and returns the collected warnings
+    pub(crate) fn take(&self) -> Vec<Warning> {
+        std::mem::take(&mut *self.collected.lock().unwrap())
+    }
+
+    /// The number of warnings raised
+    pub(crate) fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+/// Finds the symbols of a record that are outside the IUPAC nucleotide codes
+/// # Returns
+/// * a `Warning::SuspiciousSymbols` if there are any
+pub(crate) fn check_symbols(contig: &str, sequence: &[u8]) -> Option<Warning> {
+    let is_suspicious = |symbol: &u8| !Alphabet::Iupac.is_valid(*symbol);
+    let position = sequence.iter().position(is_suspicious)?;
+    Some(Warning::SuspiciousSymbols {
+        contig: contig.to_string(),
+        symbol: sequence[position],
+        position,
+        count: sequence[position..].iter().filter(|s| is_suspicious(s)).count()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warning_channel() {
+        let seen = Arc::new(AtomicUsize::new(0));
+        let mut channel = WarningChannel::default();
+        let counter = seen.clone();
+        channel.set_handler(Arc::new(move |_| { counter.fetch_add(1, Ordering::Relaxed); }));
+
+        let clone = channel.clone();
+        clone.emit(Warning::DuplicateRenamed { original: "chr1".to_string(), renamed: "chr1_2".to_string() });
+        assert_eq!(channel.collected().len(), 1);
+        assert_eq!(channel.take()[0].to_string(), "Renamed repeated record ID \"chr1\" to \"chr1_2\"");
+        assert!(clone.collected().is_empty());
+        assert_eq!((channel.count(), seen.load(Ordering::Relaxed)), (1, 1));
+    }
+
+    #[test]
+    fn test_check_symbols() {
+        assert_eq!(check_symbols("chr1", b"ACGTNRYacgt"), None);
+        assert_eq!(check_symbols("chr1", b"AC-GT1-"), Some(Warning::SuspiciousSymbols {
+            contig: "chr1".to_string(),
+            symbol: b'-',
+            position: 2,
+            count: 3
+        }));
+    }
+}
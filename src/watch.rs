@@ -0,0 +1,206 @@
+use crate::reference_genome::ReferenceGenome;
+use crate::shared::SharedReferenceGenome;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+/// Identifies the on-disk version of each watched file by modification time and size, or `None` while a file is missing
+type FileSignature = Vec<Option<(Option<SystemTime>, u64)>>;
+
+/// Reads the current signature of the watched files
+fn file_signature(paths: &[PathBuf]) -> FileSignature {
+    paths.iter()
+        .map(|p| std::fs::metadata(p).ok().map(|m| (m.modified().ok(), m.len())))
+        .collect()
+}
+
+/// A background thread that reloads a `SharedReferenceGenome` when its backing files change on disk, from
+/// `SharedReferenceGenome::watch(...)`. Files are polled, so no platform notification API is needed.
+/// A change is only acted on once the files have been unchanged for a full poll interval, so a file that is still being
+/// written (or replaced) is not loaded half-way; the new genome is loaded without holding any lock and then swapped in,
+/// so readers see either the old or the new genome, never a mix. If a reload fails, the old genome stays in place and
+/// the next change is tried again. Dropping the watcher stops the thread.
+pub struct GenomeWatcher {
+    /// Signals the thread to stop when dropped
+    stop: Option<Sender<()>>,
+    /// The polling thread
+    thread: Option<JoinHandle<()>>,
+    /// The number of successful reloads
+    reload_count: Arc<AtomicUsize>,
+    /// The error from the most recent reload, cleared by a successful reload
+    last_error: Arc<Mutex<Option<String>>>
+}
+
+impl GenomeWatcher {
+    /// The number of times the genome has been reloaded
+    pub fn reload_count(&self) -> usize {
+        self.reload_count.load(Ordering::Acquire)
+    }
+
+    /// The error message from the most recent reload, or `None` if it succeeded or there has been none
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+impl Drop for GenomeWatcher {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl SharedReferenceGenome {
+    /// Watches files on disk and atomically replaces the shared genome with a fresh load whenever they change,
+    /// for long-running services whose reference assets are updated in place. All clones of this handle see the new genome.
+    /// Changes made through the handle, such as `add_contig(...)`, are lost on reload unless the loader repeats them.
+    /// # Arguments
+    /// * `paths` - the files to watch, e.g. the FASTA and its index or a cache file; missing files are waited for
+    /// * `poll_interval` - how often the files are checked
+    /// * `loader` - loads the new genome, e.g. `move || ReferenceGenome::from_fasta(&fasta_fn)`
+    pub fn watch<F>(&self, paths: &[PathBuf], poll_interval: Duration, loader: F) -> GenomeWatcher
+        where F: Fn() -> Result<ReferenceGenome, Box<dyn Error>> + Send + 'static {
+        let (stop, stop_receiver) = channel::<()>();
+        let reload_count: Arc<AtomicUsize> = Default::default();
+        let last_error: Arc<Mutex<Option<String>>> = Default::default();
+
+        let shared = self.clone();
+        let paths = paths.to_vec();
+        // read before the thread starts, so changes right after this returns are not missed
+        let mut loaded = file_signature(&paths);
+        let thread_reload_count = reload_count.clone();
+        let thread_last_error = last_error.clone();
+        let thread = std::thread::spawn(move || {
+            let mut previous = loaded.clone();
+            // any message or a dropped sender stops the thread
+            while let Err(RecvTimeoutError::Timeout) = stop_receiver.recv_timeout(poll_interval) {
+                let current = file_signature(&paths);
+                let settled = current == previous;
+                previous = current.clone();
+                if !settled || current == loaded || current.iter().any(|s| s.is_none()) {
+                    continue;
+                }
+                loaded = current;
+                match loader() {
+                    Ok(reference_genome) => {
+                        *shared.write() = reference_genome;
+                        *thread_last_error.lock().unwrap_or_else(PoisonError::into_inner) = None;
+                        thread_reload_count.fetch_add(1, Ordering::AcqRel);
+                        #[cfg(feature = "tracing")]
+                        tracing::info!(paths = ?paths, "Reloaded reference genome after a file change");
+                    },
+                    Err(e) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(paths = ?paths, error = %e, "Failed to reload reference genome after a file change");
+                        *thread_last_error.lock().unwrap_or_else(PoisonError::into_inner) = Some(e.to_string());
+                    }
+                }
+            }
+        });
+
+        GenomeWatcher {
+            stop: Some(stop),
+            thread: Some(thread),
+            reload_count,
+            last_error
+        }
+    }
+
+    /// Watches a FASTA file and reloads it with `ReferenceGenome::from_fasta(...)` whenever it changes, see `watch(...)`.
+    /// The FASTA index (`.fai`) is watched too when it exists, so a file and its index replaced together trigger one reload.
+    /// # Arguments
+    /// * `fasta_fn` - the FASTA filename
+    /// * `poll_interval` - how often the file is checked
+    pub fn watch_fasta(&self, fasta_fn: &Path, poll_interval: Duration) -> GenomeWatcher {
+        let mut paths = vec![fasta_fn.to_path_buf()];
+        let mut index_fn = fasta_fn.as_os_str().to_owned();
+        index_fn.push(".fai");
+        if Path::new(&index_fn).exists() {
+            paths.push(PathBuf::from(index_fn));
+        }
+        let fasta_fn = fasta_fn.to_path_buf();
+        self.watch(&paths, poll_interval, move || ReferenceGenome::from_fasta(&fasta_fn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Waits for a condition, failing after a generous timeout
+    fn wait_for<F: Fn() -> bool>(condition: F) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(10), "Timed out waiting for the watcher");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_watch_fasta() {
+        let directory = std::env::temp_dir().join(format!("refgenome_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let fasta_fn = directory.join("reference.fa");
+        std::fs::write(&fasta_fn, b">chr1\nACGT\n").unwrap();
+
+        let shared = SharedReferenceGenome::new(ReferenceGenome::from_fasta(&fasta_fn).unwrap());
+        let watcher = shared.watch_fasta(&fasta_fn, Duration::from_millis(10));
+        assert_eq!(watcher.reload_count(), 0);
+
+        // the sizes differ, so the change is seen even with coarse modification times
+        std::fs::write(&fasta_fn, b">chr1\nGGGGCC\n>chr2\nA\n").unwrap();
+        wait_for(|| watcher.reload_count() == 1);
+        assert_eq!(shared.get_full_chromosome("chr1").unwrap(), &b"GGGGCC"[..]);
+        assert_eq!(shared.contig_keys(), vec!["chr1".to_string(), "chr2".to_string()]);
+        assert_eq!(watcher.last_error(), None);
+
+        // a missing file is waited for rather than reloaded
+        std::fs::remove_file(&fasta_fn).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(watcher.reload_count(), 1);
+        assert_eq!(shared.contig_length("chr1"), Some(6));
+
+        drop(watcher);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_watch_reload_error() {
+        let directory = std::env::temp_dir().join(format!("refgenome_watch_error_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let fasta_fn = directory.join("reference.fa");
+        std::fs::write(&fasta_fn, b">chr1\nACGT\n").unwrap();
+
+        let shared = SharedReferenceGenome::new(ReferenceGenome::from_fasta(&fasta_fn).unwrap());
+        let loader_fn = fasta_fn.clone();
+        let watcher = shared.watch(std::slice::from_ref(&fasta_fn), Duration::from_millis(10), move || {
+            let reference_genome = ReferenceGenome::from_fasta(&loader_fn)?;
+            if reference_genome.contig_keys().len() > 1 {
+                return Err("Only one contig is allowed".into());
+            }
+            Ok(reference_genome)
+        });
+
+        // a failed reload keeps the old genome
+        std::fs::write(&fasta_fn, b">chr1\nAC\n>chr2\nA\n").unwrap();
+        wait_for(|| watcher.last_error().is_some());
+        assert_eq!(watcher.reload_count(), 0);
+        assert_eq!(shared.get_full_chromosome("chr1").unwrap(), &b"ACGT"[..]);
+
+        // and a later fix is picked up
+        std::fs::write(&fasta_fn, b">chr1\nTTTTTTTT\n").unwrap();
+        wait_for(|| watcher.reload_count() == 1);
+        assert_eq!(watcher.last_error(), None);
+        assert_eq!(shared.get_full_chromosome("chr1").unwrap(), &b"TTTTTTTT"[..]);
+
+        drop(watcher);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,337 @@
+use crate::digest::md5_hex_uppercase;
+use crate::reference_genome::ReferenceGenome;
+use bytes::Bytes;
+#[cfg(feature = "gzip")]
+use flate2::{write::GzEncoder, Compression};
+use rustc_hash::FxHashSet as HashSet;
+use simple_error::bail;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The line width used for FASTA output, matching samtools and most reference distributions
+pub const DEFAULT_LINE_WIDTH: usize = 60;
+
+/// The file name of the manifest written by `write_split_fasta(...)`
+pub const SPLIT_MANIFEST_NAME: &str = "manifest.tsv";
+
+/// The header description and line layout of a FASTA record, captured by `ReferenceGenomeBuilder::preserve_format(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecordFormat {
+    /// Header text after the contig name, including the separating whitespace (e.g. " description text"), or empty
+    pub description: String,
+    /// The sequence characters per line; 0 if the record had no sequence
+    pub line_width: usize
+}
+
+impl RecordFormat {
+    /// The line width to write with, falling back to `DEFAULT_LINE_WIDTH` if the original record had no sequence
+    fn output_line_width(&self) -> usize {
+        if self.line_width == 0 { DEFAULT_LINE_WIDTH } else { self.line_width }
+    }
+}
+
+/// Writes a single FASTA record
+/// # Arguments
+/// * `writer` - the output to write to
+/// * `name` - the contig name for the header line
+/// * `sequence` - the ASCII sequence
+/// * `line_width` - the maximum sequence characters per line, at least 1
+/// # Errors
+/// * any errors from the underlying writer
+pub fn write_fasta_record<W: Write>(writer: &mut W, name: &str, sequence: &[u8], line_width: usize) -> std::io::Result<()> {
+    writeln!(writer, ">{name}")?;
+    for line in sequence.chunks(line_width.max(1)) {
+        writer.write_all(line)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Converts a contig name into a file name stem, replacing characters that are unsafe in file names (e.g. `/`, `*`, `:`) with `_`
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') { c } else { '_' })
+        .collect()
+}
+
+impl ReferenceGenome {
+    /// Retrieves a contig sequence for writing without keeping lazily loaded contigs in memory
+    fn sequence_for_output(&self, contig: &str) -> Result<Bytes, Box<dyn std::error::Error>> {
+        match self.contig_sequence(contig).and_then(|s| s.try_as_bytes()) {
+            Some(sequence) => Ok(sequence),
+            None => bail!("Contig key \"{contig}\" has been unloaded and cannot be written")
+        }
+    }
+
+    /// Writes every contig to a single FASTA output, in `contig_keys()` order
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// * `line_width` - the maximum sequence characters per line, see `DEFAULT_LINE_WIDTH`
+    /// # Errors
+    /// * any errors from the underlying writer
+    /// * if a contig has been unloaded from an in-memory genome
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn write_fasta<W: Write>(&self, writer: W, line_width: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let mut writer = BufWriter::new(writer);
+        for contig in self.contig_keys().iter() {
+            write_fasta_record(&mut writer, contig, &self.sequence_for_output(contig)?, line_width)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes every contig with its original formatting, in `contig_keys()` order.
+    /// For a genome loaded with `ReferenceGenomeBuilder::preserve_format(true)`, this reproduces the loaded FASTA byte for byte,
+    /// including record descriptions and line widths; contigs without a captured format (e.g. from `add_contig(...)`)
+    /// are written without a description at `DEFAULT_LINE_WIDTH`.
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// # Errors
+    /// * any errors from the underlying writer
+    /// * if a contig has been unloaded from an in-memory genome
+    pub fn write_fasta_preserved<W: Write>(&self, writer: W) -> Result<(), Box<dyn std::error::Error>> {
+        let default_format = RecordFormat::default();
+        let mut writer = BufWriter::new(writer);
+        for contig in self.contig_keys().iter() {
+            let format = self.record_format(contig).unwrap_or(&default_format);
+            let header = format!("{}{}", contig, format.description);
+            write_fasta_record(&mut writer, &header, &self.sequence_for_output(contig)?, format.output_line_width())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes a samtools-compatible `.fai` index for the output of `write_fasta(...)` with the same line width,
+    /// matching `samtools faidx` on that output byte for byte. Only contig lengths are needed, so unloaded contigs are included.
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// * `line_width` - the line width passed to `write_fasta(...)`
+    /// # Errors
+    /// * any errors from the underlying writer
+    pub fn write_fai<W: Write>(&self, writer: W, line_width: usize) -> std::io::Result<()> {
+        self.write_fai_records(writer, |_| (0, line_width.max(1)))
+    }
+
+    /// Writes a samtools-compatible `.fai` index for the output of `write_fasta_preserved(...)`.
+    /// Only contig lengths are needed, so unloaded contigs are included.
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// # Errors
+    /// * any errors from the underlying writer
+    pub fn write_fai_preserved<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        let default_format = RecordFormat::default();
+        self.write_fai_records(writer, |contig| {
+            let format = self.record_format(contig).unwrap_or(&default_format);
+            (format.description.len(), format.output_line_width())
+        })
+    }
+
+    /// Writes a `.fai` line per contig, tracking the offsets of the FASTA output
+    /// # Arguments
+    /// * `writer` - the output to write to
+    /// * `layout` - the header description length and line width of each contig in the output
+    fn write_fai_records<W, F>(&self, writer: W, layout: F) -> std::io::Result<()> where W: Write, F: Fn(&str) -> (usize, usize) {
+        let mut writer = BufWriter::new(writer);
+        let mut offset: usize = 0;
+        for contig in self.contig_keys().iter() {
+            let (description_len, line_width) = layout(contig);
+            let length = self.contig_length(contig).unwrap_or_default();
+            // the offset of the sequence follows the header line
+            offset += 1 + contig.len() + description_len + 1;
+            if length == 0 {
+                writeln!(writer, "{contig}\t0\t{offset}\t0\t0")?;
+            } else {
+                // samtools takes the line length from the first line, which is shorter than the width for short contigs
+                let first_line = line_width.min(length);
+                writeln!(writer, "{}\t{}\t{}\t{}\t{}", contig, length, offset, first_line, first_line + 1)?;
+                offset += length + length.div_ceil(line_width);
+            }
+        }
+        writer.flush()
+    }
+
+    /// Writes every contig to a FASTA file along with its `.fai` index (the FASTA path with `.fai` appended),
+    /// so the output can be used by samtools and other indexed readers without running `samtools faidx`
+    /// # Arguments
+    /// * `fasta_fn` - the uncompressed FASTA filename
+    /// * `line_width` - the maximum sequence characters per line, see `DEFAULT_LINE_WIDTH`
+    /// # Errors
+    /// * any file creation and/or writing errors
+    /// * if a contig has been unloaded from an in-memory genome
+    pub fn write_indexed_fasta(&self, fasta_fn: &Path, line_width: usize) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_fasta(std::fs::File::create(fasta_fn)?, line_width)?;
+        let mut fai_fn = fasta_fn.as_os_str().to_os_string();
+        fai_fn.push(".fai");
+        self.write_fai(std::fs::File::create(fai_fn)?, line_width)?;
+        Ok(())
+    }
+
+    /// Writes each contig to its own FASTA file in a directory, along with a tab-separated manifest (`SPLIT_MANIFEST_NAME`).
+    /// Files are named after their contig, with unsafe characters replaced by `_` and a numeric suffix if two names would collide.
+    /// The manifest has a header line and one row per contig in `contig_keys()` order with the columns `contig`, `file`, `length`, and `md5`,
+    /// where `md5` is the digest of the upper-cased sequence, matching the `M5` tag of a SAM header.
+    /// The directory can be loaded again with `ReferenceGenome::from_fasta(...)`, although the files are then ordered by name.
+    /// # Arguments
+    /// * `directory` - the output directory, which is created if needed
+    /// * `compress` - if true, files are gzip-compressed with a `.fa.gz` extension; requires the `gzip` feature
+    /// # Errors
+    /// * any file creation and/or writing errors
+    /// * if a contig has been unloaded from an in-memory genome
+    /// * if `compress` is set without the `gzip` feature
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn write_split_fasta(&self, directory: &Path, compress: bool) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        if compress && cfg!(not(feature = "gzip")) {
+            bail!("Writing gzip-compressed files requires the \"gzip\" feature");
+        }
+        std::fs::create_dir_all(directory)?;
+        let extension = if compress { "fa.gz" } else { "fa" };
+
+        let mut manifest = BufWriter::new(std::fs::File::create(directory.join(SPLIT_MANIFEST_NAME))?);
+        writeln!(manifest, "contig\tfile\tlength\tmd5")?;
+        let mut used_names: HashSet<String> = Default::default();
+        let mut paths: Vec<PathBuf> = Vec::with_capacity(self.contig_keys().len());
+        for contig in self.contig_keys().iter() {
+            let stem = file_stem(contig);
+            let mut file_name = format!("{stem}.{extension}");
+            let mut suffix = 1;
+            while !used_names.insert(file_name.clone()) {
+                suffix += 1;
+                file_name = format!("{stem}_{suffix}.{extension}");
+            }
+
+            let sequence = self.sequence_for_output(contig)?;
+            let path = directory.join(&file_name);
+            let file = std::fs::File::create(&path)?;
+            if compress {
+                #[cfg(feature = "gzip")] {
+                    let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+                    write_fasta_record(&mut encoder, contig, &sequence, DEFAULT_LINE_WIDTH)?;
+                    encoder.finish()?.flush()?;
+                }
+            } else {
+                let mut writer = BufWriter::new(file);
+                write_fasta_record(&mut writer, contig, &sequence, DEFAULT_LINE_WIDTH)?;
+                writer.flush()?;
+            }
+            writeln!(manifest, "{}\t{}\t{}\t{}", contig, file_name, sequence.len(), md5_hex_uppercase(&sequence))?;
+            paths.push(path);
+        }
+        manifest.flush()?;
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ReferenceGenomeBuilder;
+    use crate::digest::md5_hex;
+
+    #[test]
+    fn test_write_fasta() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), "ACGTACGT").unwrap();
+        reference_genome.add_contig("chr2".to_string(), "").unwrap();
+        let mut output: Vec<u8> = vec![];
+        reference_genome.write_fasta(&mut output, 3).unwrap();
+        assert_eq!(output, b">chr1\nACG\nTAC\nGT\n>chr2\n");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for fasta_fn in ["./test_data/test_reference.fa", "./test_data/test_iupac.fa"] {
+            let original = std::fs::read(fasta_fn).unwrap();
+            let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from(fasta_fn)).preserve_format(true).build().unwrap();
+            let mut output: Vec<u8> = vec![];
+            reference_genome.write_fasta_preserved(&mut output).unwrap();
+            assert_eq!(output, original, "{fasta_fn}");
+        }
+
+        let reference_genome = ReferenceGenomeBuilder::new(&PathBuf::from("./test_data/test_reference.fa")).preserve_format(true).build().unwrap();
+        let mut fai: Vec<u8> = vec![];
+        reference_genome.write_fai_preserved(&mut fai).unwrap();
+        assert_eq!(fai, std::fs::read("./test_data/test_reference.fa.fai").unwrap());
+    }
+
+    #[test]
+    fn test_write_fai() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), "ACGTACGT").unwrap();
+        reference_genome.add_contig("chr2".to_string(), "").unwrap();
+        reference_genome.add_contig("chr3".to_string(), "AC").unwrap();
+        reference_genome.add_contig("chr4".to_string(), "ACGTAC").unwrap();
+        let mut fai: Vec<u8> = vec![];
+        reference_genome.write_fai(&mut fai, 3).unwrap();
+        // matches `samtools faidx` on the output of write_fasta(..., 3)
+        assert_eq!(String::from_utf8(fai).unwrap(), "chr1\t8\t6\t3\t4\nchr2\t0\t23\t0\t0\nchr3\t2\t29\t2\t3\nchr4\t6\t38\t3\t4\n");
+
+        let reference_genome = ReferenceGenome::from_fasta(&PathBuf::from("./test_data/test_reference.fa")).unwrap();
+        let directory = std::env::temp_dir().join(format!("refgenome_write_fai_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let fasta_fn = directory.join("copy.fa");
+        reference_genome.write_indexed_fasta(&fasta_fn, 4).unwrap();
+        assert_eq!(std::fs::read_to_string(directory.join("copy.fa.fai")).unwrap(), "chr1\t8\t6\t4\t5\nchr2\t8\t22\t4\t5\n");
+        let reloaded = ReferenceGenomeBuilder::new(&fasta_fn).backend(crate::builder::Backend::Mmap).build().unwrap();
+        assert_eq!(reloaded.get_full_chromosome("chr2"), reference_genome.get_full_chromosome("chr2"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_preserved_descriptions() {
+        let content = b">chr1 first record\nAC\nGT\nA\n>chr2\n>chr3 third\nacg\n";
+        let mut reference_genome = ReferenceGenomeBuilder::from_bytes(&content[..]).preserve_format(true).build().unwrap();
+        assert_eq!(reference_genome.record_format("chr3").unwrap().description, " third");
+        reference_genome.rename_contigs(&[("chr1".to_string(), "1".to_string())]).unwrap();
+        reference_genome.add_contig("chr4".to_string(), "TT").unwrap();
+
+        let mut output: Vec<u8> = vec![];
+        reference_genome.write_fasta_preserved(&mut output).unwrap();
+        assert_eq!(output, b">1 first record\nAC\nGT\nA\n>chr2\n>chr3 third\nacg\n>chr4\nTT\n");
+        let mut fai: Vec<u8> = vec![];
+        reference_genome.write_fai_preserved(&mut fai).unwrap();
+        assert_eq!(String::from_utf8(fai).unwrap(), "1\t5\t16\t2\t3\nchr2\t0\t30\t0\t0\nchr3\t3\t42\t3\t4\nchr4\t2\t52\t2\t3\n");
+
+        // subsets keep the formats of the retained contigs
+        let subset = reference_genome.subset(|c| c == "chr3");
+        assert_eq!(subset.record_format("chr3").unwrap().line_width, 3);
+        assert!(subset.record_format("1").is_none());
+    }
+
+    #[test]
+    fn test_write_split_fasta() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr10".to_string(), "ACgt").unwrap();
+        reference_genome.add_contig("HLA-A*01:01".to_string(), "GGCC").unwrap();
+        reference_genome.add_contig("HLA-A_01_01".to_string(), "TT").unwrap();
+
+        let directory = std::env::temp_dir().join(format!("refgenome_split_{}", std::process::id()));
+        let paths = reference_genome.write_split_fasta(&directory, false).unwrap();
+        let file_names: Vec<String> = paths.iter().map(|p| p.file_name().unwrap().to_string_lossy().to_string()).collect();
+        assert_eq!(file_names, vec!["chr10.fa", "HLA-A_01_01.fa", "HLA-A_01_01_2.fa"]);
+        assert_eq!(std::fs::read_to_string(&paths[1]).unwrap(), ">HLA-A*01:01\nGGCC\n");
+
+        let manifest = std::fs::read_to_string(directory.join(SPLIT_MANIFEST_NAME)).unwrap();
+        let lines: Vec<&str> = manifest.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1], format!("chr10\tchr10.fa\t4\t{}", md5_hex(b"ACGT")));
+
+        // the directory loads back with the same contigs
+        let reloaded = ReferenceGenome::from_fasta(&directory).unwrap();
+        assert_eq!(reloaded.contig_keys().len(), 3);
+        assert!(reloaded.compare(&reference_genome).unwrap().is_identical());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn test_write_split_fasta_gzip() {
+        let mut reference_genome = ReferenceGenome::empty_reference();
+        reference_genome.add_contig("chr1".to_string(), "ACGT").unwrap();
+        let directory = std::env::temp_dir().join(format!("refgenome_split_gz_{}", std::process::id()));
+        let paths = reference_genome.write_split_fasta(&directory, true).unwrap();
+        assert!(paths[0].to_string_lossy().ends_with("chr1.fa.gz"));
+        let reloaded = ReferenceGenome::from_fasta(&paths[0]).unwrap();
+        assert_eq!(reloaded.get_full_chromosome("chr1"), b"ACGT");
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}